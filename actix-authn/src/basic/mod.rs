@@ -15,7 +15,7 @@ use md5::Digest;
 
 mod cache;
 
-use crate::Authenticator;
+use crate::{Authenticator, Identity};
 
 /// Re-export crypt3 crypt and Hash
 pub use crypt3_rs::{Hash, crypt};
@@ -49,7 +49,7 @@ pub use crypt3_rs::{Hash, crypt};
 pub struct Basic {
     realm: Option<String>,
     auth: HashMap<String, Hash>,
-    cache: cache::AuthCache<bool>,
+    cache: cache::AuthCache<Option<String>>,
 }
 
 /// Basic Auth [`Authenticator`] implementation.
@@ -132,22 +132,22 @@ impl Basic {
     /// This method makes use of the credential-cache which hashes
     /// the given base64 string to store the result.
     pub fn verify_basic(&mut self, basic_base64: String) -> bool {
+        self.basic_user(basic_base64).is_some()
+    }
+
+    /// Verify `Authorzation: Basic <basic_base64>` header value
+    /// returning the authenticated user.
+    fn basic_user(&mut self, basic_base64: String) -> Option<String> {
         let key = md5::Md5::digest(&basic_base64).to_vec();
         if let Some(entry) = self.cache.get(&key) {
-            return *entry;
+            return entry.clone();
         }
-        let Ok(auth) = STANDARD.decode(&basic_base64) else {
-            return false;
-        };
-        let Ok(auth) = std::str::from_utf8(&auth) else {
-            return false;
-        };
-        let Some((user, secret)) = auth.split_once(':') else {
-            return false;
-        };
-        let verify = self.verify(user, secret);
-        self.cache.insert(key, verify);
-        verify
+        let auth = STANDARD.decode(&basic_base64).ok()?;
+        let auth = std::str::from_utf8(&auth).ok()?;
+        let (user, secret) = auth.split_once(':')?;
+        let user = self.verify(user, secret).then(|| user.to_owned());
+        self.cache.insert(key, user.clone());
+        user
     }
 
     pub(crate) fn prompt(&self) -> HttpResponse {
@@ -171,17 +171,18 @@ impl Basic {
 }
 
 impl Authenticator for BasicAuth {
-    async fn authorize(&self, req: &HttpRequest) -> Result<bool, Error> {
+    async fn authorize(&self, req: &HttpRequest) -> Result<Option<Identity>, Error> {
         let Some(basic) = parse_authorization(req.headers(), "Basic ") else {
-            return Ok(false);
+            return Ok(None);
         };
         let this = Arc::clone(&self.0);
         let basic = basic.to_owned();
-        Ok(actix_web::rt::task::spawn_blocking(move || {
-            this.lock().expect("failed to unlock").verify_basic(basic)
+        let user = actix_web::rt::task::spawn_blocking(move || {
+            this.lock().expect("failed to unlock").basic_user(basic)
         })
         .await
-        .expect("failed to spawn actix thread"))
+        .expect("failed to spawn actix thread");
+        Ok(user.map(Identity::new))
     }
 
     #[inline]
@@ -192,27 +193,27 @@ impl Authenticator for BasicAuth {
 
 #[cfg(feature = "basic_session")]
 impl Authenticator for BasicAuthSession {
-    async fn authorize(&self, req: &HttpRequest) -> Result<bool, Error> {
+    async fn authorize(&self, req: &HttpRequest) -> Result<Option<Identity>, Error> {
         let session = actix_session::Session::extract(req).await?;
-        if session.get::<bool>("logged_in")?.unwrap_or_default() {
-            return Ok(true);
+        if let Some(user) = session.get::<String>("user")? {
+            return Ok(Some(Identity::new(user)));
         }
 
         let Some(basic) = parse_authorization(req.headers(), "Basic ") else {
-            return Ok(false);
+            return Ok(None);
         };
         let this = Arc::clone(&self.0);
         let basic = basic.to_owned();
-        let logged_in = actix_web::rt::task::spawn_blocking(move || {
-            this.lock().expect("failed to unlock").verify_basic(basic)
+        let user = actix_web::rt::task::spawn_blocking(move || {
+            this.lock().expect("failed to unlock").basic_user(basic)
         })
         .await
         .expect("failed to spawn actix thread");
 
-        if logged_in {
-            session.insert("logged_in", true)?;
+        if let Some(user) = user.as_ref() {
+            session.insert("user", user)?;
         }
-        Ok(logged_in)
+        Ok(user.map(Identity::new))
     }
 
     #[inline]
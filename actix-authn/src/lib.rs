@@ -1,5 +1,7 @@
 use actix_web::{Error, HttpRequest, HttpResponse};
 
+pub use actix_common::Identity;
+
 mod factory;
 mod service;
 
@@ -8,7 +10,10 @@ pub mod basic;
 
 /// Trait abstraction for web authenticator services
 pub trait Authenticator {
-    fn authorize(&self, req: &HttpRequest) -> impl Future<Output = Result<bool, Error>>;
+    /// Validate the request credentials returning the authenticated
+    /// [`Identity`], which is inserted into the request extensions.
+    fn authorize(&self, req: &HttpRequest)
+    -> impl Future<Output = Result<Option<Identity>, Error>>;
     fn prompt(&self, req: &HttpRequest) -> impl Future<Output = Result<HttpResponse, Error>>;
 }
 
@@ -1,6 +1,7 @@
 use std::{ops::Deref, rc::Rc};
 
 use actix_web::{
+    HttpMessage,
     body::BoxBody,
     dev::{self, Service, ServiceRequest, ServiceResponse},
     error::Error as ActixError,
@@ -41,19 +42,20 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let this = Rc::clone(&self.0);
         Box::pin(async move {
-            if !this
+            let identity = this
                 .authn
                 .authorize(req.request())
                 .await
-                .inspect_err(|err| tracing::error!("auth failed: {err:?}"))?
-            {
+                .inspect_err(|err| tracing::error!("auth failed: {err:?}"))?;
+            let Some(identity) = identity else {
                 let res = this
                     .authn
                     .prompt(req.request())
                     .await
                     .inspect_err(|err| tracing::error!("prompt failed: {err:?}"))?;
                 return Ok(req.into_response(res));
-            }
+            };
+            req.extensions_mut().insert(identity);
             this.service.call(req).await
         })
     }
@@ -1,4 +1,10 @@
-use actix_authn::basic::Basic;
+use actix_authn::{Authn, Identity, basic::Basic};
+use actix_web::{
+    App,
+    http::{StatusCode, header},
+    test::{self, TestRequest},
+    web,
+};
 
 mod common;
 
@@ -14,3 +20,46 @@ fn test_basic() {
     assert!(basic.verify("root", "password"));
     assert!(!basic.verify("root", "wrong"));
 }
+
+async fn user(identity: Identity) -> String {
+    identity.user().to_owned()
+}
+
+#[actix_web::test]
+async fn test_identity() {
+    common::setup();
+
+    let basic = Basic::default()
+        .passwd("admin:$5$zf2X2LFe6AL0ZBWn$y9Ox4HNZwHtZM85cNW8iaUgE7EiuNF01vNveiXnwk68")
+        .build();
+    let srv = test::init_service(
+        App::new()
+            .service(web::scope("/open").route("/user", web::get().to(user)))
+            .service(
+                web::scope("")
+                    .wrap(Authn::new(basic))
+                    .route("/user", web::get().to(user)),
+            ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/user")
+        .insert_header((header::AUTHORIZATION, "Basic YWRtaW46YWRtaW4="))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "admin");
+
+    let req = TestRequest::with_uri("/user")
+        .insert_header((header::AUTHORIZATION, "Basic YWRtaW46d3Jvbmc="))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // the extractor rejects requests not passing through the middleware
+    let req = TestRequest::with_uri("/open/user")
+        .insert_header((header::AUTHORIZATION, "Basic YWRtaW46YWRtaW4="))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,62 @@
+use actix_chain::{Chain, Link};
+use actix_web::{
+    App, HttpResponse, body,
+    dev::{Payload, Service},
+    rt::System,
+    test::{self, TestRequest},
+    web::{self, Bytes},
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use futures_util::stream;
+
+async fn fail(body: Bytes) -> HttpResponse {
+    HttpResponse::NotFound().body(body.len().to_string())
+}
+
+async fn echo(body: Bytes) -> HttpResponse {
+    HttpResponse::Ok().body(body)
+}
+
+fn chain_buffer(c: &mut Criterion) {
+    let system = System::new();
+    let srv = system.block_on(test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::post().to(fail)))
+                .link(Link::new(web::post().to(fail)))
+                .link(Link::new(web::post().to(echo))),
+        ),
+    ));
+
+    let mut group = c.benchmark_group("chain_buffer");
+    for (body_size, chunk_size) in [(1_024, 1_024), (16_384, 1_024), (30_720, 64)] {
+        let body = Bytes::from(vec![b'x'; body_size]);
+        let chunks: Vec<_> = body
+            .chunks(chunk_size)
+            .map(|chunk| body.slice_ref(chunk))
+            .collect();
+        group.throughput(Throughput::Bytes(body_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new(format!("chunk_{chunk_size}"), body_size),
+            &chunks,
+            |b, chunks| {
+                b.iter(|| {
+                    system.block_on(async {
+                        let chunks = chunks.clone().into_iter().map(Ok);
+                        let payload: Payload = Payload::Stream {
+                            payload: Box::pin(stream::iter(chunks)),
+                        };
+                        let (req, _) = TestRequest::post().to_request().replace_payload(payload);
+                        let res = srv.call(req).await.expect("chain failed");
+                        let body = body::to_bytes(res.into_body()).await.expect("invalid body");
+                        assert_eq!(body.len(), body_size);
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, chain_buffer);
+criterion_main!(benches);
@@ -0,0 +1,193 @@
+//! Authentication Offload via Sub-Requests
+
+use std::{fmt::Debug, rc::Rc};
+
+use actix_service::{IntoServiceFactory, Service, ServiceFactory};
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderName},
+};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    Link,
+    link::box_factory,
+    next::NextWithRequest,
+    service::{HttpNewService, HttpService},
+};
+
+/// Marker for responses of granted authentication sub-requests
+#[derive(Clone, Copy)]
+struct Granted;
+
+/// Continue to the next link once the sub-request was granted
+struct IsGranted;
+
+impl NextWithRequest for IsGranted {
+    #[inline]
+    fn next(&self, _req: &HttpRequest, res: &HttpResponse) -> bool {
+        res.extensions().contains::<Granted>()
+    }
+}
+
+/// Authentication sub-request used by [`Link::auth_request`]
+///
+/// Similar to nginx `auth_request`. Every request is first sent, without
+/// its body, to the authentication service. A `2xx` response lets the
+/// chain continue with the next link, copying the selected response
+/// headers into the request. Any other response, such as
+/// `401 Unauthorized` or `403 Forbidden`, is returned to the client.
+/// Granted requests which no later link handles are answered with
+/// `404 Not Found`.
+///
+/// Client supplied copies of the selected headers are always removed, so
+/// later links can trust the values set by the authentication service.
+///
+/// The authentication service may modify the sub-request, such as
+/// rewriting its URI, without affecting the request seen by later links.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::{HttpRequest, HttpResponse, web};
+/// use actix_chain::{AuthRequest, Chain, Link};
+///
+/// async fn auth(req: HttpRequest) -> HttpResponse {
+///     match req.headers().get("Authorization") {
+///         Some(_) => HttpResponse::Ok().insert_header(("X-User", "alice")).finish(),
+///         None => HttpResponse::Unauthorized().finish(),
+///     }
+/// }
+///
+/// async fn index(req: HttpRequest) -> String {
+///     format!("hello {:?}", req.headers().get("X-User"))
+/// }
+///
+/// let auth = AuthRequest::new(web::to(auth)).copy_header("X-User");
+/// Chain::default()
+///     .link(Link::auth_request(auth))
+///     .link(Link::new(web::get().to(index)));
+/// ```
+#[derive(Clone)]
+pub struct AuthRequest {
+    service: Rc<HttpNewService>,
+    headers: Vec<HeaderName>,
+}
+
+impl AuthRequest {
+    /// Create a new sub-request to the specified authentication service.
+    ///
+    /// Any Actix-Web service can be passed, such as a reverse-proxy to an
+    /// external authentication server.
+    pub fn new<F, U>(service: F) -> Self
+    where
+        F: IntoServiceFactory<U, ServiceRequest>,
+        U: ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = Error>
+            + 'static,
+        U::InitError: Debug,
+    {
+        Self {
+            service: box_factory(service),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Copy the specified header from granted responses into the request.
+    ///
+    /// Default is no headers are copied.
+    pub fn copy_header<H>(mut self, name: H) -> Self
+    where
+        H: TryInto<HeaderName>,
+        H::Error: Debug,
+    {
+        match name.try_into() {
+            Ok(name) => self.headers.push(name),
+            Err(err) => tracing::error!("invalid auth request header: {err:?}"),
+        }
+        self
+    }
+}
+
+impl ServiceFactory<ServiceRequest> for AuthRequest {
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Config = ();
+    type Service = AuthRequestService;
+    type InitError = String;
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let factory = self.service.clone();
+        let headers = Rc::new(self.headers.clone());
+        Box::pin(async move {
+            Ok(AuthRequestService {
+                service: Rc::new(factory.new_service(()).await?),
+                headers,
+            })
+        })
+    }
+}
+
+/// Service produced by [`AuthRequest`]
+pub struct AuthRequestService {
+    service: Rc<HttpService>,
+    headers: Rc<Vec<HeaderName>>,
+}
+
+impl Service<ServiceRequest> for AuthRequestService {
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let headers = self.headers.clone();
+        Box::pin(async move {
+            // the sub-request takes over the request so the service may
+            // modify it, and the original head is restored afterwards
+            let head = req.head().clone();
+            let path = req.match_info().clone();
+            let (http_req, payload) = req.into_parts();
+            let mut sub = ServiceRequest::from_parts(http_req, Payload::None);
+            sub.headers_mut().remove(header::CONTENT_LENGTH);
+            sub.headers_mut().remove(header::TRANSFER_ENCODING);
+
+            let (http_req, res) = service.call(sub).await?.into_parts();
+            let mut req = ServiceRequest::from_parts(http_req, payload);
+            *req.head_mut() = head;
+            *req.match_info_mut() = path;
+            if !res.status().is_success() {
+                tracing::debug!("auth request denied with {}", res.status());
+                return Ok(req.into_response(res));
+            }
+
+            for name in headers.iter() {
+                req.headers_mut().remove(name);
+                for value in res.headers().get_all(name) {
+                    req.headers_mut().append(name.clone(), value.clone());
+                }
+            }
+            // answered as not found unless a later link handles the request
+            let mut granted = HttpResponse::NotFound().finish();
+            granted.extensions_mut().insert(Granted);
+            Ok(req.into_response(granted))
+        })
+    }
+}
+
+impl Link {
+    /// Create a new [`Link`] authenticating requests using an [`AuthRequest`].
+    ///
+    /// Granted requests continue with the next link in the chain while
+    /// denied requests are answered with the authentication response.
+    /// Granted requests are answered with `404 Not Found` when no later
+    /// link matches.
+    pub fn auth_request(auth: AuthRequest) -> Self {
+        let mut link = Self::new(auth).next(IsGranted);
+        link.auth_request = true;
+        link
+    }
+}
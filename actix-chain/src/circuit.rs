@@ -0,0 +1,75 @@
+//! Link Level Circuit Breaker
+
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+/// Tracks consecutive failures of a single link and temporarily
+/// opens the circuit once the threshold has been reached.
+pub(crate) struct Circuit {
+    threshold: usize,
+    cooldown: Duration,
+    failures: Cell<usize>,
+    opened: Cell<Option<Instant>>,
+    probe: Cell<Option<Instant>>,
+}
+
+impl Circuit {
+    pub(crate) fn new(threshold: usize, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            failures: Cell::new(0),
+            opened: Cell::new(None),
+            probe: Cell::new(None),
+        }
+    }
+
+    /// Check if the link should currently be skipped.
+    ///
+    /// Once the cooldown elapses the circuit is half-open and the first
+    /// request claims the probe, while other requests are skipped until the
+    /// probe reports. Unreported probes expire after another cooldown.
+    pub(crate) fn is_open(&self) -> bool {
+        let Some(opened) = self.opened.get() else {
+            return false;
+        };
+        if opened.elapsed() < self.cooldown {
+            return true;
+        }
+        if self
+            .probe
+            .get()
+            .is_some_and(|probe| probe.elapsed() < self.cooldown)
+        {
+            return true;
+        }
+        self.probe.set(Some(Instant::now()));
+        false
+    }
+
+    /// Circuit cooldown before a probe is allowed through
+    #[inline]
+    pub(crate) fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// Record the outcome of a link evaluation
+    /// and return whether the circuit (re-)opened.
+    pub(crate) fn report(&self, ok: bool) -> bool {
+        self.probe.set(None);
+        if ok {
+            self.failures.set(0);
+            self.opened.set(None);
+            return false;
+        }
+        let failures = self.failures.get().saturating_add(1);
+        self.failures.set(failures);
+        if failures >= self.threshold {
+            self.opened.set(Some(Instant::now()));
+            return true;
+        }
+        false
+    }
+}
@@ -0,0 +1,160 @@
+//! Per-Request Decision Log
+
+use std::{fmt, sync::OnceLock};
+
+use actix_web::{HttpRequest, http::StatusCode};
+
+/// Environment variable enabling the decision log in debug builds
+pub const DECISION_LOG_ENV: &str = "ACTIX_CHAIN_DECISIONS";
+
+/// Check if the decision log is enabled for this process
+#[inline]
+fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    cfg!(debug_assertions)
+        && *ENABLED.get_or_init(|| {
+            std::env::var_os(DECISION_LOG_ENV).is_some_and(|v| !v.is_empty() && v != "0")
+        })
+}
+
+/// Link evaluated against the request
+struct Considered {
+    link: usize,
+    matched: bool,
+    circuit_open: bool,
+}
+
+impl fmt::Debug for Considered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match (self.matched, self.circuit_open) {
+            (false, _) => "unmatched",
+            (true, true) => "circuit-open",
+            (true, false) => "matched",
+        };
+        write!(f, "link {} {result}", self.link)
+    }
+}
+
+/// Response of a link call and the resulting decision
+struct Outcome {
+    link: usize,
+    attempt: usize,
+    status: u16,
+    retry: bool,
+    next: bool,
+}
+
+impl fmt::Debug for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decision = match (self.retry, self.next) {
+            (true, _) => "retry",
+            (false, true) => "next",
+            (false, false) => "respond",
+        };
+        let (link, attempt, status) = (self.link, self.attempt, self.status);
+        write!(
+            f,
+            "link {link} attempt {attempt} status {status} {decision}"
+        )
+    }
+}
+
+/// Service producing the final response
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Responder {
+    Link(usize),
+    Error(usize),
+    Deadline,
+    Memo,
+    Default,
+}
+
+impl fmt::Display for Responder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Link(n) => write!(f, "link {n}"),
+            Self::Error(n) => write!(f, "error from link {n}"),
+            Self::Deadline => write!(f, "deadline"),
+            Self::Memo => write!(f, "etag memo"),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+struct Entries {
+    request: String,
+    considered: Vec<Considered>,
+    outcomes: Vec<Outcome>,
+}
+
+/// Decisions made by the chain for a single request
+///
+/// Only collected in debug builds when [`DECISION_LOG_ENV`] is set and
+/// emitted as a single `actix_chain::decisions` tracing event once the
+/// final responder is known.
+pub(crate) struct DecisionLog(Option<Entries>);
+
+impl DecisionLog {
+    /// Start a new log for the request if enabled
+    #[inline]
+    pub(crate) fn start(req: &HttpRequest) -> Self {
+        Self(enabled().then(|| Entries {
+            request: format!("{} {}", req.method(), req.uri()),
+            considered: Vec::new(),
+            outcomes: Vec::new(),
+        }))
+    }
+
+    /// Check if decisions are being collected
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Record the guard and circuit result of a link
+    pub(crate) fn consider(&mut self, link: usize, matched: bool, circuit_open: bool) {
+        if let Some(entries) = self.0.as_mut() {
+            entries.considered.push(Considered {
+                link,
+                matched,
+                circuit_open,
+            });
+        }
+    }
+
+    /// Record the next predicate outcome of a link response
+    pub(crate) fn outcome(
+        &mut self,
+        link: usize,
+        attempt: usize,
+        status: StatusCode,
+        retry: bool,
+        next: bool,
+    ) {
+        if let Some(entries) = self.0.as_mut() {
+            entries.outcomes.push(Outcome {
+                link,
+                attempt,
+                status: status.as_u16(),
+                retry,
+                next,
+            });
+        }
+    }
+
+    /// Emit the collected decisions with the final responder
+    pub(crate) fn finish(&mut self, mount: &str, responder: Responder) {
+        let Some(entries) = self.0.take() else {
+            return;
+        };
+        tracing::info!(
+            target: "actix_chain::decisions",
+            mount,
+            request = entries.request,
+            considered = ?entries.considered,
+            outcomes = ?entries.outcomes,
+            %responder,
+            "chain decision"
+        );
+    }
+}
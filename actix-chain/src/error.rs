@@ -0,0 +1,27 @@
+//! Chain Initialization Errors
+
+use std::fmt::Display;
+
+/// Error returned when a [`Link`](crate::Link) within the
+/// [`Chain`](crate::Chain) fails to construct its service.
+#[derive(Debug, Clone)]
+pub struct InitError {
+    /// Position of the failed link within the chain.
+    pub index: usize,
+    /// Prefix assigned to the failed link.
+    pub prefix: String,
+    /// Description of the underlying initialization failure.
+    pub reason: String,
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "chain link {} (prefix={:?}) failed to initialize: {}",
+            self.index, self.prefix, self.reason
+        )
+    }
+}
+
+impl std::error::Error for InitError {}
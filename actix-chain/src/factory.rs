@@ -1,5 +1,6 @@
-use std::rc::Rc;
+use std::{cell::Cell, fmt::Debug, rc::Rc, time::Duration};
 
+use actix_common::{Concurrency, ErrorPages, Maintenance};
 use actix_service::{ServiceFactory, Transform};
 use actix_web::{
     Error,
@@ -9,7 +10,10 @@ use actix_web::{
 };
 use futures_core::future::LocalBoxFuture;
 
-use crate::{link::Link, next::Next, service::HttpService, wrap::Wrappable};
+use crate::{
+    error::InitError, link::Link, memo::EtagMemo, next::NextWithRequest, select::Selection,
+    service::HttpService, stats::ChainStats, wrap::Wrappable,
+};
 
 use super::service::{ChainInner, ChainService};
 
@@ -50,8 +54,15 @@ pub struct Chain {
     pub(crate) mount_path: String,
     pub(crate) links: Vec<Link>,
     pub(crate) guards: Vec<Rc<dyn Guard>>,
-    pub(crate) next: Vec<Rc<dyn Next>>, // For Into<Link> only
+    pub(crate) next: Vec<Rc<dyn NextWithRequest>>, // For Into<Link> only
     body_buffer_size: usize,
+    concurrency: Option<Concurrency>,
+    deadline: Option<Duration>,
+    error_pages: Option<ErrorPages>,
+    etag_memo: Option<Duration>,
+    maintenance: Option<Maintenance>,
+    selection: Selection,
+    skip_failed: bool,
 }
 
 impl Chain {
@@ -66,6 +77,13 @@ impl Chain {
             guards: Vec::new(),
             next: Vec::new(),
             body_buffer_size: 32 * 1024, // 32 kb default
+            concurrency: None,
+            deadline: None,
+            error_pages: None,
+            etag_memo: None,
+            maintenance: None,
+            selection: Selection::Ordered,
+            skip_failed: false,
         }
     }
 
@@ -91,6 +109,120 @@ impl Chain {
         self
     }
 
+    /// Limit the number of concurrent requests handled by the service.
+    ///
+    /// Default is unlimited.
+    pub fn concurrency(mut self, concurrency: Concurrency) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Limit the total time spent calling links for a single request.
+    ///
+    /// The budget is shared by every link, retry and backoff, so trying
+    /// multiple slow fallbacks cannot exceed it. Once exhausted, including
+    /// while a link is still running, the last fallen-through response is
+    /// returned, or `504 Gateway Timeout` when no link responded yet.
+    ///
+    /// The deadline is assigned to the request as an
+    /// [`actix_common::Deadline`] so links can forward the remaining
+    /// budget to their backends.
+    ///
+    /// Default is unlimited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_web::web;
+    /// use actix_chain::{Chain, Link};
+    ///
+    /// let chain = Chain::default()
+    ///     .deadline(Duration::from_secs(2))
+    ///     .link(Link::new(web::to(|| async { "primary" })))
+    ///     .link(Link::new(web::to(|| async { "fallback" })));
+    /// ```
+    pub fn deadline(mut self, budget: Duration) -> Self {
+        self.deadline = Some(budget);
+        self
+    }
+
+    /// Render errors raised by links, and the `404 Not Found` returned
+    /// when no link responds, using the specified pages.
+    ///
+    /// Default renders errors using their
+    /// [`ResponseError`](actix_web::ResponseError) implementation.
+    pub fn error_pages(mut self, pages: ErrorPages) -> Self {
+        self.error_pages = Some(pages);
+        self
+    }
+
+    /// Answer conditional requests for responses of fallback links with
+    /// `304 Not Modified` directly.
+    ///
+    /// When a link answers a `GET` or `HEAD` request with an `ETag` after
+    /// earlier links fell through, the ETag is remembered for the request
+    /// host and uri. Subsequent requests whose `If-None-Match` matches it are
+    /// answered by the chain without invoking the failing links again, until
+    /// the ttl expires or a different response is observed. Responses with
+    /// `Vary` or `Cache-Control: no-store` or `private` are never memoized.
+    ///
+    /// Leading [`Link::auth_request`] links and authentication requirements
+    /// of the first remaining link still run before a memoized response is
+    /// served.
+    ///
+    /// Default is disabled.
+    pub fn etag_memo(mut self, ttl: Duration) -> Self {
+        self.etag_memo = Some(ttl);
+        self
+    }
+
+    /// Answer requests with `503 Service Unavailable` while in maintenance.
+    ///
+    /// Keep a clone of the [`Maintenance`] handle to switch maintenance
+    /// mode at runtime. Requests are short-circuited before any link runs.
+    ///
+    /// Default is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_web::web;
+    /// use actix_chain::{Chain, Link};
+    /// use actix_common::Maintenance;
+    ///
+    /// let maintenance = Maintenance::new().allow_path("/health");
+    /// let chain = Chain::default()
+    ///     .maintenance(maintenance.clone())
+    ///     .link(Link::new(web::to(|| async { "hello" })));
+    ///
+    /// maintenance.enable();
+    /// ```
+    pub fn maintenance(mut self, maintenance: Maintenance) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// Configure how the first link is selected among matching links.
+    ///
+    /// Default is [`Selection::Ordered`].
+    pub fn selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Skip links whose services fail to initialize instead of
+    /// failing the whole chain.
+    ///
+    /// Skipped links are reported via logs. Initialization still
+    /// fails if no links could be constructed.
+    ///
+    /// Default is disabled.
+    pub fn skip_failed_links(mut self, skip: bool) -> Self {
+        self.skip_failed = skip;
+        self
+    }
+
     /// Registers a chain specific middleware.
     ///
     /// Wrapping a chain advantagously does not construct an object
@@ -121,13 +253,9 @@ impl Chain {
     #[inline]
     pub fn wrap<M, B>(self, middleware: M) -> Chain
     where
-        M: Transform<
-                HttpService,
-                ServiceRequest,
-                Response = ServiceResponse<B>,
-                Error = Error,
-                InitError = (),
-            > + 'static,
+        M: Transform<HttpService, ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+            + 'static,
+        M::InitError: Debug,
         B: MessageBody + 'static,
     {
         self.wrap_with(middleware)
@@ -146,19 +274,70 @@ impl Chain {
         self.links.push(link);
         self
     }
+
+    /// Shared handle reporting request statistics of each link.
+    ///
+    /// Only covers links added before the handle is taken.
+    /// See [`ChainStats`] for more details.
+    pub fn stats(&self) -> ChainStats {
+        ChainStats(
+            self.links
+                .iter()
+                .map(|link| (link.prefix.as_str().to_owned(), link.counters.clone()))
+                .collect(),
+        )
+    }
+
+    /// Construct the [`ChainService`] reporting which link
+    /// failed to initialize and why.
+    ///
+    /// This is the typed equivalent of
+    /// [`ServiceFactory::new_service`](actix_service::ServiceFactory::new_service).
+    pub async fn init(&self) -> Result<ChainService, InitError> {
+        let mut links = vec![];
+        let mut failed = None;
+        for (index, link) in self.links.iter().enumerate() {
+            match link.inner().await {
+                Ok(link) => links.push(link),
+                Err(reason) => {
+                    let err = InitError {
+                        index,
+                        prefix: link.prefix.as_str().to_owned(),
+                        reason,
+                    };
+                    if !self.skip_failed {
+                        return Err(err);
+                    }
+                    tracing::warn!("skipping {err}");
+                    failed = Some(err);
+                }
+            }
+        }
+        if let (true, Some(err)) = (links.is_empty(), failed) {
+            return Err(err);
+        }
+        Ok(ChainService(Rc::new(ChainInner {
+            mount_path: self.mount_path.clone(),
+            links,
+            body_buffer_size: self.body_buffer_size,
+            concurrency: self.concurrency.clone(),
+            deadline: self.deadline,
+            error_pages: self.error_pages.clone(),
+            memo: self.etag_memo.map(EtagMemo::new),
+            maintenance: self.maintenance.clone(),
+            selection: self.selection.clone(),
+            counter: Cell::new(0),
+        })))
+    }
 }
 
 impl Wrappable for Chain {
     /// See [`Chain::wrap`] for more information.
     fn wrap_with<M, B>(mut self, middleware: M) -> Chain
     where
-        M: Transform<
-                HttpService,
-                ServiceRequest,
-                Response = ServiceResponse<B>,
-                Error = Error,
-                InitError = (),
-            > + 'static,
+        M: Transform<HttpService, ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+            + 'static,
+        M::InitError: Debug,
         B: MessageBody + 'static,
     {
         let prefix = self.mount_path.clone();
@@ -182,7 +361,7 @@ impl Default for Chain {
 impl From<Link> for Chain {
     /// Convert link into single linked chain.
     fn from(mut value: Link) -> Self {
-        let prefix = value.prefix.clone();
+        let prefix = value.prefix.as_str().to_owned();
         let guards: Vec<_> = value.guards.drain(0..).collect();
         let next: Vec<_> = value.next.clone();
         let mut chain = Self::new(&prefix).link(value);
@@ -229,18 +408,6 @@ impl ServiceFactory<ServiceRequest> for Chain {
             panic!("Chain contains no links!")
         }
         let this = self.clone();
-        Box::pin(async move {
-            let mut links = vec![];
-            for link in this.links {
-                match link.inner().await {
-                    Ok(link) => links.push(link),
-                    Err(_) => return Err(()),
-                }
-            }
-            Ok(ChainService(Rc::new(ChainInner {
-                links,
-                body_buffer_size: this.body_buffer_size,
-            })))
-        })
+        Box::pin(async move { this.init().await.map_err(|err| tracing::error!("{err}")) })
     }
 }
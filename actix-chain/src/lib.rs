@@ -25,15 +25,35 @@
 //!         .link(Link::new(web::get().to(default)))
 //! );
 //! ```
+//!
+//! # Decision Log
+//!
+//! Debug builds emit a single structured `actix_chain::decisions` tracing
+//! event per request when the `ACTIX_CHAIN_DECISIONS` environment variable
+//! is set, listing the links considered with their guard and circuit
+//! results, the next predicate outcome of every link called and the final
+//! responder.
 
+mod auth;
+mod circuit;
+mod decision;
+mod error;
 mod factory;
 mod link;
+mod memo;
 pub mod next;
-mod payload;
+pub mod schedule;
+mod select;
 mod service;
+mod stats;
 mod wrap;
 
+pub use auth::{AuthRequest, AuthRequestService};
+pub use decision::DECISION_LOG_ENV;
+pub use error::InitError;
 pub use factory::Chain;
 pub use link::Link;
+pub use select::Selection;
 pub use service::ChainService;
+pub use stats::{ChainStats, LinkStats};
 pub use wrap::Wrappable;
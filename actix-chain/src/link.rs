@@ -1,23 +1,42 @@
-use std::{rc::Rc, str::FromStr};
+use std::{
+    fmt::Debug,
+    pin::Pin,
+    rc::Rc,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
 
+use actix_common::{ErrorPages, Identity, PathPrefix};
 use actix_service::{IntoServiceFactory, ServiceFactory, ServiceFactoryExt, Transform, boxed};
 use actix_web::{
-    Error, HttpResponse,
-    body::MessageBody,
+    Error, HttpMessage, HttpRequest, HttpResponse,
+    body::{BodySize, BoxBody, MessageBody},
     dev::{ServiceRequest, ServiceResponse},
     guard::{Guard, GuardContext},
-    http::{StatusCode, Uri, header, uri::PathAndQuery},
+    http::{
+        StatusCode, Uri,
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        uri::PathAndQuery,
+    },
     middleware::Compat,
     mime,
+    web::{Bytes, BytesMut},
 };
 
 use crate::{
     Chain,
-    next::{IsStatus, Next},
+    circuit::Circuit,
+    next::{IsStatus, Next, NextBody, NextWithRequest},
     service::{HttpNewService, HttpService},
+    stats::Counters,
     wrap::Wrappable,
 };
 
+/// Default maximum response body size buffered for [`Link::next_body`]
+const DEFAULT_NEXT_BODY_LIMIT: usize = 1024 * 1024;
+
 /// A single [`Link`] in the greater [`Chain`](crate::Chain)
 ///
 /// Wraps an Actix-Web service factory with details on when the service should
@@ -40,20 +59,40 @@ use crate::{
 /// ```
 #[derive(Clone)]
 pub struct Link {
-    pub(crate) prefix: String,
+    pub(crate) prefix: PathPrefix,
     pub(crate) guards: Vec<Rc<dyn Guard>>,
-    pub(crate) next: Vec<Rc<dyn Next>>,
+    pub(crate) next: Vec<Rc<dyn NextWithRequest>>,
+    pub(crate) next_body: Vec<Rc<dyn NextBody>>,
+    pub(crate) next_body_limit: usize,
+    pub(crate) stream_passthrough: bool,
+    pub(crate) retries: usize,
+    pub(crate) backoff: Duration,
+    pub(crate) retry_on: Vec<Rc<dyn Next>>,
+    pub(crate) weight: u32,
+    pub(crate) merge: Vec<HeaderName>,
+    pub(crate) circuit: Option<(usize, Duration)>,
+    pub(crate) content_types: Vec<mime::Mime>,
+    pub(crate) max_body: Option<usize>,
+    pub(crate) auth_scheme: Option<String>,
+    pub(crate) auth_challenge: Option<String>,
+    pub(crate) auth_request: bool,
+    pub(crate) counters: Arc<Counters>,
     pub(crate) service: Rc<HttpNewService>,
 }
 
 #[inline]
-fn box_factory<F, U>(service: F) -> Rc<HttpNewService>
+pub(crate) fn box_factory<F, U>(service: F) -> Rc<HttpNewService>
 where
     F: IntoServiceFactory<U, ServiceRequest>,
     U: ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = Error>
         + 'static,
+    U::InitError: Debug,
 {
-    Rc::new(boxed::factory(service.into_factory().map_init_err(|_| ())))
+    Rc::new(boxed::factory(
+        service
+            .into_factory()
+            .map_init_err(|err| format!("{err:?}")),
+    ))
 }
 
 impl Link {
@@ -65,11 +104,27 @@ impl Link {
         F: IntoServiceFactory<U, ServiceRequest>,
         U: ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = Error>
             + 'static,
+        U::InitError: Debug,
     {
         Self {
-            prefix: String::new(),
+            prefix: PathPrefix::default(),
             guards: Vec::new(),
             next: Vec::new(),
+            next_body: Vec::new(),
+            next_body_limit: DEFAULT_NEXT_BODY_LIMIT,
+            stream_passthrough: false,
+            retries: 0,
+            backoff: Duration::ZERO,
+            retry_on: Vec::new(),
+            weight: 1,
+            merge: Vec::new(),
+            circuit: None,
+            content_types: Vec::new(),
+            max_body: None,
+            auth_scheme: None,
+            auth_challenge: None,
+            auth_request: false,
+            counters: Arc::default(),
             service: box_factory(service),
         }
     }
@@ -78,7 +133,26 @@ impl Link {
     ///
     /// The prefix is the root URL at which the service is used.
     /// For example, /assets will serve files at example.com/assets/....
-    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+    ///
+    /// Prefixes match on segment boundaries, so `/assets` never matches
+    /// `/assets-old`. Pass a [`PathPrefix`] to configure trailing slash
+    /// handling and case-insensitivity.
+    ///
+    /// Prefixes may contain dynamic segments such as `/users/{id}/files`
+    /// whose captured values are exposed to the service through
+    /// [`HttpRequest::match_info`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_web::web;
+    /// use actix_common::PathPrefix;
+    /// use actix_chain::Link;
+    ///
+    /// Link::new(web::to(|| async { "assets" }))
+    ///     .prefix(PathPrefix::new("/Assets").case_insensitive(true));
+    /// ```
+    pub fn prefix<P: Into<PathPrefix>>(mut self, prefix: P) -> Self {
         self.prefix = prefix.into();
         self
     }
@@ -111,6 +185,42 @@ impl Link {
         self
     }
 
+    /// Only evaluate the link for requests with a matching `Content-Type`.
+    ///
+    /// Parameters such as `charset` are ignored and a wildcard subtype
+    /// (e.g. `text/*`) matches any subtype. May be called multiple times
+    /// to accept several content types. Requests that do not match are
+    /// forwarded to the next [`Link`] without calling the service.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{mime, web};
+    /// use actix_chain::Link;
+    ///
+    /// async fn index() -> &'static str {
+    ///     "Hello world!"
+    /// }
+    ///
+    /// Link::new(web::post().to(index))
+    ///     .content_type(mime::APPLICATION_JSON);
+    /// ```
+    pub fn content_type(mut self, content_type: mime::Mime) -> Self {
+        self.content_types.push(content_type);
+        self
+    }
+
+    /// Only evaluate the link for requests with a body of at most `limit` bytes.
+    ///
+    /// The check relies on the `Content-Length` header so the body is never
+    /// read. Chunked requests without a declared length are forwarded to the
+    /// next [`Link`] since their size cannot be known in advance.
+    ///
+    /// Default is no limit.
+    pub fn max_body(mut self, limit: usize) -> Self {
+        self.max_body = Some(limit);
+        self
+    }
+
     /// Configure when a [`Link`] should forward to the next chain
     /// instead of returning its [`ServiceResponse`](actix_web::dev::ServiceResponse).
     ///
@@ -120,6 +230,9 @@ impl Link {
     /// The default [`Link`] behavior is to continue down the chain
     /// on "404 Not Found" and "405 Method Not Allowed" responses only.
     ///
+    /// Criteria may also inspect the original request by implementing
+    /// [`NextWithRequest`], such as [`IfMethod`](crate::next::IfMethod).
+    ///
     /// # Examples
     /// ```
     /// use actix_web::{http::StatusCode, web};
@@ -134,12 +247,238 @@ impl Link {
     /// ```
     pub fn next<N>(mut self, next: N) -> Self
     where
-        N: Next + 'static,
+        N: NextWithRequest + 'static,
     {
         self.next.push(Rc::new(next));
         self
     }
 
+    /// Configure body based criteria for when a [`Link`] should forward
+    /// to the next chain.
+    ///
+    /// Body criteria are only evaluated when the criteria supplied via
+    /// [`Link::next`] did not already match, and require the response
+    /// body to be buffered in memory up to [`Link::next_body_limit`].
+    /// See [`Link::stream_passthrough`] to avoid buffering streaming
+    /// responses. Responses of the last matching link are never buffered.
+    ///
+    /// Default is no body criteria.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::web;
+    /// use actix_chain::{Link, next::BodyContains};
+    ///
+    /// async fn index() -> &'static str {
+    ///     "Hello world!"
+    /// }
+    ///
+    /// Link::new(web::get().to(index))
+    ///     .next_body(BodyContains::new("maintenance mode"));
+    /// ```
+    pub fn next_body<N>(mut self, next: N) -> Self
+    where
+        N: NextBody + 'static,
+    {
+        self.next_body.push(Rc::new(next));
+        self
+    }
+
+    /// Maximum response body size buffered to evaluate [`Link::next_body`]
+    /// criteria.
+    ///
+    /// Larger responses never fall through on body criteria and are sent
+    /// to the client as-is.
+    ///
+    /// Default is 1MiB.
+    pub fn next_body_limit(mut self, bytes: usize) -> Self {
+        self.next_body_limit = bytes;
+        self
+    }
+
+    /// Decide fall-through for streaming responses using head-only
+    /// criteria and stream the body through untouched.
+    ///
+    /// When enabled, criteria supplied via [`Link::next_body`] are
+    /// skipped for responses without a known body size.
+    ///
+    /// Default is disabled.
+    pub fn stream_passthrough(mut self, passthrough: bool) -> Self {
+        self.stream_passthrough = passthrough;
+        self
+    }
+
+    /// Re-invoke the link up to `retries` times when its response matches
+    /// a retry predicate, before falling through to the next link once
+    /// all retries are exhausted.
+    ///
+    /// The delay between attempts starts at `backoff` and doubles after
+    /// each attempt. Request bodies are replayed from the chain buffer.
+    ///
+    /// Default is no retries.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_web::{http::StatusCode, web};
+    /// use actix_chain::{Link, next::IsStatus};
+    ///
+    /// async fn index() -> &'static str {
+    ///     "Hello world!"
+    /// }
+    ///
+    /// Link::new(web::get().to(index))
+    ///     .retries(3, Duration::from_millis(100))
+    ///     .retry_on(IsStatus(StatusCode::SERVICE_UNAVAILABLE));
+    /// ```
+    pub fn retries(mut self, retries: usize, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Configure which responses are retried when [`Link::retries`] is set.
+    ///
+    /// The default is to retry "502 Bad Gateway", "503 Service Unavailable"
+    /// and "504 Gateway Timeout" responses only.
+    pub fn retry_on<N>(mut self, next: N) -> Self
+    where
+        N: Next + 'static,
+    {
+        self.retry_on.push(Rc::new(next));
+        self
+    }
+
+    /// Set the relative weight used when the [`Chain`](crate::Chain)
+    /// selects links by a [`Selection`](crate::Selection) strategy.
+    ///
+    /// Links with a weight of zero are never selected first but are
+    /// still evaluated as fallbacks.
+    ///
+    /// Default is 1.
+    pub fn weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Carry the specified header over from this link's response into the
+    /// final response when the link falls through to the next link.
+    ///
+    /// Useful when a fallback must preserve cookies or diagnostics set by
+    /// an earlier attempt. Carried headers are appended to the final
+    /// response rather than replacing existing values.
+    ///
+    /// Default is no headers are carried over.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{http::header, web};
+    /// use actix_chain::Link;
+    ///
+    /// async fn index() -> &'static str {
+    ///     "Hello world!"
+    /// }
+    ///
+    /// Link::new(web::get().to(index))
+    ///     .merge_header(header::SET_COOKIE);
+    /// ```
+    pub fn merge_header(mut self, name: HeaderName) -> Self {
+        self.merge.push(name);
+        self
+    }
+
+    /// Temporarily skip the link after `threshold` consecutive
+    /// fall-throughs or errors.
+    ///
+    /// While the circuit is open the link is not evaluated at all.
+    /// Once `cooldown` elapses a single request is let through as a probe,
+    /// which closes the circuit again on success or re-opens it on failure.
+    ///
+    /// Default is no circuit breaker.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_web::web;
+    /// use actix_chain::Link;
+    ///
+    /// async fn index() -> &'static str {
+    ///     "Hello world!"
+    /// }
+    ///
+    /// Link::new(web::get().to(index))
+    ///     .circuit_breaker(5, Duration::from_secs(30));
+    /// ```
+    pub fn circuit_breaker(mut self, threshold: usize, cooldown: Duration) -> Self {
+        self.circuit = Some((threshold, cooldown));
+        self
+    }
+
+    /// Require the request to be authenticated before the link runs.
+    ///
+    /// Requests without an `Authorization` header using the specified
+    /// scheme, or a validated [`Identity`](actix_common::Identity) in the
+    /// request extensions, are answered with `401 Unauthorized`. Unlike a
+    /// guard the rejection is final and does not fall through to the next
+    /// link, so only the protected links of a chain need the requirement.
+    ///
+    /// Default is no authentication requirement.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::web;
+    /// use actix_chain::Link;
+    ///
+    /// async fn index() -> &'static str {
+    ///     "Hello world!"
+    /// }
+    ///
+    /// Link::new(web::get().to(index))
+    ///     .prefix("/admin")
+    ///     .require_auth("Bearer")
+    ///     .auth_challenge(r#"Bearer realm="admin""#);
+    /// ```
+    pub fn require_auth(mut self, scheme: &str) -> Self {
+        self.auth_scheme = Some(scheme.to_owned());
+        self
+    }
+
+    /// Set the `WWW-Authenticate` challenge sent with `401 Unauthorized`
+    /// responses when [`Link::require_auth`] is set.
+    ///
+    /// Default is the authentication scheme.
+    pub fn auth_challenge(mut self, challenge: &str) -> Self {
+        self.auth_challenge = Some(challenge.to_owned());
+        self
+    }
+
+    /// Apply a rewrite pass to the request before the link's service runs.
+    ///
+    /// Rewrites are scoped to this link only, and the original URI is
+    /// restored before the request is forwarded to the next [`Link`].
+    /// Redirects and status responses produced by the rules become the
+    /// link's response and are subject to the [`Link::next`] criteria.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::web;
+    /// use actix_chain::Link;
+    /// use actix_rewrite::Engine;
+    ///
+    /// async fn index() -> &'static str {
+    ///     "Hello world!"
+    /// }
+    ///
+    /// let engine = Engine::new()
+    ///     .rules("RewriteRule ^/old/(.*) /new/$1 [L]")
+    ///     .expect("invalid rules");
+    /// Link::new(web::get().to(index)).rewrite(engine);
+    /// ```
+    #[cfg(feature = "rewrite")]
+    pub fn rewrite(self, engine: actix_rewrite::Engine) -> Self {
+        self.wrap_with(engine.middleware())
+    }
+
     /// Registers a link specific middleware.
     ///
     /// Wrapping a link advantagously does not construct
@@ -165,34 +504,65 @@ impl Link {
     #[inline]
     pub fn wrap<M, B>(self, middleware: M) -> Self
     where
-        M: Transform<
-                HttpService,
-                ServiceRequest,
-                Response = ServiceResponse<B>,
-                Error = Error,
-                InitError = (),
-            > + 'static,
+        M: Transform<HttpService, ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+            + 'static,
+        M::InitError: Debug,
         B: MessageBody + 'static,
     {
         self.wrap_with(middleware)
     }
 
     /// Convert public [`Link`] builder into [`LinkInner`]
-    pub(crate) async fn inner(&self) -> Result<LinkInner, ()> {
+    pub(crate) async fn inner(&self) -> Result<LinkInner, String> {
         let guard = match self.guards.is_empty() {
             true => None,
             false => Some(AllGuard(self.guards.clone())),
         };
-        let next: Vec<Rc<dyn Next>> = match self.next.is_empty() {
+        let next: Vec<Rc<dyn NextWithRequest>> = match self.next.is_empty() {
             true => vec![
                 IsStatus::rc(StatusCode::NOT_FOUND),
                 IsStatus::rc(StatusCode::METHOD_NOT_ALLOWED),
             ],
             false => self.next.clone(),
         };
+        let retry_on: Vec<Rc<dyn Next>> = match self.retry_on.is_empty() {
+            true => vec![
+                IsStatus::rc(StatusCode::BAD_GATEWAY),
+                IsStatus::rc(StatusCode::SERVICE_UNAVAILABLE),
+                IsStatus::rc(StatusCode::GATEWAY_TIMEOUT),
+            ],
+            false => self.retry_on.clone(),
+        };
+        let auth = self.auth_scheme.as_ref().map(|scheme| {
+            let challenge = self.auth_challenge.as_deref().unwrap_or(scheme);
+            let challenge = HeaderValue::from_str(challenge).unwrap_or_else(|_| {
+                tracing::error!("invalid auth challenge: {challenge:?}");
+                HeaderValue::from_str(scheme).unwrap_or(HeaderValue::from_static("Basic"))
+            });
+            AuthRequirement {
+                scheme: scheme.clone(),
+                challenge,
+            }
+        });
         Ok(LinkInner {
             guard,
+            auth,
             next,
+            next_body: self.next_body.clone(),
+            next_body_limit: self.next_body_limit,
+            stream_passthrough: self.stream_passthrough,
+            retries: self.retries,
+            backoff: self.backoff,
+            retry_on,
+            weight: self.weight,
+            merge: self.merge.clone(),
+            circuit: self
+                .circuit
+                .map(|(threshold, cooldown)| Circuit::new(threshold, cooldown)),
+            content_types: self.content_types.clone(),
+            max_body: self.max_body,
+            auth_request: self.auth_request,
+            counters: self.counters.clone(),
             prefix: self.prefix.clone(),
             service: Rc::new(self.service.new_service(()).await?),
         })
@@ -203,17 +573,25 @@ impl Wrappable for Link {
     /// See [`Link::wrap`] for more information.
     fn wrap_with<M, B>(mut self, middleware: M) -> Self
     where
-        M: Transform<
-                HttpService,
-                ServiceRequest,
-                Response = ServiceResponse<B>,
-                Error = Error,
-                InitError = (),
-            > + 'static,
+        M: Transform<HttpService, ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+            + 'static,
+        M::InitError: Debug,
         B: MessageBody + 'static,
     {
-        let svc = actix_service::apply(Compat::new(middleware), self.service.clone());
-        self.service = box_factory(svc);
+        let middleware = Rc::new(Compat::new(middleware));
+        let factory = self.service.clone();
+        let svc = actix_service::fn_factory(move || {
+            let middleware = middleware.clone();
+            let factory = factory.clone();
+            async move {
+                let service = factory.new_service(()).await?;
+                middleware
+                    .new_transform(service)
+                    .await
+                    .map_err(|err| format!("link middleware failed to initialize: {err:?}"))
+            }
+        });
+        self.service = Rc::new(boxed::factory(svc));
         self
     }
 }
@@ -223,7 +601,7 @@ impl From<Chain> for Link {
         let prefix = value.mount_path.clone();
         let guards: Vec<_> = value.guards.drain(0..).collect();
         let next: Vec<_> = value.next.drain(0..).collect();
-        let mut link = Self::new(value).prefix(&prefix);
+        let mut link = Self::new(value).prefix(prefix);
         link.guards = guards;
         link.next = next;
         link
@@ -239,21 +617,82 @@ impl Guard for AllGuard {
     }
 }
 
+/// Authentication required before calling a link
+struct AuthRequirement {
+    scheme: String,
+    challenge: HeaderValue,
+}
+
+impl AuthRequirement {
+    /// Check if the request carries credentials or a validated identity
+    fn is_met(&self, req: &ServiceRequest) -> bool {
+        if req.extensions().contains::<Identity>() {
+            return true;
+        }
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .is_some_and(|(scheme, credentials)| {
+                scheme.eq_ignore_ascii_case(&self.scheme) && !credentials.trim().is_empty()
+            })
+    }
+}
+
 /// Default 404 Response when service is unable to respond
 #[inline]
-pub(crate) fn default_response(req: ServiceRequest) -> ServiceResponse {
-    req.into_response(
-        HttpResponse::NotFound()
+pub(crate) fn default_response(req: ServiceRequest, pages: Option<&ErrorPages>) -> ServiceResponse {
+    let res = match pages {
+        Some(pages) => pages.render(StatusCode::NOT_FOUND, req.path()),
+        None => HttpResponse::NotFound()
             .insert_header(header::ContentType(mime::TEXT_PLAIN_UTF_8))
             .body("Not Found"),
-    )
+    };
+    req.into_response(res)
+}
+
+/// Response body replaying the already read bytes before the remaining body
+struct PrefixedBody(Option<Bytes>, BoxBody);
+
+impl MessageBody for PrefixedBody {
+    type Error = Box<dyn std::error::Error>;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        if let Some(head) = this.0.take() {
+            return Poll::Ready(Some(Ok(head)));
+        }
+        Pin::new(&mut this.1).poll_next(cx)
+    }
 }
 
 pub(crate) struct LinkInner {
-    prefix: String,
+    prefix: PathPrefix,
     guard: Option<AllGuard>,
+    auth: Option<AuthRequirement>,
     pub(crate) service: Rc<HttpService>,
-    pub(crate) next: Vec<Rc<dyn Next>>,
+    pub(crate) next: Vec<Rc<dyn NextWithRequest>>,
+    pub(crate) next_body: Vec<Rc<dyn NextBody>>,
+    pub(crate) next_body_limit: usize,
+    pub(crate) stream_passthrough: bool,
+    pub(crate) retries: usize,
+    pub(crate) backoff: Duration,
+    pub(crate) retry_on: Vec<Rc<dyn Next>>,
+    pub(crate) weight: u32,
+    pub(crate) merge: Vec<HeaderName>,
+    pub(crate) circuit: Option<Circuit>,
+    content_types: Vec<mime::Mime>,
+    max_body: Option<usize>,
+    pub(crate) auth_request: bool,
+    pub(crate) counters: Arc<Counters>,
 }
 
 impl LinkInner {
@@ -263,22 +702,150 @@ impl LinkInner {
             return None;
         }
         let mut parts = uri.clone().into_parts();
-        parts.path_and_query = parts
-            .path_and_query
-            .and_then(|pq| PathAndQuery::from_str(pq.as_str().strip_prefix(&self.prefix)?).ok());
+        parts.path_and_query = parts.path_and_query.and_then(|pq| {
+            let path = match self.prefix.strip(pq.path())? {
+                "" => "/",
+                path => path,
+            };
+            let pq = match pq.query() {
+                Some(query) => format!("{path}?{query}"),
+                None => path.to_owned(),
+            };
+            PathAndQuery::from_str(&pq).ok()
+        });
         Uri::from_parts(parts).ok()
     }
 
+    /// Add the values of dynamic prefix segments to the request match info
+    pub(crate) fn capture(&self, req: &mut ServiceRequest) {
+        for (name, value) in self.prefix.captures(req.uri().path()) {
+            req.match_info_mut().add_static(name, value);
+        }
+    }
+
     /// Check if request path matches prefix and any guards are met
     #[inline]
     pub(crate) fn matches(&self, path: &str, ctx: &GuardContext) -> bool {
-        path.starts_with(&self.prefix) && self.guard.as_ref().map(|g| !g.check(ctx)).unwrap_or(true)
+        self.prefix.matches(path)
+            && self.guard.as_ref().map(|g| g.check(ctx)).unwrap_or(true)
+            && self.accepts(ctx.head().headers())
+    }
+
+    /// Check if request content-type and body size are accepted by the link
+    fn accepts(&self, headers: &HeaderMap) -> bool {
+        if !self.content_types.is_empty() {
+            let Some(content_type) = headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<mime::Mime>().ok())
+            else {
+                return false;
+            };
+            let matched = self.content_types.iter().any(|expected| {
+                expected.type_() == content_type.type_()
+                    && (expected.subtype() == mime::STAR
+                        || expected.subtype() == content_type.subtype())
+            });
+            if !matched {
+                return false;
+            }
+        }
+        if let Some(limit) = self.max_body {
+            let length = match headers.get(header::CONTENT_LENGTH) {
+                Some(value) => value.to_str().ok().and_then(|v| v.parse::<usize>().ok()),
+                None if headers.contains_key(header::TRANSFER_ENCODING) => None,
+                None => Some(0),
+            };
+            return length.map(|length| length <= limit).unwrap_or(false);
+        }
+        true
+    }
+
+    /// Build the `401 Unauthorized` response if the request does not meet
+    /// the link's authentication requirement
+    pub(crate) fn unauthorized(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        let auth = self.auth.as_ref()?;
+        if auth.is_met(req) {
+            return None;
+        }
+        Some(
+            HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, auth.challenge.clone()))
+                .finish(),
+        )
     }
 
     /// Check if response is invalid, and next link should execute
     #[inline]
-    pub(crate) fn go_next(&self, res: &HttpResponse) -> bool {
-        self.next.iter().any(|next| next.next(res))
+    pub(crate) fn go_next(&self, req: &HttpRequest, res: &HttpResponse) -> bool {
+        self.next.iter().any(|next| next.next(req, res))
+    }
+
+    /// Check if response body is invalid, and next link should execute
+    ///
+    /// Buffers the response body unless no body criteria are configured
+    /// or the response is streamed through untouched.
+    pub(crate) async fn go_next_body(
+        &self,
+        res: HttpResponse,
+    ) -> Result<(HttpResponse, bool), Error> {
+        if self.next_body.is_empty()
+            || (self.stream_passthrough && matches!(res.body().size(), BodySize::Stream))
+        {
+            return Ok((res, false));
+        }
+        if let BodySize::Sized(size) = res.body().size()
+            && size > self.next_body_limit as u64
+        {
+            return Ok((res, false));
+        }
+        let (res, mut body) = res.into_parts();
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await {
+            buf.extend_from_slice(&chunk.map_err(actix_web::error::ErrorInternalServerError)?);
+            if buf.len() > self.next_body_limit {
+                tracing::debug!("response exceeds next body limit, skipping body criteria");
+                let body = PrefixedBody(Some(buf.freeze()), body);
+                return Ok((res.set_body(BoxBody::new(body)), false));
+            }
+        }
+        let body = buf.freeze();
+        let res = res.set_body(BoxBody::new(body.clone()));
+        let next = self.next_body.iter().any(|next| next.next(&res, &body));
+        Ok((res, next))
+    }
+
+    /// Check if response should be retried against the same link
+    #[inline]
+    pub(crate) fn go_retry(&self, res: &HttpResponse) -> bool {
+        self.retry_on.iter().any(|next| next.next(res))
+    }
+
+    /// Check if the link circuit is open and the link should be skipped
+    #[inline]
+    pub(crate) fn is_open(&self) -> bool {
+        self.circuit.as_ref().map(|c| c.is_open()).unwrap_or(false)
+    }
+
+    /// Report link outcome to the circuit breaker if configured
+    #[inline]
+    pub(crate) fn report(&self, ok: bool) {
+        if let Some(circuit) = self.circuit.as_ref() {
+            match circuit.report(ok) {
+                true => self.counters.trip(circuit.cooldown()),
+                false if ok => self.counters.close(),
+                false => {}
+            }
+        }
+    }
+
+    /// Collect headers to carry over into the final response
+    pub(crate) fn merge_into(&self, res: &HttpResponse, carried: &mut HeaderMap) {
+        for name in self.merge.iter() {
+            for value in res.headers().get_all(name) {
+                carried.append(name.clone(), value.clone());
+            }
+        }
     }
 
     /// Call inner service once and return [`actix_web::dev::ServiceResponse`]
@@ -287,13 +854,22 @@ impl LinkInner {
     pub(crate) async fn call_once(
         &self,
         mut req: ServiceRequest,
+        pages: Option<&ErrorPages>,
     ) -> Result<ServiceResponse, Error> {
         if !self.matches(req.uri().path(), &req.guard_ctx()) {
-            return Ok(default_response(req));
+            return Ok(default_response(req, pages));
+        }
+        if let Some(res) = self.unauthorized(&req) {
+            return Ok(req.into_response(res));
         }
+        self.capture(&mut req);
         if let Some(uri) = self.new_uri(req.uri()) {
             req.head_mut().uri = uri;
         }
-        self.service.call(req).await
+        self.counters.request();
+        self.service
+            .call(req)
+            .await
+            .inspect_err(|_| self.counters.error())
     }
 }
@@ -0,0 +1,131 @@
+//! ETag Memoization of Fallen-Through Responses
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{
+        Method, StatusCode,
+        header::{self, HeaderValue},
+    },
+};
+
+/// Maximum number of memoized ETags per worker
+const MAX_ENTRIES: usize = 4096;
+
+struct Entry {
+    etag: HeaderValue,
+    cache_control: Option<HeaderValue>,
+    expires: Instant,
+}
+
+/// Compare entity tags using the weak comparison function
+fn weak_eq(a: &str, b: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    opaque(a) == opaque(b)
+}
+
+/// ETags of responses produced by links after earlier links fell through
+///
+/// Conditional requests matching a memoized ETag are answered with
+/// `304 Not Modified` without invoking any link past authentication.
+pub(crate) struct EtagMemo {
+    ttl: Duration,
+    entries: RefCell<HashMap<String, Entry>>,
+}
+
+impl EtagMemo {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: RefCell::default(),
+        }
+    }
+
+    /// Memo key of the request if eligible
+    fn key(req: &HttpRequest) -> Option<String> {
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return None;
+        }
+        let target = req.uri().path_and_query().map(|pq| pq.as_str());
+        Some(format!(
+            "{} {}",
+            req.connection_info().host(),
+            target.unwrap_or("/")
+        ))
+    }
+
+    /// Build the `304 Not Modified` response if the request matches a memoized ETag
+    pub(crate) fn lookup(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        let condition = req
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())?;
+        let key = Self::key(req)?;
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.get(&key)?;
+        if entry.expires <= Instant::now() {
+            entries.remove(&key);
+            return None;
+        }
+        let etag = entry.etag.to_str().ok()?;
+        if !condition.split(',').any(|tag| weak_eq(tag, etag)) {
+            return None;
+        }
+        let mut res = HttpResponse::NotModified();
+        res.insert_header((header::ETAG, entry.etag.clone()));
+        if let Some(cache_control) = entry.cache_control.as_ref() {
+            res.insert_header((header::CACHE_CONTROL, cache_control.clone()));
+        }
+        Some(res.finish())
+    }
+
+    /// Record the response of a link answering after earlier links fell through
+    pub(crate) fn remember(&self, req: &HttpRequest, res: &HttpResponse) {
+        let Some(key) = Self::key(req) else {
+            return;
+        };
+        let headers = res.headers();
+        let cache_control = headers.get(header::CACHE_CONTROL);
+        let uncacheable = cache_control
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("no-store") || value.contains("private"));
+        let etag = headers.get(header::ETAG);
+        let mut entries = self.entries.borrow_mut();
+        let Some(etag) = etag.filter(|_| {
+            matches!(res.status(), StatusCode::OK | StatusCode::NOT_MODIFIED)
+                && !uncacheable
+                && !headers.contains_key(header::VARY)
+        }) else {
+            entries.remove(&key);
+            return;
+        };
+
+        let now = Instant::now();
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() >= MAX_ENTRIES {
+                return;
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                etag: etag.clone(),
+                cache_control: cache_control.cloned(),
+                expires: now + self.ttl,
+            },
+        );
+    }
+
+    /// Forget the memoized ETag once the first link answers the request itself
+    pub(crate) fn forget(&self, req: &HttpRequest) {
+        if let Some(key) = Self::key(req) {
+            self.entries.borrow_mut().remove(&key);
+        }
+    }
+}
@@ -3,8 +3,9 @@
 use std::rc::Rc;
 
 use actix_web::{
-    HttpResponse,
-    http::{StatusCode, header::HeaderName},
+    HttpRequest, HttpResponse,
+    http::{Method, StatusCode, header::HeaderName},
+    web::Bytes,
 };
 
 /// Response equivalent of [`actix_web::guard::Guard`].
@@ -16,6 +17,31 @@ pub trait Next {
     fn next(&self, res: &HttpResponse) -> bool;
 }
 
+/// Request aware equivalent of [`Next`].
+///
+/// Considers the original request alongside the response when deciding
+/// whether to forward the request to the next [`Link`](crate::Link).
+/// Every [`Next`] implementation is also a [`NextWithRequest`].
+pub trait NextWithRequest {
+    fn next(&self, req: &HttpRequest, res: &HttpResponse) -> bool;
+}
+
+impl<N: Next> NextWithRequest for N {
+    #[inline]
+    fn next(&self, _req: &HttpRequest, res: &HttpResponse) -> bool {
+        Next::next(self, res)
+    }
+}
+
+/// Body aware equivalent of [`Next`].
+///
+/// Registered via [`Link::next_body`](crate::Link::next_body) and only
+/// evaluated when the response head did not already trigger a fall-through.
+/// The response body is buffered in order to be inspected.
+pub trait NextBody {
+    fn next(&self, res: &HttpResponse, body: &Bytes) -> bool;
+}
+
 /// Simple [`StatusCode`] response guard.
 ///
 /// Blocks the response the specified status-code is present.
@@ -71,3 +97,67 @@ impl Next for HasHeader {
         res.headers().contains_key(&self.0)
     }
 }
+
+/// Request [`Method`] filter for another [`Next`] guard.
+///
+/// Only blocks the response when the request method is one of the
+/// specified methods and the inner guard also blocks the response.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::http::{Method, StatusCode};
+/// use actix_chain::next::{IfMethod, IsStatus};
+///
+/// // only GET requests fall through, POSTs keep the first response
+/// IfMethod::new([Method::GET], IsStatus(StatusCode::NOT_FOUND));
+/// ```
+pub struct IfMethod<N> {
+    methods: Vec<Method>,
+    next: N,
+}
+
+impl<N: Next> IfMethod<N> {
+    pub fn new<I>(methods: I, next: N) -> Self
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        Self {
+            methods: methods.into_iter().collect(),
+            next,
+        }
+    }
+}
+
+impl<N: Next> NextWithRequest for IfMethod<N> {
+    #[inline]
+    fn next(&self, req: &HttpRequest, res: &HttpResponse) -> bool {
+        self.methods.contains(req.method()) && self.next.next(res)
+    }
+}
+
+/// Simple response body guard.
+///
+/// Blocks the response if the body contains the specified bytes.
+///
+/// # Examples
+///
+/// ```
+/// use actix_chain::next::BodyContains;
+///
+/// BodyContains::new("maintenance mode");
+/// ```
+pub struct BodyContains(pub Bytes);
+
+impl BodyContains {
+    pub fn new<B: Into<Bytes>>(needle: B) -> Self {
+        Self(needle.into())
+    }
+}
+
+impl NextBody for BodyContains {
+    #[inline]
+    fn next(&self, _res: &HttpResponse, body: &Bytes) -> bool {
+        self.0.is_empty() || body.windows(self.0.len()).any(|w| w == self.0)
+    }
+}
@@ -0,0 +1,317 @@
+//! Time based guards for activating [`Link`](crate::Link) within windows
+
+use std::{fmt::Display, str::FromStr};
+
+use actix_web::{
+    HttpResponse,
+    guard::{Guard, GuardContext},
+};
+use jiff::{Timestamp, tz::TimeZone};
+use time::{OffsetDateTime, Time, UtcOffset};
+
+use crate::next::Next;
+
+/// Error returned when parsing an invalid [`DuringWindow`] expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleError {
+    /// Expression which failed to parse.
+    pub expr: String,
+    /// Description of the parsing failure.
+    pub reason: String,
+}
+
+impl ScheduleError {
+    fn new(expr: &str, reason: impl Into<String>) -> Self {
+        Self {
+            expr: expr.to_owned(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid schedule {:?}: {}", self.expr, self.reason)
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Set of allowed values for a single cron field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    bits: u64,
+    any: bool,
+}
+
+impl Field {
+    /// Parse a comma separated list of values, ranges and steps
+    fn parse(field: &str, min: u8, max: u8, names: &[&str], offset: u8) -> Result<Self, String> {
+        let value = |v: &str| -> Result<u8, String> {
+            let name = v.to_ascii_lowercase();
+            if let Some(pos) = names.iter().position(|n| *n == name) {
+                return Ok(pos as u8 + offset);
+            }
+            v.parse::<u8>()
+                .ok()
+                .filter(|v| (min..=max).contains(v))
+                .ok_or_else(|| format!("value {v:?} out of range {min}-{max}"))
+        };
+
+        let mut bits = 0u64;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => match step.parse::<u8>() {
+                    Ok(step) if step > 0 => (range, step),
+                    _ => return Err(format!("invalid step {step:?}")),
+                },
+                None => (part, 1),
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                range => match range.split_once('-') {
+                    Some((start, end)) => (value(start)?, value(end)?),
+                    None if step > 1 => (value(range)?, max),
+                    None => (value(range)?, value(range)?),
+                },
+            };
+            if start > end {
+                return Err(format!("invalid range {range:?}"));
+            }
+            for v in (start..=end).step_by(step as usize) {
+                bits |= 1 << v;
+            }
+        }
+        Ok(Self {
+            bits,
+            any: field.starts_with('*'),
+        })
+    }
+
+    #[inline]
+    fn contains(&self, value: u8) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+/// Parsed five field cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cron {
+    minute: Field,
+    hour: Field,
+    day: Field,
+    month: Field,
+    weekday: Field,
+}
+
+impl Cron {
+    fn parse(expr: &str) -> Result<Self, ScheduleError> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(ScheduleError::new(expr, "expected five fields"));
+        };
+        let err = |reason| ScheduleError::new(expr, reason);
+        let mut weekday = Field::parse(weekday, 0, 7, &WEEKDAYS, 0).map_err(err)?;
+        // sunday may be written as either 0 or 7
+        if weekday.contains(7) {
+            weekday.bits |= 1;
+        }
+        Ok(Self {
+            minute: Field::parse(minute, 0, 59, &[], 0).map_err(err)?,
+            hour: Field::parse(hour, 0, 23, &[], 0).map_err(err)?,
+            day: Field::parse(day, 1, 31, &[], 0).map_err(err)?,
+            month: Field::parse(month, 1, 12, &MONTHS, 1).map_err(err)?,
+            weekday,
+        })
+    }
+
+    fn matches(&self, at: OffsetDateTime) -> bool {
+        let day = self.day.contains(at.day());
+        let weekday = self
+            .weekday
+            .contains(at.weekday().number_days_from_sunday());
+        // standard cron semantics when both day fields are restricted
+        let day = match (self.day.any, self.weekday.any) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        day && self.minute.contains(at.minute())
+            && self.hour.contains(at.hour())
+            && self.month.contains(at.month() as u8)
+    }
+}
+
+/// Time zone windows are evaluated in
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Zone {
+    Fixed(UtcOffset),
+    Named(TimeZone),
+}
+
+impl Zone {
+    /// Resolve the utc offset of the zone at the specified moment
+    fn offset(&self, at: OffsetDateTime) -> UtcOffset {
+        match self {
+            Self::Fixed(offset) => *offset,
+            Self::Named(tz) => Timestamp::from_second(at.unix_timestamp())
+                .ok()
+                .and_then(|ts| UtcOffset::from_whole_seconds(tz.to_offset(ts).seconds()).ok())
+                .unwrap_or(UtcOffset::UTC),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Window {
+    Cron(Box<Cron>),
+    Range(Time, Time),
+}
+
+/// Guard active during a recurring time window.
+///
+/// Windows are either a daily time range, which may wrap past midnight,
+/// or a standard five field cron expression (`minute hour day month
+/// weekday`) active during every matching minute. Cron fields support
+/// lists, ranges, steps and english month and weekday abbreviations.
+///
+/// The current time is evaluated on every request in the configured
+/// time zone, which defaults to UTC. Named time zones follow daylight
+/// saving time transitions while fixed offsets do not.
+///
+/// Implements both [`Guard`], to only consider a link during the window,
+/// and [`Next`], to fall through to the next link during the window.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::{HttpResponse, web};
+/// use actix_chain::{Chain, Link, schedule::DuringWindow};
+///
+/// async fn maintenance() -> HttpResponse {
+///     HttpResponse::ServiceUnavailable().body("scheduled maintenance")
+/// }
+///
+/// async fn index() -> &'static str {
+///     "Hello world!"
+/// }
+///
+/// // sundays between 02:00 and 03:59
+/// let window = DuringWindow::cron("* 2-3 * * sun").unwrap();
+/// Chain::default()
+///     .link(Link::new(web::to(maintenance)).guard(window))
+///     .link(Link::new(web::get().to(index)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuringWindow {
+    window: Window,
+    zone: Zone,
+}
+
+impl DuringWindow {
+    /// Create a window from a five field cron expression.
+    pub fn cron(expr: &str) -> Result<Self, ScheduleError> {
+        Ok(Self::new(Window::Cron(Box::new(Cron::parse(expr)?))))
+    }
+
+    /// Create a daily window from `start` until `end`.
+    ///
+    /// Windows with an end before the start wrap past midnight, and equal
+    /// start and end times cover the whole day.
+    pub fn between(start: Time, end: Time) -> Self {
+        Self::new(Window::Range(start, end))
+    }
+
+    fn new(window: Window) -> Self {
+        Self {
+            window,
+            zone: Zone::Fixed(UtcOffset::UTC),
+        }
+    }
+
+    /// Evaluate the window in the specified fixed UTC offset.
+    ///
+    /// The offset is not adjusted for daylight saving time.
+    ///
+    /// Default is UTC.
+    pub fn offset(mut self, offset: UtcOffset) -> Self {
+        self.zone = Zone::Fixed(offset);
+        self
+    }
+
+    /// Evaluate the window in the specified IANA time zone such as
+    /// `Europe/Berlin`, following its daylight saving time transitions.
+    ///
+    /// Zones are looked up in the system time zone database, falling back
+    /// to a bundled copy when it is unavailable.
+    ///
+    /// Default is UTC.
+    pub fn timezone(mut self, name: &str) -> Result<Self, ScheduleError> {
+        let tz = TimeZone::get(name).map_err(|err| ScheduleError::new(name, err.to_string()))?;
+        self.zone = Zone::Named(tz);
+        Ok(self)
+    }
+
+    /// Check if the window contains the specified moment.
+    pub fn contains(&self, at: OffsetDateTime) -> bool {
+        let at = at.to_offset(self.zone.offset(at));
+        match &self.window {
+            Window::Cron(cron) => cron.matches(at),
+            Window::Range(start, end) => {
+                let now = at.time();
+                match start < end {
+                    true => *start <= now && now < *end,
+                    false if start == end => true,
+                    false => *start <= now || now < *end,
+                }
+            }
+        }
+    }
+
+    /// Check if the window contains the current time.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.contains(OffsetDateTime::now_utc())
+    }
+}
+
+/// Parse either a `HH:MM-HH:MM` daily range or a cron expression.
+impl FromStr for DuringWindow {
+    type Err = ScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.contains(char::is_whitespace) {
+            return Self::cron(s);
+        }
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| ScheduleError::new(s, "expected HH:MM-HH:MM range"))?;
+        let time = |t: &str| {
+            let (hour, minute) = t.split_once(':')?;
+            Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()
+        };
+        match (time(start), time(end)) {
+            (Some(start), Some(end)) => Ok(Self::between(start, end)),
+            _ => Err(ScheduleError::new(s, "invalid time of day")),
+        }
+    }
+}
+
+impl Guard for DuringWindow {
+    #[inline]
+    fn check(&self, _ctx: &GuardContext<'_>) -> bool {
+        self.is_active()
+    }
+}
+
+impl Next for DuringWindow {
+    #[inline]
+    fn next(&self, _res: &HttpResponse) -> bool {
+        self.is_active()
+    }
+}
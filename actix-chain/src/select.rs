@@ -0,0 +1,107 @@
+//! Alternative Link Selection Strategies
+
+use std::{
+    cell::Cell,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use actix_web::{
+    dev::ServiceRequest,
+    http::header::{self, HeaderName},
+};
+
+/// Strategy used to pick which matching [`Link`](crate::Link) runs first.
+///
+/// Links other than the selected one keep their original order and are
+/// still used as fallbacks, so a two link chain acts as an A/B switch
+/// that falls back to the other variant when the selected one fails.
+/// Selection is weighted by [`Link::weight`](crate::Link::weight).
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::web;
+/// use actix_chain::{Chain, Link, Selection};
+///
+/// async fn a() -> &'static str {
+///     "A"
+/// }
+///
+/// async fn b() -> &'static str {
+///     "B"
+/// }
+///
+/// Chain::default()
+///     .selection(Selection::Cookie("variant".to_owned()))
+///     .link(Link::new(web::get().to(a)).weight(90))
+///     .link(Link::new(web::get().to(b)).weight(10));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub enum Selection {
+    /// Links are always evaluated in the order they were added.
+    #[default]
+    Ordered,
+    /// Links are distributed across requests in proportion to their weight.
+    Weighted,
+    /// Requests are assigned by a hash of the header value.
+    ///
+    /// Requests without the header are distributed by weight.
+    Header(HeaderName),
+    /// Requests are assigned by a hash of the cookie value.
+    ///
+    /// Requests without the cookie are distributed by weight.
+    Cookie(String),
+}
+
+/// Find the value of the named cookie within the request
+fn cookie<'a>(req: &'a ServiceRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn hash(value: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish() as usize
+}
+
+impl Selection {
+    /// Select the index of the link to evaluate first based on its weight
+    pub(crate) fn select(
+        &self,
+        req: &ServiceRequest,
+        weights: &[u32],
+        counter: &Cell<usize>,
+    ) -> Option<usize> {
+        let key = match self {
+            Self::Ordered => return None,
+            Self::Weighted => None,
+            Self::Header(name) => req.headers().get(name).map(|v| hash(v.as_bytes())),
+            Self::Cookie(name) => cookie(req, name).map(|v| hash(v.as_bytes())),
+        };
+        let key = key.unwrap_or_else(|| {
+            let count = counter.get();
+            counter.set(count.wrapping_add(1));
+            count
+        });
+
+        let total: usize = weights.iter().map(|w| *w as usize).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut point = key % total;
+        weights.iter().position(|weight| {
+            let weight = *weight as usize;
+            if point < weight {
+                return true;
+            }
+            point -= weight;
+            false
+        })
+    }
+}
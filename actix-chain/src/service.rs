@@ -1,19 +1,34 @@
-use std::{ops::Deref, rc::Rc};
+use std::{
+    cell::Cell,
+    ops::Deref,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
 
+#[cfg(feature = "opentelemetry")]
+use actix_common::telemetry::{SpanKind, SpanScope};
+use actix_common::{
+    Concurrency, Deadline, ErrorPages, Maintenance, body::BodyBuffer, metrics::Timer,
+};
 use actix_service::boxed::{BoxService, BoxServiceFactory};
 use actix_web::{
-    HttpMessage,
+    HttpMessage, HttpResponse,
     body::BoxBody,
-    dev::{self, Service, ServiceRequest, ServiceResponse},
-    error::Error,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    error::{Error, InternalError},
+    http::header::HeaderMap,
+    rt::time::Instant,
 };
 use futures_core::future::LocalBoxFuture;
 
+use crate::decision::{DecisionLog, Responder};
 use crate::link::{LinkInner, default_response};
-use crate::payload::PayloadRef;
+use crate::memo::EtagMemo;
+use crate::select::Selection;
 
 pub type HttpService = BoxService<ServiceRequest, ServiceResponse, Error>;
-pub type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, ()>;
+pub type HttpNewService = BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, String>;
 
 /// Assembled chain service.
 #[derive(Clone)]
@@ -28,8 +43,16 @@ impl Deref for ChainService {
 }
 
 pub struct ChainInner {
+    pub(crate) mount_path: String,
     pub(crate) links: Vec<LinkInner>,
     pub(crate) body_buffer_size: usize,
+    pub(crate) concurrency: Option<Concurrency>,
+    pub(crate) deadline: Option<Duration>,
+    pub(crate) error_pages: Option<ErrorPages>,
+    pub(crate) memo: Option<EtagMemo>,
+    pub(crate) maintenance: Option<Maintenance>,
+    pub(crate) selection: Selection,
+    pub(crate) counter: Cell<usize>,
 }
 
 impl Service<ServiceRequest> for ChainService {
@@ -37,65 +60,239 @@ impl Service<ServiceRequest> for ChainService {
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    dev::always_ready!();
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.concurrency.as_ref() {
+            Some(concurrency) => concurrency.poll_ready(cx).map_err(Error::from),
+            None => Poll::Ready(Ok(())),
+        }
+    }
 
-    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+    fn call(&self, req: ServiceRequest) -> Self::Future {
         let this = self.clone();
-        if self.links.len() == 1 {
-            return Box::pin(async move { this.links[0].call_once(req).await });
+        Box::pin(async move {
+            let timer = Timer::start("chain", &this.mount_path, req.request());
+            let res = match this.error_pages.as_ref() {
+                Some(pages) => {
+                    let path = req.path().to_owned();
+                    pages.recover(&path, this.serve(req).await)
+                }
+                None => this.serve(req).await,
+            };
+            timer.finish(&res);
+            res
+        })
+    }
+}
+
+impl ChainService {
+    /// Run the request through the matching links in order
+    async fn serve(&self, mut req: ServiceRequest) -> Result<ServiceResponse, Error> {
+        if let Some(res) = self
+            .maintenance
+            .as_ref()
+            .and_then(|maintenance| maintenance.check(req.request()))
+        {
+            return Ok(req.into_response(res));
+        }
+        let _permit = match self.concurrency.as_ref() {
+            Some(concurrency) => Some(concurrency.acquire().await?),
+            None => None,
+        };
+        let deadline = self.deadline.map(|budget| {
+            Deadline::after(budget).apply(req.request());
+            Instant::now() + budget
+        });
+        let mut log = DecisionLog::start(req.request());
+        if self.links.len() == 1
+            && self.links[0].retries == 0
+            && self.links[0].circuit.is_none()
+            && deadline.is_none()
+            && !log.is_enabled()
+        {
+            return self.links[0]
+                .call_once(req, self.error_pages.as_ref())
+                .await;
         }
 
-        Box::pin(async move {
-            let payload = req.take_payload();
-            let buf = PayloadRef::new(payload, this.body_buffer_size);
-            req.set_payload(buf.payload());
-
-            let ctx = req.guard_ctx();
-            let active_links: Vec<_> = this
-                .links
-                .iter()
-                .enumerate()
-                .filter(|(_, link)| link.matches(req.uri().path(), &ctx))
-                .collect();
-
-            let addr = req
-                .peer_addr()
-                .map(|addr| addr.to_string())
-                .unwrap_or_default();
-            tracing::debug!(
-                "{addr} {}/{} links matched {:?} {:?}",
-                active_links.len(),
-                this.links.len(),
-                req.method(),
-                req.uri()
-            );
-
-            let mut link_iter = active_links.into_iter().peekable();
-            while let Some((n, link)) = link_iter.next() {
+        let payload = req.take_payload();
+        let buf = BodyBuffer::new(payload, self.body_buffer_size);
+        req.set_payload(buf.payload());
+
+        let ctx = req.guard_ctx();
+        let mut active_links = Vec::new();
+        for (n, link) in self.links.iter().enumerate() {
+            let matched = link.matches(req.uri().path(), &ctx);
+            let open = matched && link.is_open();
+            if open {
+                tracing::debug!("skipping link {n} with open circuit");
+            }
+            log.consider(n, matched, open);
+            if matched && !open {
+                active_links.push((n, link));
+            }
+        }
+
+        let weights: Vec<_> = active_links.iter().map(|(_, link)| link.weight).collect();
+        if let Some(idx) = self.selection.select(&req, &weights, &self.counter) {
+            let selected = active_links.remove(idx);
+            active_links.insert(0, selected);
+        }
+
+        let addr = req
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        tracing::debug!(
+            "{addr} {}/{} links matched {:?} {:?}",
+            active_links.len(),
+            self.links.len(),
+            req.method(),
+            req.uri()
+        );
+
+        let mut carried = HeaderMap::new();
+        let mut best: Option<HttpResponse> = None;
+        let mut fell_through = false;
+        let mut memo_checked = false;
+        let mut link_iter = active_links.into_iter().peekable();
+        while let Some((n, link)) = link_iter.next() {
+            if let Some(res) = link.unauthorized(&req) {
+                tracing::debug!("{addr} link {n} requires authentication");
+                log.finish(&self.mount_path, Responder::Link(n));
+                return Ok(merge_headers(req.into_response(res), carried));
+            }
+            // memoized responses are only served once authentication passed
+            if !link.auth_request && !memo_checked {
+                memo_checked = true;
+                if let Some(res) = self
+                    .memo
+                    .as_ref()
+                    .and_then(|memo| memo.lookup(req.request()))
+                {
+                    log.finish(&self.mount_path, Responder::Memo);
+                    return Ok(merge_headers(req.into_response(res), carried));
+                }
+            }
+            let mut attempt = 0;
+            loop {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    tracing::debug!("{addr} deadline exceeded before link {n}");
+                    log.finish(&self.mount_path, Responder::Deadline);
+                    return match best {
+                        Some(res) => Ok(req.into_response(res)),
+                        None => Err(actix_common::Error::DeadlineExceeded.into()),
+                    };
+                }
                 tracing::debug!("{addr} calling link {n}");
-                let mut original_uri = None;
+                let original_uri = req.uri().clone();
+                let original_path = req.match_info().clone();
+                link.capture(&mut req);
                 if let Some(uri) = link.new_uri(req.uri()) {
-                    original_uri = Some(req.uri().clone());
                     tracing::debug!("{addr} updated uri {:?} -> {uri:?}", req.uri());
                     req.head_mut().uri = uri;
                 }
 
-                let res = link.service.call(req).await?;
-                let (http_req, http_res) = res.into_parts();
+                link.counters.request();
+                #[cfg(feature = "opentelemetry")]
+                let scope =
+                    SpanScope::enter(req.request(), format!("chain link {n}"), SpanKind::Internal);
+                let call = link.service.call(req);
+                let res = match deadline {
+                    Some(deadline) => {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        match actix_web::rt::time::timeout(remaining, call).await {
+                            Ok(res) => res,
+                            Err(_) => {
+                                tracing::debug!("{addr} deadline exceeded calling link {n}");
+                                link.counters.error();
+                                log.finish(&self.mount_path, Responder::Deadline);
+                                // the request was consumed by the link, so the best
+                                // response so far is returned as the error response
+                                let err = actix_common::Error::DeadlineExceeded;
+                                return Err(match best {
+                                    Some(res) => InternalError::from_response(err, res).into(),
+                                    None => err.into(),
+                                });
+                            }
+                        }
+                    }
+                    None => call.await,
+                };
+                #[cfg(feature = "opentelemetry")]
+                scope.exit(&res);
+
+                let res = res.inspect_err(|_| {
+                    link.counters.error();
+                    link.report(false);
+                    log.finish(&self.mount_path, Responder::Error(n));
+                })?;
+                let (http_req, mut http_res) = res.into_parts();
                 tracing::debug!("{addr} link {n} response={:?}", http_res.status());
-                if link_iter.peek().is_none() || !link.go_next(&http_res) {
-                    return Ok(ServiceResponse::new(http_req, http_res));
+                let retryable = link.retries > 0 && link.go_retry(&http_res);
+                let retry = retryable && attempt < link.retries;
+                let mut next = retryable || link.go_next(&http_req, &http_res);
+                if !next && link_iter.peek().is_some() {
+                    (http_res, next) = link.go_next_body(http_res).await?;
+                }
+                if !retry {
+                    link.report(!next);
+                }
+                log.outcome(n, attempt, http_res.status(), retry, next);
+                if !retry && (link_iter.peek().is_none() || !next) {
+                    if let Some(memo) = self.memo.as_ref() {
+                        match fell_through {
+                            true => memo.remember(&http_req, &http_res),
+                            false => memo.forget(&http_req),
+                        }
+                    }
+                    log.finish(&self.mount_path, Responder::Link(n));
+                    let res = ServiceResponse::new(http_req, http_res);
+                    return Ok(merge_headers(res, carried));
+                }
+                if !retry {
+                    fell_through = true;
+                    link.counters.fallthrough();
+                    link.merge_into(&http_res, &mut carried);
                 }
 
-                buf.get_mut().reset_stream();
+                if deadline.is_some() {
+                    best = Some(http_res);
+                }
+                buf.rewind();
                 req = ServiceRequest::from_parts(http_req, buf.payload());
 
-                if let Some(uri) = original_uri {
-                    req.head_mut().uri = uri;
+                req.head_mut().uri = original_uri;
+                *req.match_info_mut() = original_path;
+                if !retry {
+                    break;
                 }
+
+                let mut delay = link
+                    .backoff
+                    .saturating_mul(2u32.saturating_pow(attempt as u32));
+                if let Some(deadline) = deadline {
+                    delay = delay.min(deadline.saturating_duration_since(Instant::now()));
+                }
+                tracing::debug!("{addr} retrying link {n} in {delay:?}");
+                actix_web::rt::time::sleep(delay).await;
+                attempt += 1;
             }
+        }
 
-            Ok(default_response(req))
-        })
+        log.finish(&self.mount_path, Responder::Default);
+        Ok(merge_headers(
+            default_response(req, self.error_pages.as_ref()),
+            carried,
+        ))
+    }
+}
+
+/// Append headers carried over from fallen-through links
+#[inline]
+fn merge_headers(mut res: ServiceResponse, carried: HeaderMap) -> ServiceResponse {
+    let headers = res.headers_mut();
+    for (name, value) in carried {
+        headers.append(name, value);
     }
+    res
 }
@@ -0,0 +1,116 @@
+//! Link Statistics Shared Across Workers
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Milliseconds since the unix epoch
+#[inline]
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Counters shared by every worker's instance of a link
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    fallthroughs: AtomicU64,
+    circuit_trips: AtomicU64,
+    open_until: AtomicU64,
+}
+
+impl Counters {
+    #[inline]
+    pub(crate) fn request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn fallthrough(&self) {
+        self.fallthroughs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the circuit opening for the specified cooldown
+    pub(crate) fn trip(&self, cooldown: Duration) {
+        self.circuit_trips.fetch_add(1, Ordering::Relaxed);
+        let until = now_millis().saturating_add(cooldown.as_millis() as u64);
+        self.open_until.fetch_max(until, Ordering::Relaxed);
+    }
+
+    /// Record the circuit closing after a successful probe
+    #[inline]
+    pub(crate) fn close(&self) {
+        self.open_until.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time statistics of a single [`Link`](crate::Link)
+///
+/// Counters are aggregated across all workers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkStats {
+    /// Prefix assigned to the link.
+    pub prefix: String,
+    /// Number of requests passed to the link service.
+    pub requests: u64,
+    /// Number of requests the link service failed with an error.
+    pub errors: u64,
+    /// Number of responses discarded in favor of the next link.
+    pub fallthroughs: u64,
+    /// Number of times the link circuit breaker opened.
+    pub circuit_trips: u64,
+    /// Whether the circuit breaker last opened and is still cooling down.
+    pub circuit_open: bool,
+}
+
+/// Shared handle reporting statistics for every link within a
+/// [`Chain`](crate::Chain).
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::web;
+/// use actix_chain::{Chain, Link};
+///
+/// async fn index() -> &'static str {
+///     "Hello World!"
+/// }
+///
+/// let chain = Chain::default().link(Link::new(web::get().to(index)));
+/// let stats = chain.stats();
+///
+/// assert_eq!(stats.links()[0].requests, 0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ChainStats(pub(crate) Vec<(String, Arc<Counters>)>);
+
+impl ChainStats {
+    /// Current statistics of each link in order of declaration.
+    pub fn links(&self) -> Vec<LinkStats> {
+        let now = now_millis();
+        self.0
+            .iter()
+            .map(|(prefix, counters)| LinkStats {
+                prefix: prefix.clone(),
+                requests: counters.requests.load(Ordering::Relaxed),
+                errors: counters.errors.load(Ordering::Relaxed),
+                fallthroughs: counters.fallthroughs.load(Ordering::Relaxed),
+                circuit_trips: counters.circuit_trips.load(Ordering::Relaxed),
+                circuit_open: counters.open_until.load(Ordering::Relaxed) > now,
+            })
+            .collect()
+    }
+}
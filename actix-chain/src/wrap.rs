@@ -1,3 +1,5 @@
+use std::fmt::Debug;
+
 use actix_service::Transform;
 use actix_web::{
     Error,
@@ -11,12 +13,8 @@ use crate::service::HttpService;
 pub trait Wrappable {
     fn wrap_with<M, B>(self, middleware: M) -> Self
     where
-        M: Transform<
-                HttpService,
-                ServiceRequest,
-                Response = ServiceResponse<B>,
-                Error = Error,
-                InitError = (),
-            > + 'static,
+        M: Transform<HttpService, ServiceRequest, Response = ServiceResponse<B>, Error = Error>
+            + 'static,
+        M::InitError: Debug,
         B: MessageBody + 'static;
 }
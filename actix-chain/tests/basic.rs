@@ -1,7 +1,11 @@
-use actix_chain::{Chain, Link, next::IsStatus};
+use actix_chain::{
+    AuthRequest, Chain, Link, Selection,
+    next::{IfMethod, IsStatus},
+};
+use actix_common::PathPrefix;
 use actix_web::{
     App, HttpRequest, HttpResponse, Responder,
-    http::StatusCode,
+    http::{Method, StatusCode, header},
     test::{self, TestRequest},
     web,
 };
@@ -78,6 +82,65 @@ async fn test_prefix() {
     assert_eq!(common::get_body(res).await, "First link failed!");
 }
 
+#[actix_web::test]
+async fn test_prefix_segments() {
+    common::setup();
+
+    let path = |req: HttpRequest| async move { req.path().to_owned() };
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::get().to(path)).prefix("/assets/"))
+                .link(
+                    Link::new(web::get().to(path))
+                        .prefix(PathPrefix::new("/Static").case_insensitive(true)),
+                )
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    for (uri, expected) in [
+        ("/assets", "/"),
+        ("/assets/app.js?v=1", "/app.js"),
+        ("/as%73ets/app.js", "/app.js"),
+        ("/assets-old/app.js", "First link failed!"),
+        ("/static/logo.png", "/logo.png"),
+        ("/STATIC/logo.png", "/logo.png"),
+        ("/statics", "First link failed!"),
+    ] {
+        let req = TestRequest::with_uri(uri).to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(common::get_body(res).await, expected, "{uri}");
+    }
+}
+
+#[actix_web::test]
+async fn test_prefix_captures() {
+    common::setup();
+
+    let files = |req: HttpRequest| async move {
+        let id = req.match_info().get("id").unwrap_or_default().to_owned();
+        format!("{id} {}", req.path())
+    };
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::get().to(files)).prefix("/users/{id}/files"))
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/users/42/files/a.txt").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "42 /a.txt");
+
+    let req = TestRequest::with_uri("/users/42/filesystem").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "First link failed!");
+}
+
 #[actix_web::test]
 async fn test_next() {
     common::setup();
@@ -103,3 +166,936 @@ async fn test_next() {
     assert_eq!(res.status().to_string(), "404 Not Found");
     assert_eq!(common::get_body(res).await, "Request Failed");
 }
+
+#[actix_web::test]
+async fn test_next_with_request() {
+    common::setup();
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::route().to(might_fail)).next(IfMethod::new(
+                    [Method::GET],
+                    IsStatus(StatusCode::NOT_FOUND),
+                )))
+                .link(Link::new(web::route().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+    assert_eq!(common::get_body(res).await, "First link failed!");
+
+    let req = TestRequest::post().uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "404 Not Found");
+    assert_eq!(common::get_body(res).await, "Request Failed");
+}
+
+#[actix_web::test]
+async fn test_retries() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+    use std::time::Duration;
+
+    common::setup();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let flaky = web::post().to(move |body: String| {
+        let attempt = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            match attempt < 5 {
+                true => HttpResponse::BadGateway().finish(),
+                false => HttpResponse::Ok().body(body),
+            }
+        }
+    });
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(flaky).retries(2, Duration::from_millis(1)))
+                .link(Link::new(web::post().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::post()
+        .uri("/")
+        .set_payload("replayed")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "First link failed!");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    let req = TestRequest::post()
+        .uri("/")
+        .set_payload("replayed")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+    assert_eq!(common::get_body(res).await, "replayed");
+    assert_eq!(calls.load(Ordering::SeqCst), 6);
+}
+
+#[actix_web::test]
+async fn test_selection() {
+    common::setup();
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .selection(Selection::Cookie("variant".to_owned()))
+                .link(Link::new(web::get().to(might_fail)))
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    let mut bodies = Vec::new();
+    for _ in 0..4 {
+        let req = TestRequest::with_uri("/")
+            .insert_header(("Required-Header", "value"))
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        bodies.push(common::get_body(res).await);
+    }
+    assert_eq!(bodies.iter().filter(|b| *b == "It worked!").count(), 2);
+    assert_eq!(
+        bodies.iter().filter(|b| *b == "First link failed!").count(),
+        2
+    );
+
+    let mut bodies = Vec::new();
+    for _ in 0..4 {
+        let req = TestRequest::with_uri("/")
+            .insert_header(("Required-Header", "value"))
+            .insert_header(("Cookie", "session=1; variant=b"))
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        bodies.push(common::get_body(res).await);
+    }
+    assert!(bodies.iter().all(|b| *b == bodies[0]));
+
+    // failed variant still falls back to the other link
+    for _ in 0..2 {
+        let req = TestRequest::with_uri("/").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status().to_string(), "200 OK");
+        assert_eq!(common::get_body(res).await, "First link failed!");
+    }
+}
+
+#[actix_web::test]
+async fn test_merge_headers() {
+    common::setup();
+
+    async fn login() -> HttpResponse {
+        HttpResponse::NotFound()
+            .insert_header(("Set-Cookie", "session=abc"))
+            .insert_header(("X-Debug", "login"))
+            .finish()
+    }
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::get().to(login)).merge_header(header::SET_COOKIE))
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+    assert_eq!(res.headers().get("Set-Cookie").unwrap(), "session=abc");
+    assert!(!res.headers().contains_key("X-Debug"));
+    assert_eq!(common::get_body(res).await, "First link failed!");
+}
+
+#[actix_web::test]
+async fn test_circuit_breaker() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+    use std::time::Duration;
+
+    common::setup();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let failing = web::get().to(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async { HttpResponse::NotFound().finish() }
+    });
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(failing).circuit_breaker(2, Duration::from_secs(60)))
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    for _ in 0..4 {
+        let req = TestRequest::with_uri("/").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status().to_string(), "200 OK");
+        assert_eq!(common::get_body(res).await, "First link failed!");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[actix_web::test]
+async fn test_circuit_breaker_probe() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+    use std::time::Duration;
+
+    common::setup();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let failing = web::get().to(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async {
+            actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+            HttpResponse::NotFound().finish()
+        }
+    });
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(failing).circuit_breaker(2, Duration::from_millis(100)))
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    for _ in 0..2 {
+        let req = TestRequest::with_uri("/").to_request();
+        test::call_service(&srv, req).await;
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // only a single concurrent request probes the half-open circuit
+    actix_web::rt::time::sleep(Duration::from_millis(150)).await;
+    let (first, second) = futures_util::future::join(
+        test::call_service(&srv, TestRequest::with_uri("/").to_request()),
+        test::call_service(&srv, TestRequest::with_uri("/").to_request()),
+    )
+    .await;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(second.status(), StatusCode::OK);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[actix_web::test]
+async fn test_init_error() {
+    use std::future::{Ready, ready};
+
+    use actix_chain::ChainService;
+    use actix_service::{Service, Transform};
+    use actix_web::dev::{ServiceRequest, ServiceResponse};
+
+    common::setup();
+
+    let broken =
+        || actix_service::fn_factory(|| async { Err::<ChainService, _>("missing backend") });
+
+    let chain = Chain::default()
+        .link(Link::new(web::get().to(default)).prefix("/ok"))
+        .link(Link::new(broken()).prefix("/broken"));
+    let err = chain.init().await.err().expect("init should fail");
+    assert_eq!(err.index, 1);
+    assert_eq!(err.prefix, "/broken");
+    assert!(err.reason.contains("missing backend"));
+
+    /// Middleware failing to initialize with a reason
+    struct Broken;
+
+    impl<S> Transform<S, ServiceRequest> for Broken
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        type Response = ServiceResponse;
+        type Error = actix_web::Error;
+        type Transform = S;
+        type InitError = &'static str;
+        type Future = Ready<Result<S, Self::InitError>>;
+
+        fn new_transform(&self, _service: S) -> Self::Future {
+            ready(Err("missing certificate"))
+        }
+    }
+
+    let chain = Chain::default().link(Link::new(web::get().to(default)).wrap(Broken));
+    let err = chain.init().await.err().expect("init should fail");
+    assert!(err.reason.contains("missing certificate"));
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .skip_failed_links(true)
+                .link(Link::new(broken()))
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+    assert_eq!(common::get_body(res).await, "First link failed!");
+}
+
+#[actix_web::test]
+async fn test_content_guards() {
+    use actix_web::mime;
+
+    common::setup();
+
+    async fn json() -> &'static str {
+        "JSON API"
+    }
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(
+                    Link::new(web::post().to(json))
+                        .content_type(mime::APPLICATION_JSON)
+                        .max_body(16),
+                )
+                .link(Link::new(web::post().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::post()
+        .uri("/")
+        .insert_header(("Content-Type", "application/json; charset=utf-8"))
+        .set_payload("{}")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "JSON API");
+
+    let req = TestRequest::post()
+        .uri("/")
+        .insert_header(("Content-Type", "text/plain"))
+        .set_payload("{}")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "First link failed!");
+
+    let req = TestRequest::post()
+        .uri("/")
+        .insert_header(("Content-Type", "application/json"))
+        .insert_header(("Content-Length", "29"))
+        .set_payload(r#"{"key": "too large for link"}"#)
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "First link failed!");
+}
+
+#[actix_web::test]
+async fn test_next_body() {
+    use actix_chain::next::BodyContains;
+    use actix_web::web::Bytes;
+
+    common::setup();
+
+    async fn maintenance() -> &'static str {
+        "maintenance mode"
+    }
+
+    async fn streaming() -> HttpResponse {
+        let chunks = [Ok::<_, actix_web::Error>(Bytes::from("maintenance mode"))];
+        HttpResponse::Ok().streaming(futures_util::stream::iter(chunks))
+    }
+
+    async fn chunked() -> HttpResponse {
+        let chunks =
+            ["maint", "enance", " mode"].map(|c| Ok::<_, actix_web::Error>(Bytes::from(c)));
+        HttpResponse::Ok().streaming(futures_util::stream::iter(chunks))
+    }
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(
+                    Link::new(web::get().to(maintenance))
+                        .prefix("/sized")
+                        .next_body(BodyContains::new("maintenance")),
+                )
+                .link(
+                    Link::new(web::get().to(streaming))
+                        .prefix("/stream")
+                        .next_body(BodyContains::new("maintenance"))
+                        .stream_passthrough(true),
+                )
+                .link(
+                    Link::new(web::get().to(chunked))
+                        .prefix("/limited")
+                        .next_body(BodyContains::new("maintenance"))
+                        .next_body_limit(8),
+                )
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/sized").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "First link failed!");
+
+    let req = TestRequest::with_uri("/stream").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "maintenance mode");
+
+    // bodies over the limit are sent untouched without falling through
+    let req = TestRequest::with_uri("/limited").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "maintenance mode");
+}
+
+#[cfg(feature = "rewrite")]
+#[actix_web::test]
+async fn test_rewrite() {
+    use actix_rewrite::Engine;
+
+    common::setup();
+
+    async fn path(req: HttpRequest) -> HttpResponse {
+        match req.path() {
+            "/new/page" => HttpResponse::Ok().body("rewritten"),
+            _ => HttpResponse::NotFound().finish(),
+        }
+    }
+
+    async fn original(req: HttpRequest) -> String {
+        req.path().to_owned()
+    }
+
+    let engine = Engine::new()
+        .rules(
+            r#"
+        RewriteRule ^/old/(.*) /new/$1 [L]
+        RewriteRule ^/moved$ /new/page [R=301,L]
+    "#,
+        )
+        .expect("invalid rules");
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::get().to(path)).rewrite(engine))
+                .link(Link::new(web::get().to(original))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/old/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "rewritten");
+
+    let req = TestRequest::with_uri("/other").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "/other");
+
+    // the next link receives the original uri rather than the rewritten one
+    let req = TestRequest::with_uri("/old/missing").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "/old/missing");
+
+    // redirects produced by the rules become the link response
+    let req = TestRequest::with_uri("/moved").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/new/page");
+}
+
+#[actix_web::test]
+async fn test_error_pages() {
+    use actix_common::ErrorPages;
+
+    common::setup();
+
+    async fn failing() -> actix_web::Result<HttpResponse> {
+        Err(actix_web::error::ErrorBadGateway("upstream down"))
+    }
+
+    let pages = ErrorPages::new()
+        .page(StatusCode::NOT_FOUND, "missing {path}")
+        .page(StatusCode::BAD_GATEWAY, "{status} {reason}");
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .error_pages(pages)
+                .link(Link::new(web::get().to(failing)).prefix("/fail"))
+                .link(Link::new(web::get().to(default)).prefix("/ok")),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/fail").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "502 Bad Gateway");
+    assert_eq!(common::get_body(res).await, "502 Bad Gateway");
+
+    let req = TestRequest::with_uri("/missing").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "404 Not Found");
+    assert_eq!(common::get_body(res).await, "missing /missing");
+}
+
+#[actix_web::test]
+async fn test_link_stats() {
+    use std::time::Duration;
+
+    common::setup();
+
+    async fn unavailable() -> HttpResponse {
+        HttpResponse::ServiceUnavailable().finish()
+    }
+
+    let chain = Chain::default()
+        .link(
+            Link::new(web::get().to(unavailable))
+                .next(IsStatus(StatusCode::SERVICE_UNAVAILABLE))
+                .circuit_breaker(2, Duration::from_secs(60)),
+        )
+        .link(Link::new(web::get().to(default)));
+    let stats = chain.stats();
+    let srv = test::init_service(App::new().service(chain)).await;
+
+    for _ in 0..3 {
+        let req = TestRequest::with_uri("/").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status().to_string(), "200 OK");
+    }
+
+    let links = stats.links();
+    assert_eq!(links[0].requests, 2);
+    assert_eq!(links[0].fallthroughs, 2);
+    assert_eq!(links[0].circuit_trips, 1);
+    assert!(links[0].circuit_open);
+    assert_eq!(links[1].requests, 3);
+    assert!(!links[1].circuit_open);
+}
+
+#[actix_web::test]
+async fn test_require_auth() {
+    common::setup();
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(
+                    Link::new(web::get().to(|| async { "secret" }))
+                        .prefix("/admin")
+                        .require_auth("Bearer")
+                        .auth_challenge(r#"Bearer realm="admin""#),
+                )
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/admin/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        r#"Bearer realm="admin""#
+    );
+
+    let req = TestRequest::with_uri("/admin/")
+        .insert_header((header::AUTHORIZATION, "Basic dXNlcjpwYXNz"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let req = TestRequest::with_uri("/admin/")
+        .insert_header((header::AUTHORIZATION, "Bearer token"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "secret");
+
+    let req = TestRequest::with_uri("/public").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "First link failed!");
+}
+
+#[actix_web::test]
+async fn test_deadline() {
+    common::setup();
+    tokio::time::pause();
+
+    async fn unavailable() -> HttpResponse {
+        HttpResponse::ServiceUnavailable().body("unavailable")
+    }
+    async fn slow() -> &'static str {
+        actix_web::rt::time::sleep(std::time::Duration::from_millis(60)).await;
+        "slow"
+    }
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::new("/fallback")
+                .deadline(std::time::Duration::from_millis(40))
+                .link(Link::new(web::get().to(slow)))
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+    let req = TestRequest::with_uri("/fallback").to_request();
+    let err = test::try_call_service(&srv, req).await.unwrap_err();
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::GATEWAY_TIMEOUT
+    );
+
+    // the best response so far is returned once the deadline expires
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .deadline(std::time::Duration::from_millis(40))
+                .link(Link::new(web::get().to(unavailable)))
+                .link(Link::new(web::get().to(slow))),
+        ),
+    )
+    .await;
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(common::get_body(res).await, "unavailable");
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .deadline(std::time::Duration::from_millis(50))
+                .link(
+                    Link::new(web::get().to(unavailable))
+                        .retries(3, std::time::Duration::from_millis(100)),
+                )
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(common::get_body(res).await, "unavailable");
+
+    async fn remaining(req: HttpRequest) -> String {
+        let deadline = actix_common::deadline(&req).expect("deadline assigned");
+        (deadline.remaining() <= std::time::Duration::from_secs(5)).to_string()
+    }
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .deadline(std::time::Duration::from_secs(5))
+                .link(Link::new(web::get().to(remaining))),
+        ),
+    )
+    .await;
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "true");
+}
+
+#[actix_web::test]
+async fn test_etag_memo() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    common::setup();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let missing = {
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { HttpResponse::NotFound().finish() }
+        }
+    };
+    let tagged = || async {
+        HttpResponse::Ok()
+            .insert_header((header::ETAG, "\"v1\""))
+            .body("tagged")
+    };
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .etag_memo(std::time::Duration::from_secs(60))
+                .link(Link::new(web::get().to(missing)))
+                .link(Link::new(web::get().to(tagged))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/file").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let req = TestRequest::with_uri("/file")
+        .insert_header((header::IF_NONE_MATCH, "W/\"v1\""))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"v1\"");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let req = TestRequest::with_uri("/file")
+        .insert_header((header::IF_NONE_MATCH, "\"v0\""))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[actix_web::test]
+async fn test_maintenance() {
+    common::setup();
+
+    let maintenance = actix_common::Maintenance::new()
+        .allow_path("/health")
+        .retry_after(std::time::Duration::from_secs(120))
+        .page("<h1>back soon</h1>");
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .maintenance(maintenance.clone())
+                .link(Link::new(web::get().to(default))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    maintenance.enable();
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "120");
+    assert_eq!(common::get_body(res).await, "<h1>back soon</h1>");
+
+    let req = TestRequest::with_uri("/health/ready").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    maintenance.disable();
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_auth_request() {
+    common::setup();
+
+    async fn auth(req: HttpRequest) -> HttpResponse {
+        match req.headers().get(header::AUTHORIZATION) {
+            Some(token) if token == "Bearer ok" => HttpResponse::Ok()
+                .insert_header(("X-User", "alice"))
+                .finish(),
+            _ => HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .body("denied"),
+        }
+    }
+    async fn whoami(req: HttpRequest) -> String {
+        let user = req.headers().get("X-User").and_then(|v| v.to_str().ok());
+        format!("hello {}", user.unwrap_or("anonymous"))
+    }
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::auth_request(
+                    AuthRequest::new(web::to(auth)).copy_header("X-User"),
+                ))
+                .link(Link::new(web::get().to(whoami))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/")
+        .insert_header((header::AUTHORIZATION, "Bearer ok"))
+        .insert_header(("X-User", "mallory"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(common::get_body(res).await, "hello alice");
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-User", "mallory"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        "Bearer"
+    );
+    assert_eq!(common::get_body(res).await, "denied");
+
+    // granted requests without a following link are not found
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::auth_request(AuthRequest::new(web::to(auth))))
+                .link(Link::new(web::to(whoami)).prefix("/api")),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/api/me")
+        .insert_header((header::AUTHORIZATION, "Bearer ok"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = TestRequest::with_uri("/other")
+        .insert_header((header::AUTHORIZATION, "Bearer ok"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(common::get_body(res).await.is_empty());
+}
+
+#[actix_web::test]
+async fn test_auth_request_mutation() {
+    use actix_web::dev::{ServiceRequest, fn_service};
+
+    common::setup();
+
+    // the authentication service rewrites its sub-request
+    let auth = fn_service(|mut req: ServiceRequest| async move {
+        let bodiless = !req.headers().contains_key(header::CONTENT_LENGTH)
+            && !req.headers().contains_key(header::TRANSFER_ENCODING);
+        req.head_mut().uri = "/auth".parse().unwrap();
+        req.headers_mut().insert(
+            header::HeaderName::from_static("x-auth"),
+            "rewritten".parse().unwrap(),
+        );
+        let res = match bodiless {
+            true => HttpResponse::Ok().finish(),
+            false => HttpResponse::BadRequest().finish(),
+        };
+        Ok(req.into_response(res))
+    });
+    async fn echo(req: HttpRequest, body: String) -> String {
+        let auth = req.headers().get("X-Auth").is_some();
+        format!("{} {auth} {body}", req.path())
+    }
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::auth_request(AuthRequest::new(auth)))
+                .link(Link::new(web::post().to(echo))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::post()
+        .uri("/upload")
+        .set_payload("data")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(common::get_body(res).await, "/upload false data");
+}
+
+#[actix_web::test]
+async fn test_etag_memo_auth() {
+    common::setup();
+
+    async fn auth(req: HttpRequest) -> HttpResponse {
+        match req.headers().get(header::AUTHORIZATION) {
+            Some(token) if token == "Bearer ok" => HttpResponse::Ok().finish(),
+            _ => HttpResponse::Unauthorized().finish(),
+        }
+    }
+    let tagged = || async {
+        HttpResponse::Ok()
+            .insert_header((header::ETAG, "\"v1\""))
+            .body("tagged")
+    };
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .etag_memo(std::time::Duration::from_secs(60))
+                .link(Link::auth_request(AuthRequest::new(web::to(auth))))
+                .link(Link::new(web::get().to(tagged))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/file")
+        .insert_header((header::AUTHORIZATION, "Bearer ok"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = TestRequest::with_uri("/file")
+        .insert_header((header::AUTHORIZATION, "Bearer ok"))
+        .insert_header((header::IF_NONE_MATCH, "\"v1\""))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    // memoized responses never skip the authentication sub-request
+    let req = TestRequest::with_uri("/file")
+        .insert_header((header::IF_NONE_MATCH, "\"v1\""))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_link_guard() {
+    use actix_web::guard::Header;
+
+    common::setup();
+
+    async fn admin() -> &'static str {
+        "admin"
+    }
+
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::to(admin)).guard(Header("X-Admin", "1")))
+                .link(Link::new(web::to(default))),
+        ),
+    )
+    .await;
+
+    // a matching guard selects the link
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-Admin", "1"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "admin");
+
+    // a failing guard skips to the next link
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(common::get_body(res).await, "First link failed!");
+}
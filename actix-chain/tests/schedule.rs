@@ -0,0 +1,104 @@
+use actix_chain::{Chain, Link, schedule::DuringWindow};
+use actix_web::{
+    App, HttpResponse,
+    http::StatusCode,
+    test::{self, TestRequest},
+    web,
+};
+use time::{Date, Month, OffsetDateTime, Time, UtcOffset};
+
+mod common;
+
+fn at(month: Month, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+    Date::from_calendar_date(2025, month, day)
+        .unwrap()
+        .with_hms(hour, minute, 0)
+        .unwrap()
+        .assume_utc()
+}
+
+#[test]
+fn test_cron_window() {
+    // 2025-03-16 is a sunday
+    let window = DuringWindow::cron("*/15 2-3 * * sun").unwrap();
+    assert!(window.contains(at(Month::March, 16, 2, 0)));
+    assert!(window.contains(at(Month::March, 16, 3, 45)));
+    assert!(!window.contains(at(Month::March, 16, 3, 50)));
+    assert!(!window.contains(at(Month::March, 16, 4, 0)));
+    assert!(!window.contains(at(Month::March, 17, 2, 0)));
+
+    let window = DuringWindow::cron("* * 1 jan-mar 7").unwrap();
+    assert!(window.contains(at(Month::March, 1, 12, 0)));
+    assert!(window.contains(at(Month::March, 16, 12, 0)));
+    assert!(!window.contains(at(Month::April, 1, 12, 0)));
+
+    let window = DuringWindow::cron("* 9-17 * * mon-fri")
+        .unwrap()
+        .offset(UtcOffset::from_hms(-5, 0, 0).unwrap());
+    assert!(window.contains(at(Month::March, 17, 14, 0)));
+    assert!(!window.contains(at(Month::March, 17, 9, 0)));
+
+    assert!(DuringWindow::cron("* * * *").is_err());
+    assert!(DuringWindow::cron("60 * * * *").is_err());
+    assert!(DuringWindow::cron("*/0 * * * *").is_err());
+    assert!(DuringWindow::cron("* 5-2 * * *").is_err());
+}
+
+#[test]
+fn test_range_window() {
+    let window: DuringWindow = "22:00-02:30".parse().unwrap();
+    assert!(window.contains(at(Month::March, 16, 23, 0)));
+    assert!(window.contains(at(Month::March, 16, 2, 29)));
+    assert!(!window.contains(at(Month::March, 16, 2, 30)));
+    assert!(!window.contains(at(Month::March, 16, 12, 0)));
+
+    let start = Time::from_hms(9, 0, 0).unwrap();
+    let end = Time::from_hms(17, 0, 0).unwrap();
+    let window = DuringWindow::between(start, end).offset(UtcOffset::from_hms(2, 0, 0).unwrap());
+    assert!(window.contains(at(Month::March, 16, 7, 0)));
+    assert!(!window.contains(at(Month::March, 16, 16, 0)));
+
+    // central european time is utc+1 in winter and utc+2 in summer
+    let window: DuringWindow = "02:00-04:00".parse().unwrap();
+    let window = window.timezone("Europe/Berlin").unwrap();
+    assert!(window.contains(at(Month::January, 15, 1, 30)));
+    assert!(window.contains(at(Month::January, 15, 2, 30)));
+    assert!(!window.contains(at(Month::January, 15, 3, 0)));
+    assert!(window.contains(at(Month::July, 15, 0, 30)));
+    assert!(!window.contains(at(Month::July, 15, 2, 30)));
+    assert!(
+        DuringWindow::cron("* * * * *")
+            .unwrap()
+            .timezone("Mars/Olympus")
+            .is_err()
+    );
+
+    assert!("25:00-02:00".parse::<DuringWindow>().is_err());
+    assert!("noon".parse::<DuringWindow>().is_err());
+}
+
+#[actix_web::test]
+async fn test_window_guard() {
+    common::setup();
+
+    async fn maintenance() -> HttpResponse {
+        HttpResponse::ServiceUnavailable().body("maintenance")
+    }
+
+    let always = DuringWindow::cron("* * * * *").unwrap();
+    let never = DuringWindow::cron("0 0 31 feb *").unwrap();
+    let srv = test::init_service(
+        App::new().service(
+            Chain::default()
+                .link(Link::new(web::to(maintenance)).guard(never))
+                .link(Link::new(web::to(|| async { "hello" })).next(always))
+                .link(Link::new(web::to(|| async { "fallback" }))),
+        ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(common::get_body(res).await, "fallback");
+}
@@ -0,0 +1,216 @@
+//! Bounded Body Buffering
+//!
+//! [`BodyBuffer`] captures a request or response body up to a size limit
+//! while it is read, so the captured bytes can be inspected and the body
+//! replayed to another consumer without copying.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::web::Bytes;
+//! use actix_common::body::BodyBuffer;
+//!
+//! # actix_web::rt::System::new().block_on(async {
+//! let buffer = BodyBuffer::from_body(Bytes::from_static(b"hello world"), 1024);
+//! let captured = buffer.capture().await.unwrap();
+//! assert_eq!(captured, "hello world");
+//!
+//! let body = actix_web::body::to_bytes(buffer.body()).await.unwrap();
+//! assert_eq!(body, "hello world");
+//! # });
+//! ```
+
+use std::{
+    cell::RefCell,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    body::{BodyStream, BoxBody, MessageBody},
+    dev::Payload,
+    error::PayloadError,
+    web::{Bytes, BytesMut},
+};
+use futures_core::{Stream, stream::LocalBoxStream};
+
+//TODO: implement max-body-size which writes to file buffer
+// to support beyond memory-limit
+
+/// Shared buffer capturing a body as it is read.
+///
+/// Received chunks are captured once and frozen into a shared [`Bytes`]
+/// that is replayed after [`BodyBuffer::rewind`] without copying. Reading
+/// beyond the configured limit fails with [`PayloadError::Overflow`].
+///
+/// Clones share the same underlying buffer and stream.
+#[derive(Clone)]
+pub struct BodyBuffer(Rc<RefCell<Inner>>);
+
+struct Inner {
+    stream: LocalBoxStream<'static, Result<Bytes, PayloadError>>,
+    buf: BytesMut,
+    replay: Bytes,
+
+    eof: bool,
+    overflow: bool,
+
+    replayed: bool,
+    limit: usize,
+}
+
+impl BodyBuffer {
+    /// Create a new buffer capturing up to `limit` bytes of the stream.
+    pub fn new<S>(stream: S, limit: usize) -> Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        Self(Rc::new(RefCell::new(Inner {
+            stream: Box::pin(stream),
+            buf: BytesMut::new(),
+            replay: Bytes::new(),
+            eof: false,
+            overflow: false,
+            replayed: false,
+            limit,
+        })))
+    }
+
+    /// Create a new buffer capturing up to `limit` bytes of a response body.
+    pub fn from_body<B>(body: B, limit: usize) -> Self
+    where
+        B: MessageBody + 'static,
+    {
+        Self::new(BodyChunks(Box::pin(body)), limit)
+    }
+
+    /// Stream reading the remaining body, starting with any replayed bytes.
+    #[inline]
+    pub fn stream(&self) -> LocalBoxStream<'static, Result<Bytes, PayloadError>> {
+        Box::pin(self.clone())
+    }
+
+    /// Request payload reading the remaining body.
+    pub fn payload(&self) -> Payload {
+        Payload::Stream {
+            payload: self.stream(),
+        }
+    }
+
+    /// Response body reading the remaining body.
+    ///
+    /// Fully captured bodies are returned with their known size.
+    pub fn body(&self) -> BoxBody {
+        let inner = self.0.borrow();
+        if inner.eof && inner.buf.is_empty() && !inner.replayed {
+            return BoxBody::new(inner.replay.clone());
+        }
+        drop(inner);
+        BoxBody::new(BodyStream::new(self.clone()))
+    }
+
+    /// Replay all bytes read so far to the next reader.
+    pub fn rewind(&self) {
+        let mut inner = self.0.borrow_mut();
+        if !inner.buf.is_empty() {
+            let data = inner.buf.split().freeze();
+            inner.replay = match inner.replay.is_empty() {
+                true => data,
+                false => Bytes::from([inner.replay.clone(), data].concat()),
+            };
+        }
+        inner.replayed = false;
+    }
+
+    /// Read the remaining body and return the captured bytes for inspection.
+    ///
+    /// The buffer is rewound afterwards so the complete body is replayed
+    /// to the next reader.
+    pub async fn capture(&self) -> Result<Bytes, PayloadError> {
+        let mut stream = self.clone();
+        while let Some(chunk) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            chunk?;
+        }
+        self.rewind();
+        Ok(self.captured())
+    }
+
+    /// Bytes captured as of the last [`BodyBuffer::rewind`].
+    #[inline]
+    pub fn captured(&self) -> Bytes {
+        self.0.borrow().replay.clone()
+    }
+
+    /// Check if the body was read to completion.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.0.borrow().eof
+    }
+
+    /// Check if the body exceeded the configured limit.
+    #[inline]
+    pub fn is_overflow(&self) -> bool {
+        self.0.borrow().overflow
+    }
+}
+
+impl Inner {
+    #[inline]
+    fn read_buffered(&mut self) -> Option<Bytes> {
+        if self.replayed || self.replay.is_empty() {
+            return None;
+        }
+        self.replayed = true;
+        Some(self.replay.clone())
+    }
+}
+
+impl Stream for BodyBuffer {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.0.borrow_mut();
+        if let Some(data) = this.read_buffered() {
+            return Poll::Ready(Some(Ok(data)));
+        }
+        if this.eof {
+            return Poll::Ready(None);
+        }
+        if this.overflow {
+            return Poll::Ready(Some(Err(PayloadError::Overflow)));
+        }
+        match Pin::new(&mut this.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(data))) => {
+                if this.replay.len() + this.buf.len() + data.len() > this.limit {
+                    this.overflow = true;
+                    return Poll::Ready(Some(Err(PayloadError::Overflow)));
+                }
+                this.buf.extend_from_slice(&data);
+                Poll::Ready(Some(Ok(data)))
+            }
+            Poll::Ready(None) => {
+                this.eof = true;
+                Poll::Ready(None)
+            }
+            status => status,
+        }
+    }
+}
+
+/// Stream of the chunks produced by a [`MessageBody`]
+struct BodyChunks<B>(Pin<Box<B>>);
+
+impl<B: MessageBody> Stream for BodyChunks<B> {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().0.as_mut().poll_next(cx).map(|item| {
+            item.map(|chunk| {
+                chunk.map_err(|err| PayloadError::Io(io::Error::other(err.into().to_string())))
+            })
+        })
+    }
+}
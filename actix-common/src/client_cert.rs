@@ -0,0 +1,142 @@
+//! TLS Client Certificate Details for Upstream Forwarding
+
+use actix_web::{HttpMessage, HttpRequest};
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+/// Client certificate presented during mutual TLS
+///
+/// Actix does not expose peer certificates directly, so the certificate
+/// must be attached to the connection using
+/// [`HttpServer::on_connect`](https://docs.rs/actix-web/latest/actix_web/struct.HttpServer.html#method.on_connect)
+/// or inserted into the request extensions by a middleware.
+///
+/// # Examples
+///
+/// ```ignore
+/// use actix_common::ClientCert;
+///
+/// HttpServer::new(app).on_connect(|conn, ext| {
+///     let Some(tls) = conn.downcast_ref::<TlsStream<TcpStream>>() else { return };
+///     if let Some(cert) = tls.get_ref().1.peer_certificates().and_then(|c| c.first()) {
+///         let parsed = parse_x509(cert);
+///         ext.insert(
+///             ClientCert::new(cert.to_vec())
+///                 .subject(parsed.subject)
+///                 .issuer(parsed.issuer)
+///                 .serial(parsed.serial)
+///                 .verified(true),
+///         );
+///     }
+/// });
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientCert {
+    der: Vec<u8>,
+    subject: Option<String>,
+    issuer: Option<String>,
+    serial: Option<String>,
+    verified: bool,
+}
+
+impl ClientCert {
+    /// Construct client certificate details from the DER encoded certificate.
+    pub fn new(der: Vec<u8>) -> Self {
+        Self {
+            der,
+            ..Default::default()
+        }
+    }
+
+    /// Set the subject distinguished name.
+    pub fn subject<S: Into<String>>(mut self, subject: S) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Set the issuer distinguished name.
+    pub fn issuer<S: Into<String>>(mut self, issuer: S) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// Set the hex encoded certificate serial number.
+    pub fn serial<S: Into<String>>(mut self, serial: S) -> Self {
+        self.serial = Some(serial.into());
+        self
+    }
+
+    /// Mark the certificate as verified against the trusted client CAs.
+    ///
+    /// Default is unverified.
+    pub fn verified(mut self, verified: bool) -> Self {
+        self.verified = verified;
+        self
+    }
+
+    /// DER encoded certificate.
+    #[inline]
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// Subject distinguished name.
+    #[inline]
+    pub fn subject_dn(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// Issuer distinguished name.
+    #[inline]
+    pub fn issuer_dn(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /// Hex encoded certificate serial number.
+    #[inline]
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial.as_deref()
+    }
+
+    /// Verification result using the mod_ssl/nginx vocabulary.
+    ///
+    /// Returns `SUCCESS` for verified certificates and `GENEROUS` otherwise.
+    pub fn verify_status(&self) -> &'static str {
+        match self.verified {
+            true => "SUCCESS",
+            false => "GENEROUS",
+        }
+    }
+
+    /// PEM encoded certificate.
+    pub fn pem(&self) -> String {
+        let encoded = STANDARD.encode(&self.der);
+        let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+        for line in encoded.as_bytes().chunks(64) {
+            pem.push_str(&String::from_utf8_lossy(line));
+            pem.push('\n');
+        }
+        pem.push_str("-----END CERTIFICATE-----\n");
+        pem
+    }
+
+    /// Url-encoded PEM certificate safe for use as a header value.
+    ///
+    /// Matches the format of nginx `$ssl_client_escaped_cert`.
+    pub fn escaped_pem(&self) -> String {
+        let mut escaped = String::new();
+        for c in self.pem().chars() {
+            match c {
+                'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '.' | '_' | '~' => escaped.push(c),
+                c => escaped.push_str(&format!("%{:02X}", c as u32)),
+            }
+        }
+        escaped
+    }
+}
+
+/// Retrieve the client certificate attached to the connection or request.
+pub fn client_cert(req: &HttpRequest) -> Option<ClientCert> {
+    req.conn_data::<ClientCert>()
+        .cloned()
+        .or_else(|| req.extensions().get::<ClientCert>().cloned())
+}
@@ -0,0 +1,145 @@
+//! Per-Service Request Concurrency and Queueing Policy
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::Error;
+
+/// Request concurrency limit shared by all workers of a service
+///
+/// Requests beyond `max_inflight` wait in a queue of up to `queue_depth`
+/// requests for at most `queue_timeout` before being rejected with
+/// `503 Service Unavailable`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_common::Concurrency;
+///
+/// let concurrency = Concurrency::new(64)
+///     .queue_depth(128)
+///     .queue_timeout(Duration::from_secs(5));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Concurrency {
+    semaphore: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+    waiters: Arc<Mutex<Vec<Waker>>>,
+    queue_depth: usize,
+    queue_timeout: Duration,
+}
+
+impl Concurrency {
+    /// Create a new limit with the specified maximum in-flight requests.
+    pub fn new(max_inflight: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_inflight)),
+            queued: Arc::new(AtomicUsize::new(0)),
+            waiters: Arc::new(Mutex::new(Vec::new())),
+            queue_depth: 0,
+            queue_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the maximum number of requests waiting for an in-flight slot.
+    ///
+    /// Default is 0.
+    pub fn queue_depth(mut self, depth: usize) -> Self {
+        self.queue_depth = depth;
+        self
+    }
+
+    /// Set the maximum time a request may wait in the queue.
+    ///
+    /// Default is 30 seconds.
+    pub fn queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = timeout;
+        self
+    }
+
+    #[inline]
+    fn is_ready(&self) -> bool {
+        self.semaphore.available_permits() > 0
+            || self.queued.load(Ordering::Acquire) < self.queue_depth
+    }
+
+    fn wake(&self) {
+        let waiters = std::mem::take(&mut *self.waiters.lock().expect("poisoned lock"));
+        waiters.into_iter().for_each(Waker::wake);
+    }
+
+    /// Check if the service is able to accept another request.
+    ///
+    /// Returns pending while all in-flight slots and queue slots are taken.
+    pub fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        if self.is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        // repeated polls of the same task are only registered once
+        let mut waiters = self.waiters.lock().expect("poisoned lock");
+        if !waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+            waiters.push(cx.waker().clone());
+        }
+        drop(waiters);
+        match self.is_ready() {
+            true => Poll::Ready(Ok(())),
+            false => Poll::Pending,
+        }
+    }
+
+    /// Acquire an in-flight slot, waiting in the queue when saturated.
+    ///
+    /// The slot is released when the returned [`Permit`] is dropped.
+    pub async fn acquire(&self) -> Result<Permit, Error> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(self.permit(permit));
+        }
+        if self.queued.fetch_add(1, Ordering::AcqRel) >= self.queue_depth {
+            self.queued.fetch_sub(1, Ordering::AcqRel);
+            tracing::warn!("request queue full. rejecting request");
+            return Err(Error::Overloaded);
+        }
+        let result =
+            tokio::time::timeout(self.queue_timeout, self.semaphore.clone().acquire_owned()).await;
+        self.queued.fetch_sub(1, Ordering::AcqRel);
+        self.wake();
+        match result {
+            Ok(Ok(permit)) => Ok(self.permit(permit)),
+            _ => {
+                tracing::warn!("request queue timeout exceeded. rejecting request");
+                Err(Error::Overloaded)
+            }
+        }
+    }
+
+    #[inline]
+    fn permit(&self, permit: OwnedSemaphorePermit) -> Permit {
+        Permit {
+            permit: Some(permit),
+            concurrency: self.clone(),
+        }
+    }
+}
+
+/// In-flight request slot acquired from [`Concurrency::acquire`]
+#[derive(Debug)]
+pub struct Permit {
+    permit: Option<OwnedSemaphorePermit>,
+    concurrency: Concurrency,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+        self.concurrency.wake();
+    }
+}
@@ -0,0 +1,239 @@
+//! Gateway Cross-Origin Resource Sharing Policy
+
+use std::time::Duration;
+
+use actix_web::{
+    HttpResponse,
+    dev::ServiceRequest,
+    http::{
+        Method,
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+    },
+};
+
+/// Origins accepted by the policy
+#[derive(Clone, Debug)]
+enum Origins {
+    Any,
+    List(Vec<String>),
+}
+
+/// CORS policy answering preflight requests at the gateway
+///
+/// Preflight requests from an allowed origin are answered locally with
+/// `204 No Content` instead of being forwarded, and preflights for any
+/// other origin, method or header are rejected with `403 Forbidden`.
+/// Responses to actual cross-origin requests from allowed origins receive
+/// the matching `Access-Control-Allow-*` headers.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_web::http::Method;
+/// use actix_common::Cors;
+///
+/// let cors = Cors::new()
+///     .allow_origin("https://app.example.com")
+///     .allow_method(Method::PUT)
+///     .allow_header("Content-Type")
+///     .max_age(Duration::from_secs(3600));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cors {
+    origins: Origins,
+    methods: Vec<Method>,
+    headers: Option<Vec<HeaderName>>,
+    max_age: Option<Duration>,
+    credentials: bool,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cors {
+    /// Create a new policy without any allowed origins.
+    pub fn new() -> Self {
+        Self {
+            origins: Origins::List(Vec::new()),
+            methods: vec![Method::GET, Method::HEAD, Method::POST],
+            headers: Some(Vec::new()),
+            max_age: None,
+            credentials: false,
+        }
+    }
+
+    /// Allow requests from the specified origin such as `https://example.com`.
+    ///
+    /// Passing `*` allows every origin.
+    ///
+    /// # Panics
+    ///
+    /// Panics when passing `*` after enabling
+    /// [`allow_credentials`](Self::allow_credentials).
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        match (&mut self.origins, origin) {
+            (_, "*") => self.origins = Origins::Any,
+            (Origins::List(origins), origin) => {
+                origins.push(origin.trim_end_matches('/').to_ascii_lowercase())
+            }
+            (Origins::Any, _) => {}
+        }
+        self.check_credentials();
+        self
+    }
+
+    /// Allow an additional request method.
+    ///
+    /// Default is `GET`, `HEAD` and `POST`.
+    pub fn allow_method(mut self, method: Method) -> Self {
+        if !self.methods.contains(&method) {
+            self.methods.push(method);
+        }
+        self
+    }
+
+    /// Allow an additional request header.
+    pub fn allow_header(mut self, name: &str) -> Self {
+        match HeaderName::try_from(name) {
+            Ok(name) => {
+                if let Some(headers) = self.headers.as_mut() {
+                    headers.push(name);
+                }
+            }
+            Err(_) => tracing::error!("invalid cors header name: {name:?}"),
+        }
+        self
+    }
+
+    /// Allow every request header requested by the preflight.
+    pub fn allow_any_header(mut self) -> Self {
+        self.headers = None;
+        self
+    }
+
+    /// Set how long browsers may cache preflight responses.
+    ///
+    /// Default is unset, leaving the browser default.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Allow requests including credentials such as cookies.
+    ///
+    /// Default is disabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics when enabled while every origin is allowed, since echoing
+    /// any origin with credentials lets every site make credentialed reads.
+    pub fn allow_credentials(mut self, credentials: bool) -> Self {
+        self.credentials = credentials;
+        self.check_credentials();
+        self
+    }
+
+    /// Reject credentials combined with a wildcard origin
+    fn check_credentials(&self) {
+        if self.credentials && matches!(self.origins, Origins::Any) {
+            panic!("cors credentials cannot be allowed for any origin");
+        }
+    }
+
+    /// Check if the origin is allowed
+    fn is_allowed(&self, origin: &str) -> bool {
+        match &self.origins {
+            Origins::Any => true,
+            Origins::List(origins) => origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin)),
+        }
+    }
+
+    /// Add the origin headers for an allowed origin
+    fn allow(&self, origin: &HeaderValue, headers: &mut HeaderMap) {
+        match &self.origins {
+            Origins::Any => {
+                let any = HeaderValue::from_static("*");
+                headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, any);
+            }
+            Origins::List(_) => {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+                headers.append(header::VARY, HeaderValue::from_static("Origin"));
+            }
+        }
+        if self.credentials {
+            let allow = HeaderValue::from_static("true");
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, allow);
+        }
+    }
+
+    /// Answer the request if it is a CORS preflight.
+    ///
+    /// Returns `None` for any other request.
+    pub fn preflight(&self, req: &ServiceRequest) -> Option<HttpResponse> {
+        let headers = req.headers();
+        let origin = headers.get(header::ORIGIN)?;
+        let method = headers.get(header::ACCESS_CONTROL_REQUEST_METHOD)?;
+        if req.method() != Method::OPTIONS {
+            return None;
+        }
+
+        let allowed_origin = origin.to_str().is_ok_and(|origin| self.is_allowed(origin));
+        let allowed_method = Method::from_bytes(method.as_bytes())
+            .is_ok_and(|method| self.methods.contains(&method));
+        let requested: Vec<_> = headers
+            .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        let allowed_headers = match self.headers.as_ref() {
+            None => true,
+            Some(allowed) => requested.iter().all(|name| {
+                allowed
+                    .iter()
+                    .any(|h| h.as_str().eq_ignore_ascii_case(name))
+            }),
+        };
+        if !(allowed_origin && allowed_method && allowed_headers) {
+            tracing::debug!("rejecting cors preflight from {origin:?}");
+            return Some(HttpResponse::Forbidden().finish());
+        }
+
+        let mut res = HttpResponse::NoContent().finish();
+        let res_headers = res.headers_mut();
+        self.allow(origin, res_headers);
+        let methods = self.methods.iter().map(Method::as_str);
+        let methods = methods.collect::<Vec<_>>().join(", ");
+        if let Ok(methods) = HeaderValue::from_str(&methods) {
+            res_headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if !requested.is_empty()
+            && let Ok(allow) = HeaderValue::from_str(&requested.join(", "))
+        {
+            res_headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allow);
+        }
+        if let Some(max_age) = self.max_age {
+            res_headers.insert(header::ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+        }
+        Some(res)
+    }
+
+    /// Add the CORS headers to the response of an actual request.
+    ///
+    /// Responses to requests without an allowed `Origin` are left untouched.
+    pub fn apply<B>(&self, origin: Option<&HeaderValue>, res: &mut HttpResponse<B>) {
+        let Some(origin) = origin else {
+            return;
+        };
+        if origin.to_str().is_ok_and(|origin| self.is_allowed(origin)) {
+            self.allow(origin, res.headers_mut());
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Per-Request Deadline Shared with Upstream Services
+
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpMessage, HttpRequest};
+
+/// Point in time by which the response to a request is due
+///
+/// Stored in the request extensions by the service enforcing the
+/// deadline, such as a chain, so upstream services can forward the
+/// remaining budget to their backends. Setting a deadline never extends
+/// an earlier one already assigned to the request.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_web::test::TestRequest;
+/// use actix_common::{Deadline, deadline};
+///
+/// let req = TestRequest::default().to_http_request();
+/// Deadline::after(Duration::from_secs(5)).apply(&req);
+/// Deadline::after(Duration::from_secs(30)).apply(&req);
+///
+/// let remaining = deadline(&req).unwrap().remaining();
+/// assert!(remaining <= Duration::from_secs(5));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Construct a deadline at the specified instant.
+    #[inline]
+    pub fn new(at: Instant) -> Self {
+        Self(at)
+    }
+
+    /// Construct a deadline the specified duration from now.
+    #[inline]
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now() + budget)
+    }
+
+    /// Instant the deadline expires at.
+    #[inline]
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Time left until the deadline expires.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    /// Check if the deadline already expired.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.0 <= Instant::now()
+    }
+
+    /// Assign the deadline to the request unless an earlier one is assigned.
+    pub fn apply(self, req: &HttpRequest) {
+        let mut extensions = req.extensions_mut();
+        match extensions.get::<Self>() {
+            Some(existing) if *existing <= self => {}
+            _ => {
+                extensions.insert(self);
+            }
+        }
+    }
+}
+
+/// Retrieve the deadline assigned to the request, if any.
+#[inline]
+pub fn deadline(req: &HttpRequest) -> Option<Deadline> {
+    req.extensions().get::<Deadline>().copied()
+}
@@ -0,0 +1,57 @@
+//! Error and Result Types
+
+use actix_web::{HttpResponse, ResponseError, body::BoxBody, http::StatusCode};
+use derive_more::{Display, Error, From};
+
+use crate::{ErrorKind, GatewayError};
+
+/// Errors which occur within shared utilities
+#[derive(Debug, Display, From, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Invalid address within CIDR notation
+    #[display("Invalid CIDR address")]
+    InvalidAddr(std::net::AddrParseError),
+
+    /// Invalid prefix length within CIDR notation
+    #[display("Invalid CIDR prefix length")]
+    InvalidPrefix,
+
+    /// Service concurrency limit and queue are exhausted
+    #[display("Service Overloaded")]
+    Overloaded,
+
+    /// Request time budget was exhausted before a response was produced
+    #[display("Deadline Exceeded")]
+    DeadlineExceeded,
+
+    /// Invalid or unknown placeholder within a header template
+    #[display("Invalid template: {_0:?}")]
+    #[from(skip)]
+    InvalidTemplate(#[error(not(source))] String),
+}
+
+impl GatewayError for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Overloaded => ErrorKind::Overloaded,
+            Self::DeadlineExceeded => ErrorKind::UpstreamTimeout,
+            Self::InvalidAddr(_) | Self::InvalidPrefix | Self::InvalidTemplate(_) => {
+                ErrorKind::Config
+            }
+        }
+    }
+}
+
+impl ResponseError for Error {
+    /// Returns `503 Service Unavailable` when overloaded, `504 Gateway
+    /// Timeout` when the deadline is exceeded and `500 Internal Server
+    /// Error` otherwise.
+    fn status_code(&self) -> StatusCode {
+        self.kind().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        self.gateway_response()
+    }
+}
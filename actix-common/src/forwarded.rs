@@ -0,0 +1,198 @@
+//! Trusted Proxy and Client Address Resolution
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use actix_web::{
+    HttpRequest,
+    http::header::{self, HeaderMap},
+};
+
+use crate::Error;
+
+/// IPv4/IPv6 network address in CIDR notation
+///
+/// Single addresses without a prefix length are treated as host routes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Check if the specified address is contained within the network.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    fn from(addr: IpAddr) -> Self {
+        let prefix = if addr.is_ipv4() { 32 } else { 128 };
+        Self { addr, prefix }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((addr, prefix)) = s.trim().split_once('/') else {
+            return Ok(Self::from(IpAddr::from_str(s.trim())?));
+        };
+        let addr = IpAddr::from_str(addr)?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = u8::from_str(prefix)
+            .ok()
+            .filter(|prefix| *prefix <= max)
+            .ok_or(Error::InvalidPrefix)?;
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl TryFrom<&str> for Cidr {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Trusted reverse-proxy networks used to resolve the real client address
+///
+/// Register as app-data so all services agree on the client address.
+/// When the connecting peer is trusted the `Forwarded` header is consulted
+/// first, then `X-Forwarded-For`, walking the chain from the nearest hop until
+/// an untrusted address is found.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::App;
+/// use actix_common::TrustedProxies;
+///
+/// let app = App::new()
+///     .app_data(TrustedProxies::new().trust("10.0.0.0/8").trust("127.0.0.1"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(Vec<Cidr>);
+
+impl TrustedProxies {
+    /// Create an empty trusted proxy list which trusts no peers.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a trusted network in CIDR notation.
+    pub fn trust<C>(mut self, cidr: C) -> Self
+    where
+        C: TryInto<Cidr>,
+        C::Error: std::fmt::Debug,
+    {
+        match cidr.try_into() {
+            Ok(cidr) => self.0.push(cidr),
+            Err(err) => tracing::warn!("invalid trusted proxy: {err:?}"),
+        }
+        self
+    }
+
+    /// Check if the specified address belongs to a trusted proxy.
+    #[inline]
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Resolve the real client address of the specified request.
+    ///
+    /// Forwarded addresses without a port are returned with port `0`.
+    #[inline]
+    pub fn client_addr(&self, req: &HttpRequest) -> Option<SocketAddr> {
+        Some(self.resolve(req.peer_addr()?, req.headers()))
+    }
+
+    /// Resolve the real client address from the connection peer address
+    /// and the request headers.
+    ///
+    /// Forwarded addresses without a port are returned with port `0`.
+    pub fn resolve(&self, peer: SocketAddr, headers: &HeaderMap) -> SocketAddr {
+        if !self.is_trusted(peer.ip()) {
+            return peer;
+        }
+        let mut chain = forwarded_for(headers);
+        if chain.is_empty() {
+            chain = x_forwarded_for(headers);
+        }
+        let mut client = peer;
+        for node in chain.into_iter().rev() {
+            let Some(addr) = parse_node(node) else {
+                break;
+            };
+            client = addr;
+            if !self.is_trusted(addr.ip()) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Resolve the real client address of the specified request
+///
+/// Uses the [`TrustedProxies`] registered as app-data, falling back to
+/// the connection peer address when none are configured.
+pub fn client_addr(req: &HttpRequest) -> Option<SocketAddr> {
+    match req.app_data::<TrustedProxies>() {
+        Some(proxies) => proxies.client_addr(req),
+        None => req.peer_addr(),
+    }
+}
+
+/// Collect `for=` node values from all `Forwarded` headers.
+fn forwarded_for(headers: &HeaderMap) -> Vec<&str> {
+    headers
+        .get_all(header::FORWARDED)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.trim().split_once('=')?;
+                key.eq_ignore_ascii_case("for")
+                    .then(|| value.trim().trim_matches('"'))
+            })
+        })
+        .collect()
+}
+
+/// Collect node values from all `X-Forwarded-For` headers.
+fn x_forwarded_for(headers: &HeaderMap) -> Vec<&str> {
+    headers
+        .get_all(header::X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|node| !node.is_empty())
+        .collect()
+}
+
+/// Parse a forwarded node identifier into a socket address.
+fn parse_node(node: &str) -> Option<SocketAddr> {
+    if let Ok(addr) = SocketAddr::from_str(node) {
+        return Some(addr);
+    }
+    let ip = node.trim_start_matches('[').trim_end_matches(']');
+    IpAddr::from_str(ip).ok().map(|ip| SocketAddr::new(ip, 0))
+}
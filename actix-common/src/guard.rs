@@ -0,0 +1,84 @@
+//! Client Address Allow-List Guard
+
+use std::net::IpAddr;
+
+use actix_web::guard::{Guard, GuardContext};
+
+use crate::{Cidr, TrustedProxies};
+
+/// Routing guard matching requests from allowed client networks
+///
+/// The client address is resolved through the [`TrustedProxies`]
+/// registered as app-data, the same way as [`client_addr`](crate::client_addr),
+/// so forwarded requests are matched against the real client instead of
+/// the proxy. Requests without a peer address never match.
+///
+/// Usable with any service accepting an [`actix_web::guard::Guard`].
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::{App, web};
+/// use actix_common::{ClientIpGuard, TrustedProxies};
+///
+/// let app = App::new()
+///     .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+///     .service(
+///         web::resource("/admin")
+///             .guard(ClientIpGuard::new().allow("192.168.0.0/16").allow("::1"))
+///             .to(|| async { "admin" }),
+///     );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ClientIpGuard {
+    allow: Vec<Cidr>,
+}
+
+impl ClientIpGuard {
+    /// Construct a new guard which allows no clients.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow clients from the specified network in CIDR notation.
+    pub fn allow<C>(mut self, cidr: C) -> Self
+    where
+        C: TryInto<Cidr>,
+        C::Error: std::fmt::Debug,
+    {
+        match cidr.try_into() {
+            Ok(cidr) => self.allow.push(cidr),
+            Err(err) => tracing::warn!("invalid client network: {err:?}"),
+        }
+        self
+    }
+
+    /// Check if the specified client address is allowed.
+    #[inline]
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        self.allow.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+impl FromIterator<Cidr> for ClientIpGuard {
+    fn from_iter<T: IntoIterator<Item = Cidr>>(iter: T) -> Self {
+        Self {
+            allow: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Guard for ClientIpGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        let head = ctx.head();
+        let Some(peer) = head.peer_addr else {
+            return false;
+        };
+        let client = match ctx.app_data::<TrustedProxies>() {
+            Some(proxies) => proxies.resolve(peer, &head.headers),
+            None => peer,
+        };
+        self.is_allowed(client.ip())
+    }
+}
@@ -0,0 +1,57 @@
+//! Validated Client Identity
+
+use std::future::{Ready, ready};
+
+use actix_web::{
+    Error, FromRequest, HttpMessage, HttpRequest, dev::Payload, error::ErrorUnauthorized,
+};
+
+/// Identity of a client validated by an authentication middleware
+///
+/// Inserted into the request extensions once credentials have been
+/// verified so downstream services can rely on the request being
+/// authenticated without re-checking the `Authorization` header.
+///
+/// Handlers may extract the identity directly, answering requests that
+/// were not authenticated with `401 Unauthorized`.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::{HttpMessage, test::TestRequest};
+/// use actix_common::Identity;
+///
+/// let req = TestRequest::default().to_srv_request();
+/// req.extensions_mut().insert(Identity::new("admin"));
+///
+/// let extensions = req.extensions();
+/// assert_eq!(extensions.get::<Identity>().map(Identity::user), Some("admin"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    user: String,
+}
+
+impl Identity {
+    /// Construct the identity of an authenticated user.
+    pub fn new<S: Into<String>>(user: S) -> Self {
+        Self { user: user.into() }
+    }
+
+    /// Name of the authenticated user.
+    #[inline]
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+}
+
+impl FromRequest for Identity {
+    type Error = Error;
+    type Future = Ready<Result<Self, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let identity = req.extensions().get::<Self>().cloned();
+        ready(identity.ok_or_else(|| ErrorUnauthorized("request is not authenticated")))
+    }
+}
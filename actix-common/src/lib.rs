@@ -0,0 +1,54 @@
+//! Shared request utilities for Actix-Web services.
+//!
+//! Provides common building blocks shared across the individual
+//! services so they agree on request handling behavior.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::App;
+//! use actix_common::TrustedProxies;
+//!
+//! let app = App::new()
+//!     .app_data(TrustedProxies::new().trust("10.0.0.0/8").trust("::1"));
+//! ```
+pub mod body;
+mod client_cert;
+mod concurrency;
+mod cors;
+mod deadline;
+mod error;
+pub mod forwarded;
+mod guard;
+mod identity;
+mod maintenance;
+mod methods;
+pub mod metrics;
+mod normalize;
+mod pages;
+mod prefix;
+pub mod problem;
+#[cfg(feature = "opentelemetry")]
+pub mod telemetry;
+mod template;
+mod transaction;
+
+pub use client_cert::{ClientCert, client_cert};
+pub use concurrency::{Concurrency, Permit};
+pub use cors::Cors;
+pub use deadline::{Deadline, deadline};
+pub use error::Error;
+pub use forwarded::{Cidr, TrustedProxies, client_addr};
+pub use guard::ClientIpGuard;
+pub use identity::Identity;
+pub use maintenance::Maintenance;
+pub use methods::AllowedMethods;
+pub use normalize::{NormalizeService, Normalizer, normalized_uri};
+pub use pages::{ErrorHandler, ErrorPages};
+pub use prefix::{PathPrefix, TrailingSlash};
+pub use problem::{ErrorKind, GatewayError};
+pub use template::Template;
+pub use transaction::{MAX_TRANSACTION_ID_LEN, TransactionId, X_REQUEST_ID, transaction_id};
+
+#[cfg(feature = "problem-details")]
+pub use problem::ProblemDetails;
@@ -0,0 +1,174 @@
+//! Runtime-Toggleable Maintenance Mode
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{StatusCode, header},
+};
+
+use crate::{Cidr, PathPrefix, client_addr};
+
+/// Default `Retry-After` sent while in maintenance
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Default page sent while in maintenance
+const DEFAULT_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>503 Service Unavailable</title></head>
+<body>
+<h1>Down for Maintenance</h1>
+<p>The service is undergoing maintenance. Please try again shortly.</p>
+<hr><address>actix-services</address>
+</body>
+</html>
+"#;
+
+/// Maintenance mode answering requests with `503 Service Unavailable`
+///
+/// While enabled, requests are short-circuited with a `Retry-After`
+/// header and the maintenance page instead of reaching any upstream.
+/// Requests for allowed path prefixes or from allowed client networks
+/// bypass maintenance, so health checks and operators can still reach
+/// the service during a deploy window. Client addresses are resolved
+/// using the [`TrustedProxies`](crate::TrustedProxies) app-data.
+///
+/// Clones share the enabled state, so a single handle can switch every
+/// service it was passed to at runtime.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_common::Maintenance;
+///
+/// let maintenance = Maintenance::new()
+///     .allow_path("/health")
+///     .allow_ip("10.0.0.0/8")
+///     .retry_after(Duration::from_secs(300));
+///
+/// maintenance.enable();
+/// assert!(maintenance.is_enabled());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Maintenance {
+    enabled: Arc<AtomicBool>,
+    allow_paths: Vec<PathPrefix>,
+    allow_ips: Vec<Cidr>,
+    retry_after: Duration,
+    page: String,
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Maintenance {
+    /// Construct a new disabled maintenance mode.
+    pub fn new() -> Self {
+        Self {
+            enabled: Arc::default(),
+            allow_paths: Vec::new(),
+            allow_ips: Vec::new(),
+            retry_after: DEFAULT_RETRY_AFTER,
+            page: DEFAULT_PAGE.to_owned(),
+        }
+    }
+
+    /// Let requests for the specified path prefix bypass maintenance.
+    pub fn allow_path<P: Into<PathPrefix>>(mut self, prefix: P) -> Self {
+        self.allow_paths.push(prefix.into());
+        self
+    }
+
+    /// Let clients from the specified network in CIDR notation bypass maintenance.
+    pub fn allow_ip<C>(mut self, cidr: C) -> Self
+    where
+        C: TryInto<Cidr>,
+        C::Error: std::fmt::Debug,
+    {
+        match cidr.try_into() {
+            Ok(cidr) => self.allow_ips.push(cidr),
+            Err(err) => tracing::warn!("invalid maintenance network: {err:?}"),
+        }
+        self
+    }
+
+    /// Duration clients are asked to wait before retrying.
+    ///
+    /// Default is 60 seconds.
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Replace the `text/html` page sent while in maintenance.
+    pub fn page<S: Into<String>>(mut self, page: S) -> Self {
+        self.page = page.into();
+        self
+    }
+
+    /// Start answering requests with the maintenance page.
+    #[inline]
+    pub fn enable(&self) {
+        self.set_enabled(true);
+    }
+
+    /// Stop answering requests with the maintenance page.
+    #[inline]
+    pub fn disable(&self) {
+        self.set_enabled(false);
+    }
+
+    /// Switch maintenance mode on or off.
+    pub fn set_enabled(&self, enabled: bool) {
+        if self.enabled.swap(enabled, Ordering::Relaxed) != enabled {
+            tracing::info!(
+                "maintenance mode {}",
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+    }
+
+    /// Check if maintenance mode is enabled.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Check if the request bypasses maintenance
+    fn is_allowed(&self, req: &HttpRequest) -> bool {
+        if self
+            .allow_paths
+            .iter()
+            .any(|prefix| prefix.matches(req.path()))
+        {
+            return true;
+        }
+        !self.allow_ips.is_empty()
+            && client_addr(req)
+                .is_some_and(|addr| self.allow_ips.iter().any(|cidr| cidr.contains(addr.ip())))
+    }
+
+    /// Build the maintenance response if the request must be short-circuited.
+    pub fn check(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        if !self.is_enabled() || self.is_allowed(req) {
+            return None;
+        }
+        Some(
+            HttpResponse::build(StatusCode::SERVICE_UNAVAILABLE)
+                .insert_header((header::RETRY_AFTER, self.retry_after.as_secs()))
+                .insert_header((header::CACHE_CONTROL, "no-store"))
+                .content_type("text/html; charset=utf-8")
+                .body(self.page.clone()),
+        )
+    }
+}
@@ -0,0 +1,154 @@
+//! Per-Service Request Method Allow-List
+
+use actix_web::{
+    HttpResponse,
+    dev::ServiceRequest,
+    http::{Method, header},
+};
+
+/// Methods accepted by default
+const DEFAULT_METHODS: [Method; 7] = [
+    Method::GET,
+    Method::HEAD,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::OPTIONS,
+];
+
+/// WebDAV methods (RFC 4918)
+const WEBDAV_METHODS: [&str; 7] = [
+    "PROPFIND",
+    "PROPPATCH",
+    "MKCOL",
+    "COPY",
+    "MOVE",
+    "LOCK",
+    "UNLOCK",
+];
+
+/// Request methods accepted by a service
+///
+/// Requests using any other method, such as `TRACE` or `TRACK`, are
+/// answered locally with `405 Method Not Allowed` and an `Allow` header
+/// listing the accepted methods, without involving the backend.
+///
+/// Requests for multiple byte ranges may additionally have their `Range`
+/// header removed so the backend serves the full representation instead
+/// of an expensive `multipart/byteranges` response.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::http::Method;
+/// use actix_common::AllowedMethods;
+///
+/// let methods = AllowedMethods::new([Method::GET, Method::HEAD])
+///     .allow(Method::from_bytes(b"PROPFIND").unwrap())
+///     .multipart_ranges(false);
+///
+/// assert!(methods.is_allowed(&Method::GET));
+/// assert!(!methods.is_allowed(&Method::TRACE));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AllowedMethods {
+    methods: Option<Vec<Method>>,
+    multipart_ranges: bool,
+}
+
+impl Default for AllowedMethods {
+    /// Accept `GET`, `HEAD`, `POST`, `PUT`, `PATCH`, `DELETE` and `OPTIONS`.
+    fn default() -> Self {
+        Self::new(DEFAULT_METHODS)
+    }
+}
+
+impl AllowedMethods {
+    /// Create a new allow-list accepting only the specified methods.
+    pub fn new<I: IntoIterator<Item = Method>>(methods: I) -> Self {
+        Self {
+            methods: Some(methods.into_iter().collect()),
+            multipart_ranges: true,
+        }
+    }
+
+    /// Create a new allow-list accepting every method.
+    pub fn any() -> Self {
+        Self {
+            methods: None,
+            multipart_ranges: true,
+        }
+    }
+
+    /// Accept an additional method.
+    pub fn allow(mut self, method: Method) -> Self {
+        if let Some(methods) = self.methods.as_mut()
+            && !methods.contains(&method)
+        {
+            methods.push(method);
+        }
+        self
+    }
+
+    /// Accept the WebDAV methods `PROPFIND`, `PROPPATCH`, `MKCOL`, `COPY`,
+    /// `MOVE`, `LOCK` and `UNLOCK`.
+    pub fn webdav(self) -> Self {
+        WEBDAV_METHODS.into_iter().fold(self, |methods, name| {
+            methods.allow(Method::from_bytes(name.as_bytes()).expect("valid method"))
+        })
+    }
+
+    /// Allow requests for multiple byte ranges.
+    ///
+    /// When disabled, multi-range `Range` headers are removed and the full
+    /// representation is served instead.
+    ///
+    /// Default is enabled.
+    pub fn multipart_ranges(mut self, multipart_ranges: bool) -> Self {
+        self.multipart_ranges = multipart_ranges;
+        self
+    }
+
+    /// Check if the method is accepted.
+    #[inline]
+    pub fn is_allowed(&self, method: &Method) -> bool {
+        self.methods
+            .as_ref()
+            .is_none_or(|methods| methods.contains(method))
+    }
+
+    /// Value of the `Allow` header listing the accepted methods
+    fn allow_header(&self) -> String {
+        self.methods
+            .iter()
+            .flatten()
+            .map(Method::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Apply the allow-list to the request.
+    ///
+    /// Returns the `405 Method Not Allowed` response for rejected methods
+    /// and strips multi-range `Range` headers when disabled.
+    pub fn check(&self, req: &mut ServiceRequest) -> Option<HttpResponse> {
+        if !self.is_allowed(req.method()) {
+            tracing::debug!("rejecting method {:?}", req.method());
+            return Some(
+                HttpResponse::MethodNotAllowed()
+                    .insert_header((header::ALLOW, self.allow_header()))
+                    .finish(),
+            );
+        }
+        let multipart = req
+            .headers()
+            .get(header::RANGE)
+            .is_some_and(|range| range.as_bytes().contains(&b','));
+        if multipart && !self.multipart_ranges {
+            tracing::debug!("ignoring multipart range request");
+            req.headers_mut().remove(header::RANGE);
+        }
+        None
+    }
+}
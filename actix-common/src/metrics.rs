@@ -0,0 +1,340 @@
+//! Request Metrics Facade
+//!
+//! Services record request and response sizes along with request durations
+//! as histograms, and notable events as counters, through a process-wide
+//! [`MetricsSink`] using consistent metric names and labels, so every
+//! service shares the same dashboards.
+//!
+//! | Metric                         | Unit    |
+//! | ------------------------------ | ------- |
+//! | [`REQUEST_DURATION`]           | seconds |
+//! | [`REQUEST_SIZE`]               | bytes   |
+//! | [`RESPONSE_SIZE`]              | bytes   |
+//! | [`CLIENT_ABORTS`]              | total   |
+//!
+//! Every metric is labeled with [`LABELS`]: the `service` kind, the `mount`
+//! path of the service and the `upstream` the request was sent to.
+//!
+//! Sinks for the `prometheus` and `metrics` crates are available with the
+//! features of the same name. Nothing is recorded until a sink is installed.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_common::metrics::{self, Labels, MetricsSink};
+//!
+//! struct LogSink;
+//!
+//! impl MetricsSink for LogSink {
+//!     fn histogram(&self, name: &'static str, labels: &Labels<'_>, value: f64) {
+//!         println!("{name}{{service={:?}}} {value}", labels.service);
+//!     }
+//! }
+//!
+//! metrics::set_sink(LogSink);
+//! ```
+
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    Error, HttpMessage, HttpRequest,
+    body::{BodySize, MessageBody},
+    dev::ServiceResponse,
+    http::header,
+};
+
+/// Histogram of request durations until the response head in seconds
+pub const REQUEST_DURATION: &str = "actix_services_request_duration_seconds";
+
+/// Histogram of request body sizes in bytes
+pub const REQUEST_SIZE: &str = "actix_services_request_size_bytes";
+
+/// Histogram of response body sizes in bytes
+pub const RESPONSE_SIZE: &str = "actix_services_response_size_bytes";
+
+/// Counter of responses abandoned by the client before completion
+pub const CLIENT_ABORTS: &str = "actix_services_client_aborts_total";
+
+/// Label names attached to every metric
+pub const LABELS: [&str; 3] = ["service", "mount", "upstream"];
+
+static SINK: OnceLock<Box<dyn MetricsSink>> = OnceLock::new();
+
+/// Labels attached to a single metric sample
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Labels<'a> {
+    /// Kind of service such as `fastcgi` or `revproxy`.
+    pub service: &'a str,
+    /// Mount path of the service.
+    pub mount: &'a str,
+    /// Upstream the request was sent to, empty when not applicable.
+    pub upstream: &'a str,
+}
+
+impl Labels<'_> {
+    /// Label values in the order of [`LABELS`].
+    #[inline]
+    pub fn values(&self) -> [&str; 3] {
+        [self.service, self.mount, self.upstream]
+    }
+}
+
+/// Backend receiving the metrics recorded by services
+pub trait MetricsSink: Send + Sync + 'static {
+    /// Record a single histogram sample.
+    fn histogram(&self, name: &'static str, labels: &Labels<'_>, value: f64);
+
+    /// Increment a counter by the specified value.
+    ///
+    /// Default discards the increment.
+    fn counter(&self, name: &'static str, labels: &Labels<'_>, value: u64) {
+        let _ = (name, labels, value);
+    }
+}
+
+/// Install the process-wide metrics sink.
+///
+/// Returns `false` if a sink was already installed.
+pub fn set_sink<S: MetricsSink>(sink: S) -> bool {
+    SINK.set(Box::new(sink)).is_ok()
+}
+
+/// Currently installed metrics sink
+#[inline]
+pub fn sink() -> Option<&'static dyn MetricsSink> {
+    SINK.get().map(|sink| sink.as_ref())
+}
+
+/// Increment a counter of the installed sink, if any.
+#[inline]
+pub fn increment(name: &'static str, labels: &Labels<'_>) {
+    if let Some(sink) = sink() {
+        sink.counter(name, labels, 1);
+    }
+}
+
+/// Upstream label stored within the request extensions
+#[derive(Clone, Debug)]
+struct Upstream(String);
+
+/// Label the request metrics with the upstream the request was sent to.
+pub fn set_upstream<S: Into<String>>(req: &HttpRequest, upstream: S) {
+    if sink().is_some() {
+        req.extensions_mut().insert(Upstream(upstream.into()));
+    }
+}
+
+/// Measurement of a single service call
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::{HttpResponse, test::TestRequest};
+/// use actix_common::metrics::Timer;
+///
+/// let req = TestRequest::default().to_srv_request();
+/// let timer = Timer::start("example", "/", req.request());
+/// let res = Ok(req.into_response(HttpResponse::Ok().body("hello")));
+/// timer.finish(&res);
+/// ```
+pub struct Timer {
+    service: &'static str,
+    mount: String,
+    start: Instant,
+    request_size: Option<u64>,
+}
+
+impl Timer {
+    /// Start measuring a service call for the specified request.
+    pub fn start(service: &'static str, mount: &str, req: &HttpRequest) -> Self {
+        let request_size = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        Self {
+            service,
+            mount: mount.to_owned(),
+            start: Instant::now(),
+            request_size,
+        }
+    }
+
+    /// Time elapsed since the call started.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Record the metrics of the finished service call.
+    ///
+    /// Response sizes are only recorded for bodies of a known size.
+    pub fn finish<B: MessageBody>(self, res: &Result<ServiceResponse<B>, Error>) {
+        let Some(sink) = sink() else {
+            return;
+        };
+        let upstream = res
+            .as_ref()
+            .ok()
+            .and_then(|res| res.request().extensions().get::<Upstream>().cloned())
+            .map(|upstream| upstream.0)
+            .unwrap_or_default();
+        let labels = Labels {
+            service: self.service,
+            mount: &self.mount,
+            upstream: &upstream,
+        };
+        sink.histogram(REQUEST_DURATION, &labels, self.elapsed().as_secs_f64());
+        if let Some(size) = self.request_size {
+            sink.histogram(REQUEST_SIZE, &labels, size as f64);
+        }
+        if let Ok(res) = res
+            && let BodySize::Sized(size) = res.response().body().size()
+        {
+            sink.histogram(RESPONSE_SIZE, &labels, size as f64);
+        }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+mod prom {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+
+    use super::*;
+
+    /// [`MetricsSink`] recording into a [`prometheus::Registry`]
+    ///
+    /// Histograms and counters are registered with the registry when first
+    /// recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_common::metrics::{self, PrometheusSink};
+    ///
+    /// let registry = prometheus::Registry::new();
+    /// metrics::set_sink(PrometheusSink::new(registry.clone()));
+    /// ```
+    pub struct PrometheusSink {
+        registry: Registry,
+        histograms: Mutex<HashMap<&'static str, HistogramVec>>,
+        counters: Mutex<HashMap<&'static str, IntCounterVec>>,
+    }
+
+    impl PrometheusSink {
+        /// Create a new sink registering metrics with the specified registry.
+        pub fn new(registry: Registry) -> Self {
+            Self {
+                registry,
+                histograms: Mutex::default(),
+                counters: Mutex::default(),
+            }
+        }
+
+        /// Build and register the histogram for the metric
+        fn register(&self, name: &'static str) -> prometheus::Result<HistogramVec> {
+            let opts = match name {
+                REQUEST_DURATION => HistogramOpts::new(name, "Service request duration"),
+                REQUEST_SIZE => HistogramOpts::new(name, "Service request body size")
+                    .buckets(prometheus::exponential_buckets(64.0, 4.0, 10)?),
+                RESPONSE_SIZE => HistogramOpts::new(name, "Service response body size")
+                    .buckets(prometheus::exponential_buckets(64.0, 4.0, 10)?),
+                _ => HistogramOpts::new(name, name),
+            };
+            let histogram = HistogramVec::new(opts, &LABELS)?;
+            self.registry.register(Box::new(histogram.clone()))?;
+            Ok(histogram)
+        }
+
+        /// Build and register the counter for the metric
+        fn register_counter(&self, name: &'static str) -> prometheus::Result<IntCounterVec> {
+            let opts = match name {
+                CLIENT_ABORTS => Opts::new(name, "Responses abandoned by the client"),
+                _ => Opts::new(name, name),
+            };
+            let counter = IntCounterVec::new(opts, &LABELS)?;
+            self.registry.register(Box::new(counter.clone()))?;
+            Ok(counter)
+        }
+    }
+
+    impl MetricsSink for PrometheusSink {
+        fn histogram(&self, name: &'static str, labels: &Labels<'_>, value: f64) {
+            let mut histograms = self.histograms.lock().expect("poisoned lock");
+            let histogram = match histograms.get(name) {
+                Some(histogram) => histogram.clone(),
+                None => match self.register(name) {
+                    Ok(histogram) => histograms.entry(name).or_insert(histogram).clone(),
+                    Err(err) => {
+                        tracing::error!("failed to register metric {name}: {err}");
+                        return;
+                    }
+                },
+            };
+            drop(histograms);
+            histogram.with_label_values(&labels.values()).observe(value);
+        }
+
+        fn counter(&self, name: &'static str, labels: &Labels<'_>, value: u64) {
+            let mut counters = self.counters.lock().expect("poisoned lock");
+            let counter = match counters.get(name) {
+                Some(counter) => counter.clone(),
+                None => match self.register_counter(name) {
+                    Ok(counter) => counters.entry(name).or_insert(counter).clone(),
+                    Err(err) => {
+                        tracing::error!("failed to register metric {name}: {err}");
+                        return;
+                    }
+                },
+            };
+            drop(counters);
+            counter.with_label_values(&labels.values()).inc_by(value);
+        }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+pub use prom::PrometheusSink;
+
+/// [`MetricsSink`] recording through the [`metrics`](::metrics) crate facade
+///
+/// Samples are recorded using the globally installed `metrics` recorder.
+///
+/// # Examples
+///
+/// ```
+/// use actix_common::metrics::{self, MetricsRsSink};
+///
+/// metrics::set_sink(MetricsRsSink);
+/// ```
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsRsSink;
+
+#[cfg(feature = "metrics")]
+impl MetricsSink for MetricsRsSink {
+    fn histogram(&self, name: &'static str, labels: &Labels<'_>, value: f64) {
+        ::metrics::histogram!(
+            name,
+            "service" => labels.service.to_owned(),
+            "mount" => labels.mount.to_owned(),
+            "upstream" => labels.upstream.to_owned(),
+        )
+        .record(value);
+    }
+
+    fn counter(&self, name: &'static str, labels: &Labels<'_>, value: u64) {
+        ::metrics::counter!(
+            name,
+            "service" => labels.service.to_owned(),
+            "mount" => labels.mount.to_owned(),
+            "upstream" => labels.upstream.to_owned(),
+        )
+        .increment(value);
+    }
+}
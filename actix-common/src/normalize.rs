@@ -0,0 +1,341 @@
+//! Consistent Request URI Normalization Across Services
+
+use std::{
+    future::{Ready, ready},
+    rc::Rc,
+    str::FromStr,
+};
+
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    body::EitherBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+    http::{
+        Uri,
+        header::{self, HeaderValue},
+    },
+};
+use futures_core::future::LocalBoxFuture;
+
+/// Request uri normalization applied before rule evaluation
+///
+/// Registered as app-data so every inspecting service (ModSecurity,
+/// rewrite engine) evaluates the same canonical form of the request uri.
+/// Header names need no normalization as they are always lowercased by
+/// the HTTP parser.
+///
+/// Also usable as middleware rewriting the request uri itself. Wrapped
+/// outermost, FastCGI path mapping, rewrite matching and WAF analysis all
+/// see the canonical uri, and requests with control characters in the
+/// path are rejected with `400 Bad Request`.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::App;
+/// use actix_common::Normalizer;
+///
+/// let app = App::new().app_data(Normalizer::new());
+///
+/// let app = App::new().wrap(Normalizer::new().lowercase_host(true));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Normalizer {
+    decode_percent: bool,
+    collapse_dot_segments: bool,
+    merge_slashes: bool,
+    reject_control: bool,
+    lowercase_host: bool,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            decode_percent: true,
+            collapse_dot_segments: true,
+            merge_slashes: true,
+            reject_control: true,
+            lowercase_host: false,
+        }
+    }
+}
+
+/// Check if the decoded byte is safe to leave unescaped
+///
+/// Escaped slashes in paths are kept since decoding them would split
+/// a single segment into several.
+fn is_decodable(byte: u8, query: bool) -> bool {
+    match byte {
+        b'&' | b'=' | b'+' if query => false,
+        b'/' if !query => false,
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => true,
+        b'-' | b'.' | b'_' | b'~' | b'!' | b'$' | b'&' | b'\'' | b'(' | b')' => true,
+        b'*' | b'+' | b',' | b';' | b'=' | b':' | b'@' | b'/' => true,
+        _ => false,
+    }
+}
+
+/// Decode escapes into bytes, optionally including non-ASCII bytes
+fn decode_bytes(s: &str, query: bool, non_ascii: bool) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escape = (bytes[idx] == b'%')
+            .then(|| s.get(idx + 1..idx + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) if is_decodable(byte, query) || (non_ascii && !byte.is_ascii()) => {
+                decoded.push(byte)
+            }
+            Some(byte) => decoded.extend_from_slice(format!("%{byte:02X}").as_bytes()),
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+                continue;
+            }
+        }
+        idx += 3;
+    }
+    decoded
+}
+
+/// Decode escaped characters that do not need to be escaped
+///
+/// Escapes are decoded into bytes before the result is interpreted as
+/// UTF-8 so escaped multi-byte characters decode as a whole. Escaped
+/// bytes which do not form valid UTF-8 are kept escaped.
+fn decode_percent(s: &str, query: bool) -> String {
+    String::from_utf8(decode_bytes(s, query, true))
+        .or_else(|_| String::from_utf8(decode_bytes(s, query, false)))
+        .expect("decoded ascii escapes of a valid string")
+}
+
+/// Escape the non-ASCII characters of a decoded uri component
+fn encode_non_ascii(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte.is_ascii() {
+            true => encoded.push(byte as char),
+            false => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Check if the path contains control characters, escaped or not
+fn contains_control(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.iter().enumerate().any(|(idx, byte)| {
+        let byte = match *byte {
+            b'%' => path
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .unwrap_or(b'%'),
+            byte => byte,
+        };
+        byte.is_ascii_control()
+    })
+}
+
+/// Collapse runs of consecutive slashes into a single slash
+fn merge_slashes(path: &str) -> String {
+    let mut merged = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c == '/' && merged.ends_with('/') {
+            continue;
+        }
+        merged.push(c);
+    }
+    merged
+}
+
+/// Remove `.` and `..` segments as described by RFC 3986 section 5.2.4
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let trailing = path.ends_with("/.") || path.ends_with("/..");
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut path = format!("/{}", segments.join("/"));
+    if trailing && !path.ends_with('/') {
+        path.push('/');
+    }
+    path
+}
+
+impl Normalizer {
+    /// Construct a normalizer with all normalizations enabled.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode `%xx` escapes of characters that never require escaping.
+    ///
+    /// Escapes of slashes within the path and of structural query
+    /// characters (`&`, `=`, `+`) are kept. Escaped UTF-8 sequences are
+    /// decoded by [`path`](Self::path) and re-escaped in uppercase by
+    /// [`uri`](Self::uri).
+    ///
+    /// Default is enabled.
+    pub fn decode_percent(mut self, enable: bool) -> Self {
+        self.decode_percent = enable;
+        self
+    }
+
+    /// Collapse `.` and `..` path segments after decoding.
+    ///
+    /// Default is enabled.
+    pub fn collapse_dot_segments(mut self, enable: bool) -> Self {
+        self.collapse_dot_segments = enable;
+        self
+    }
+
+    /// Merge duplicate slashes within the path.
+    ///
+    /// Default is enabled.
+    pub fn merge_slashes(mut self, enable: bool) -> Self {
+        self.merge_slashes = enable;
+        self
+    }
+
+    /// Reject requests with NUL or other control characters in the path.
+    ///
+    /// Only applies when used as middleware.
+    ///
+    /// Default is enabled.
+    pub fn reject_control(mut self, enable: bool) -> Self {
+        self.reject_control = enable;
+        self
+    }
+
+    /// Lowercase the request host.
+    ///
+    /// Default is disabled.
+    pub fn lowercase_host(mut self, enable: bool) -> Self {
+        self.lowercase_host = enable;
+        self
+    }
+
+    /// Normalize a request path.
+    pub fn path(&self, path: &str) -> String {
+        let mut path = match self.decode_percent {
+            true => decode_percent(path, false),
+            false => path.to_owned(),
+        };
+        if self.merge_slashes {
+            path = merge_slashes(&path);
+        }
+        if self.collapse_dot_segments && path.starts_with('/') {
+            path = remove_dot_segments(&path);
+        }
+        path
+    }
+
+    /// Normalize a request uri.
+    pub fn uri(&self, uri: &Uri) -> Uri {
+        let mut normalized = String::new();
+        if let Some(scheme) = uri.scheme_str() {
+            normalized.push_str(&format!("{scheme}://"));
+        }
+        if let Some(authority) = uri.authority() {
+            match self.lowercase_host {
+                true => normalized.push_str(&authority.as_str().to_ascii_lowercase()),
+                false => normalized.push_str(authority.as_str()),
+            }
+        }
+        normalized.push_str(&encode_non_ascii(&self.path(uri.path())));
+        if let Some(query) = uri.query() {
+            normalized.push('?');
+            match self.decode_percent {
+                true => normalized.push_str(&encode_non_ascii(&decode_percent(query, true))),
+                false => normalized.push_str(query),
+            }
+        }
+        Uri::from_str(&normalized).unwrap_or_else(|_| uri.clone())
+    }
+}
+
+/// Resolve the normalized uri of the specified request
+///
+/// Uses the [`Normalizer`] registered as app-data, falling back to the
+/// unmodified request uri when none is configured.
+pub fn normalized_uri(req: &HttpRequest) -> Uri {
+    match req.app_data::<Normalizer>() {
+        Some(normalizer) => normalizer.uri(req.uri()),
+        None => req.uri().clone(),
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Normalizer
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = NormalizeService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(NormalizeService {
+            service: Rc::new(service),
+            normalizer: *self,
+        }))
+    }
+}
+
+/// Middleware service produced by [`Normalizer`]
+pub struct NormalizeService<S> {
+    service: Rc<S>,
+    normalizer: Normalizer,
+}
+
+impl<S, B> Service<ServiceRequest> for NormalizeService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if self.normalizer.reject_control && contains_control(req.path()) {
+            tracing::debug!("rejecting request with control characters in path");
+            let res = HttpResponse::BadRequest().finish().map_into_right_body();
+            return Box::pin(async move { Ok(req.into_response(res)) });
+        }
+
+        let uri = self.normalizer.uri(req.uri());
+        if uri != *req.uri() {
+            tracing::trace!("normalized {} to {uri}", req.uri());
+            req.match_info_mut().get_mut().update(&uri);
+            req.head_mut().uri = uri;
+        }
+        if self.normalizer.lowercase_host
+            && let Some(host) = req.headers().get(header::HOST)
+            && let Ok(host) = host.to_str()
+            && host.bytes().any(|b| b.is_ascii_uppercase())
+            && let Ok(value) = HeaderValue::from_str(&host.to_ascii_lowercase())
+        {
+            req.headers_mut().insert(header::HOST, value);
+        }
+
+        let service = self.service.clone();
+        Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) })
+    }
+}
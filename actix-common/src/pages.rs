@@ -0,0 +1,190 @@
+//! Shared Customizable Error Pages
+
+use std::{collections::HashMap, rc::Rc};
+
+use actix_web::{
+    HttpResponse,
+    dev::ServiceResponse,
+    error::{Error as ActixError, InternalError},
+    http::{StatusCode, header},
+};
+
+/// Handler rendering the error page for a status and request path
+pub type ErrorHandler = Rc<dyn Fn(StatusCode, &str) -> HttpResponse>;
+
+/// Default branded page used for statuses without a configured page
+const DEFAULT_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>{status} {reason}</title></head>
+<body>
+<h1>{status} {reason}</h1>
+<p>The gateway could not complete the request for <code>{path}</code>.</p>
+<hr><address>actix-services</address>
+</body>
+</html>
+"#;
+
+#[derive(Clone)]
+enum Page {
+    Template(String),
+    Handler(ErrorHandler),
+}
+
+/// Error pages rendered for responses generated by gateway services.
+///
+/// Templates are `text/html` and may contain the following
+/// placeholders which are HTML escaped before substitution:
+///
+/// - `{status}`: Numeric status code
+/// - `{reason}`: Canonical status reason
+/// - `{path}`: Request path
+///
+/// Statuses without a configured page use a built-in branded page
+/// unless replaced with [`ErrorPages::default_page`] or
+/// [`ErrorPages::default_handler`].
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::{HttpResponse, http::StatusCode};
+/// use actix_common::ErrorPages;
+///
+/// let pages = ErrorPages::new()
+///     .page(StatusCode::BAD_GATEWAY, "<h1>{status}: try again soon</h1>")
+///     .handler(StatusCode::SERVICE_UNAVAILABLE, |status, _path| {
+///         HttpResponse::build(status)
+///             .insert_header(("Retry-After", "30"))
+///             .body("busy")
+///     });
+/// ```
+#[derive(Clone)]
+pub struct ErrorPages {
+    pages: HashMap<StatusCode, Page>,
+    default: Page,
+}
+
+impl ErrorPages {
+    /// Create a new set of error pages using the built-in default page.
+    pub fn new() -> Self {
+        Self {
+            pages: HashMap::new(),
+            default: Page::Template(DEFAULT_PAGE.to_owned()),
+        }
+    }
+
+    /// Render the specified template for responses with the given status.
+    pub fn page<S: Into<String>>(mut self, status: StatusCode, template: S) -> Self {
+        self.pages.insert(status, Page::Template(template.into()));
+        self
+    }
+
+    /// Render responses with the given status using a custom handler.
+    pub fn handler<F>(mut self, status: StatusCode, handler: F) -> Self
+    where
+        F: Fn(StatusCode, &str) -> HttpResponse + 'static,
+    {
+        self.pages.insert(status, Page::Handler(Rc::new(handler)));
+        self
+    }
+
+    /// Replace the built-in page used for statuses without a configured page.
+    pub fn default_page<S: Into<String>>(mut self, template: S) -> Self {
+        self.default = Page::Template(template.into());
+        self
+    }
+
+    /// Render statuses without a configured page using a custom handler.
+    pub fn default_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(StatusCode, &str) -> HttpResponse + 'static,
+    {
+        self.default = Page::Handler(Rc::new(handler));
+        self
+    }
+
+    /// Render the error page for the specified status and request path.
+    pub fn render(&self, status: StatusCode, path: &str) -> HttpResponse {
+        match self.pages.get(&status).unwrap_or(&self.default) {
+            Page::Handler(handler) => handler(status, path),
+            Page::Template(template) => HttpResponse::build(status)
+                .insert_header(header::ContentType::html())
+                .body(fill(template, status, path)),
+        }
+    }
+
+    /// Replace the response of a failed service call with its error page.
+    ///
+    /// Covers both errors returned by the service and responses generated
+    /// from an error, such as those of failed route handlers. Returned
+    /// errors are preserved as the cause so they remain visible to
+    /// logging middleware.
+    pub fn recover(
+        &self,
+        path: &str,
+        res: Result<ServiceResponse, ActixError>,
+    ) -> Result<ServiceResponse, ActixError> {
+        match res {
+            Ok(res) => match res.response().error() {
+                Some(err) => {
+                    let page = self.render(err.as_response_error().status_code(), path);
+                    Ok(res.into_response(page))
+                }
+                None => Ok(res),
+            },
+            Err(err) => {
+                let page = self.render(err.as_response_error().status_code(), path);
+                Err(InternalError::from_response(err, page).into())
+            }
+        }
+    }
+}
+
+impl Default for ErrorPages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Escape a value for inclusion within html
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Substitute template placeholders in a single pass so
+/// substituted values are never re-interpreted as placeholders
+fn fill(template: &str, status: StatusCode, path: &str) -> String {
+    let values = [
+        ("{status}", status.as_str().to_owned()),
+        ("{reason}", escape(status.canonical_reason().unwrap_or(""))),
+        ("{path}", escape(path)),
+    ];
+    let mut body = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        body.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match values.iter().find(|(key, _)| rest.starts_with(key)) {
+            Some((key, value)) => {
+                body.push_str(value);
+                rest = &rest[key.len()..];
+            }
+            None => {
+                body.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    body.push_str(rest);
+    body
+}
@@ -0,0 +1,198 @@
+//! Segment-Aware Path Prefix Matching
+
+use actix_web::dev::{Path, ResourceDef};
+
+/// Handling of the trailing slash after a matched [`PathPrefix`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Match the prefix itself with or without a trailing slash.
+    #[default]
+    Optional,
+    /// Only match paths continuing with a slash after the prefix.
+    Required,
+}
+
+/// Decode a percent-encoded unreserved character at the start of the path
+///
+/// Returns the decoded byte and the number of raw bytes consumed.
+fn next_byte(path: &[u8]) -> Option<(u8, usize)> {
+    let first = *path.first()?;
+    if first != b'%' {
+        return Some((first, 1));
+    }
+    let decoded = std::str::from_utf8(path.get(1..3)?)
+        .ok()
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        .filter(|byte| byte.is_ascii_alphanumeric() || b"-._~".contains(byte));
+    match decoded {
+        Some(byte) => Some((byte, 3)),
+        None => Some((first, 1)),
+    }
+}
+
+/// Path prefix matched on segment boundaries
+///
+/// Unlike a plain `starts_with`, `/assets` matches `/assets` and
+/// `/assets/app.js` but never `/assets-old`. A trailing slash on the
+/// prefix is insignificant and percent-encoded unreserved characters in
+/// the request path are compared in their decoded form.
+///
+/// Prefixes may contain [`ResourceDef`] style dynamic segments such as
+/// `/users/{id}/files`, whose values are available through
+/// [`PathPrefix::captures`]. Dynamic prefixes are matched against the raw
+/// path and are always case-sensitive.
+///
+/// # Examples
+///
+/// ```
+/// use actix_common::{PathPrefix, TrailingSlash};
+///
+/// let prefix = PathPrefix::new("/assets/");
+/// assert!(prefix.matches("/assets"));
+/// assert!(prefix.matches("/as%73ets/app.js"));
+/// assert!(!prefix.matches("/assets-old"));
+/// assert_eq!(prefix.strip("/assets/app.js"), Some("/app.js"));
+///
+/// let prefix = PathPrefix::new("/Assets")
+///     .case_insensitive(true)
+///     .trailing_slash(TrailingSlash::Required);
+/// assert!(prefix.matches("/assets/"));
+/// assert!(!prefix.matches("/assets"));
+///
+/// let prefix = PathPrefix::new("/users/{id}/files");
+/// assert_eq!(prefix.strip("/users/42/files/a.txt"), Some("/a.txt"));
+/// assert_eq!(prefix.captures("/users/42/files"), vec![("id".to_owned(), "42".to_owned())]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathPrefix {
+    prefix: String,
+    pattern: Option<ResourceDef>,
+    trailing_slash: TrailingSlash,
+    case_insensitive: bool,
+}
+
+impl PathPrefix {
+    /// Construct a new path prefix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prefix contains an invalid dynamic segment pattern.
+    pub fn new(prefix: &str) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        Self {
+            prefix: prefix.to_owned(),
+            pattern: prefix.contains('{').then(|| ResourceDef::prefix(prefix)),
+            trailing_slash: TrailingSlash::default(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Configure how the slash following the prefix is handled.
+    ///
+    /// Default is [`TrailingSlash::Optional`].
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+
+    /// Compare the prefix ignoring ASCII case.
+    ///
+    /// Default is disabled.
+    pub fn case_insensitive(mut self, enable: bool) -> Self {
+        self.case_insensitive = enable;
+        self
+    }
+
+    /// Prefix without any trailing slash.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Check if the prefix matches every path.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.prefix.is_empty() && self.trailing_slash == TrailingSlash::Optional
+    }
+
+    /// Number of raw path bytes matched by the prefix
+    fn match_len(&self, path: &str) -> Option<usize> {
+        if let Some(pattern) = self.pattern.as_ref() {
+            let end = pattern.find_match(path)?;
+            return self.boundary(path.as_bytes(), end);
+        }
+        let path = path.as_bytes();
+        let mut consumed = 0;
+        for expected in self.prefix.bytes() {
+            let (byte, len) = next_byte(&path[consumed..])?;
+            let equal = match self.case_insensitive {
+                true => byte.eq_ignore_ascii_case(&expected),
+                false => byte == expected,
+            };
+            if !equal {
+                return None;
+            }
+            consumed += len;
+        }
+        self.boundary(path, consumed)
+    }
+
+    /// Check the prefix ends on a segment boundary of the path
+    fn boundary(&self, path: &[u8], end: usize) -> Option<usize> {
+        match path.get(end) {
+            Some(b'/') => Some(end),
+            None if self.trailing_slash == TrailingSlash::Optional => Some(end),
+            _ => None,
+        }
+    }
+
+    /// Check if the path matches the prefix on a segment boundary.
+    #[inline]
+    pub fn matches(&self, path: &str) -> bool {
+        self.match_len(path).is_some()
+    }
+
+    /// Strip the prefix from the path returning the remainder.
+    ///
+    /// The remainder is empty or starts with a slash.
+    pub fn strip<'a>(&self, path: &'a str) -> Option<&'a str> {
+        self.match_len(path).map(|len| &path[len..])
+    }
+
+    /// Values of the dynamic segments captured from a matching path.
+    pub fn captures(&self, path: &str) -> Vec<(String, String)> {
+        let Some(pattern) = self.pattern.as_ref() else {
+            return Vec::new();
+        };
+        if !self.matches(path) {
+            return Vec::new();
+        }
+        let mut captured = Path::new(path);
+        pattern.capture_match_info(&mut captured);
+        captured
+            .iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect()
+    }
+}
+
+impl From<&str> for PathPrefix {
+    #[inline]
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&String> for PathPrefix {
+    #[inline]
+    fn from(value: &String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for PathPrefix {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self::new(&value)
+    }
+}
@@ -0,0 +1,126 @@
+//! Shared Error Taxonomy with Optional RFC 7807 Rendering
+
+use actix_web::{
+    HttpResponse,
+    body::BoxBody,
+    http::{
+        StatusCode,
+        header::{self, HeaderValue},
+    },
+};
+use derive_more::Display;
+
+/// Shared classification of errors raised by gateway services
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Failed to establish a connection to the upstream
+    #[display("upstream-connect")]
+    UpstreamConnect,
+
+    /// Upstream failed to respond in time
+    #[display("upstream-timeout")]
+    UpstreamTimeout,
+
+    /// Upstream returned an invalid or unexpected response
+    #[display("protocol-violation")]
+    ProtocolViolation,
+
+    /// Service was misconfigured
+    #[display("config")]
+    Config,
+
+    /// Service concurrency limit was exceeded
+    #[display("overloaded")]
+    Overloaded,
+
+    /// Unexpected internal error
+    #[display("internal")]
+    Internal,
+}
+
+impl ErrorKind {
+    /// Response status code associated with the error kind.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UpstreamConnect | Self::ProtocolViolation => StatusCode::BAD_GATEWAY,
+            Self::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Config | Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Short human-readable summary of the error kind.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::UpstreamConnect => "Upstream Connection Failed",
+            Self::UpstreamTimeout => "Upstream Timed Out",
+            Self::ProtocolViolation => "Upstream Protocol Violation",
+            Self::Config => "Service Misconfigured",
+            Self::Overloaded => "Service Overloaded",
+            Self::Internal => "Internal Error",
+        }
+    }
+}
+
+/// Error classified by a shared [`ErrorKind`]
+///
+/// Implemented by the error types of each service so that
+/// [`ResponseError`](actix_web::ResponseError) rendering is consistent.
+pub trait GatewayError: std::error::Error {
+    /// Classification of the error.
+    fn kind(&self) -> ErrorKind;
+
+    /// Render the error as a response.
+    ///
+    /// Renders an `application/problem+json` body when the
+    /// `problem-details` feature is enabled and plain-text otherwise.
+    fn gateway_response(&self) -> HttpResponse<BoxBody> {
+        let kind = self.kind();
+        let mut res = HttpResponse::new(kind.status_code());
+        #[cfg(feature = "problem-details")]
+        {
+            let problem = ProblemDetails::new(kind, self.to_string());
+            if let Ok(body) = serde_json::to_string(&problem) {
+                res.headers_mut().insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/problem+json"),
+                );
+                return res.set_body(BoxBody::new(body));
+            }
+        }
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        res.set_body(BoxBody::new(self.to_string()))
+    }
+}
+
+/// RFC 7807 `application/problem+json` response body
+#[cfg(feature = "problem-details")]
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ProblemDetails {
+    /// URI reference identifying the problem type
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Short human-readable summary of the problem type
+    pub title: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Human-readable explanation of this occurrence
+    pub detail: String,
+}
+
+#[cfg(feature = "problem-details")]
+impl ProblemDetails {
+    /// Build problem details for the specified error kind.
+    pub fn new(kind: ErrorKind, detail: String) -> Self {
+        Self {
+            problem_type: format!("urn:actix-services:error:{kind}"),
+            title: kind.title().to_owned(),
+            status: kind.status_code().as_u16(),
+            detail,
+        }
+    }
+}
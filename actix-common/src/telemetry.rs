@@ -0,0 +1,139 @@
+//! OpenTelemetry Trace Context Propagation
+//!
+//! Extracts and injects trace context using the globally registered
+//! [`TextMapPropagator`](opentelemetry::propagation::TextMapPropagator),
+//! typically a composite of the W3C TraceContext and Baggage propagators.
+
+use std::{borrow::Cow, collections::HashMap};
+
+use actix_web::{
+    Error, HttpMessage, HttpRequest,
+    dev::ServiceResponse,
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+};
+use opentelemetry::{
+    Context, KeyValue, global,
+    propagation::{Extractor, Injector},
+    trace::{Status, TraceContextExt, Tracer},
+};
+
+pub use opentelemetry::trace::SpanKind;
+
+const TRACER_NAME: &str = "actix-services";
+
+/// [`Extractor`] over request headers
+pub struct HeaderExtractor<'a>(pub &'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// [`Injector`] over request headers
+pub struct HeaderInjector<'a>(pub &'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        let Ok(name) = HeaderName::from_bytes(key.as_bytes()) else {
+            return;
+        };
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Trace context of the specified request
+///
+/// Uses the context of an enclosing [`SpanScope`] when present,
+/// otherwise extracts it from the request headers.
+pub fn request_context(req: &HttpRequest) -> Context {
+    if let Some(cx) = req.extensions().get::<Context>() {
+        return cx.clone();
+    }
+    global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(req.headers()))
+    })
+}
+
+/// Inject the request trace context into the specified headers.
+pub fn inject_headers(req: &HttpRequest, headers: &mut HeaderMap) {
+    let cx = request_context(req);
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut HeaderInjector(headers))
+    })
+}
+
+/// Collect the request trace context as propagation key-value pairs.
+pub fn inject_map(req: &HttpRequest) -> HashMap<String, String> {
+    let cx = request_context(req);
+    let mut map = HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&cx, &mut map));
+    map
+}
+
+/// Child span covering a single service call for a request
+///
+/// While entered the span context is stored within the request extensions so
+/// nested services and upstream requests are parented to the span.
+pub struct SpanScope {
+    cx: Context,
+    parent: Option<Context>,
+}
+
+impl SpanScope {
+    /// Start a child span of the request trace context.
+    pub fn enter<N>(req: &HttpRequest, name: N, kind: SpanKind) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let parent = req.extensions().get::<Context>().cloned();
+        let tracer = global::tracer(TRACER_NAME);
+        let span = tracer
+            .span_builder(name)
+            .with_kind(kind)
+            .with_attributes([
+                KeyValue::new("http.request.method", req.method().to_string()),
+                KeyValue::new("url.path", req.path().to_owned()),
+            ])
+            .start_with_context(&tracer, &request_context(req));
+        let cx = Context::current_with_span(span);
+        req.extensions_mut().insert(cx.clone());
+        Self { cx, parent }
+    }
+
+    /// Span context of the scope.
+    #[inline]
+    pub fn context(&self) -> &Context {
+        &self.cx
+    }
+
+    /// Record the service result, end the span and restore the parent context.
+    pub fn exit<B>(self, res: &Result<ServiceResponse<B>, Error>) {
+        let span = self.cx.span();
+        match res {
+            Ok(res) => {
+                let status = res.status();
+                span.set_attribute(KeyValue::new(
+                    "http.response.status_code",
+                    status.as_u16() as i64,
+                ));
+                if status.is_server_error() {
+                    span.set_status(Status::error(status.to_string()));
+                }
+                let mut extensions = res.request().extensions_mut();
+                match self.parent {
+                    Some(parent) => extensions.insert(parent),
+                    None => extensions.remove::<Context>(),
+                };
+            }
+            Err(err) => span.set_status(Status::error(err.to_string())),
+        }
+        span.end();
+    }
+}
@@ -0,0 +1,153 @@
+//! Request Derived Header Value Templates
+
+use std::{fmt::Write, str::FromStr};
+
+use actix_web::HttpRequest;
+
+use crate::Error;
+
+/// Request value substituted for a template placeholder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Var {
+    Uri,
+    Path,
+    Query,
+    Method,
+    Scheme,
+    Host,
+    ClientIp,
+}
+
+impl Var {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "uri" => Self::Uri,
+            "path" => Self::Path,
+            "query" => Self::Query,
+            "method" => Self::Method,
+            "scheme" => Self::Scheme,
+            "host" => Self::Host,
+            "client_ip" => Self::ClientIp,
+            _ => return None,
+        })
+    }
+
+    fn render(&self, req: &HttpRequest, out: &mut String) {
+        let _ = match self {
+            Self::Uri => match req.uri().path_and_query() {
+                Some(uri) => write!(out, "{uri}"),
+                None => write!(out, "{}", req.path()),
+            },
+            Self::Path => write!(out, "{}", req.path()),
+            Self::Query => write!(out, "{}", req.query_string()),
+            Self::Method => write!(out, "{}", req.method()),
+            Self::Scheme => write!(out, "{}", req.connection_info().scheme()),
+            Self::Host => write!(out, "{}", req.connection_info().host()),
+            Self::ClientIp => match crate::client_addr(req) {
+                Some(addr) => write!(out, "{}", addr.ip()),
+                None => Ok(()),
+            },
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Var(Var),
+}
+
+/// Header value rendered from the request on every call
+///
+/// Placeholders within braces are replaced with values of the incoming
+/// request, similar to nginx variables in `proxy_set_header`. Literal
+/// braces are written as `{{` and `}}`.
+///
+/// | Placeholder   | Value                                               |
+/// |---------------|-----------------------------------------------------|
+/// | `{uri}`       | original request path and query                     |
+/// | `{path}`      | original request path                               |
+/// | `{query}`     | original request query string                       |
+/// | `{method}`    | request method                                      |
+/// | `{scheme}`    | request scheme                                      |
+/// | `{host}`      | request host                                        |
+/// | `{client_ip}` | client address resolved via [`TrustedProxies`](crate::TrustedProxies) |
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::test::TestRequest;
+/// use actix_common::Template;
+///
+/// let template: Template = "{method} {uri}".parse().unwrap();
+/// let req = TestRequest::with_uri("/index.php?page=2").to_http_request();
+/// assert_eq!(template.render(&req), "GET /index.php?page=2");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template(Vec<Part>);
+
+impl Template {
+    /// Check if the template contains no placeholders.
+    pub fn is_static(&self) -> bool {
+        self.0.iter().all(|part| matches!(part, Part::Literal(_)))
+    }
+
+    /// Render the template for the specified request.
+    pub fn render(&self, req: &HttpRequest) -> String {
+        let mut out = String::new();
+        for part in self.0.iter() {
+            match part {
+                Part::Literal(literal) => out.push_str(literal),
+                Part::Var(var) => var.render(req, &mut out),
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidTemplate(s.to_owned());
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars.as_str().split_once('}').ok_or_else(invalid)?;
+                    let var = Var::parse(name.trim()).ok_or_else(invalid)?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Var(var));
+                    chars = rest.chars();
+                }
+                '}' => return Err(invalid()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self(parts))
+    }
+}
+
+impl TryFrom<&str> for Template {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
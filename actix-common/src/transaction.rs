@@ -0,0 +1,123 @@
+//! Per-Request Transaction ID for Log Correlation
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{
+    HttpMessage, HttpRequest,
+    http::header::{HeaderName, HeaderValue},
+};
+use derive_more::Display;
+
+/// Default header carrying the transaction ID
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Maximum length of a transaction ID adopted from a request header
+pub const MAX_TRANSACTION_ID_LEN: usize = 128;
+
+/// Per-process counter distinguishing IDs generated within the same second
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Identifier correlating a single request across every service layer
+///
+/// Assigned once per request and stored in the request extensions so the
+/// WAF, proxy and FastCGI services all log and forward the same ID. An
+/// ID supplied by the client or an upstream load balancer is adopted
+/// instead of generating a new one.
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::test::TestRequest;
+/// use actix_common::{TransactionId, X_REQUEST_ID, transaction_id};
+///
+/// let req = TestRequest::default()
+///     .insert_header(("X-Request-ID", "abc123"))
+///     .to_http_request();
+/// let id = TransactionId::resolve(&req, &X_REQUEST_ID);
+/// assert_eq!(id.as_str(), "abc123");
+/// assert_eq!(transaction_id(&req), Some(id));
+/// ```
+#[derive(Clone, Debug, Display, PartialEq, Eq, Hash)]
+pub struct TransactionId(String);
+
+impl TransactionId {
+    /// Construct a transaction ID from an existing value.
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        Self(id.into())
+    }
+
+    /// Generate a 24 character ID from the time, process and a counter.
+    pub fn generate() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self(format!(
+            "{:08x}{:08x}{count:08x}",
+            secs as u32,
+            std::process::id()
+        ))
+    }
+
+    /// Value of the transaction ID.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check if an ID supplied by a client may be adopted.
+    ///
+    /// Valid IDs are non-empty, at most [`MAX_TRANSACTION_ID_LEN`] bytes
+    /// and only contain ASCII letters, digits or `-_.:+/=@`, keeping them
+    /// safe to embed in logs and forwarded params.
+    pub fn is_valid(id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= MAX_TRANSACTION_ID_LEN
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.:+/=@".contains(&b))
+    }
+
+    /// Resolve the transaction ID of the request.
+    ///
+    /// Returns the ID already assigned to the request, otherwise adopts the
+    /// value of the specified header or generates a new ID. Header values
+    /// failing [`is_valid`](Self::is_valid) are replaced by a generated ID.
+    /// The resolved ID is stored in the request extensions.
+    pub fn resolve(req: &HttpRequest, header: &HeaderName) -> Self {
+        if let Some(id) = transaction_id(req) {
+            return id;
+        }
+        let id = req
+            .headers()
+            .get(header)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|id| {
+                let valid = Self::is_valid(id);
+                if !valid {
+                    tracing::debug!("ignoring invalid transaction id {id:?}");
+                }
+                valid
+            })
+            .map(Self::new)
+            .unwrap_or_else(Self::generate);
+        req.extensions_mut().insert(id.clone());
+        id
+    }
+
+    /// Header value of the transaction ID.
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        HeaderValue::from_str(&self.0).ok()
+    }
+}
+
+/// Retrieve the transaction ID assigned to the request, if any.
+#[inline]
+pub fn transaction_id(req: &HttpRequest) -> Option<TransactionId> {
+    req.extensions().get::<TransactionId>().cloned()
+}
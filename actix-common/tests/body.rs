@@ -0,0 +1,39 @@
+use actix_common::body::BodyBuffer;
+use actix_web::{
+    body::{self, BodySize, MessageBody},
+    error::PayloadError,
+    web::Bytes,
+};
+use futures_util::{StreamExt, stream};
+
+#[actix_web::test]
+async fn test_body_buffer_replay() {
+    let chunks = ["hello ", "world"].map(|chunk| Ok(Bytes::from_static(chunk.as_bytes())));
+    let buffer = BodyBuffer::new(stream::iter(chunks), 1024);
+
+    let first = buffer.stream().next().await.unwrap().unwrap();
+    assert_eq!(first, "hello ");
+
+    let captured = buffer.capture().await.unwrap();
+    assert_eq!(captured, "hello world");
+    assert!(buffer.is_complete());
+
+    let body = buffer.body();
+    assert_eq!(body.size(), BodySize::Sized(11));
+    assert_eq!(body::to_bytes(body).await.unwrap(), "hello world");
+
+    buffer.rewind();
+    let replay = body::to_bytes(buffer.body()).await.unwrap();
+    assert_eq!(replay, "hello world");
+}
+
+#[actix_web::test]
+async fn test_body_buffer_overflow() {
+    let buffer = BodyBuffer::from_body(Bytes::from_static(b"too large"), 4);
+    let err = buffer
+        .capture()
+        .await
+        .expect_err("limit should be exceeded");
+    assert!(matches!(err, PayloadError::Overflow));
+    assert!(buffer.is_overflow());
+}
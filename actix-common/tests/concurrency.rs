@@ -0,0 +1,61 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Wake, Waker},
+    time::Duration,
+};
+
+use actix_common::{Concurrency, Error};
+use actix_web::{ResponseError, http::StatusCode};
+
+#[actix_web::test]
+async fn test_concurrency_overloaded() {
+    let concurrency = Concurrency::new(1);
+    let permit = concurrency.acquire().await.expect("first acquire failed");
+
+    let err = concurrency.acquire().await.expect_err("queue should be full");
+    assert!(matches!(err, Error::Overloaded));
+    assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+    drop(permit);
+    assert!(concurrency.acquire().await.is_ok());
+}
+
+#[actix_web::test]
+async fn test_concurrency_queue_timeout() {
+    let concurrency = Concurrency::new(1)
+        .queue_depth(1)
+        .queue_timeout(Duration::from_millis(10));
+    let _permit = concurrency.acquire().await.expect("first acquire failed");
+
+    let err = concurrency.acquire().await.expect_err("queue should time out");
+    assert!(matches!(err, Error::Overloaded));
+}
+
+#[actix_web::test]
+async fn test_concurrency_ready_wakers() {
+    struct Counter(AtomicUsize);
+
+    impl Wake for Counter {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let concurrency = Concurrency::new(1);
+    let permit = concurrency.acquire().await.expect("first acquire failed");
+
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    let waker = Waker::from(Arc::clone(&counter));
+    let mut cx = Context::from_waker(&waker);
+    for _ in 0..3 {
+        assert!(concurrency.poll_ready(&mut cx).is_pending());
+    }
+
+    // repeated polls of the same task are woken once
+    drop(permit);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert!(concurrency.poll_ready(&mut cx).is_ready());
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use actix_common::Cors;
+use actix_web::{
+    HttpResponse,
+    http::{Method, StatusCode, header},
+    test::TestRequest,
+};
+
+#[test]
+fn test_cors_preflight() {
+    let cors = Cors::new()
+        .allow_origin("https://app.example.com")
+        .allow_method(Method::PUT)
+        .allow_header("Content-Type")
+        .max_age(Duration::from_secs(600));
+
+    let req = TestRequest::default()
+        .method(Method::OPTIONS)
+        .insert_header((header::ORIGIN, "https://app.example.com"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PUT"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type"))
+        .to_srv_request();
+    let res = cors.preflight(&req).expect("preflight should be answered");
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let headers = res.headers();
+    assert_eq!(
+        headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://app.example.com"
+    );
+    assert_eq!(
+        headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap(),
+        "GET, HEAD, POST, PUT"
+    );
+    assert_eq!(
+        headers.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+        "content-type"
+    );
+    assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+
+    let req = TestRequest::default()
+        .method(Method::OPTIONS)
+        .insert_header((header::ORIGIN, "https://evil.example.com"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "PUT"))
+        .to_srv_request();
+    let res = cors.preflight(&req).expect("preflight should be answered");
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let req = TestRequest::default()
+        .method(Method::OPTIONS)
+        .insert_header((header::ORIGIN, "https://app.example.com"))
+        .to_srv_request();
+    assert!(cors.preflight(&req).is_none());
+}
+
+#[test]
+fn test_cors_apply() {
+    let cors = Cors::new().allow_origin("*");
+    let origin = header::HeaderValue::from_static("https://other.example.com");
+
+    let mut res = HttpResponse::Ok().finish();
+    cors.apply(Some(&origin), &mut res);
+    assert_eq!(
+        res.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .unwrap(),
+        "*"
+    );
+
+    let cors = Cors::new()
+        .allow_origin("https://other.example.com")
+        .allow_credentials(true);
+    let mut res = HttpResponse::Ok().finish();
+    cors.apply(Some(&origin), &mut res);
+    assert_eq!(
+        res.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .unwrap(),
+        "https://other.example.com"
+    );
+    assert_eq!(
+        res.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .unwrap(),
+        "true"
+    );
+    assert_eq!(res.headers().get(header::VARY).unwrap(), "Origin");
+}
+
+#[test]
+#[should_panic(expected = "cors credentials cannot be allowed for any origin")]
+fn test_cors_any_origin_credentials() {
+    let _ = Cors::new().allow_origin("*").allow_credentials(true);
+}
+
+#[test]
+#[should_panic(expected = "cors credentials cannot be allowed for any origin")]
+fn test_cors_credentials_any_origin() {
+    let _ = Cors::new().allow_credentials(true).allow_origin("*");
+}
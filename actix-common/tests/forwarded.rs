@@ -0,0 +1,79 @@
+use std::net::SocketAddr;
+
+use actix_common::{Cidr, ClientIpGuard, TrustedProxies, client_addr};
+use actix_web::{guard::Guard, test::TestRequest};
+
+#[test]
+fn test_cidr() {
+    let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+    assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+    assert!(cidr.contains("::ffff:10.1.2.3".parse().unwrap()));
+    assert!(!cidr.contains("11.0.0.1".parse().unwrap()));
+    assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+}
+
+#[test]
+fn test_client_addr_precedence() {
+    let peer: SocketAddr = "10.0.0.2:4000".parse().unwrap();
+    let proxies = TrustedProxies::new().trust("10.0.0.0/8");
+
+    let req = TestRequest::default()
+        .peer_addr(peer)
+        .app_data(proxies.clone())
+        .insert_header(("X-Forwarded-For", "1.1.1.1"))
+        .insert_header(("Forwarded", "for=\"[2001:db8::1]:4711\", for=10.0.0.5"))
+        .to_http_request();
+    assert_eq!(client_addr(&req), Some("[2001:db8::1]:4711".parse().unwrap()));
+
+    let req = TestRequest::default()
+        .peer_addr(peer)
+        .app_data(proxies)
+        .insert_header(("X-Forwarded-For", "9.9.9.9, 2.2.2.2, 10.0.0.3"))
+        .to_http_request();
+    assert_eq!(client_addr(&req), Some("2.2.2.2:0".parse().unwrap()));
+}
+
+#[test]
+fn test_client_addr_untrusted() {
+    let peer: SocketAddr = "3.3.3.3:4000".parse().unwrap();
+    let req = TestRequest::default()
+        .peer_addr(peer)
+        .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+        .insert_header(("X-Forwarded-For", "1.1.1.1"))
+        .to_http_request();
+    assert_eq!(client_addr(&req), Some(peer));
+}
+
+#[test]
+fn test_client_ip_guard() {
+    let guard = ClientIpGuard::new().allow("192.168.0.0/16").allow("::1");
+    let proxy: SocketAddr = "10.0.0.2:4000".parse().unwrap();
+
+    let req = TestRequest::default()
+        .peer_addr("192.168.1.10:4000".parse().unwrap())
+        .to_srv_request();
+    assert!(guard.check(&req.guard_ctx()));
+
+    let req = TestRequest::default()
+        .peer_addr(proxy)
+        .insert_header(("X-Forwarded-For", "192.168.1.10"))
+        .to_srv_request();
+    assert!(!guard.check(&req.guard_ctx()));
+
+    let req = TestRequest::default()
+        .peer_addr(proxy)
+        .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+        .insert_header(("X-Forwarded-For", "192.168.1.10"))
+        .to_srv_request();
+    assert!(guard.check(&req.guard_ctx()));
+
+    let req = TestRequest::default()
+        .peer_addr(proxy)
+        .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+        .insert_header(("X-Forwarded-For", "8.8.8.8"))
+        .to_srv_request();
+    assert!(!guard.check(&req.guard_ctx()));
+
+    let req = TestRequest::default().to_srv_request();
+    assert!(!guard.check(&req.guard_ctx()));
+}
@@ -0,0 +1,57 @@
+use actix_common::AllowedMethods;
+use actix_web::{
+    http::{Method, StatusCode, header},
+    test::TestRequest,
+};
+
+#[test]
+fn test_allowed_methods() {
+    let methods = AllowedMethods::default();
+
+    let mut req = TestRequest::get().to_srv_request();
+    assert!(methods.check(&mut req).is_none());
+
+    let mut req = TestRequest::default()
+        .method(Method::TRACE)
+        .to_srv_request();
+    let res = methods.check(&mut req).expect("trace should be rejected");
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(
+        res.headers().get(header::ALLOW).unwrap(),
+        "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS"
+    );
+
+    let mut req = TestRequest::default()
+        .method(Method::TRACE)
+        .to_srv_request();
+    assert!(AllowedMethods::any().check(&mut req).is_none());
+}
+
+#[test]
+fn test_webdav_methods() {
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    assert!(!AllowedMethods::default().is_allowed(&propfind));
+
+    let methods = AllowedMethods::default().webdav();
+    for name in ["PROPFIND", "MKCOL", "MOVE", "COPY", "LOCK"] {
+        assert!(methods.is_allowed(&Method::from_bytes(name.as_bytes()).unwrap()));
+    }
+    assert!(!methods.is_allowed(&Method::TRACE));
+}
+
+#[test]
+fn test_multipart_ranges() {
+    let methods = AllowedMethods::default().multipart_ranges(false);
+
+    let mut req = TestRequest::get()
+        .insert_header((header::RANGE, "bytes=0-10"))
+        .to_srv_request();
+    assert!(methods.check(&mut req).is_none());
+    assert!(req.headers().contains_key(header::RANGE));
+
+    let mut req = TestRequest::get()
+        .insert_header((header::RANGE, "bytes=0-10, 20-30"))
+        .to_srv_request();
+    assert!(methods.check(&mut req).is_none());
+    assert!(!req.headers().contains_key(header::RANGE));
+}
@@ -0,0 +1,47 @@
+use std::sync::Mutex;
+
+use actix_common::metrics::{self, Labels, MetricsSink, Timer};
+use actix_web::{HttpResponse, test::TestRequest};
+
+static SAMPLES: Mutex<Vec<(&'static str, String, f64)>> = Mutex::new(Vec::new());
+
+struct Collector;
+
+impl MetricsSink for Collector {
+    fn histogram(&self, name: &'static str, labels: &Labels<'_>, value: f64) {
+        let labels = labels.values().join(",");
+        SAMPLES.lock().unwrap().push((name, labels, value));
+    }
+}
+
+#[test]
+fn test_metrics_timer() {
+    assert!(metrics::set_sink(Collector));
+    assert!(!metrics::set_sink(Collector));
+
+    let req = TestRequest::post()
+        .insert_header(("Content-Length", "5"))
+        .to_srv_request();
+    let timer = Timer::start("revproxy", "/api", req.request());
+    metrics::set_upstream(req.request(), "http://127.0.0.1:8080/");
+    let res = Ok(req.into_response(HttpResponse::Ok().body("hello world")));
+    timer.finish(&res);
+
+    let samples = SAMPLES.lock().unwrap();
+    let names: Vec<_> = samples.iter().map(|(name, _, _)| *name).collect();
+    assert_eq!(
+        names,
+        [
+            metrics::REQUEST_DURATION,
+            metrics::REQUEST_SIZE,
+            metrics::RESPONSE_SIZE
+        ]
+    );
+    assert!(
+        samples
+            .iter()
+            .all(|(_, labels, _)| labels == "revproxy,/api,http://127.0.0.1:8080/")
+    );
+    assert_eq!(samples[1].2, 5.0);
+    assert_eq!(samples[2].2, 11.0);
+}
@@ -0,0 +1,67 @@
+use actix_common::{Normalizer, normalized_uri};
+use actix_web::{
+    App, HttpRequest, HttpResponse,
+    http::{StatusCode, header},
+    test::{self, TestRequest},
+    web,
+};
+
+#[test]
+fn test_normalize_uri() {
+    let normalizer = Normalizer::new();
+    let uri = "/static/%2e%2e/%2E%2e%2fetc/passwd?q=%61%26b&x=%41%20"
+        .parse()
+        .unwrap();
+    assert_eq!(normalizer.uri(&uri), "/..%2Fetc/passwd?q=a%26b&x=A%20");
+
+    // escaped slashes never become segment separators
+    assert_eq!(normalizer.path("/a%2fb/../c"), "/c");
+    assert_eq!(normalizer.path("/a/b%2F..%2Fc"), "/a/b%2F..%2Fc");
+
+    // escapes decode to bytes before being interpreted as utf-8
+    assert_eq!(normalizer.path("/caf%c3%a9/%41"), "/café/A");
+    assert_eq!(
+        normalizer.uri(&"/caf%c3%a9?q=%c3%a9".parse().unwrap()),
+        "/caf%C3%A9?q=%C3%A9"
+    );
+    assert_eq!(normalizer.path("/%ff%41"), "/%FFA");
+
+    let req = TestRequest::with_uri("/a/./b/../c").to_http_request();
+    assert_eq!(normalized_uri(&req), "/a/./b/../c");
+
+    let req = TestRequest::with_uri("/a/./b/../c")
+        .app_data(Normalizer::new().collapse_dot_segments(false))
+        .to_http_request();
+    assert_eq!(normalized_uri(&req), "/a/./b/../c");
+
+    let req = TestRequest::with_uri("/a/./b/../c/%7e")
+        .app_data(normalizer)
+        .to_http_request();
+    assert_eq!(normalized_uri(&req), "/a/c/~");
+}
+
+#[actix_web::test]
+async fn test_normalize_middleware() {
+    let srv = test::init_service(
+        App::new()
+            .wrap(Normalizer::new().lowercase_host(true))
+            .route(
+                "/a/c",
+                web::get().to(|req: HttpRequest| async move {
+                    let host = req.headers().get(header::HOST).unwrap().clone();
+                    HttpResponse::Ok().body(format!("{} {}", req.uri(), host.to_str().unwrap()))
+                }),
+            ),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("//a//b/%2e%2e/c?x=1")
+        .insert_header((header::HOST, "Example.COM"))
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "/a/c?x=1 example.com");
+
+    let req = TestRequest::with_uri("/a/c%00.php").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,39 @@
+use actix_common::{Error, ErrorPages};
+use actix_web::{
+    HttpResponse, body,
+    http::{StatusCode, header},
+};
+
+#[actix_web::test]
+async fn test_error_pages_template() {
+    let pages = ErrorPages::new().page(StatusCode::BAD_GATEWAY, "{status} {reason} at {path}");
+
+    let res = pages.render(StatusCode::BAD_GATEWAY, "/<script>");
+    assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(
+        res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/html; charset=utf-8"
+    );
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(data, "502 Bad Gateway at /&lt;script&gt;");
+
+    let res = pages.render(StatusCode::GATEWAY_TIMEOUT, "/");
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert!(String::from_utf8_lossy(&data).contains("<h1>504 Gateway Timeout</h1>"));
+}
+
+#[actix_web::test]
+async fn test_error_pages_recover() {
+    let pages = ErrorPages::new().handler(StatusCode::SERVICE_UNAVAILABLE, |status, path| {
+        HttpResponse::build(status).body(format!("busy: {path}"))
+    });
+
+    let res = Err(Error::Overloaded.into());
+    let err = pages
+        .recover("/api", res)
+        .expect_err("error should be preserved");
+    let res = err.error_response();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(data, "busy: /api");
+}
@@ -0,0 +1,38 @@
+use actix_common::{Error, ErrorKind, GatewayError};
+use actix_web::{
+    ResponseError,
+    body::MessageBody,
+    http::{StatusCode, header},
+};
+
+#[test]
+fn test_error_kind_status() {
+    assert_eq!(
+        ErrorKind::UpstreamConnect.status_code(),
+        StatusCode::BAD_GATEWAY
+    );
+    assert_eq!(
+        ErrorKind::UpstreamTimeout.status_code(),
+        StatusCode::GATEWAY_TIMEOUT
+    );
+    assert_eq!(Error::Overloaded.kind(), ErrorKind::Overloaded);
+}
+
+#[test]
+fn test_error_response() {
+    let res = Error::Overloaded.error_response();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let content_type = res.headers().get(header::CONTENT_TYPE).cloned().unwrap();
+    let body = res.into_body().try_into_bytes().unwrap();
+    if cfg!(feature = "problem-details") {
+        assert_eq!(content_type, "application/problem+json");
+        assert_eq!(
+            body,
+            r#"{"type":"urn:actix-services:error:overloaded","title":"Service Overloaded","status":503,"detail":"Service Overloaded"}"#
+        );
+    } else {
+        assert_eq!(content_type, "text/plain; charset=utf-8");
+        assert_eq!(body, "Service Overloaded");
+    }
+}
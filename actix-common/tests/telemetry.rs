@@ -0,0 +1,25 @@
+#![cfg(feature = "opentelemetry")]
+
+use actix_common::telemetry::{SpanKind, SpanScope, inject_map};
+use actix_web::{HttpMessage, test::TestRequest};
+use opentelemetry::Context;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+
+const TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+#[test]
+fn test_trace_propagation() {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let req = TestRequest::default()
+        .insert_header(("traceparent", TRACEPARENT))
+        .to_http_request();
+
+    let scope = SpanScope::enter(&req, "upstream", SpanKind::Client);
+    assert!(req.extensions().get::<Context>().is_some());
+
+    let map = inject_map(&req);
+    let traceparent = map.get("traceparent").expect("missing traceparent");
+    assert!(traceparent.starts_with("00-0af7651916cd43dd8448eb211c80319c-"));
+    drop(scope);
+}
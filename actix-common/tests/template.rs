@@ -0,0 +1,37 @@
+use std::net::SocketAddr;
+
+use actix_common::{Template, TrustedProxies};
+use actix_web::{http::Method, test::TestRequest};
+
+#[test]
+fn test_template_render() {
+    let peer: SocketAddr = "10.0.0.2:4000".parse().unwrap();
+    let req = TestRequest::with_uri("/app/index.php?a=1")
+        .method(Method::POST)
+        .peer_addr(peer)
+        .app_data(TrustedProxies::new().trust("10.0.0.0/8"))
+        .insert_header(("Host", "example.com"))
+        .insert_header(("X-Forwarded-For", "1.2.3.4"))
+        .to_http_request();
+
+    let template: Template = "{method} {uri} {{{path}}}?{query}".parse().unwrap();
+    assert!(!template.is_static());
+    assert_eq!(
+        template.render(&req),
+        "POST /app/index.php?a=1 {/app/index.php}?a=1"
+    );
+
+    let template: Template = "{scheme}://{host} for={client_ip}".parse().unwrap();
+    assert_eq!(template.render(&req), "http://example.com for=1.2.3.4");
+
+    let template: Template = "static".parse().unwrap();
+    assert!(template.is_static());
+    assert_eq!(template.render(&req), "static");
+}
+
+#[test]
+fn test_template_invalid() {
+    assert!("{unknown}".parse::<Template>().is_err());
+    assert!("{uri".parse::<Template>().is_err());
+    assert!("uri}".parse::<Template>().is_err());
+}
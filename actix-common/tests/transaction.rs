@@ -0,0 +1,42 @@
+use actix_common::{MAX_TRANSACTION_ID_LEN, TransactionId, X_REQUEST_ID, transaction_id};
+use actix_web::test::TestRequest;
+
+#[test]
+fn test_transaction_id_validation() {
+    assert!(TransactionId::is_valid("abc-123_4.5:6+7/8=@"));
+    assert!(TransactionId::is_valid(&"a".repeat(MAX_TRANSACTION_ID_LEN)));
+    assert!(!TransactionId::is_valid(""));
+    assert!(!TransactionId::is_valid(
+        &"a".repeat(MAX_TRANSACTION_ID_LEN + 1)
+    ));
+    assert!(!TransactionId::is_valid("abc 123"));
+    assert!(!TransactionId::is_valid("abc\"123"));
+    assert!(!TransactionId::is_valid("abc%0a123"));
+}
+
+#[test]
+fn test_transaction_id_resolve() {
+    let req = TestRequest::default()
+        .insert_header(("X-Request-ID", "  abc123 "))
+        .to_http_request();
+    let id = TransactionId::resolve(&req, &X_REQUEST_ID);
+    assert_eq!(id.as_str(), "abc123");
+    assert_eq!(transaction_id(&req), Some(id.clone()));
+    assert_eq!(TransactionId::resolve(&req, &X_REQUEST_ID), id);
+
+    // invalid client ids are replaced with a generated id
+    let req = TestRequest::default()
+        .insert_header(("X-Request-ID", "<script>"))
+        .to_http_request();
+    let id = TransactionId::resolve(&req, &X_REQUEST_ID);
+    assert_ne!(id.as_str(), "<script>");
+    assert_eq!(id.as_str().len(), 24);
+    assert_eq!(transaction_id(&req), Some(id));
+
+    let long = "a".repeat(MAX_TRANSACTION_ID_LEN + 1);
+    let req = TestRequest::default()
+        .insert_header(("X-Request-ID", long.as_str()))
+        .to_http_request();
+    let id = TransactionId::resolve(&req, &X_REQUEST_ID);
+    assert_eq!(id.as_str().len(), 24);
+}
@@ -0,0 +1,43 @@
+use actix_fastcgi::ResponseStream;
+use actix_web::{body, web::Bytes};
+use criterion::{BenchmarkId, Criterion, Throughput, async_executor::FuturesExecutor};
+use criterion::{criterion_group, criterion_main};
+use fastcgi_client::{ClientError, response::Content};
+use futures_util::stream;
+
+const HEADERS: &[u8] = b"Status: 200 OK\r\nContent-Type: text/html\r\nX-Powered-By: PHP\r\n\r\n";
+
+/// Split a fastcgi response into stdout records of the specified size
+fn records(body_size: usize, record_size: usize) -> Vec<Bytes> {
+    let mut data = HEADERS.to_vec();
+    data.resize(HEADERS.len() + body_size, b'x');
+    let data = Bytes::from(data);
+    data.chunks(record_size)
+        .map(|chunk| data.slice_ref(chunk))
+        .collect()
+}
+
+fn parse_response(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fastcgi_response");
+    for (body_size, record_size) in [(1_024, 8), (1_024, u16::MAX as usize), (1 << 20, 8_192)] {
+        let records = records(body_size, record_size);
+        group.throughput(Throughput::Bytes(body_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new(format!("record_{record_size}"), body_size),
+            &records,
+            |b, records| {
+                b.to_async(FuturesExecutor).iter(|| async {
+                    let records = records.clone().into_iter().map(Content::Stdout);
+                    let stream =
+                        ResponseStream::new(stream::iter(records.map(Ok::<_, ClientError>)));
+                    let res = stream.into_response().await.expect("invalid response");
+                    body::to_bytes(res.into_body()).await.expect("invalid body")
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse_response);
+criterion_main!(benches);
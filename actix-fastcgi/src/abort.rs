@@ -0,0 +1,85 @@
+//! Upstream Request Abort on Client Disconnect
+
+use std::{
+    cell::Cell,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::SockStream;
+
+/// `FCGI_ABORT_REQUEST` record for the request ID of single request connections
+const ABORT_RECORD: [u8; 8] = [1, 2, 0, 1, 0, 0, 0, 0];
+
+/// Socket sending `FCGI_ABORT_REQUEST` when dropped before the response completed
+///
+/// Dropping the response early, such as when the client disconnects,
+/// drops the socket. The abort record is written without blocking on a
+/// best-effort basis before the connection is closed, so the backend can
+/// stop executing the script instead of only noticing on its next write.
+pub(crate) struct AbortStream {
+    inner: SockStream,
+    done: Rc<Cell<bool>>,
+}
+
+impl AbortStream {
+    /// Wrap the socket returning the flag marking the response as completed
+    pub(crate) fn new(inner: SockStream) -> (Self, Rc<Cell<bool>>) {
+        let done = Rc::new(Cell::new(false));
+        let stream = Self {
+            inner,
+            done: done.clone(),
+        };
+        (stream, done)
+    }
+}
+
+impl Drop for AbortStream {
+    fn drop(&mut self) {
+        if self.done.get() {
+            return;
+        }
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut inner = Pin::new(&mut self.inner);
+        match inner.as_mut().poll_write(&mut cx, &ABORT_RECORD) {
+            Poll::Ready(Ok(len)) if len == ABORT_RECORD.len() => {
+                let _ = inner.poll_flush(&mut cx);
+                tracing::debug!("aborted fastcgi request after client disconnect");
+            }
+            _ => tracing::debug!("failed to abort fastcgi request"),
+        }
+    }
+}
+
+impl AsyncRead for AbortStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for AbortStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
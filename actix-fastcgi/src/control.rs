@@ -0,0 +1,107 @@
+//! Runtime Control Handle for FastCGI Services
+
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    Connector, EgressProxy, SockPool, StreamAddr,
+    pool::{Manager, Upstream},
+};
+
+/// Snapshot of a fastcgi connection pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolStatus {
+    /// Maximum number of connections.
+    pub max_size: usize,
+    /// Number of currently open connections.
+    pub size: usize,
+    /// Number of idle connections ready for use.
+    pub available: usize,
+    /// Number of requests waiting for a connection.
+    pub waiting: usize,
+}
+
+/// Runtime control over a [`FastCGI`](crate::FastCGI) service upstream
+///
+/// Changes apply to all services sharing the handle without restarting
+/// the server. New settings are used for all subsequent connections.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_fastcgi::{Connector, FastCGI};
+///
+/// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000");
+/// let control = fastcgi.control_handle();
+///
+/// control.set_address("unix:///var/run/php-fpm.sock".parse().unwrap());
+/// control.set_connector(Connector::new().timeout(Duration::from_secs(2)));
+/// control.resize_pool(32);
+/// ```
+#[derive(Clone)]
+pub struct ControlHandle {
+    upstream: Arc<RwLock<Upstream>>,
+    pool: SockPool,
+}
+
+impl ControlHandle {
+    pub(crate) fn new(addr: StreamAddr) -> Self {
+        let upstream = Arc::new(RwLock::new(Upstream {
+            addr,
+            connector: Connector::default(),
+        }));
+        let pool = SockPool::builder(Manager(upstream.clone()))
+            .build()
+            .expect("failed to build fastcgi pool");
+        Self { upstream, pool }
+    }
+
+    #[inline]
+    pub(crate) fn pool(&self) -> &SockPool {
+        &self.pool
+    }
+
+    /// Current fastcgi service address.
+    pub fn address(&self) -> StreamAddr {
+        self.upstream.read().expect("poisoned lock").addr.clone()
+    }
+
+    /// Change the fastcgi service address.
+    pub fn set_address(&self, addr: StreamAddr) {
+        self.upstream.write().expect("poisoned lock").addr = addr;
+    }
+
+    /// Replace the [`Connector`] used to dial the fastcgi service.
+    ///
+    /// Use this to update connect timeouts and TLS settings.
+    pub fn set_connector(&self, connector: Connector) {
+        self.upstream.write().expect("poisoned lock").connector = connector;
+    }
+
+    /// Tunnel subsequent TCP connections through the specified [`EgressProxy`].
+    pub fn set_egress_proxy(&self, proxy: EgressProxy) {
+        let mut upstream = self.upstream.write().expect("poisoned lock");
+        upstream.connector = upstream.connector.clone().proxy(proxy);
+    }
+
+    /// Maximum number of concurrent fastcgi connections.
+    pub fn pool_size(&self) -> usize {
+        self.pool.status().max_size
+    }
+
+    /// Current state of the fastcgi connection pool.
+    pub fn pool_status(&self) -> PoolStatus {
+        let status = self.pool.status();
+        PoolStatus {
+            max_size: status.max_size,
+            size: status.size,
+            available: status.available,
+            waiting: status.waiting,
+        }
+    }
+
+    /// Change the maximum number of concurrent fastcgi connections.
+    pub fn resize_pool(&self, max_size: usize) {
+        self.pool.resize(max_size);
+    }
+}
@@ -1,6 +1,7 @@
 //! Error and Result module
 
-use actix_web::{ResponseError, error::PayloadError};
+use actix_common::{ErrorKind, GatewayError};
+use actix_web::{HttpResponse, ResponseError, body::BoxBody, error::PayloadError};
 use derive_more::{Display, Error, From};
 
 /// Errors which occur when processing FastCGI Requests/Responses
@@ -26,9 +27,28 @@ pub enum Error {
     #[display("Failed to parse response headers")]
     InvalidHeaders(httparse::Error),
 
+    /// Response header block exceeds the configured limit
+    #[display("Response headers exceed {_0} bytes")]
+    #[from(skip)]
+    HeadersTooLarge(#[error(not(source))] usize),
+
+    /// FastCGI service ended the request with a failure status
+    #[display("FastCGI request failed with {_0}")]
+    #[from(skip)]
+    EndRequest(#[error(not(source))] crate::EndStatus),
+
     /// FastCGI Status header code is invalid
     #[display("Invalid status code passed")]
     StatusCode(http::status::InvalidStatusCode),
+
+    /// php-fpm status page returned an invalid document
+    #[display("Invalid php-fpm status document")]
+    FpmStatus(serde_json::Error),
+
+    /// php-fpm ping or status page answered unexpectedly
+    #[display("php-fpm probe failed: {_0}")]
+    #[from(skip)]
+    Probe(#[error(not(source))] String),
 }
 
 impl From<std::convert::Infallible> for Error {
@@ -37,9 +57,34 @@ impl From<std::convert::Infallible> for Error {
     }
 }
 
+impl GatewayError for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(err) if err.kind() == std::io::ErrorKind::TimedOut => {
+                ErrorKind::UpstreamTimeout
+            }
+            Self::Io(_) => ErrorKind::UpstreamConnect,
+            Self::ClientError(fastcgi_client::ClientError::Io(_)) => ErrorKind::UpstreamConnect,
+            Self::ClientError(_) => ErrorKind::ProtocolViolation,
+            Self::UnexpectedEnd
+            | Self::InvalidHeaders(_)
+            | Self::HeadersTooLarge(_)
+            | Self::EndRequest(_)
+            | Self::StatusCode(_)
+            | Self::FpmStatus(_)
+            | Self::Probe(_) => ErrorKind::ProtocolViolation,
+            Self::Payload(_) => ErrorKind::Internal,
+        }
+    }
+}
+
 impl ResponseError for Error {
-    /// Returns `500 Internal Server Error`.
+    /// Returns the status code of the error's [`ErrorKind`].
     fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::INTERNAL_SERVER_ERROR
+        self.kind().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        self.gateway_response()
     }
 }
@@ -1,21 +1,50 @@
 //! FastCGI Service Factory
 
-use std::{path::PathBuf, rc::Rc};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    rc::Rc,
+};
 
+use actix_common::{AllowedMethods, Concurrency, Cors, ErrorPages, Template};
 use actix_service::ServiceFactory;
 use actix_web::{
     Error,
     dev::{AppService, HttpServiceFactory, ResourceDef, ServiceRequest, ServiceResponse},
     guard::Guard,
+    http::header::HeaderName,
 };
 use futures_core::future::LocalBoxFuture;
 
 use crate::{
-    SockPool, pool,
-    stream::{DEFAULT_ADDRESS, StreamAddr},
+    Connector, ControlHandle, EgressProxy, Recorder, RequestId, ScriptStats, SlowLog, StreamAddr,
+    payload::DEFAULT_MAX_HEADER_SIZE,
 };
 
-use super::service::{FastCGIInner, FastCGIService};
+use super::service::{FastCGIInner, FastCGIService, HeaderJoin, join_header};
+
+/// Default socket address on failure to parse configured address
+const DEFAULT_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9000);
+
+/// Default `SERVER_SOFTWARE` param passed to the fastcgi service
+const DEFAULT_SERVER_SOFTWARE: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Default `GATEWAY_INTERFACE` param passed to the fastcgi service
+const DEFAULT_GATEWAY_INTERFACE: &str = "CGI/1.1";
+
+/// Path confinement mode for resolved script paths
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Confinement {
+    /// Trust the request path sanitization alone
+    #[default]
+    Disabled,
+    /// Canonicalize the script path and reject any symlinks or escapes from the root
+    Strict,
+    /// Canonicalize the script path and allow symlinks that resolve inside the root
+    FollowSymlinks,
+}
 
 /// FastCGI client service
 ///
@@ -36,7 +65,29 @@ pub struct FastCGI {
     guards: Vec<Rc<dyn Guard>>,
     root: PathBuf,
     indexes: Vec<String>,
-    fastcgi_pool: SockPool,
+    front_controller: Option<String>,
+    control: ControlHandle,
+    concurrency: Option<Concurrency>,
+    methods: AllowedMethods,
+    cors: Option<Cors>,
+    max_header_size: usize,
+    max_body_size: Option<usize>,
+    forward_body: bool,
+    client_cert: bool,
+    recorder: Option<Recorder>,
+    stats: Option<ScriptStats>,
+    slow_log: Option<SlowLog>,
+    request_id: Option<RequestId>,
+    deadline_param: Option<String>,
+    abort_on_disconnect: bool,
+    confinement: Confinement,
+    server_software: String,
+    gateway_interface: String,
+    dev_mode: bool,
+    early_hints: bool,
+    error_pages: Option<ErrorPages>,
+    header_join: HeaderJoin,
+    header_templates: Vec<(HeaderName, Template)>,
 }
 
 impl FastCGI {
@@ -68,13 +119,34 @@ impl FastCGI {
                 StreamAddr::from(DEFAULT_ADDRESS)
             }
         };
-        let mgr = pool::Manager(fastcgi_address);
         Self {
             mount_path: mount_path.to_owned(),
             guards: Vec::new(),
             root,
             indexes: Vec::new(),
-            fastcgi_pool: SockPool::builder(mgr).build().unwrap(),
+            front_controller: None,
+            control: ControlHandle::new(fastcgi_address),
+            concurrency: None,
+            methods: AllowedMethods::default(),
+            cors: None,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            max_body_size: None,
+            forward_body: true,
+            client_cert: false,
+            recorder: None,
+            stats: None,
+            slow_log: None,
+            request_id: None,
+            deadline_param: None,
+            abort_on_disconnect: true,
+            confinement: Confinement::default(),
+            server_software: DEFAULT_SERVER_SOFTWARE.to_owned(),
+            gateway_interface: DEFAULT_GATEWAY_INTERFACE.to_owned(),
+            dev_mode: false,
+            early_hints: false,
+            error_pages: None,
+            header_join: Rc::new(join_header),
+            header_templates: Vec::new(),
         }
     }
 
@@ -112,6 +184,347 @@ impl FastCGI {
         self.indexes.push(index.into());
         self
     }
+
+    /// Run the specified script for requests not matching a file on disk.
+    ///
+    /// Equivalent of nginx `try_files $uri $uri/ /index.php?$query_string`
+    /// for front controller applications. The request uri and query are
+    /// passed to the script unchanged.
+    ///
+    /// Default is disabled.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_fastcgi::FastCGI;
+    ///
+    /// let fastcgi = FastCGI::new("/", "/var/www/html", "tcp://127.0.0.1:9000")
+    ///     .index_file("index.php")
+    ///     .front_controller("index.php");
+    /// ```
+    pub fn front_controller<S: Into<String>>(mut self, script: S) -> Self {
+        self.front_controller = Some(script.into());
+        self
+    }
+
+    /// Limit the number of concurrent requests handled by the service.
+    ///
+    /// Default is unlimited.
+    pub fn concurrency(mut self, concurrency: Concurrency) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Render errors raised by the service using the specified pages.
+    ///
+    /// Default renders errors using their
+    /// [`ResponseError`](actix_web::ResponseError) implementation.
+    pub fn error_pages(mut self, pages: ErrorPages) -> Self {
+        self.error_pages = Some(pages);
+        self
+    }
+
+    /// Set the maximum size of a fastcgi response header block.
+    ///
+    /// Responses with larger headers are rejected with a 502.
+    ///
+    /// Default is 64KiB.
+    pub fn max_header_size(mut self, max_header_size: usize) -> Self {
+        self.max_header_size = max_header_size;
+        self
+    }
+
+    /// Set the maximum declared `Content-Length` of a request body.
+    ///
+    /// Larger requests are rejected with a 413 before the body is read
+    /// or a fastcgi connection is opened. Bodies without a declared length
+    /// are not limited.
+    ///
+    /// The server answers `Expect: 100-continue` before any service runs,
+    /// so such clients may still upload the body before being refused.
+    ///
+    /// Default is unlimited.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    /// Forward request bodies to the fastcgi service as stdin.
+    ///
+    /// Disable this for GET-only mounts so request bodies are discarded
+    /// and `CONTENT_LENGTH`/`CONTENT_TYPE` are never passed along.
+    ///
+    /// Default is enabled.
+    pub fn forward_body(mut self, forward_body: bool) -> Self {
+        self.forward_body = forward_body;
+        self
+    }
+
+    /// Pass mTLS client certificate details as `SSL_CLIENT_*` params.
+    ///
+    /// Sets `SSL_CLIENT_CERT`, `SSL_CLIENT_S_DN`, `SSL_CLIENT_I_DN`,
+    /// `SSL_CLIENT_M_SERIAL` and `SSL_CLIENT_VERIFY` from the
+    /// [`ClientCert`](actix_common::ClientCert) attached to the connection.
+    ///
+    /// Default is disabled.
+    pub fn client_cert_params(mut self, enable: bool) -> Self {
+        self.client_cert = enable;
+        self
+    }
+
+    /// Set the path confinement mode for resolved script paths.
+    ///
+    /// Requests resolving outside of the root are rejected with a 403.
+    ///
+    /// Default is [`Confinement::Disabled`].
+    pub fn confinement(mut self, confinement: Confinement) -> Self {
+        self.confinement = confinement;
+        self
+    }
+
+    /// Override the `SERVER_SOFTWARE` param passed to the fastcgi service.
+    ///
+    /// Default is `actix-fastcgi/<version>`.
+    pub fn server_software<S: Into<String>>(mut self, server_software: S) -> Self {
+        self.server_software = server_software.into();
+        self
+    }
+
+    /// Override the `GATEWAY_INTERFACE` param passed to the fastcgi service.
+    ///
+    /// Default is `CGI/1.1`.
+    pub fn gateway_interface<S: Into<String>>(mut self, gateway_interface: S) -> Self {
+        self.gateway_interface = gateway_interface.into();
+        self
+    }
+
+    /// Set the strategy for joining repeated request headers into a single param.
+    ///
+    /// Default is [`join_header`](crate::join_header).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::http::header;
+    /// use actix_fastcgi::{FastCGI, join_header};
+    ///
+    /// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000")
+    ///     .header_join(|name, values| match *name == header::X_FORWARDED_FOR {
+    ///         true => values.last().copied().unwrap_or_default().to_owned(),
+    ///         false => join_header(name, values),
+    ///     });
+    /// ```
+    pub fn header_join<F>(mut self, join: F) -> Self
+    where
+        F: Fn(&HeaderName, &[&str]) -> String + 'static,
+    {
+        self.header_join = Rc::new(join);
+        self
+    }
+
+    /// Set a request header param rendered from each request.
+    ///
+    /// The value is a [`Template`] whose placeholders such as `{uri}`,
+    /// `{method}`, `{client_ip}` and `{host}` are replaced with values of
+    /// the incoming request, and is passed to the script as the `HTTP_*`
+    /// param of the header, replacing any value sent by the client.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_fastcgi::FastCGI;
+    ///
+    /// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000")
+    ///     .set_request_header("X-Original-URI", "{uri}")
+    ///     .set_request_header("X-Real-IP", "{client_ip}");
+    /// ```
+    pub fn set_request_header(mut self, name: &str, template: &str) -> Self {
+        let Ok(name) = HeaderName::try_from(name) else {
+            tracing::warn!("invalid request header name {name:?}");
+            return self;
+        };
+        match template.parse::<Template>() {
+            Ok(template) => self.header_templates.push((name, template)),
+            Err(err) => tracing::warn!("invalid request header template {name:?}: {err}"),
+        }
+        self
+    }
+
+    /// Render detailed error pages for broken scripts.
+    ///
+    /// Failed fastcgi responses, including protocol and application
+    /// status errors, and 5xx responses accompanied by stderr output
+    /// are replaced with a 500 page showing the error and stderr tail.
+    /// Otherwise failed requests are answered with a generic 502.
+    /// Never enable this in production.
+    ///
+    /// Default is disabled.
+    pub fn dev_mode(mut self, dev_mode: bool) -> Self {
+        self.dev_mode = dev_mode;
+        self
+    }
+
+    /// Merge the preload links of `103 Early Hints` sent by scripts.
+    ///
+    /// Interim responses emitted by scripts, such as those sent by PHP's
+    /// `headers_send(103)`, are never mistaken for the final response.
+    /// Actix-Web cannot send interim responses to the client, so no `103`
+    /// is ever forwarded. When enabled their `Link: <...>; rel=preload`
+    /// headers are added to the final response instead of being discarded,
+    /// letting a CDN in front of the server emit the early hints.
+    ///
+    /// Default is disabled.
+    pub fn early_hints(mut self, early_hints: bool) -> Self {
+        self.early_hints = early_hints;
+        self
+    }
+
+    /// Restrict the request methods forwarded to the fastcgi service.
+    ///
+    /// Other methods are answered with `405 Method Not Allowed` and an
+    /// `Allow` header. Use [`AllowedMethods::any`] to forward every method.
+    ///
+    /// Default is `GET`, `HEAD`, `POST`, `PUT`, `PATCH`, `DELETE` and `OPTIONS`.
+    pub fn methods(mut self, methods: AllowedMethods) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Answer CORS preflight requests locally using the [`Cors`] policy.
+    ///
+    /// Preflights are never forwarded to the fastcgi service, and responses to
+    /// cross-origin requests from allowed origins receive the matching
+    /// `Access-Control-Allow-*` headers.
+    ///
+    /// Default is disabled.
+    pub fn cors(mut self, cors: Cors) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Record raw fastcgi exchanges for debugging.
+    ///
+    /// See [`Recorder`] for configuring which requests are recorded.
+    ///
+    /// Default is disabled.
+    pub fn recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Collect per-script request counts, errors and latency percentiles.
+    ///
+    /// See [`ScriptStats`] for sharing statistics across workers.
+    ///
+    /// Default is disabled.
+    pub fn stats(mut self, stats: ScriptStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Log requests to scripts exceeding a latency threshold.
+    ///
+    /// See [`SlowLog`] for configuring the threshold and redacted params.
+    ///
+    /// Default is disabled.
+    pub fn slow_log(mut self, slow_log: SlowLog) -> Self {
+        self.slow_log = Some(slow_log);
+        self
+    }
+
+    /// Pass a request ID to the fastcgi service for log correlation.
+    ///
+    /// See [`RequestId`] for configuring the header and params used.
+    ///
+    /// Default is disabled.
+    pub fn request_id(mut self, request_id: RequestId) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    /// Pass the remaining request deadline in milliseconds as the specified param.
+    ///
+    /// The deadline is assigned by an enclosing service such as a chain
+    /// with a deadline, see [`actix_common::Deadline`]. Requests without
+    /// a deadline are passed without the param.
+    ///
+    /// Default is disabled.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_fastcgi::FastCGI;
+    ///
+    /// let fastcgi = FastCGI::new("/", ".", "tcp://localhost:9000")
+    ///     .deadline_param("X_REQUEST_DEADLINE_MS");
+    /// ```
+    pub fn deadline_param<S: Into<String>>(mut self, param: S) -> Self {
+        self.deadline_param = Some(param.into());
+        self
+    }
+
+    /// Send `FCGI_ABORT_REQUEST` when the response is dropped before completion.
+    ///
+    /// Once the client disconnects the fastcgi service is told to abort
+    /// the request so scripts stop working for clients that are gone.
+    /// Scripts ignoring aborts, such as PHP with `ignore_user_abort`,
+    /// keep running until completion.
+    ///
+    /// Default is enabled.
+    pub fn abort_on_disconnect(mut self, enable: bool) -> Self {
+        self.abort_on_disconnect = enable;
+        self
+    }
+
+    /// Set the [`Connector`] used to dial the fastcgi service.
+    ///
+    /// Use this to configure connect timeouts and TLS settings.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_fastcgi::{Connector, FastCGI};
+    ///
+    /// let fastcgi = FastCGI::new("/", ".", "tcp://localhost:9000")
+    ///     .connector(Connector::new().timeout(Duration::from_secs(5)));
+    /// ```
+    pub fn connector(self, connector: Connector) -> Self {
+        self.control.set_connector(connector);
+        self
+    }
+
+    /// Dial TCP fastcgi connections through an egress SOCKS5 or HTTP CONNECT proxy.
+    ///
+    /// Unix socket addresses are always dialed directly.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_fastcgi::{EgressProxy, FastCGI};
+    ///
+    /// let fastcgi = FastCGI::new("/", ".", "tcp://php.internal:9000")
+    ///     .egress_proxy(EgressProxy::http("proxy.corp", 3128));
+    /// ```
+    pub fn egress_proxy(self, proxy: EgressProxy) -> Self {
+        self.control.set_egress_proxy(proxy);
+        self
+    }
+
+    /// Set the maximum number of concurrent fastcgi connections.
+    pub fn pool_size(self, max_size: usize) -> Self {
+        self.control.resize_pool(max_size);
+        self
+    }
+
+    /// Share runtime control of the fastcgi upstream with another handle.
+    ///
+    /// Use this to control services constructed separately for each worker
+    /// using a single [`ControlHandle`].
+    pub fn with_control(mut self, control: ControlHandle) -> Self {
+        self.control = control;
+        self
+    }
+
+    /// Returns a [`ControlHandle`] for runtime changes to the fastcgi upstream.
+    #[inline]
+    pub fn control_handle(&self) -> ControlHandle {
+        self.control.clone()
+    }
 }
 
 impl HttpServiceFactory for FastCGI {
@@ -148,9 +561,32 @@ impl ServiceFactory<ServiceRequest> for FastCGI {
 
     fn new_service(&self, _: ()) -> Self::Future {
         let inner = FastCGIInner {
+            mount_path: self.mount_path.clone(),
             root: self.root.clone(),
             indexes: self.indexes.clone(),
-            fastcgi_pool: self.fastcgi_pool.clone(),
+            front_controller: self.front_controller.clone(),
+            fastcgi_pool: self.control.pool().clone(),
+            concurrency: self.concurrency.clone(),
+            methods: self.methods.clone(),
+            cors: self.cors.clone(),
+            max_header_size: self.max_header_size,
+            max_body_size: self.max_body_size,
+            forward_body: self.forward_body,
+            client_cert: self.client_cert,
+            recorder: self.recorder.clone(),
+            stats: self.stats.clone(),
+            slow_log: self.slow_log.clone(),
+            request_id: self.request_id.clone(),
+            deadline_param: self.deadline_param.clone(),
+            abort_on_disconnect: self.abort_on_disconnect,
+            confinement: self.confinement,
+            server_software: self.server_software.clone(),
+            gateway_interface: self.gateway_interface.clone(),
+            dev_mode: self.dev_mode,
+            early_hints: self.early_hints,
+            error_pages: self.error_pages.clone(),
+            header_join: self.header_join.clone(),
+            header_templates: self.header_templates.clone(),
         };
         Box::pin(async move { Ok(FastCGIService(Rc::new(inner))) })
     }
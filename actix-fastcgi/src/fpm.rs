@@ -0,0 +1,240 @@
+//! PHP-FPM Status and Ping Probes
+
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use deadpool::managed::Manager as _;
+use fastcgi_client::{Client, Params, Request};
+use serde::Deserialize;
+
+use crate::{ControlHandle, Error};
+
+/// Maximum number of cgi headers preceding the probe response body
+const MAX_HEADERS: usize = 16;
+
+/// State of a single php-fpm worker process
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct FpmProcess {
+    /// Process ID of the worker.
+    pub pid: u32,
+    /// Worker state such as `Idle` or `Running`.
+    pub state: String,
+    /// Seconds since the worker was started.
+    #[serde(rename = "start since")]
+    pub start_since: u64,
+    /// Number of requests served by the worker.
+    pub requests: u64,
+    /// Duration of the current or last request in microseconds.
+    #[serde(rename = "request duration")]
+    pub request_duration: u64,
+    /// Method of the current or last request.
+    #[serde(rename = "request method")]
+    pub request_method: String,
+    /// Uri of the current or last request.
+    #[serde(rename = "request uri")]
+    pub request_uri: String,
+    /// Script of the current or last request.
+    pub script: String,
+    /// Memory used by the last request in bytes.
+    #[serde(rename = "last request memory", default)]
+    pub last_request_memory: u64,
+}
+
+/// Pool statistics reported by the php-fpm status page
+///
+/// Parsed from the `json&full` format of the page configured with
+/// `pm.status_path`. The process list is only populated by php-fpm when
+/// the full report is requested.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct FpmStatus {
+    /// Name of the php-fpm pool.
+    pub pool: String,
+    /// Process manager mode such as `dynamic` or `static`.
+    #[serde(rename = "process manager")]
+    pub process_manager: String,
+    /// Seconds since the pool was started.
+    #[serde(rename = "start since")]
+    pub start_since: u64,
+    /// Number of requests accepted by the pool.
+    #[serde(rename = "accepted conn")]
+    pub accepted_conn: u64,
+    /// Number of requests waiting in the listen queue.
+    #[serde(rename = "listen queue")]
+    pub listen_queue: u64,
+    /// Highest number of requests waiting in the listen queue.
+    #[serde(rename = "max listen queue")]
+    pub max_listen_queue: u64,
+    /// Number of idle worker processes.
+    #[serde(rename = "idle processes")]
+    pub idle_processes: u64,
+    /// Number of active worker processes.
+    #[serde(rename = "active processes")]
+    pub active_processes: u64,
+    /// Number of idle and active worker processes.
+    #[serde(rename = "total processes")]
+    pub total_processes: u64,
+    /// Highest number of simultaneously active worker processes.
+    #[serde(rename = "max active processes")]
+    pub max_active_processes: u64,
+    /// Number of times `pm.max_children` was reached.
+    #[serde(rename = "max children reached")]
+    pub max_children_reached: u64,
+    /// Number of requests exceeding `request_slowlog_timeout`.
+    #[serde(rename = "slow requests", default)]
+    pub slow_requests: u64,
+    /// Worker processes of the pool.
+    #[serde(default)]
+    pub processes: Vec<FpmProcess>,
+}
+
+impl FpmStatus {
+    /// Parse the JSON document produced by the php-fpm status page.
+    pub fn parse(json: &[u8]) -> Result<Self, Error> {
+        Ok(serde_json::from_slice(json)?)
+    }
+}
+
+/// Probe querying the php-fpm `ping` and `status` pages of a service
+///
+/// Requests are sent over a dedicated connection to the address of the
+/// [`ControlHandle`], bypassing the connection pool so probes are not
+/// delayed by a saturated pool. [`FpmProbe::ping`] is suitable as a
+/// health check while [`FpmProbe::status`] reports pool statistics.
+/// The most recent status is retained for synchronous reporting such as
+/// the admin status endpoint. Clones share the retained status.
+///
+/// # Examples
+///
+/// ```no_run
+/// use actix_fastcgi::{FastCGI, FpmProbe};
+///
+/// # async fn probe() -> Result<(), actix_fastcgi::Error> {
+/// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000");
+/// let probe = FpmProbe::new(fastcgi.control_handle()).status_path("/fpm-status");
+///
+/// probe.ping().await?;
+/// let status = probe.status().await?;
+/// println!("{} idle of {}", status.idle_processes, status.total_processes);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FpmProbe {
+    control: ControlHandle,
+    status_path: String,
+    ping_path: String,
+    pong: String,
+    last_status: Arc<RwLock<Option<FpmStatus>>>,
+}
+
+impl FpmProbe {
+    /// Create a new probe for the fastcgi service of the control handle.
+    pub fn new(control: ControlHandle) -> Self {
+        Self {
+            control,
+            status_path: "/status".to_owned(),
+            ping_path: "/ping".to_owned(),
+            pong: "pong".to_owned(),
+            last_status: Arc::default(),
+        }
+    }
+
+    /// Path of the status page configured with `pm.status_path`.
+    ///
+    /// Default is `/status`.
+    pub fn status_path(mut self, path: &str) -> Self {
+        self.status_path = path.to_owned();
+        self
+    }
+
+    /// Path of the ping page configured with `ping.path`.
+    ///
+    /// Default is `/ping`.
+    pub fn ping_path(mut self, path: &str) -> Self {
+        self.ping_path = path.to_owned();
+        self
+    }
+
+    /// Expected ping response configured with `ping.response`.
+    ///
+    /// Default is `pong`.
+    pub fn ping_response(mut self, response: &str) -> Self {
+        self.pong = response.to_owned();
+        self
+    }
+
+    /// Request the page at the specified path and return its body
+    async fn query(&self, path: &str, query: &str) -> Result<Vec<u8>, Error> {
+        let sock = self.control.pool().manager().create().await?;
+        let params = Params::default()
+            .request_method("GET")
+            .script_name(path)
+            .script_filename(path)
+            .request_uri(path)
+            .document_uri(path)
+            .query_string(query);
+        let res = Client::new(sock)
+            .execute_once(Request::new(params, &[][..]))
+            .await?;
+        let stdout = res.stdout.unwrap_or_default();
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let (offset, headers) = match httparse::parse_headers(&stdout, &mut headers)? {
+            httparse::Status::Complete(parsed) => parsed,
+            httparse::Status::Partial => return Err(Error::UnexpectedEnd),
+        };
+        let status = headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case("status"))
+            .and_then(|header| std::str::from_utf8(header.value).ok());
+        if let Some(status) = status.filter(|status| !status.starts_with("200")) {
+            return Err(Error::Probe(format!("{path} answered {status}")));
+        }
+        Ok(stdout[offset..].to_vec())
+    }
+
+    /// Check the service answers the ping page with the expected response.
+    pub async fn ping(&self) -> Result<(), Error> {
+        let body = self.query(&self.ping_path, "").await?;
+        match String::from_utf8_lossy(&body).trim() == self.pong {
+            true => Ok(()),
+            false => Err(Error::Probe(format!(
+                "{} answered unexpected response",
+                self.ping_path
+            ))),
+        }
+    }
+
+    /// Query the full pool statistics from the status page.
+    pub async fn status(&self) -> Result<FpmStatus, Error> {
+        let body = self.query(&self.status_path, "json&full").await?;
+        let status = FpmStatus::parse(&body)?;
+        *self.last_status.write().expect("poisoned lock") = Some(status.clone());
+        Ok(status)
+    }
+
+    /// Most recent status retrieved by [`FpmProbe::status`].
+    pub fn last_status(&self) -> Option<FpmStatus> {
+        self.last_status.read().expect("poisoned lock").clone()
+    }
+
+    /// Refresh the status in the background at the specified interval.
+    ///
+    /// Must be called from within an actix runtime. Failed queries clear
+    /// the retained status until the next successful query.
+    pub fn poll(&self, interval: Duration) {
+        let probe = self.clone();
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(err) = probe.status().await {
+                    tracing::warn!("php-fpm status query failed: {err}");
+                    *probe.last_status.write().expect("poisoned lock") = None;
+                }
+            }
+        });
+    }
+}
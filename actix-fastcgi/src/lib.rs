@@ -1,13 +1,28 @@
+mod abort;
+mod control;
 mod error;
 mod factory;
+mod fpm;
 mod payload;
 mod pool;
+mod record;
+mod recorder;
+mod request_id;
 mod service;
-mod stream;
+mod stats;
 
+pub use control::{ControlHandle, PoolStatus};
 pub use error::Error;
-pub use factory::FastCGI;
+pub use factory::{Confinement, FastCGI};
+pub use fpm::{FpmProbe, FpmProcess, FpmStatus};
 pub use payload::{RequestStream, ResponseStream};
 pub use pool::SockPool;
-pub use service::FastCGIService;
-pub use stream::{SockStream, StreamAddr};
+pub use record::EndStatus;
+pub use recorder::Recorder;
+pub use request_id::RequestId;
+pub use service::{FastCGIService, HeaderJoin, join_header};
+pub use stats::{ScriptStat, ScriptStats, SlowEntry, SlowLog};
+
+pub use actix_upstream::{
+    Connector, EgressProxy, IpPreference, ProxyProtocol, SockStream, StreamAddr,
+};
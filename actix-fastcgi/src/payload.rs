@@ -1,27 +1,75 @@
 //! Stream Abstraction for FastCGI
 
 use std::{
+    cell::Cell,
+    collections::BTreeSet,
     io,
     pin::Pin,
+    rc::Rc,
+    sync::Mutex,
     task::{Context, Poll},
 };
 
 use actix_web::{
-    HttpMessage, HttpResponse,
+    HttpMessage, HttpResponse, HttpResponseBuilder,
     dev::ServiceRequest,
     error::PayloadError,
-    http::StatusCode,
+    http::{StatusCode, header},
     web::{Bytes, BytesMut},
 };
 use fastcgi_client::{ClientError, response::Content};
 use futures_core::{Stream, stream::LocalBoxStream};
-use futures_util::StreamExt;
+use futures_util::{FutureExt, StreamExt};
 use tokio_util::io::StreamReader;
 
 use super::error::Error;
+use crate::EndStatus;
 
 const STATUS_HEADER: &str = "Status";
 
+/// Maximum number of interim header blocks skipped before the final response
+const MAX_INTERIM_RESPONSES: usize = 8;
+
+/// Maximum number of distinct custom reason phrases retained
+const MAX_REASON_PHRASES: usize = 64;
+
+/// Interned custom reason phrases since actix requires `&'static str`
+///
+/// Shared by every service and response of the process, so the leaked
+/// phrases stay bounded across workers and reloads.
+static REASON_PHRASES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// Resolve a custom reason phrase differing from the canonical reason
+///
+/// Invalid phrases are discarded and new phrases are ignored once
+/// the intern limit is reached.
+fn reason_phrase(status: StatusCode, reason: &[u8]) -> Option<&'static str> {
+    let reason = std::str::from_utf8(reason).ok()?;
+    let valid = reason
+        .bytes()
+        .all(|b| b == b'\t' || b == b' ' || b.is_ascii_graphic());
+    if reason.is_empty() || !valid || status.canonical_reason() == Some(reason) {
+        return None;
+    }
+    let mut phrases = REASON_PHRASES.lock().expect("poisoned lock");
+    if let Some(phrase) = phrases.get(reason) {
+        return Some(phrase);
+    }
+    if phrases.len() >= MAX_REASON_PHRASES {
+        tracing::warn!("reason phrase limit reached. ignoring {reason:?}");
+        return None;
+    }
+    let phrase: &'static str = Box::leak(reason.to_owned().into_boxed_str());
+    phrases.insert(phrase);
+    Some(phrase)
+}
+
+/// Maximum number of stderr bytes kept for development error pages
+const MAX_STDERR_TAIL: usize = 8 * 1024;
+
+/// Default maximum size of a response header block
+pub(crate) const DEFAULT_MAX_HEADER_SIZE: usize = 64 * 1024;
+
 /// Request Stream wrapper for converting
 /// [`ServiceRequest`](actix_web::dev::ServiceRequest) into
 /// [`StreamReader`](tokio_util::io::StreamReader)
@@ -65,8 +113,13 @@ impl Stream for RequestStream {
 /// [`HttpResponse`](actix_web::HttpResponse)
 pub struct ResponseStream {
     stream: LocalBoxStream<'static, Result<Content, ClientError>>,
-    buf: BytesMut,
-    eof: Option<usize>,
+    buf: Bytes,
+    max_header_size: usize,
+    dev_mode: bool,
+    early_hints: bool,
+    stderr: Vec<u8>,
+    end: Option<Rc<Cell<Option<EndStatus>>>>,
+    end_reported: bool,
 }
 
 impl ResponseStream {
@@ -75,49 +128,246 @@ impl ResponseStream {
         S: Stream<Item = Result<Content, ClientError>> + 'static,
     {
         Self {
-            stream: Box::pin(stream),
-            buf: BytesMut::with_capacity(1_024), // pre-allocate 1KiB
-            eof: None,
+            stream: Box::pin(stream.fuse()),
+            buf: Bytes::new(),
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            dev_mode: false,
+            early_hints: false,
+            stderr: Vec::new(),
+            end: None,
+            end_reported: false,
         }
     }
 
+    /// Fail on the `FCGI_END_REQUEST` status recorded from the socket.
+    ///
+    /// Failures known before the response starts become a 502, while
+    /// later failures abort the response body.
+    pub(crate) fn end_status(mut self, end: Rc<Cell<Option<EndStatus>>>) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Render a detailed error page for broken scripts.
+    ///
+    /// Failed responses and 5xx responses preceded by stderr output
+    /// are replaced with a 500 page including the error and stderr tail.
+    ///
+    /// Default is disabled.
+    pub fn dev_mode(mut self, dev_mode: bool) -> Self {
+        self.dev_mode = dev_mode;
+        self
+    }
+
+    /// Keep the preload links of interim `103 Early Hints` responses.
+    ///
+    /// Interim header blocks emitted by the script are always consumed
+    /// before the final response. When enabled, their `Link` headers with
+    /// `rel=preload` are added to the final response unless already present.
+    ///
+    /// No `103` response is sent to the client since actix-web cannot send
+    /// interim responses, and `Link` headers of the final response are
+    /// passed through unchanged.
+    ///
+    /// Default is disabled.
+    pub fn early_hints(mut self, early_hints: bool) -> Self {
+        self.early_hints = early_hints;
+        self
+    }
+
+    /// Set the maximum size of the response header block.
+    ///
+    /// Default is 64KiB.
+    pub fn max_header_size(mut self, max_header_size: usize) -> Self {
+        self.max_header_size = max_header_size;
+        self
+    }
+
+    /// Read stdout until the end of the response headers
+    ///
+    /// Headers contained within a single record are sliced without copying.
+    /// Any trailing body content is kept for the response stream.
     #[inline]
-    async fn read_until_body(&mut self) -> Result<(), Error> {
-        while self.eof.is_none() {
-            match self.next().await {
-                Some(item) => item?,
+    async fn read_headers(&mut self) -> Result<Bytes, Error> {
+        let mut buf = Bytes::new();
+        loop {
+            let data = match self.next().await {
+                Some(data) => data?,
                 None => return Err(Error::UnexpectedEnd),
             };
+            let start = buf.len().saturating_sub(3);
+            buf = match buf.is_empty() {
+                true => data,
+                false => Bytes::from([buf, data].concat()),
+            };
+            let eof = buf[start..].windows(4).position(|w| w == b"\r\n\r\n");
+            let size = eof.map(|idx| start + idx + 4).unwrap_or(buf.len());
+            if size > self.max_header_size {
+                tracing::error!("response headers too large: {size} bytes");
+                return Err(Error::HeadersTooLarge(self.max_header_size));
+            }
+            if eof.is_some() {
+                self.buf = buf.split_off(size);
+                return Ok(buf);
+            }
         }
-        Ok(())
     }
 
     /// Convert Stream Buffer into HttpResponse
     ///
-    /// Internally writes stream stdout to temporary memory-buffer
-    /// until all headers can be read, then passes the rest of the stream
-    /// body directly until final EOF is reached.
+    /// Reads stream stdout until all headers can be read, then passes
+    /// the rest of the stream body directly until final EOF is reached.
     pub async fn into_response(mut self) -> Result<HttpResponse, Error> {
-        self.read_until_body().await?;
+        let mut result = self.parse_headers().await;
+        if result.is_ok() {
+            result = self.read_ready().and(result);
+        }
+        if let Some(status) = self.failed_end() {
+            tracing::error!("fastcgi request failed with {status}");
+            result = Err(Error::EndRequest(status));
+        }
+        if self.dev_mode {
+            match &result {
+                Err(err) => return Ok(self.dev_response(&error_chain(err))),
+                Ok((_, status)) if status.is_server_error() && !self.stderr.is_empty() => {
+                    return Ok(self.dev_response(&format!("Script returned status {status}")));
+                }
+                _ => {}
+            }
+        }
+        let (mut builder, _) = result?;
+        Ok(builder.streaming(self))
+    }
 
-        let raw_headers = self.buf.split_to(self.eof.expect("missing eof") + 4);
+    /// Buffer body content which already arrived without waiting on the service
+    ///
+    /// Short responses usually arrive in full alongside their headers, so
+    /// a failed end of request is noticed before the response starts.
+    fn read_ready(&mut self) -> Result<(), Error> {
+        let mut buf = BytesMut::from(&std::mem::take(&mut self.buf)[..]);
+        while buf.len() < self.max_header_size {
+            match self.next().now_or_never() {
+                Some(Some(data)) => buf.extend_from_slice(&data?),
+                Some(None) | None => break,
+            }
+        }
+        self.buf = buf.freeze();
+        Ok(())
+    }
+
+    /// Recorded `FCGI_END_REQUEST` status when it reports a failure
+    fn failed_end(&self) -> Option<EndStatus> {
+        let end = self.end.as_ref()?;
+        end.get().filter(EndStatus::is_failure)
+    }
+
+    /// Render the detailed development error page
+    fn dev_response(&self, detail: &str) -> HttpResponse {
+        let stderr = String::from_utf8_lossy(&self.stderr);
+        HttpResponse::InternalServerError()
+            .content_type("text/plain; charset=utf-8")
+            .body(format!(
+                "FastCGI Error\n\n{detail}\n\n--- stderr (tail) ---\n{stderr}"
+            ))
+    }
+
+    /// Read and parse the final response headers into a response builder
+    ///
+    /// Informational header blocks preceding the final response are
+    /// skipped, keeping their preload links when early hints are enabled.
+    async fn parse_headers(&mut self) -> Result<(HttpResponseBuilder, StatusCode), Error> {
+        let mut hints = Vec::new();
+        for _ in 0..MAX_INTERIM_RESPONSES {
+            let (mut builder, status, links) = self.parse_header_block().await?;
+            if !status.is_informational() {
+                for link in hints.into_iter().filter(|link| !links.contains(link)) {
+                    builder.append_header((header::LINK, link));
+                }
+                return Ok((builder, status));
+            }
+            tracing::debug!("skipping interim response {status}");
+            if self.early_hints && status.as_u16() == 103 {
+                hints.extend(links.into_iter().filter(|link| is_preload(link)));
+            }
+        }
+        tracing::error!("too many interim responses");
+        Err(Error::UnexpectedEnd)
+    }
+
+    /// Read and parse a single header block into a response builder
+    ///
+    /// Returns the `Link` header values alongside the response status.
+    async fn parse_header_block(
+        &mut self,
+    ) -> Result<(HttpResponseBuilder, StatusCode, Vec<Bytes>), Error> {
+        let raw_headers = self.read_headers().await?;
         let mut headers = [httparse::EMPTY_HEADER; 32];
-        httparse::parse_headers(&raw_headers[..raw_headers.len() - 2], &mut headers)
-            .map_err(Error::InvalidHeaders)?;
+        let headers = match httparse::parse_headers(&raw_headers, &mut headers) {
+            Ok(httparse::Status::Complete((_, headers))) => headers,
+            Ok(httparse::Status::Partial) => return Err(Error::UnexpectedEnd),
+            Err(err) => {
+                let size = raw_headers.len();
+                tracing::error!("malformed response headers: {size} bytes: {err}");
+                return Err(Error::InvalidHeaders(err));
+            }
+        };
 
         let mut builder = HttpResponse::Ok();
-        for header in headers.into_iter().filter(|h| !h.name.is_empty()) {
+        let mut status = StatusCode::OK;
+        let mut links = Vec::new();
+        for header in headers.iter() {
             match header.name {
-                STATUS_HEADER => {
-                    let mut split = header.value.split(|b| b.is_ascii_whitespace());
-                    builder.status(StatusCode::from_bytes(split.next().unwrap_or(b""))?)
+                name if name.eq_ignore_ascii_case(STATUS_HEADER) => {
+                    let value = header.value.trim_ascii();
+                    let (code, reason) = match value.iter().position(u8::is_ascii_whitespace) {
+                        Some(idx) => (&value[..idx], value[idx..].trim_ascii()),
+                        None => (value, &b""[..]),
+                    };
+                    status = StatusCode::from_bytes(code)?;
+                    builder.status(status);
+                    if let Some(reason) = reason_phrase(status, reason) {
+                        builder.reason(reason);
+                    }
+                }
+                // fastcgi cannot carry trailer fields so never announce any
+                name if name.eq_ignore_ascii_case(header::TRAILER.as_str()) => {
+                    tracing::debug!("dropping trailer declaration {:?}", header.value);
+                }
+                name => {
+                    if name.eq_ignore_ascii_case(header::LINK.as_str()) {
+                        links.push(raw_headers.slice_ref(header.value));
+                    }
+                    builder.append_header((name, header.value));
                 }
-                name => builder.append_header((name, header.value)),
             };
         }
 
-        Ok(builder.streaming(self))
+        Ok((builder, status, links))
+    }
+}
+
+/// Check if a `Link` header value contains a `rel=preload` link
+fn is_preload(value: &[u8]) -> bool {
+    let Ok(value) = std::str::from_utf8(value) else {
+        return false;
+    };
+    value
+        .split([';', ','])
+        .filter_map(|param| param.trim().split_once('='))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("rel"))
+        .flat_map(|(_, rel)| rel.trim().trim_matches('"').split_ascii_whitespace())
+        .any(|rel| rel.eq_ignore_ascii_case("preload"))
+}
+
+/// Format an error including all of its sources
+fn error_chain(err: &Error) -> String {
+    let mut detail = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        detail.push_str(&format!(": {err}"));
+        source = err.source();
     }
+    detail
 }
 
 impl Stream for ResponseStream {
@@ -125,32 +375,36 @@ impl Stream for ResponseStream {
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         if !self.buf.is_empty() {
-            let idx = self.buf.len();
-            return Poll::Ready(Some(Ok(self.buf.split_to(idx).freeze())));
+            return Poll::Ready(Some(Ok(std::mem::take(&mut self.buf))));
         }
-        match Pin::new(&mut self.stream).poll_next(cx) {
-            Poll::Ready(Some(Ok(content))) => match content {
-                Content::Stdout(data) => {
-                    if self.eof.is_none() {
-                        self.buf.extend_from_slice(&data);
-                        self.eof = data.windows(4).position(|w| w == b"\r\n\r\n");
-                    }
-                    Poll::Ready(Some(Ok(data)))
-                }
-                Content::Stderr(data) => {
+        loop {
+            return match Pin::new(&mut self.stream).poll_next(cx) {
+                // empty chunks would terminate a chunked response early
+                Poll::Ready(Some(Ok(Content::Stdout(data)))) if data.is_empty() => continue,
+                Poll::Ready(Some(Ok(Content::Stdout(data)))) => Poll::Ready(Some(Ok(data))),
+                Poll::Ready(Some(Ok(Content::Stderr(data)))) => {
                     let message = std::str::from_utf8(&data);
                     tracing::warn!("FastCGI Stderr {message:?}");
-                    Poll::Ready(Some(Ok(Bytes::new())))
+                    if self.dev_mode {
+                        self.stderr.extend_from_slice(&data);
+                        let excess = self.stderr.len().saturating_sub(MAX_STDERR_TAIL);
+                        self.stderr.drain(..excess);
+                    }
+                    continue;
                 }
-            },
-            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(PayloadError::Io(
-                io::Error::other(err.to_string()),
-            )))),
-            Poll::Ready(None) => {
-                self.eof = Some(self.buf.len());
-                Poll::Ready(None)
-            }
-            Poll::Pending => Poll::Pending,
+                Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(PayloadError::Io(
+                    io::Error::other(err.to_string()),
+                )))),
+                Poll::Ready(None) => match self.failed_end() {
+                    Some(status) if !self.end_reported => {
+                        self.end_reported = true;
+                        let err = io::Error::other(Error::EndRequest(status).to_string());
+                        Poll::Ready(Some(Err(PayloadError::Io(err))))
+                    }
+                    _ => Poll::Ready(None),
+                },
+                Poll::Pending => Poll::Pending,
+            };
         }
     }
 }
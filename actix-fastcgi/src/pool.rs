@@ -1,10 +1,26 @@
+use std::sync::{Arc, RwLock};
+
 use deadpool::managed;
 
-use crate::{Error, SockStream, stream::StreamAddr};
+use crate::{Connector, Error, SockStream, StreamAddr};
 
 pub type SockPool = managed::Pool<Manager>;
 
-pub struct Manager(pub(crate) StreamAddr);
+/// Upstream dial settings shared between pool and control handle
+pub(crate) struct Upstream {
+    pub(crate) addr: StreamAddr,
+    pub(crate) connector: Connector,
+}
+
+pub struct Manager(pub(crate) Arc<RwLock<Upstream>>);
+
+impl Manager {
+    /// Current fastcgi service address
+    #[inline]
+    pub(crate) fn addr(&self) -> StreamAddr {
+        self.0.read().expect("poisoned lock").addr.clone()
+    }
+}
 
 impl managed::Manager for Manager {
     type Type = SockStream;
@@ -12,7 +28,11 @@ impl managed::Manager for Manager {
 
     #[inline]
     async fn create(&self) -> Result<Self::Type, Error> {
-        SockStream::connect(&self.0).await
+        let (addr, connector) = {
+            let upstream = self.0.read().expect("poisoned lock");
+            (upstream.addr.clone(), upstream.connector.clone())
+        };
+        Ok(connector.connect(&addr).await?)
     }
 
     async fn recycle(
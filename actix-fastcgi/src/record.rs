@@ -0,0 +1,148 @@
+//! FastCGI EndRequest Record Inspection
+
+use std::{
+    cell::Cell,
+    fmt, io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Record type of `FCGI_END_REQUEST`
+const END_REQUEST: u8 = 3;
+
+/// Size of a fastcgi record header
+const HEADER_SIZE: usize = 8;
+
+/// Application and protocol status of an `FCGI_END_REQUEST` record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndStatus {
+    /// Exit status of the script
+    pub app_status: u32,
+    /// Protocol status where zero marks a completed request
+    pub protocol_status: u8,
+}
+
+impl EndStatus {
+    /// Check if the script failed or the request was not completed
+    #[inline]
+    pub fn is_failure(&self) -> bool {
+        self.app_status != 0 || self.protocol_status != 0
+    }
+}
+
+impl fmt::Display for EndStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocol = match self.protocol_status {
+            0 => "RequestComplete",
+            1 => "CantMpxConn",
+            2 => "Overloaded",
+            3 => "UnknownRole",
+            _ => "Unknown",
+        };
+        write!(
+            f,
+            "protocol status {protocol}, app status {}",
+            self.app_status
+        )
+    }
+}
+
+/// Socket recording the `FCGI_END_REQUEST` status read from the service
+///
+/// The fastcgi client only reports non-complete protocol statuses and
+/// discards the application status, so records are inspected as they are
+/// read without altering the stream.
+pub(crate) struct EndRequestStream<S> {
+    inner: S,
+    header: Vec<u8>,
+    remaining: usize,
+    content: Option<Vec<u8>>,
+    status: Rc<Cell<Option<EndStatus>>>,
+}
+
+impl<S> EndRequestStream<S> {
+    /// Wrap the socket returning the recorded end of request status
+    pub(crate) fn new(inner: S) -> (Self, Rc<Cell<Option<EndStatus>>>) {
+        let status = Rc::new(Cell::new(None));
+        let stream = Self {
+            inner,
+            header: Vec::with_capacity(HEADER_SIZE),
+            remaining: 0,
+            content: None,
+            status: status.clone(),
+        };
+        (stream, status)
+    }
+
+    /// Track record boundaries of data read from the socket
+    fn inspect(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.remaining == 0 {
+                let take = (HEADER_SIZE - self.header.len()).min(data.len());
+                self.header.extend_from_slice(&data[..take]);
+                data = &data[take..];
+                if self.header.len() == HEADER_SIZE {
+                    let length = u16::from_be_bytes([self.header[4], self.header[5]]);
+                    self.remaining = length as usize + self.header[6] as usize;
+                    self.content = (self.header[1] == END_REQUEST).then(Vec::new);
+                    self.header.clear();
+                }
+                continue;
+            }
+            let take = self.remaining.min(data.len());
+            if let Some(content) = self.content.as_mut() {
+                content.extend_from_slice(&data[..take]);
+            }
+            self.remaining -= take;
+            data = &data[take..];
+            if self.remaining == 0
+                && let Some(content) = self.content.take()
+                && content.len() >= 5
+            {
+                let app_status =
+                    u32::from_be_bytes([content[0], content[1], content[2], content[3]]);
+                self.status.set(Some(EndStatus {
+                    app_status,
+                    protocol_status: content[4],
+                }));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for EndRequestStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let start = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            self.inspect(&buf.filled()[start..]);
+        }
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for EndRequestStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,176 @@
+//! Request/Response Recorder for FastCGI Debugging
+
+use std::{
+    cell::RefCell,
+    fmt::Write,
+    path::PathBuf,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{dev::ServiceRequest, http::header::HeaderName};
+use fastcgi_client::Params;
+
+/// Default maximum number of stdin bytes kept per recording
+const DEFAULT_MAX_STDIN: usize = 4 * 1024;
+
+/// Opt-in recorder dumping raw fastcgi exchanges to a directory
+///
+/// Each matching request writes a single file containing the exact
+/// fastcgi params, the (truncated) stdin bytes and every raw stdout/stderr
+/// record received from the fastcgi service.
+///
+/// Recordings are written synchronously once the response completes and
+/// are intended for debugging only.
+///
+/// # Examples
+///
+/// ```
+/// use actix_fastcgi::{FastCGI, Recorder};
+///
+/// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000")
+///     .recorder(Recorder::new("/tmp/fastcgi").header("X-FastCGI-Record"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    header: Option<HeaderName>,
+    sample: Option<u64>,
+    max_stdin: usize,
+    counter: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Create a new recorder writing to the specified directory.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            header: None,
+            sample: None,
+            max_stdin: DEFAULT_MAX_STDIN,
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Record requests carrying the specified header.
+    ///
+    /// Default records every request when no trigger is configured.
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = Some(HeaderName::try_from(header).expect("invalid header name"));
+        self
+    }
+
+    /// Record one out of every `rate` requests.
+    ///
+    /// Default records every request when no trigger is configured.
+    pub fn sample(mut self, rate: u64) -> Self {
+        self.sample = Some(rate.max(1));
+        self
+    }
+
+    /// Set the maximum number of stdin bytes kept per recording.
+    ///
+    /// Default is 4KiB.
+    pub fn max_stdin(mut self, max_stdin: usize) -> Self {
+        self.max_stdin = max_stdin;
+        self
+    }
+
+    /// Begin a recording when the request matches a configured trigger
+    pub(crate) fn start(&self, req: &ServiceRequest, params: &Params) -> Option<Recording> {
+        let count = self.counter.fetch_add(1, Ordering::Relaxed);
+        let by_header = self
+            .header
+            .as_ref()
+            .is_some_and(|name| req.headers().contains_key(name));
+        let by_sample = self.sample.is_some_and(|rate| count.is_multiple_of(rate));
+        let always = self.header.is_none() && self.sample.is_none();
+        if !(always || by_header || by_sample) {
+            return None;
+        }
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut record = Record {
+            path: self.dir.join(format!("{millis}-{count}.txt")),
+            max_stdin: self.max_stdin,
+            stdin: Vec::new(),
+            stdin_size: 0,
+            params: String::new(),
+            output: String::new(),
+        };
+
+        let _ = writeln!(record.params, "== {} {}", req.method(), req.uri());
+        let _ = writeln!(record.params, "== params");
+        let mut params: Vec<_> = params.iter().collect();
+        params.sort();
+        for (name, value) in params {
+            let _ = writeln!(record.params, "{name}={value}");
+        }
+        Some(Recording(Rc::new(RefCell::new(record))))
+    }
+}
+
+/// Active recording shared between request and response streams
+///
+/// The recording is written once all references are dropped.
+#[derive(Clone)]
+pub(crate) struct Recording(Rc<RefCell<Record>>);
+
+impl Recording {
+    /// Record a chunk of stdin sent to the fastcgi service
+    pub(crate) fn stdin(&self, data: &[u8]) {
+        let mut record = self.0.borrow_mut();
+        let remaining = record.max_stdin.saturating_sub(record.stdin.len());
+        record
+            .stdin
+            .extend_from_slice(&data[..data.len().min(remaining)]);
+        record.stdin_size += data.len();
+    }
+
+    /// Record a raw stdout/stderr record from the fastcgi service
+    pub(crate) fn output(&self, kind: &str, data: &[u8]) {
+        let mut record = self.0.borrow_mut();
+        let _ = writeln!(record.output, "== {kind} ({} bytes)", data.len());
+        let _ = writeln!(record.output, "{}", data.escape_ascii());
+    }
+}
+
+struct Record {
+    path: PathBuf,
+    max_stdin: usize,
+    stdin: Vec<u8>,
+    stdin_size: usize,
+    params: String,
+    output: String,
+}
+
+impl Drop for Record {
+    fn drop(&mut self) {
+        let contents = format!(
+            "{}== stdin ({} bytes, {} recorded)\n{}\n{}",
+            self.params,
+            self.stdin_size,
+            self.stdin.len(),
+            self.stdin.escape_ascii(),
+            self.output,
+        );
+
+        let result = self
+            .path
+            .parent()
+            .map(std::fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| std::fs::write(&self.path, contents));
+        match result {
+            Ok(_) => tracing::debug!("fastcgi recording written to {:?}", self.path),
+            Err(err) => tracing::error!("failed to write fastcgi recording {:?}: {err}", self.path),
+        }
+    }
+}
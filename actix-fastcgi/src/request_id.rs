@@ -0,0 +1,110 @@
+//! Request ID Propagation into FastCGI Params
+
+use actix_common::{TransactionId, X_REQUEST_ID};
+use actix_web::{HttpRequest, http::header::HeaderName};
+use fastcgi_client::Params;
+
+/// Opt-in propagation of a request ID into fastcgi params
+///
+/// The ID is read from the configured request header and passed
+/// to the fastcgi service as the header param (`HTTP_X_REQUEST_ID`
+/// by default) alongside each configured param (`UNIQUE_ID` by
+/// default, matching Apache's `mod_unique_id`), so backend logs can
+/// be correlated with proxy logs.
+///
+/// The [`TransactionId`] already assigned to the request by another
+/// middleware, such as the ModSecurity WAF, takes precedence. Header
+/// values failing [`TransactionId::is_valid`] are ignored. Requests
+/// without either are assigned a generated ID. The resolved ID is stored
+/// in the request extensions as a [`TransactionId`] and reused for the
+/// lifetime of the request.
+///
+/// # Examples
+///
+/// ```
+/// use actix_fastcgi::{FastCGI, RequestId};
+///
+/// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000")
+///     .request_id(RequestId::new().header("X-Correlation-ID").param("REQUEST_ID"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestId {
+    header: HeaderName,
+    params: Vec<String>,
+    generate: bool,
+}
+
+impl RequestId {
+    /// Create a new request ID config reading `X-Request-ID`
+    /// and passing the ID as `UNIQUE_ID`.
+    pub fn new() -> Self {
+        Self {
+            header: X_REQUEST_ID,
+            params: vec!["UNIQUE_ID".to_owned()],
+            generate: true,
+        }
+    }
+
+    /// Read the request ID from the specified header.
+    ///
+    /// Default is `X-Request-ID`.
+    pub fn header(mut self, header: &str) -> Self {
+        match HeaderName::try_from(header) {
+            Ok(header) => self.header = header,
+            Err(_) => tracing::error!("invalid request id header: {header:?}"),
+        }
+        self
+    }
+
+    /// Pass the request ID as an additional param.
+    pub fn param<S: Into<String>>(mut self, param: S) -> Self {
+        self.params.push(param.into());
+        self
+    }
+
+    /// Remove all additional params including the default `UNIQUE_ID`.
+    pub fn clear_params(mut self) -> Self {
+        self.params.clear();
+        self
+    }
+
+    /// Generate an ID for requests without the header.
+    ///
+    /// Default is enabled.
+    pub fn generate(mut self, generate: bool) -> Self {
+        self.generate = generate;
+        self
+    }
+
+    /// Resolve the request ID from the header or generate a new one
+    fn resolve(&self, req: &HttpRequest) -> Option<String> {
+        let assigned = actix_common::transaction_id(req).is_some();
+        let present = req
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| TransactionId::is_valid(value.trim()));
+        match assigned || present || self.generate {
+            true => Some(TransactionId::resolve(req, &self.header).to_string()),
+            false => None,
+        }
+    }
+
+    /// Fill the request ID params for the request
+    pub(crate) fn apply(&self, req: &HttpRequest, params: &mut Params<'_>) {
+        let Some(id) = self.resolve(req) else {
+            return;
+        };
+        let name = self.header.as_str().replace("-", "_").to_uppercase();
+        params.insert(format!("HTTP_{name}").into(), id.clone().into());
+        for param in self.params.iter() {
+            params.insert(param.clone().into(), id.clone().into());
+        }
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,24 +2,47 @@ use std::{
     ops::Deref,
     path::{Path, PathBuf},
     rc::Rc,
+    task::{Context, Poll},
+    time::Instant,
 };
 
+#[cfg(feature = "opentelemetry")]
+use actix_common::telemetry::{SpanKind, SpanScope};
+use actix_common::{AllowedMethods, Concurrency, Cors, ErrorPages, Template, metrics::Timer};
 use actix_files::PathBufWrap;
 use actix_web::{
-    HttpRequest,
+    HttpMessage, HttpRequest, HttpResponse,
     body::BoxBody,
-    dev::{self, Service, ServiceRequest, ServiceResponse},
+    dev::{Service, ServiceRequest, ServiceResponse},
     error::Error as ActixError,
+    http::header::{self, HeaderName},
 };
 use deadpool::managed::Object;
-use fastcgi_client::{Client, Params, Request};
+use fastcgi_client::{Client, Params, Request, response::Content};
 use futures_core::future::LocalBoxFuture;
+use futures_util::StreamExt;
 
-use crate::{SockPool, pool};
+use crate::{
+    Confinement, Recorder, RequestId, ScriptStats, SlowLog, SockPool, abort::AbortStream, pool,
+    record::EndRequestStream, recorder::Recording,
+};
 
 use super::error::Error;
 use super::payload::{RequestStream, ResponseStream};
 
+/// Strategy for joining repeated request headers into a single param
+pub type HeaderJoin = Rc<dyn Fn(&HeaderName, &[&str]) -> String>;
+
+/// Join repeated request headers per CGI convention
+///
+/// `Cookie` values are joined with `; ` and all others with `, `.
+pub fn join_header(name: &HeaderName, values: &[&str]) -> String {
+    match *name == header::COOKIE {
+        true => values.join("; "),
+        false => values.join(", "),
+    }
+}
+
 /// Assembled fastcgi client service
 #[derive(Clone)]
 pub struct FastCGIService(pub(crate) Rc<FastCGIInner>);
@@ -34,15 +57,7 @@ impl FastCGIService {
     ///
     /// The third argument (`req`) is the http-request object to load data from
     pub fn fill_params<'a>(&'a self, path: &Path, req: &HttpRequest) -> Params<'a> {
-        let mut real_path = self.root.join(path);
-        if real_path.is_dir() {
-            real_path = self
-                .indexes
-                .iter()
-                .map(|index| real_path.join(index))
-                .find(|path| path.exists())
-                .unwrap_or(real_path);
-        }
+        let real_path = self.script_path(path);
 
         let root = self.root.to_string_lossy().to_string();
         let path = real_path.to_string_lossy().to_string();
@@ -50,6 +65,9 @@ impl FastCGIService {
 
         let saddr = req.app_config().local_addr();
         let mut params = Params::default()
+            .server_software(self.server_software.as_str())
+            .gateway_interface(self.gateway_interface.as_str())
+            .server_protocol(format!("{:?}", req.version()))
             .document_uri(script_name.clone())
             .document_root(root)
             .request_method(req.method().as_str().to_owned())
@@ -60,25 +78,257 @@ impl FastCGIService {
             .server_addr(saddr.ip().to_string())
             .server_port(saddr.port());
 
-        for (name, value) in req.headers() {
-            let val = match value.to_str() {
-                Ok(val) => val,
-                Err(_) => continue,
-            };
+        for name in req.headers().keys() {
+            let values: Vec<_> = req
+                .headers()
+                .get_all(name)
+                .filter_map(|value| value.to_str().ok())
+                .collect();
+            if values.is_empty() {
+                continue;
+            }
+            let value = (self.header_join)(name, &values);
             let name = match name.as_str() {
+                "content-type" | "content-length" if !self.forward_body => continue,
                 "content-type" => "CONTENT_TYPE".to_owned(),
                 "content-length" => "CONTENT_LENGTH".to_owned(),
                 name => format!("HTTP_{}", name.replace("-", "_").to_uppercase()),
             };
-            params.insert(name.into(), val.to_owned().into());
+            params.insert(name.into(), value.into());
+        }
+        for (name, template) in self.header_templates.iter() {
+            let name = format!("HTTP_{}", name.as_str().replace("-", "_").to_uppercase());
+            params.insert(name.into(), template.render(req).into());
         }
 
-        if let Some(peer) = req.peer_addr() {
+        if let Some(request_id) = self.request_id.as_ref() {
+            request_id.apply(req, &mut params);
+        }
+        if let (Some(param), Some(deadline)) =
+            (self.deadline_param.as_ref(), actix_common::deadline(req))
+        {
+            let remaining = deadline.remaining().as_millis().to_string();
+            params.insert(param.as_str().into(), remaining.into());
+        }
+
+        #[cfg(feature = "opentelemetry")]
+        for (name, value) in actix_common::telemetry::inject_map(req) {
+            let name = format!("HTTP_{}", name.replace("-", "_").to_uppercase());
+            params.insert(name.into(), value.into());
+        }
+
+        if let Some(peer) = actix_common::client_addr(req) {
             let client = peer.ip().to_string();
             params = params.remote_addr(client).remote_port(peer.port());
         }
+        if self.client_cert {
+            let cert = actix_common::client_cert(req);
+            let verify = cert.as_ref().map(|c| c.verify_status()).unwrap_or("NONE");
+            params.insert("SSL_CLIENT_VERIFY".into(), verify.into());
+            if let Some(cert) = cert {
+                params.insert("SSL_CLIENT_CERT".into(), cert.pem().into());
+                let fields = [
+                    ("SSL_CLIENT_S_DN", cert.subject_dn()),
+                    ("SSL_CLIENT_I_DN", cert.issuer_dn()),
+                    ("SSL_CLIENT_M_SERIAL", cert.serial_number()),
+                ];
+                for (name, value) in fields {
+                    if let Some(value) = value {
+                        params.insert(name.into(), value.to_owned().into());
+                    }
+                }
+            }
+        }
         params
     }
+
+    /// Resolve the script path on disk including any configured index files
+    /// and the front controller
+    fn script_path(&self, path: &Path) -> PathBuf {
+        let real_path = self.root.join(path);
+        let script = match real_path.is_dir() {
+            true => self
+                .indexes
+                .iter()
+                .map(|index| real_path.join(index))
+                .find(|path| path.exists())
+                .unwrap_or(real_path),
+            false => real_path,
+        };
+        match self.front_controller.as_ref() {
+            Some(front) if !script.is_file() => self.root.join(front.trim_start_matches('/')),
+            _ => script,
+        }
+    }
+
+    /// Verify the script path stays within the root according to [`Confinement`]
+    fn is_confined(&self, path: &Path) -> bool {
+        if self.confinement == Confinement::Disabled {
+            return true;
+        }
+        let script = self.script_path(path);
+        if self.confinement == Confinement::Strict {
+            let symlink = script
+                .ancestors()
+                .take_while(|ancestor| *ancestor != self.root)
+                .any(|ancestor| ancestor.is_symlink());
+            if symlink {
+                tracing::warn!("script path contains symlink: {script:?}");
+                return false;
+            }
+        }
+        // resolve the deepest existing ancestor since the script may not exist
+        let resolved = script
+            .ancestors()
+            .find_map(|ancestor| ancestor.canonicalize().ok());
+        match resolved {
+            Some(resolved) if resolved.starts_with(&self.root) => true,
+            _ => {
+                tracing::warn!("script path escapes root: {script:?}");
+                false
+            }
+        }
+    }
+
+    /// Check the declared request body size before any of the body is read
+    fn body_too_large(&self, req: &ServiceRequest) -> bool {
+        let Some(max_body_size) = self.max_body_size.filter(|_| self.forward_body) else {
+            return false;
+        };
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        match length {
+            Some(length) if length > max_body_size => {
+                tracing::warn!("request body too large: {length} bytes");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forward the request to the fastcgi service and convert the response
+    async fn forward(&self, mut req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        if self.body_too_large(&req) {
+            let res = HttpResponse::PayloadTooLarge().force_close().finish();
+            return Ok(req.into_response(res));
+        }
+
+        let path_on_disk = PathBufWrap::parse_req(req.request(), false)
+            .inspect_err(|err| tracing::error!("invalid request: {err:?}"))?;
+        if !self.is_confined(path_on_disk.as_ref()) {
+            return Ok(req.into_response(HttpResponse::Forbidden().finish()));
+        }
+        let params = self.fill_params(path_on_disk.as_ref(), req.request());
+        let recording = self
+            .recorder
+            .as_ref()
+            .and_then(|recorder| recorder.start(&req, &params));
+
+        let script = params
+            .get("SCRIPT_NAME")
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        let sent = self.slow_log.as_ref().map(|_| params.clone());
+        let start = Instant::now();
+        let result = self.execute(&mut req, params, recording).await;
+        let elapsed = start.elapsed();
+
+        let status = result.as_ref().ok().map(|res| res.status());
+        if let Some(stats) = self.stats.as_ref() {
+            let error = status.is_none_or(|status| status.is_server_error());
+            stats.record(&script, elapsed, error);
+        }
+        if let (Some(log), Some(params)) = (self.slow_log.as_ref(), sent) {
+            log.record(&script, req.request(), elapsed, status, &params);
+        }
+        Ok(req.into_response(result?))
+    }
+
+    /// Execute the script and convert its response
+    async fn execute(
+        &self,
+        req: &mut ServiceRequest,
+        params: Params<'_>,
+        recording: Option<Recording>,
+    ) -> Result<HttpResponse, ActixError> {
+        if actix_common::metrics::sink().is_some() {
+            let addr = self.fastcgi_pool.manager().addr();
+            actix_common::metrics::set_upstream(req.request(), addr.to_string());
+        }
+        let obj = self.fastcgi_pool.get().await.unwrap();
+        let sock = Object::<pool::Manager>::take(obj);
+        let (sock, done) = AbortStream::new(sock);
+        done.set(!self.abort_on_disconnect);
+        let (sock, end) = EndRequestStream::new(sock);
+        let client = Client::new(sock);
+
+        let stdin = recording.clone();
+        let payload = match self.forward_body {
+            true => req.take_payload().boxed_local(),
+            false => futures_util::stream::empty().boxed_local(),
+        };
+        let stream = RequestStream::new(payload.inspect(move |item| {
+            if let (Some(recording), Ok(data)) = (&stdin, item) {
+                recording.stdin(data);
+            }
+        }));
+        let request = Request::new(params, stream.into_reader());
+
+        let stream = client
+            .execute_once_stream(request)
+            .await
+            .map_err(Error::ClientError)
+            .inspect_err(|err| tracing::error!("request error: {err:?}"))?;
+
+        let stream = stream.inspect(move |item| match (&recording, item) {
+            (Some(recording), Ok(Content::Stdout(data))) => recording.output("stdout", data),
+            (Some(recording), Ok(Content::Stderr(data))) => recording.output("stderr", data),
+            _ => {}
+        });
+        let stream = stream.chain(futures_util::stream::poll_fn(move |_| {
+            done.set(true);
+            Poll::Ready(None)
+        }));
+        let http_res = ResponseStream::new(stream)
+            .max_header_size(self.max_header_size)
+            .dev_mode(self.dev_mode)
+            .early_hints(self.early_hints)
+            .end_status(end)
+            .into_response()
+            .await
+            .inspect_err(|err| tracing::error!("invalid response: {err:?}"))?;
+        Ok(http_res)
+    }
+
+    /// Forward the request once a concurrency permit is acquired
+    async fn serve(&self, mut req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        if let Some(res) = self.cors.as_ref().and_then(|cors| cors.preflight(&req)) {
+            return Ok(req.into_response(res));
+        }
+        if let Some(res) = self.methods.check(&mut req) {
+            return Ok(req.into_response(res));
+        }
+        let origin = self
+            .cors
+            .as_ref()
+            .and_then(|_| req.headers().get(header::ORIGIN).cloned());
+        let _permit = match self.concurrency.as_ref() {
+            Some(concurrency) => Some(concurrency.acquire().await?),
+            None => None,
+        };
+        #[cfg(feature = "opentelemetry")]
+        let scope = SpanScope::enter(req.request(), "fastcgi", SpanKind::Client);
+        let mut res = self.forward(req).await;
+        #[cfg(feature = "opentelemetry")]
+        scope.exit(&res);
+        if let (Some(cors), Ok(res)) = (self.cors.as_ref(), res.as_mut()) {
+            cors.apply(origin.as_ref(), res.response_mut());
+        }
+        res
+    }
 }
 
 impl Deref for FastCGIService {
@@ -90,9 +340,32 @@ impl Deref for FastCGIService {
 }
 
 pub struct FastCGIInner {
+    pub(crate) mount_path: String,
     pub(crate) root: PathBuf,
     pub(crate) indexes: Vec<String>,
+    pub(crate) front_controller: Option<String>,
     pub(crate) fastcgi_pool: SockPool,
+    pub(crate) max_header_size: usize,
+    pub(crate) max_body_size: Option<usize>,
+    pub(crate) forward_body: bool,
+    pub(crate) client_cert: bool,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) stats: Option<ScriptStats>,
+    pub(crate) slow_log: Option<SlowLog>,
+    pub(crate) request_id: Option<RequestId>,
+    pub(crate) deadline_param: Option<String>,
+    pub(crate) abort_on_disconnect: bool,
+    pub(crate) confinement: Confinement,
+    pub(crate) server_software: String,
+    pub(crate) gateway_interface: String,
+    pub(crate) dev_mode: bool,
+    pub(crate) early_hints: bool,
+    pub(crate) error_pages: Option<ErrorPages>,
+    pub(crate) header_join: HeaderJoin,
+    pub(crate) header_templates: Vec<(HeaderName, Template)>,
+    pub(crate) concurrency: Option<Concurrency>,
+    pub(crate) methods: AllowedMethods,
+    pub(crate) cors: Option<Cors>,
 }
 
 impl Service<ServiceRequest> for FastCGIService {
@@ -100,34 +373,26 @@ impl Service<ServiceRequest> for FastCGIService {
     type Error = ActixError;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    dev::always_ready!();
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.concurrency.as_ref() {
+            Some(concurrency) => concurrency.poll_ready(cx).map_err(ActixError::from),
+            None => Poll::Ready(Ok(())),
+        }
+    }
 
-    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+    fn call(&self, req: ServiceRequest) -> Self::Future {
         let this = self.clone();
         Box::pin(async move {
-            let path_on_disk = PathBufWrap::parse_req(req.request(), false)
-                .inspect_err(|err| tracing::error!("invalid request: {err:?}"))?;
-            let params = this.fill_params(path_on_disk.as_ref(), req.request());
-
-            let obj = this.fastcgi_pool.get().await.unwrap();
-            let sock = Object::<pool::Manager>::take(obj);
-            let client = Client::new(sock);
-
-            let stream = RequestStream::from_request(&mut req);
-            let request = Request::new(params, stream.into_reader());
-
-            let stream = client
-                .execute_once_stream(request)
-                .await
-                .map_err(Error::ClientError)
-                .inspect_err(|err| tracing::error!("request error: {err:?}"))?;
-
-            let http_res = ResponseStream::new(stream)
-                .into_response()
-                .await
-                .inspect_err(|err| tracing::error!("invalid response: {err:?}"))?;
-
-            Ok(req.into_response(http_res))
+            let timer = Timer::start("fastcgi", &this.mount_path, req.request());
+            let res = match this.error_pages.as_ref() {
+                Some(pages) => {
+                    let path = req.path().to_owned();
+                    pages.recover(&path, this.serve(req).await)
+                }
+                None => this.serve(req).await,
+            };
+            timer.finish(&res);
+            res
         })
     }
 }
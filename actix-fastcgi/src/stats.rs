@@ -0,0 +1,257 @@
+//! Per-Script Statistics and Slow Script Log
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use actix_web::{HttpRequest, http::StatusCode};
+use fastcgi_client::Params;
+
+/// Maximum number of distinct scripts tracked
+const MAX_SCRIPTS: usize = 1024;
+
+/// Number of recent latency samples kept per script
+const MAX_SAMPLES: usize = 256;
+
+/// Default maximum number of slow log entries kept
+const DEFAULT_MAX_ENTRIES: usize = 128;
+
+/// Replacement for redacted param values
+const MASK: &str = "***";
+
+/// Params redacted from slow log entries by default
+const DEFAULT_REDACT: [&str; 5] = [
+    "HTTP_AUTHORIZATION",
+    "HTTP_PROXY_AUTHORIZATION",
+    "HTTP_COOKIE",
+    "PHP_AUTH_PW",
+    "SSL_CLIENT_CERT",
+];
+
+#[derive(Debug, Default)]
+struct Script {
+    requests: u64,
+    errors: u64,
+    samples: VecDeque<Duration>,
+}
+
+impl Script {
+    /// Latency percentile of the recent samples using the nearest rank
+    fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+        match sorted.len() {
+            0 => Duration::ZERO,
+            len => sorted[(len * pct).div_ceil(100).max(1) - 1],
+        }
+    }
+}
+
+/// Point-in-time statistics of a single script
+///
+/// Latency percentiles are computed over the most recent requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptStat {
+    /// Script path relative to the document root.
+    pub script: String,
+    /// Number of requests passed to the script.
+    pub requests: u64,
+    /// Number of requests failing or answered with a 5xx status.
+    pub errors: u64,
+    /// Median latency until the response headers.
+    pub p50: Duration,
+    /// 90th percentile latency until the response headers.
+    pub p90: Duration,
+    /// 99th percentile latency until the response headers.
+    pub p99: Duration,
+}
+
+/// Shared handle collecting per-script statistics
+///
+/// Create a single handle outside the `HttpServer` factory and pass it to
+/// every worker's [`FastCGI`](crate::FastCGI) service to aggregate
+/// statistics across workers.
+///
+/// # Examples
+///
+/// ```
+/// use actix_fastcgi::{FastCGI, ScriptStats};
+///
+/// let stats = ScriptStats::new();
+/// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000").stats(stats.clone());
+///
+/// assert!(stats.scripts().is_empty());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ScriptStats(Arc<Mutex<HashMap<String, Script>>>);
+
+impl ScriptStats {
+    /// Create a new empty statistics handle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed request for the specified script
+    pub(crate) fn record(&self, script: &str, elapsed: Duration, error: bool) {
+        let mut scripts = self.0.lock().expect("poisoned lock");
+        if !scripts.contains_key(script) && scripts.len() >= MAX_SCRIPTS {
+            tracing::debug!("script stats limit reached. ignoring {script:?}");
+            return;
+        }
+        let stats = scripts.entry(script.to_owned()).or_default();
+        stats.requests += 1;
+        stats.errors += error as u64;
+        if stats.samples.len() >= MAX_SAMPLES {
+            stats.samples.pop_front();
+        }
+        stats.samples.push_back(elapsed);
+    }
+
+    /// Current statistics of every script ordered by path.
+    pub fn scripts(&self) -> Vec<ScriptStat> {
+        let scripts = self.0.lock().expect("poisoned lock");
+        let mut stats: Vec<_> = scripts
+            .iter()
+            .map(|(script, stats)| {
+                let mut sorted: Vec<_> = stats.samples.iter().copied().collect();
+                sorted.sort_unstable();
+                ScriptStat {
+                    script: script.clone(),
+                    requests: stats.requests,
+                    errors: stats.errors,
+                    p50: Script::percentile(&sorted, 50),
+                    p90: Script::percentile(&sorted, 90),
+                    p99: Script::percentile(&sorted, 99),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.script.cmp(&b.script));
+        stats
+    }
+
+    /// Discard all collected statistics.
+    pub fn reset(&self) {
+        self.0.lock().expect("poisoned lock").clear();
+    }
+}
+
+/// Single request recorded by the [`SlowLog`]
+#[derive(Clone, Debug)]
+pub struct SlowEntry {
+    /// Time the request completed.
+    pub time: SystemTime,
+    /// Script path relative to the document root.
+    pub script: String,
+    /// Request method and uri.
+    pub request: String,
+    /// Latency until the response headers.
+    pub elapsed: Duration,
+    /// Response status, if the script responded.
+    pub status: Option<StatusCode>,
+    /// Params sent to the script with sensitive values redacted.
+    pub params: Vec<(String, String)>,
+}
+
+/// Log of requests to scripts exceeding a latency threshold
+///
+/// Slow requests are logged as warnings and the most recent entries are
+/// kept in memory together with the params sent to the script. Sensitive
+/// params such as `HTTP_AUTHORIZATION` and `HTTP_COOKIE` are redacted.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_fastcgi::{FastCGI, SlowLog};
+///
+/// let slow_log = SlowLog::new(Duration::from_secs(2)).redact("HTTP_X_API_KEY");
+/// let fastcgi = FastCGI::new("/", ".", "tcp://127.0.0.1:9000").slow_log(slow_log.clone());
+///
+/// for entry in slow_log.entries() {
+///     println!("{} took {:?}", entry.script, entry.elapsed);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SlowLog {
+    threshold: Duration,
+    max_entries: usize,
+    redact: Vec<String>,
+    entries: Arc<Mutex<VecDeque<SlowEntry>>>,
+}
+
+impl SlowLog {
+    /// Create a new slow log recording requests exceeding the threshold.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            redact: DEFAULT_REDACT.map(str::to_owned).to_vec(),
+            entries: Arc::default(),
+        }
+    }
+
+    /// Set the maximum number of entries kept in memory.
+    ///
+    /// Default is 128.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Redact the value of an additional param such as `HTTP_X_API_KEY`.
+    pub fn redact(mut self, param: &str) -> Self {
+        self.redact.push(param.to_ascii_uppercase());
+        self
+    }
+
+    /// Most recent slow requests, oldest first.
+    pub fn entries(&self) -> Vec<SlowEntry> {
+        let entries = self.entries.lock().expect("poisoned lock");
+        entries.iter().cloned().collect()
+    }
+
+    /// Copy the params sent to the script with sensitive values redacted
+    fn snapshot(&self, params: &Params) -> Vec<(String, String)> {
+        let mut params: Vec<_> = params
+            .iter()
+            .map(|(name, value)| {
+                let name = name.to_string();
+                let value = match self.redact.contains(&name) {
+                    true => MASK.to_owned(),
+                    false => value.to_string(),
+                };
+                (name, value)
+            })
+            .collect();
+        params.sort();
+        params
+    }
+
+    /// Record the request if it exceeded the threshold
+    pub(crate) fn record(
+        &self,
+        script: &str,
+        req: &HttpRequest,
+        elapsed: Duration,
+        status: Option<StatusCode>,
+        params: &Params,
+    ) {
+        if elapsed < self.threshold || self.max_entries == 0 {
+            return;
+        }
+        tracing::warn!("slow script {script:?} took {elapsed:?} status={status:?}");
+        let params = self.snapshot(params);
+        let mut entries = self.entries.lock().expect("poisoned lock");
+        if entries.len() >= self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(SlowEntry {
+            time: SystemTime::now(),
+            script: script.to_owned(),
+            request: format!("{} {}", req.method(), req.uri()),
+            elapsed,
+            status,
+            params,
+        });
+    }
+}
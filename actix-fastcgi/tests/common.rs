@@ -1,5 +1,13 @@
 ///! Common Testing Utilities
-use std::{sync::Once};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Once,
+        mpsc::{self, Receiver},
+    },
+};
 
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
@@ -28,3 +36,102 @@ macro_rules! spawn_test_server {
 
 #[allow(unused_imports)]
 pub(crate) use spawn_test_server;
+
+/// Build a fastcgi record for the request ID of single request connections
+#[allow(dead_code)]
+pub fn record(kind: u8, content: &[u8]) -> Vec<u8> {
+    let [high, low] = (content.len() as u16).to_be_bytes();
+    let mut record = vec![1, kind, 0, 1, high, low, 0, 0];
+    record.extend_from_slice(content);
+    record
+}
+
+/// Build a complete fastcgi response ending with the specified statuses
+#[allow(dead_code)]
+pub fn response(stdout: &[u8], app_status: u32, protocol_status: u8) -> Vec<u8> {
+    let mut end = app_status.to_be_bytes().to_vec();
+    end.extend([protocol_status, 0, 0, 0]);
+    [record(6, stdout), record(6, b""), record(3, &end)].concat()
+}
+
+/// Read a single record returning its type and content
+#[allow(dead_code)]
+fn read_record(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).ok()?;
+    let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    let mut content = vec![0u8; length + header[6] as usize];
+    stream.read_exact(&mut content).ok()?;
+    content.truncate(length);
+    Some((header[1], content))
+}
+
+/// Decode a fastcgi name-value pair length
+#[allow(dead_code)]
+fn read_length(data: &mut &[u8]) -> usize {
+    match data[0] >> 7 {
+        0 => {
+            let length = data[0] as usize;
+            *data = &data[1..];
+            length
+        }
+        _ => {
+            let length = u32::from_be_bytes([data[0] & 0x7f, data[1], data[2], data[3]]);
+            *data = &data[4..];
+            length as usize
+        }
+    }
+}
+
+/// Decode fastcgi name-value pairs
+#[allow(dead_code)]
+fn parse_params(mut data: &[u8]) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    while !data.is_empty() {
+        let name_len = read_length(&mut data);
+        let value_len = read_length(&mut data);
+        let name = String::from_utf8_lossy(&data[..name_len]).into_owned();
+        let value = String::from_utf8_lossy(&data[name_len..name_len + value_len]).into_owned();
+        data = &data[name_len + value_len..];
+        params.insert(name, value);
+    }
+    params
+}
+
+/// Spawn a stub fastcgi service answering requests based on their params
+///
+/// Requests answered with `None` are left open and the type of every
+/// further record received is reported until the connection closes.
+#[allow(dead_code)]
+pub fn spawn_stub<F>(respond: F) -> (SocketAddr, Receiver<u8>)
+where
+    F: Fn(&HashMap<String, String>) -> Option<Vec<u8>> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind stub");
+    let addr = listener.local_addr().expect("missing stub address");
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { break };
+            let mut params = Vec::new();
+            while let Some((kind, content)) = read_record(&mut stream) {
+                match kind {
+                    4 => params.extend(content),
+                    5 if content.is_empty() => break,
+                    _ => {}
+                }
+            }
+            match respond(&parse_params(&params)) {
+                Some(res) => {
+                    let _ = stream.write_all(&res);
+                }
+                None => {
+                    while let Some((kind, _)) = read_record(&mut stream) {
+                        let _ = tx.send(kind);
+                    }
+                }
+            }
+        }
+    });
+    (addr, rx)
+}
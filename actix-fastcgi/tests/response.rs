@@ -0,0 +1,107 @@
+//! FastCGI Response Parsing Tests
+
+use actix_fastcgi::ResponseStream;
+use actix_web::{
+    body,
+    http::{StatusCode, header},
+    web::Bytes,
+};
+use fastcgi_client::{ClientError, response::Content};
+use futures_util::stream;
+
+/// Build a response stream from raw stdout records
+fn response(records: &[&'static [u8]]) -> ResponseStream {
+    let records = records
+        .iter()
+        .map(|data| Ok::<_, ClientError>(Content::Stdout(Bytes::from_static(data))))
+        .collect::<Vec<_>>();
+    ResponseStream::new(stream::iter(records))
+}
+
+#[actix_web::test]
+async fn test_early_hints() {
+    let records: &[&[u8]] = &[
+        b"Status: 103\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n",
+        b"Status: 200 OK\r\nTrailer: X-Checksum\r\n\r\nhello",
+        b"",
+        b" world",
+    ];
+
+    let res = response(records).into_response().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get(header::LINK).is_none());
+    assert!(res.headers().get(header::TRAILER).is_none());
+    let body = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, "hello world");
+
+    let res = response(records)
+        .early_hints(true)
+        .into_response()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(header::LINK).unwrap(),
+        "</style.css>; rel=preload; as=style"
+    );
+}
+
+#[actix_web::test]
+async fn test_early_hints_links() {
+    let records: &[&[u8]] = &[
+        b"Status: 103\r\nLink: </app.js>; rel=preload; as=script\r\n\r\n",
+        b"Status: 103 Early Hints\r\nLink: </next.html>; rel=prefetch\r\n\r\n",
+        b"Status: 200 OK\r\nLink: </app.js>; rel=preload; as=script\r\n",
+        b"Link: </font.woff2>; rel=preload; as=font\r\n\r\nbody",
+    ];
+
+    // final links are kept as-is and interim links are neither duplicated
+    // nor merged unless they preload
+    let res = response(records)
+        .early_hints(true)
+        .into_response()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let links: Vec<_> = res.headers().get_all(header::LINK).collect();
+    assert_eq!(
+        links,
+        [
+            "</app.js>; rel=preload; as=script",
+            "</font.woff2>; rel=preload; as=font"
+        ]
+    );
+    let body = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, "body");
+}
+
+#[test]
+fn test_fpm_status() {
+    let json = br#"{
+        "pool": "www", "process manager": "dynamic", "start time": 1700000000,
+        "start since": 120, "accepted conn": 42, "listen queue": 0,
+        "max listen queue": 3, "listen queue len": 511, "idle processes": 2,
+        "active processes": 1, "total processes": 3, "max active processes": 2,
+        "max children reached": 0, "slow requests": 1,
+        "processes": [{
+            "pid": 101, "state": "Idle", "start time": 1700000000, "start since": 120,
+            "requests": 14, "request duration": 1500, "request method": "GET",
+            "request uri": "/index.php", "content length": 0, "user": "-",
+            "script": "/var/www/index.php", "last request cpu": 0.0,
+            "last request memory": 2097152
+        }]
+    }"#;
+
+    let status = actix_fastcgi::FpmStatus::parse(json).unwrap();
+    assert_eq!(status.pool, "www");
+    assert_eq!(status.accepted_conn, 42);
+    assert_eq!(status.max_listen_queue, 3);
+    assert_eq!(status.total_processes, 3);
+    assert_eq!(status.slow_requests, 1);
+    assert_eq!(status.processes.len(), 1);
+    assert_eq!(status.processes[0].pid, 101);
+    assert_eq!(status.processes[0].request_uri, "/index.php");
+    assert_eq!(status.processes[0].last_request_memory, 2097152);
+
+    assert!(actix_fastcgi::FpmStatus::parse(b"pong").is_err());
+}
@@ -0,0 +1,226 @@
+//! FastCGI Service Tests
+
+use std::time::Duration;
+
+use actix_common::{Deadline, TransactionId};
+use actix_web::{
+    App, Error, HttpMessage,
+    dev::{Service, ServiceResponse},
+    error::ErrorGatewayTimeout,
+    http::{Method, StatusCode},
+    rt::time::timeout,
+    test::{self, TestRequest},
+};
+
+mod common;
+use common::*;
+
+#[actix_web::test]
+async fn test_max_body_size() {
+    setup();
+
+    // nothing listens on the address so any forwarded request would fail
+    let fgi = actix_fastcgi::FastCGI::new("", "tests/php", "127.0.0.1:1").max_body_size(8);
+    let srv = test::init_service(App::new().service(fgi)).await;
+
+    let req = TestRequest::with_uri("/post.php")
+        .method(Method::POST)
+        .set_payload("too large")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let req = TestRequest::with_uri("/post.php")
+        .method(Method::POST)
+        .insert_header(("Content-Length", "9"))
+        .insert_header(("Expect", "100-continue"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+/// Status of the response, including errors raised by the service
+async fn status<S, R>(srv: &S, req: R) -> StatusCode
+where
+    S: Service<R, Response = ServiceResponse, Error = Error>,
+{
+    match test::try_call_service(srv, req).await {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    }
+}
+
+#[actix_web::test]
+async fn test_end_request_status() {
+    setup();
+
+    let (addr, _) = spawn_stub(|params| {
+        let (app_status, protocol_status) = match params["QUERY_STRING"].as_str() {
+            "app" => (1, 0),
+            "overloaded" => (0, 2),
+            _ => (0, 0),
+        };
+        let stdout = b"Status: 200 OK\r\nContent-Type: text/plain\r\n\r\nhello";
+        Some(response(stdout, app_status, protocol_status))
+    });
+    let fgi = actix_fastcgi::FastCGI::new("", "tests/php", addr.to_string());
+    let srv = test::init_service(App::new().service(fgi)).await;
+
+    let req = TestRequest::with_uri("/hello.php").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "hello");
+
+    let req = TestRequest::with_uri("/hello.php?app").to_request();
+    assert_eq!(status(&srv, req).await, StatusCode::BAD_GATEWAY);
+
+    let req = TestRequest::with_uri("/hello.php?overloaded").to_request();
+    assert_eq!(status(&srv, req).await, StatusCode::BAD_GATEWAY);
+
+    // development mode renders the statuses instead
+    let fgi = actix_fastcgi::FastCGI::new("", "tests/php", addr.to_string()).dev_mode(true);
+    let srv = test::init_service(App::new().service(fgi)).await;
+    let req = TestRequest::with_uri("/hello.php?app").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = test::read_body(res).await;
+    let body = std::str::from_utf8(&body).expect("invalid body");
+    assert!(body.contains("protocol status RequestComplete, app status 1"));
+}
+
+#[actix_web::test]
+async fn test_request_id() {
+    setup();
+
+    let (addr, _) = spawn_stub(|params| {
+        let id = params.get("HTTP_X_REQUEST_ID").cloned().unwrap_or_default();
+        let unique = params.get("UNIQUE_ID").cloned().unwrap_or_default();
+        let stdout = format!("Status: 200 OK\r\n\r\n{id} {unique}");
+        Some(response(stdout.as_bytes(), 0, 0))
+    });
+    let fgi = actix_fastcgi::FastCGI::new("", "tests/php", addr.to_string())
+        .request_id(actix_fastcgi::RequestId::new());
+    let srv = test::init_service(App::new().service(fgi)).await;
+
+    let req = TestRequest::with_uri("/hello.php")
+        .insert_header(("X-Request-ID", "abc-123"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    let id = res.request().extensions().get::<TransactionId>().cloned();
+    assert_eq!(id, Some(TransactionId::new("abc-123")));
+    assert_eq!(test::read_body(res).await, "abc-123 abc-123");
+
+    // invalid client ids are replaced with a generated id
+    let req = TestRequest::with_uri("/hello.php")
+        .insert_header(("X-Request-ID", "abc 123\"; drop"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    let id = res.request().extensions().get::<TransactionId>().cloned();
+    let id = id.expect("missing transaction id");
+    assert!(TransactionId::is_valid(id.as_str()));
+    assert_ne!(id.as_str(), "abc 123\"; drop");
+    let body = test::read_body(res).await;
+    assert_eq!(body, format!("{id} {id}"));
+}
+
+#[actix_web::test]
+async fn test_slow_log() {
+    setup();
+
+    let (addr, _) = spawn_stub(|_| Some(response(b"Status: 200 OK\r\n\r\nhello", 0, 0)));
+    let stats = actix_fastcgi::ScriptStats::new();
+    let fast = actix_fastcgi::SlowLog::new(Duration::from_secs(3600));
+    let slow = actix_fastcgi::SlowLog::new(Duration::ZERO).redact("HTTP_X_API_KEY");
+    let srv = test::init_service(
+        App::new()
+            .service(
+                actix_fastcgi::FastCGI::new("/fast", "tests/php", addr.to_string())
+                    .stats(stats.clone())
+                    .slow_log(fast.clone()),
+            )
+            .service(
+                actix_fastcgi::FastCGI::new("/slow", "tests/php", addr.to_string())
+                    .stats(stats.clone())
+                    .slow_log(slow.clone()),
+            ),
+    )
+    .await;
+
+    for path in ["/fast/hello.php", "/slow/hello.php?page=1"] {
+        let req = TestRequest::with_uri(path)
+            .insert_header(("Authorization", "Basic c2VjcmV0"))
+            .insert_header(("X-Api-Key", "secret"))
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    // requests within the threshold are not recorded
+    assert!(fast.entries().is_empty());
+
+    let entries = slow.entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].request, "GET /slow/hello.php?page=1");
+    assert_eq!(entries[0].status, Some(StatusCode::OK));
+    let param = |name: &str| {
+        let param = entries[0].params.iter().find(|(n, _)| n == name);
+        param.map(|(_, value)| value.as_str())
+    };
+    assert_eq!(param("QUERY_STRING"), Some("page=1"));
+    assert_eq!(param("HTTP_AUTHORIZATION"), Some("***"));
+    assert_eq!(param("HTTP_X_API_KEY"), Some("***"));
+
+    let scripts = stats.scripts();
+    assert_eq!(scripts.len(), 1);
+    assert_eq!((scripts[0].requests, scripts[0].errors), (2, 0));
+}
+
+#[actix_web::test]
+async fn test_deadline() {
+    setup();
+
+    let (addr, records) = spawn_stub(|params| {
+        if params["QUERY_STRING"] == "hang" {
+            return None;
+        }
+        let remaining = params.get("X_REQUEST_DEADLINE_MS").map(String::as_str);
+        let stdout = format!("Status: 200 OK\r\n\r\n{}", remaining.unwrap_or("none"));
+        Some(response(stdout.as_bytes(), 0, 0))
+    });
+    let fgi = actix_fastcgi::FastCGI::new("", "tests/php", addr.to_string())
+        .deadline_param("X_REQUEST_DEADLINE_MS");
+    let srv = test::init_service(App::new().service(fgi.clone())).await;
+
+    // requests without a deadline are passed without the param
+    let req = TestRequest::with_uri("/hello.php").to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "none");
+
+    let srv = test::init_service(
+        App::new()
+            .wrap_fn(|req, srv| {
+                let deadline = Deadline::after(Duration::from_millis(500));
+                deadline.apply(req.request());
+                let res = srv.call(req);
+                async move {
+                    timeout(deadline.remaining(), res)
+                        .await
+                        .map_err(|_| ErrorGatewayTimeout("deadline expired"))?
+                }
+            })
+            .service(fgi),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/hello.php").to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    let remaining: u64 = std::str::from_utf8(&body)
+        .expect("invalid body")
+        .parse()
+        .expect("invalid deadline");
+    assert!(remaining > 0 && remaining <= 500);
+
+    // requests outliving the deadline are aborted at the fastcgi service
+    let req = TestRequest::with_uri("/hello.php?hang").to_request();
+    assert_eq!(status(&srv, req).await, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(records.recv_timeout(Duration::from_secs(1)), Ok(2));
+}
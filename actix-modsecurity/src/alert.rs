@@ -0,0 +1,156 @@
+//! Severity-Based Alerting for Matched Rules
+
+use std::{future::Future, net::SocketAddr, rc::Rc, str::FromStr};
+
+use actix_web::{
+    HttpRequest,
+    http::{Method, Uri},
+};
+use futures_core::future::LocalBoxFuture;
+
+/// Rule severity as defined by the ModSecurity `severity` action
+///
+/// Ordered from most to least severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Emergency,
+    Alert,
+    Critical,
+    Error,
+    Warning,
+    Notice,
+    Info,
+    Debug,
+}
+
+impl FromStr for Severity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_uppercase().as_str() {
+            "0" | "EMERGENCY" => Self::Emergency,
+            "1" | "ALERT" => Self::Alert,
+            "2" | "CRITICAL" => Self::Critical,
+            "3" | "ERROR" => Self::Error,
+            "4" | "WARNING" => Self::Warning,
+            "5" | "NOTICE" => Self::Notice,
+            "6" | "INFO" => Self::Info,
+            "7" | "DEBUG" => Self::Debug,
+            _ => return Err(()),
+        })
+    }
+}
+
+/// Metadata of a matched rule parsed from the ModSecurity log message
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RuleMatch {
+    /// Rule id
+    pub id: Option<String>,
+    /// Rule `msg` action
+    pub message: Option<String>,
+    /// Rule `logdata` action
+    pub data: Option<String>,
+    /// Rule `severity` action
+    pub severity: Option<Severity>,
+    /// Rule `tag` actions
+    pub tags: Vec<String>,
+    /// Complete log message
+    pub log: String,
+}
+
+/// Collect all values of a `[name "value"]` field within a log message
+fn fields<'a>(log: &'a str, name: &str) -> Vec<&'a str> {
+    let prefix = format!("[{name} \"");
+    log.match_indices(&prefix)
+        .map(|(idx, _)| &log[idx + prefix.len()..])
+        .filter_map(|rest| rest.find("\"]").map(|end| &rest[..end]))
+        .collect()
+}
+
+fn field(log: &str, name: &str) -> Option<String> {
+    fields(log, name)
+        .into_iter()
+        .next()
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+}
+
+impl RuleMatch {
+    /// Parse rule metadata from a ModSecurity log message
+    pub(crate) fn parse(log: &str) -> Self {
+        Self {
+            id: field(log, "id"),
+            message: field(log, "msg"),
+            data: field(log, "data"),
+            severity: field(log, "severity").and_then(|s| s.parse().ok()),
+            tags: fields(log, "tag").into_iter().map(str::to_owned).collect(),
+            log: log.to_owned(),
+        }
+    }
+}
+
+/// Matched rule and summary of the request that triggered it
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Alert {
+    /// Matched rule metadata
+    pub rule: RuleMatch,
+    /// Downstream client address
+    pub peer: Option<SocketAddr>,
+    /// Request method
+    pub method: Method,
+    /// Request uri
+    pub uri: Uri,
+    /// Request host
+    pub host: String,
+    /// Whether the transaction was blocked by an intervention
+    pub blocked: bool,
+    /// Transaction ID correlating the request across services
+    pub transaction_id: Option<String>,
+}
+
+type AlertFn = Rc<dyn Fn(Alert) -> LocalBoxFuture<'static, ()>>;
+
+/// Async alert callback invoked for rule matches at or above a severity
+#[derive(Clone)]
+pub(crate) struct Alerter {
+    severity: Severity,
+    callback: AlertFn,
+}
+
+impl Alerter {
+    pub(crate) fn new<F, Fut>(severity: Severity, callback: F) -> Self
+    where
+        F: Fn(Alert) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        Self {
+            severity,
+            callback: Rc::new(move |alert| Box::pin(callback(alert))),
+        }
+    }
+
+    /// Spawn the callback for every logged rule match meeting the severity
+    pub(crate) fn dispatch(&self, req: &HttpRequest, logs: Vec<String>, blocked: bool) {
+        for log in logs {
+            let rule = RuleMatch::parse(&log);
+            if rule
+                .severity
+                .is_none_or(|severity| severity > self.severity)
+            {
+                continue;
+            }
+            let alert = Alert {
+                rule,
+                peer: actix_common::client_addr(req),
+                method: req.method().clone(),
+                uri: req.uri().clone(),
+                host: req.connection_info().host().to_owned(),
+                blocked,
+                transaction_id: actix_common::transaction_id(req).map(|id| id.to_string()),
+            };
+            actix_web::rt::spawn((self.callback)(alert));
+        }
+    }
+}
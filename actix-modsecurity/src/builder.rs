@@ -20,6 +20,51 @@ impl Builder {
         self
     }
 
+    /// Builder equivalent of [`ModSecurity::set_early_inspection`]
+    pub fn early_inspection(mut self, early_inspection: Option<usize>) -> Self {
+        self.0.set_early_inspection(early_inspection);
+        self
+    }
+
+    /// Builder equivalent of [`ModSecurity::set_default_action`]
+    pub fn default_action(mut self, phase: u8, actions: &str) -> Result<Self, Error> {
+        self.0.set_default_action(phase, actions)?;
+        Ok(self)
+    }
+
+    /// Builder equivalent of [`ModSecurity::set_request_body_access`]
+    pub fn request_body_access(mut self, enable: bool) -> Result<Self, Error> {
+        self.0.set_request_body_access(enable)?;
+        Ok(self)
+    }
+
+    /// Builder equivalent of [`ModSecurity::set_response_body_access`]
+    pub fn response_body_access(mut self, enable: bool) -> Result<Self, Error> {
+        self.0.set_response_body_access(enable)?;
+        Ok(self)
+    }
+
+    /// Builder equivalent of [`ModSecurity::set_response_body_mime_types`]
+    pub fn response_body_mime_types<'a, I>(mut self, mimes: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.0.set_response_body_mime_types(mimes)?;
+        Ok(self)
+    }
+
+    /// Builder equivalent of [`ModSecurity::set_paranoia_level`]
+    pub fn paranoia_level(mut self, level: u8) -> Result<Self, Error> {
+        self.0.set_paranoia_level(level)?;
+        Ok(self)
+    }
+
+    /// Builder equivalent of [`ModSecurity::set_anomaly_thresholds`]
+    pub fn anomaly_thresholds(mut self, inbound: u32, outbound: u32) -> Result<Self, Error> {
+        self.0.set_anomaly_thresholds(inbound, outbound)?;
+        Ok(self)
+    }
+
     /// Builder equivalent of [`ModSecurity::add_rules`]
     #[inline]
     pub fn rules(mut self, rules: &str) -> Result<Self, Error> {
@@ -0,0 +1,169 @@
+//! Evaluation Bypass for Repeated Clean Requests
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    HttpRequest,
+    http::{Method, header},
+};
+
+/// Default duration rule evaluation is skipped for a clean request
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+/// Default number of consecutive clean passes before evaluation is skipped
+const DEFAULT_PASSES: u32 = 3;
+/// Default maximum number of tracked requests
+const DEFAULT_CAPACITY: usize = 4096;
+
+#[derive(Default)]
+struct Entry {
+    passes: u32,
+    bypass_until: Option<Instant>,
+}
+
+impl Entry {
+    fn is_bypassed(&self, now: Instant) -> bool {
+        self.bypass_until.is_some_and(|until| until > now)
+    }
+}
+
+/// Cache of bodiless requests allowed to skip rule evaluation
+///
+/// Only `GET` and `HEAD` requests without a body are eligible. Requests
+/// are identified by their method, host, path and query. Once an
+/// identical request passed the request and response phases with a zero
+/// anomaly score for the configured number of consecutive times,
+/// evaluation is skipped for it entirely until the ttl expires. Any blocked or scored evaluation resets the request.
+///
+/// Rules inspecting headers, cookies or client addresses are not applied
+/// to bypassed requests, so the cache is intended for static asset traffic
+/// and should not be used with rules that depend on such inputs. Clones
+/// share the same state.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_modsecurity::{BypassCache, Middleware, ModSecurity};
+///
+/// let bypass = BypassCache::new(Duration::from_secs(300)).passes(5);
+/// let mw = Middleware::new(ModSecurity::new()).bypass_cache(bypass.clone());
+/// assert!(bypass.bypassed().is_empty());
+/// ```
+#[derive(Clone)]
+pub struct BypassCache {
+    state: Arc<Mutex<HashMap<String, Entry>>>,
+    ttl: Duration,
+    passes: u32,
+    capacity: usize,
+}
+
+impl Default for BypassCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl BypassCache {
+    /// Skip evaluation of clean requests for the specified ttl.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            state: Arc::default(),
+            ttl,
+            passes: DEFAULT_PASSES,
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Set the number of consecutive clean passes before evaluation is skipped.
+    ///
+    /// Default is 3.
+    pub fn passes(mut self, passes: u32) -> Self {
+        self.passes = passes.max(1);
+        self
+    }
+
+    /// Set the maximum number of tracked requests.
+    ///
+    /// Default is 4096.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.state.lock().expect("poisoned lock")
+    }
+
+    /// List bypassed requests and the remaining bypass duration.
+    pub fn bypassed(&self) -> Vec<(String, Duration)> {
+        let now = Instant::now();
+        self.lock()
+            .iter()
+            .filter_map(|(key, entry)| {
+                let until = entry.bypass_until.filter(|until| *until > now)?;
+                Some((key.to_owned(), until - now))
+            })
+            .collect()
+    }
+
+    /// Forget all tracked and bypassed requests.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Cache key of the request if it is eligible for bypass
+    pub(crate) fn key(&self, req: &HttpRequest) -> Option<String> {
+        // other methods may carry a body without announcing its length
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return None;
+        }
+        let headers = req.headers();
+        let has_body = headers.contains_key(header::TRANSFER_ENCODING)
+            || headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.trim() != "0");
+        if has_body {
+            return None;
+        }
+        let host = req.connection_info().host().to_owned();
+        let target = req.uri().path_and_query().map(|pq| pq.as_str());
+        Some(format!("{} {host}{}", req.method(), target.unwrap_or("/")))
+    }
+
+    /// Check if evaluation is currently skipped for the request
+    pub(crate) fn is_bypassed(&self, key: &str) -> bool {
+        let now = Instant::now();
+        self.lock()
+            .get(key)
+            .is_some_and(|entry| entry.is_bypassed(now))
+    }
+
+    /// Record the outcome of a full evaluation of the request
+    pub(crate) fn record(&self, key: String, clean: bool) {
+        let mut state = self.lock();
+        if !clean {
+            state.remove(&key);
+            return;
+        }
+
+        let now = Instant::now();
+        if !state.contains_key(&key) && state.len() >= self.capacity {
+            state.retain(|_, entry| entry.bypass_until.is_some_and(|until| until > now));
+            if state.len() >= self.capacity {
+                return;
+            }
+        }
+        let entry = state.entry(key).or_default();
+        entry.passes += 1;
+        if entry.passes >= self.passes {
+            tracing::debug!("skipping rule evaluation for clean request");
+            entry.passes = 0;
+            entry.bypass_until = Some(now + self.ttl);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! OWASP Core Rule Set Fetcher
+
+use std::{
+    fs,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{error::Error, modsecurity::ModSecurity};
+
+/// Upper bound on the size of a downloaded release archive
+const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Default cache directory within the user cache directory
+const CACHE_DIR: &str = "actix-modsecurity/crs";
+
+/// Private per-user directory release archives are cached in
+///
+/// Uses `$XDG_CACHE_HOME` or `$HOME/.cache`, only falling back to the
+/// system temporary directory when neither is set.
+fn default_cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join(CACHE_DIR)
+}
+
+/// Create the directory readable and writable by the current user only
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Download, verification and loading of a pinned OWASP Core Rule Set release
+///
+/// The release archive is downloaded once and cached after being verified
+/// against the pinned SHA-256 checksum. Every load re-verifies the cached
+/// archive and unpacks it afresh, so rules modified or planted on disk
+/// are never loaded, and later calls need no network access. The rules are
+/// loaded through [`ModSecurity::add_rules_file`] after the example setup
+/// file and the configured paranoia level.
+///
+/// Requires the `crs` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// use actix_modsecurity::{CoreRuleSet, ModSecurity};
+///
+/// let mut security = ModSecurity::new();
+/// CoreRuleSet::new("4.15.0", "<sha256 of the release archive>")
+///     .paranoia_level(2)
+///     .load(&mut security)
+///     .expect("failed to load core rule set");
+/// ```
+#[derive(Clone, Debug)]
+pub struct CoreRuleSet {
+    version: String,
+    sha256: String,
+    url: Option<String>,
+    cache_dir: PathBuf,
+    paranoia_level: u8,
+}
+
+impl CoreRuleSet {
+    /// Pin the release version and the SHA-256 checksum of its archive.
+    pub fn new(version: &str, sha256: &str) -> Self {
+        Self {
+            version: version.trim_start_matches('v').to_owned(),
+            sha256: sha256.to_ascii_lowercase(),
+            url: None,
+            cache_dir: default_cache_dir(),
+            paranoia_level: 1,
+        }
+    }
+
+    /// Override the url the release archive is downloaded from.
+    ///
+    /// Default is the GitHub source archive of the release tag.
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_owned());
+        self
+    }
+
+    /// Directory release archives are cached and unpacked into.
+    ///
+    /// The directory should only be writable by the current user.
+    ///
+    /// Default is `actix-modsecurity/crs` within `$XDG_CACHE_HOME` or
+    /// `$HOME/.cache`.
+    pub fn cache_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cache_dir = dir.as_ref().to_owned();
+        self
+    }
+
+    /// Paranoia level the rules are loaded with, between 1 and 4.
+    ///
+    /// Default is 1.
+    pub fn paranoia_level(mut self, level: u8) -> Self {
+        self.paranoia_level = level;
+        self
+    }
+
+    /// Directory of the unpacked release
+    fn release_dir(&self) -> PathBuf {
+        self.cache_dir.join(format!("coreruleset-{}", self.version))
+    }
+
+    /// Path of the cached release archive
+    fn archive_path(&self) -> PathBuf {
+        self.cache_dir
+            .join(format!("coreruleset-{}.tar.gz", self.version))
+    }
+
+    /// Check the archive against the pinned checksum
+    fn verify(&self, archive: &[u8]) -> Result<(), Error> {
+        let checksum = format!("{:x}", Sha256::digest(archive));
+        match checksum == self.sha256 {
+            true => Ok(()),
+            false => Err(Error::ChecksumMismatch(checksum)),
+        }
+    }
+
+    /// Read the cached archive, downloading it if missing or tampered with
+    fn archive(&self) -> Result<Vec<u8>, Error> {
+        let path = self.archive_path();
+        if let Ok(archive) = fs::read(&path) {
+            match self.verify(&archive) {
+                Ok(()) => return Ok(archive),
+                Err(err) => tracing::warn!("discarding cached core rule set: {err}"),
+            }
+        }
+        let archive = self.download()?;
+        self.verify(&archive)?;
+
+        create_private_dir(&self.cache_dir)?;
+        let staging = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&staging, &archive)?;
+        fs::rename(&staging, &path)?;
+        Ok(archive)
+    }
+
+    /// Download the release archive
+    fn download(&self) -> Result<Vec<u8>, Error> {
+        let url = match self.url.as_ref() {
+            Some(url) => url.to_owned(),
+            None => format!(
+                "https://github.com/coreruleset/coreruleset/archive/refs/tags/v{}.tar.gz",
+                self.version
+            ),
+        };
+        tracing::info!("downloading core rule set {} from {url}", self.version);
+        let mut res = ureq::get(&url).call().map_err(std::io::Error::other)?;
+        let mut archive = Vec::new();
+        res.body_mut()
+            .as_reader()
+            .take(MAX_ARCHIVE_SIZE)
+            .read_to_end(&mut archive)?;
+        Ok(archive)
+    }
+
+    /// Verify and unpack the release, downloading it unless already cached.
+    ///
+    /// Returns the directory of the unpacked release.
+    pub fn fetch(&self) -> Result<PathBuf, Error> {
+        let dir = self.release_dir();
+        let archive = self.archive()?;
+
+        // unpack next to the final location so a failed or concurrent
+        // unpack never leaves a partial release behind
+        let staging = self.cache_dir.join(format!(
+            ".coreruleset-{}-{}",
+            self.version,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&staging);
+        let decoder = flate2::read::GzDecoder::new(archive.as_slice());
+        tar::Archive::new(decoder).unpack(&staging)?;
+
+        let root = fs::read_dir(&staging)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| path.join("rules").is_dir())
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    "core rule set archive without rules",
+                )
+            })?;
+        let _ = fs::remove_dir_all(&dir);
+        fs::rename(&root, &dir)?;
+        let _ = fs::remove_dir_all(&staging);
+        Ok(dir)
+    }
+
+    /// Fetch the release and load its rules into the [`ModSecurity`] instance.
+    pub fn load(&self, security: &mut ModSecurity) -> Result<(), Error> {
+        if !(1..=4).contains(&self.paranoia_level) {
+            return Err(Error::InvalidDirective(self.paranoia_level.to_string()));
+        }
+        let dir = self.fetch()?;
+        let setup = dir.join("crs-setup.conf.example");
+        if setup.is_file() {
+            security.add_rules_file(&setup)?;
+        }
+        security.set_paranoia_level(self.paranoia_level)?;
+
+        let mut rules: Vec<_> = fs::read_dir(dir.join("rules"))?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "conf"))
+            .collect();
+        rules.sort();
+        for file in rules {
+            security.add_rules_file(&file)?;
+        }
+        tracing::info!(
+            "loaded core rule set {} at paranoia level {}",
+            self.version,
+            self.paranoia_level
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//! Adaptive Per-Client Blocking Based on Anomaly Scores
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::alert::{RuleMatch, Severity};
+
+/// Default sliding window over which anomaly scores are summed
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+/// Default duration a client remains blocked
+const DEFAULT_TTL: Duration = Duration::from_secs(600);
+/// Number of recorded scores between sweeps of idle clients
+const SWEEP_INTERVAL: usize = 1024;
+
+/// Anomaly score of a rule severity using the Core Rule Set weights
+fn severity_score(severity: Severity) -> u32 {
+    match severity {
+        Severity::Emergency | Severity::Alert | Severity::Critical => 5,
+        Severity::Error => 4,
+        Severity::Warning => 3,
+        Severity::Notice => 2,
+        Severity::Info | Severity::Debug => 0,
+    }
+}
+
+/// Summed anomaly score of logged rule matches
+pub(crate) fn anomaly_score(logs: &[String]) -> u32 {
+    logs.iter()
+        .filter_map(|log| RuleMatch::parse(log).severity)
+        .map(severity_score)
+        .sum()
+}
+
+#[derive(Default)]
+struct Client {
+    scores: VecDeque<(Instant, u32)>,
+    blocked_until: Option<Instant>,
+}
+
+impl Client {
+    fn is_blocked(&self, now: Instant) -> bool {
+        self.blocked_until.is_some_and(|until| until > now)
+    }
+
+    fn prune(&mut self, now: Instant, window: Duration) {
+        while self
+            .scores
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > window)
+        {
+            self.scores.pop_front();
+        }
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        self.scores.is_empty() && !self.is_blocked(now)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    clients: HashMap<IpAddr, Client>,
+    recorded: usize,
+}
+
+/// Dynamic deny list of clients exceeding an anomaly score threshold
+///
+/// Scores of logged rule matches are summed per client over a sliding
+/// window using the Core Rule Set severity weights (critical 5, error 4,
+/// warning 3, notice 2). Clients reaching the threshold are rejected before
+/// rule evaluation until the block expires. Clones share the same state so
+/// a handle can be kept to query and clear blocks at runtime.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_modsecurity::{DenyList, Middleware, ModSecurity};
+///
+/// let deny = DenyList::new(20).ttl(Duration::from_secs(300));
+/// let mw = Middleware::new(ModSecurity::new()).deny_list(deny.clone());
+/// assert!(deny.blocked().is_empty());
+/// ```
+#[derive(Clone)]
+pub struct DenyList {
+    state: Arc<Mutex<State>>,
+    threshold: u32,
+    window: Duration,
+    ttl: Duration,
+}
+
+impl DenyList {
+    /// Block clients whose summed anomaly score reaches the threshold.
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            state: Arc::default(),
+            threshold,
+            window: DEFAULT_WINDOW,
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Set the sliding window over which scores are summed.
+    ///
+    /// Default is 60 seconds.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set how long clients remain blocked.
+    ///
+    /// Default is 10 minutes.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("poisoned lock")
+    }
+
+    /// Check if the client is currently blocked.
+    pub fn is_blocked(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        self.lock()
+            .clients
+            .get(&ip)
+            .is_some_and(|client| client.is_blocked(now))
+    }
+
+    /// Current summed anomaly score of the client.
+    pub fn score(&self, ip: IpAddr) -> u32 {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let Some(client) = state.clients.get_mut(&ip) else {
+            return 0;
+        };
+        client.prune(now, self.window);
+        client.scores.iter().map(|(_, score)| score).sum()
+    }
+
+    /// List blocked clients and the remaining block duration.
+    pub fn blocked(&self) -> Vec<(IpAddr, Duration)> {
+        let now = Instant::now();
+        self.lock()
+            .clients
+            .iter()
+            .filter_map(|(ip, client)| {
+                let until = client.blocked_until.filter(|until| *until > now)?;
+                Some((*ip, until - now))
+            })
+            .collect()
+    }
+
+    /// Manually block the client for the configured ttl.
+    pub fn block(&self, ip: IpAddr) {
+        let until = Instant::now() + self.ttl;
+        self.lock().clients.entry(ip).or_default().blocked_until = Some(until);
+    }
+
+    /// Remove the block and accumulated score of the client.
+    pub fn unblock(&self, ip: IpAddr) {
+        self.lock().clients.remove(&ip);
+    }
+
+    /// Remove all blocks and accumulated scores.
+    pub fn clear(&self) {
+        self.lock().clients.clear();
+    }
+
+    /// Add the scores of logged rule matches for the client
+    pub(crate) fn record(&self, ip: IpAddr, logs: &[String]) {
+        let score = anomaly_score(logs);
+        if score == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut state = self.lock();
+        state.recorded += 1;
+        if state.recorded.is_multiple_of(SWEEP_INTERVAL) {
+            state.clients.retain(|_, client| {
+                client.prune(now, self.window);
+                !client.is_idle(now)
+            });
+        }
+
+        let client = state.clients.entry(ip).or_default();
+        client.prune(now, self.window);
+        client.scores.push_back((now, score));
+        let total: u32 = client.scores.iter().map(|(_, score)| score).sum();
+        if total >= self.threshold {
+            tracing::warn!("blocking client {ip} with anomaly score {total}");
+            client.scores.clear();
+            client.blocked_until = Some(now + self.ttl);
+        }
+    }
+}
@@ -1,7 +1,12 @@
 //! Error and Result module
 
+use actix_common::{ErrorKind, GatewayError};
 use actix_http::error::InvalidStatusCode;
-use actix_web::{ResponseError, body::BodyLimitExceeded, error::PayloadError};
+use actix_web::{
+    HttpResponse, ResponseError,
+    body::{BodyLimitExceeded, BoxBody},
+    error::PayloadError,
+};
 use derive_more::{Display, Error, From};
 
 /// Errors which occur when processing FastCGI Requests/Responses
@@ -25,11 +30,37 @@ pub enum Error {
 
     #[display("Failed to build intervention response")]
     ResponseBuildError(actix_web::Error),
+
+    /// Invalid value passed to a programmatic directive
+    #[display("Invalid directive value: {_0:?}")]
+    #[from(skip)]
+    InvalidDirective(#[error(not(source))] String),
+
+    /// Failed to download or unpack the Core Rule Set
+    #[cfg(feature = "crs")]
+    #[display("Failed to fetch core rule set: {_0}")]
+    CoreRuleSet(std::io::Error),
+
+    /// Downloaded Core Rule Set does not match the pinned checksum
+    #[cfg(feature = "crs")]
+    #[display("Core rule set checksum mismatch: {_0}")]
+    #[from(skip)]
+    ChecksumMismatch(#[error(not(source))] String),
+}
+
+impl GatewayError for Error {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Internal
+    }
 }
 
 impl ResponseError for Error {
-    /// Returns `500 Internal Server Error`.
+    /// Returns the status code of the error's [`ErrorKind`].
     fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::INTERNAL_SERVER_ERROR
+        self.kind().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        self.gateway_response()
     }
 }
@@ -1,16 +1,22 @@
-use std::future::{Ready, ready};
+use std::future::{Future, Ready, ready};
 use std::rc::Rc;
 
+use actix_common::X_REQUEST_ID;
 use actix_web::{
     Error,
     body::BoxBody,
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{StatusCode, header::HeaderName},
 };
 
 use crate::ModSecurityService;
+use crate::alert::{Alert, Alerter, Severity};
 use crate::builder::Builder;
+use crate::bypass::BypassCache;
+use crate::deny::DenyList;
 use crate::modsecurity::ModSecurity;
 use crate::service::ModSecurityInner;
+use crate::shadow::{Shadow, ShadowStats};
 
 /// ModSecurity middleware service
 ///
@@ -30,13 +36,146 @@ use crate::service::ModSecurityInner;
 ///
 /// let app = App::new().wrap(Middleware::new(security));
 /// ```
-pub struct Middleware(Rc<ModSecurity>);
+pub struct Middleware {
+    modsecurity: Rc<ModSecurity>,
+    request_status: Option<StatusCode>,
+    response_status: Option<StatusCode>,
+    alerter: Option<Alerter>,
+    deny_list: Option<DenyList>,
+    bypass: Option<BypassCache>,
+    shadow: Option<Shadow>,
+    id_header: HeaderName,
+}
 
 impl Middleware {
     /// Creates a new `ModSecurity` middleware instance
     #[inline]
     pub fn new(modsecurity: ModSecurity) -> Self {
-        Self(Rc::new(modsecurity))
+        Self {
+            modsecurity: Rc::new(modsecurity),
+            request_status: None,
+            response_status: None,
+            alerter: None,
+            deny_list: None,
+            bypass: None,
+            shadow: None,
+            id_header: X_REQUEST_ID,
+        }
+    }
+
+    /// Override the status code of interventions raised during the request phases.
+    ///
+    /// Redirect interventions are left unchanged.
+    ///
+    /// Default uses the status declared by the matching rule.
+    pub fn request_status(mut self, status: StatusCode) -> Self {
+        self.request_status = Some(status);
+        self
+    }
+
+    /// Override the status code of interventions raised during the response phases.
+    ///
+    /// Redirect interventions are left unchanged.
+    ///
+    /// Default uses the status declared by the matching rule.
+    pub fn response_status(mut self, status: StatusCode) -> Self {
+        self.response_status = Some(status);
+        self
+    }
+
+    /// Reject clients blocked by the [`DenyList`] before rule evaluation.
+    ///
+    /// Blocked clients receive the configured request intervention status,
+    /// `403 Forbidden` by default, and logged rule matches of every other
+    /// request are added to the client anomaly score.
+    ///
+    /// Default is disabled.
+    pub fn deny_list(mut self, deny_list: DenyList) -> Self {
+        self.deny_list = Some(deny_list);
+        self
+    }
+
+    /// Read and forward the request [`TransactionId`](actix_common::TransactionId)
+    /// using the specified header.
+    ///
+    /// Every request is assigned a transaction ID, adopting the value of
+    /// the header when present. Requests without the header have the
+    /// generated ID inserted, so it is recorded in the ModSecurity audit log
+    /// and forwarded to proxied and FastCGI services.
+    ///
+    /// Default is `X-Request-ID`.
+    pub fn transaction_id_header(mut self, header: &str) -> Self {
+        match HeaderName::try_from(header) {
+            Ok(header) => self.id_header = header,
+            Err(_) => tracing::error!("invalid transaction id header: {header:?}"),
+        }
+        self
+    }
+
+    /// Skip rule evaluation for bodiless requests that repeatedly pass clean.
+    ///
+    /// See [`BypassCache`] for the conditions under which evaluation is
+    /// skipped and the rules it is unsuitable for.
+    ///
+    /// Default is disabled.
+    pub fn bypass_cache(mut self, bypass: BypassCache) -> Self {
+        self.bypass = Some(bypass);
+        self
+    }
+
+    /// Evaluate a candidate rule set on the same traffic without enforcing it.
+    ///
+    /// Every inspected request and response is also run through the
+    /// candidate rules, but only the decision of the primary rules is
+    /// applied. Requests where one rule set blocks and the other allows
+    /// are logged as warnings to the `actix_modsecurity::shadow` target and
+    /// counted in the [`ShadowStats`], providing evidence from real traffic
+    /// before upgrading to a new rule set such as a Core Rule Set release.
+    ///
+    /// Requests rejected by the [`DenyList`] or skipped by the
+    /// [`BypassCache`] are not evaluated by either rule set.
+    ///
+    /// Default is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_modsecurity::{Middleware, ModSecurity, ShadowStats};
+    ///
+    /// let mut current = ModSecurity::new();
+    /// current.add_rules("SecRuleEngine On\n").expect("Failed to add rules");
+    /// let mut candidate = ModSecurity::new();
+    /// candidate.add_rules("SecRuleEngine On\n").expect("Failed to add rules");
+    ///
+    /// let stats = ShadowStats::default();
+    /// let mw = Middleware::new(current).shadow(candidate, stats.clone());
+    /// ```
+    pub fn shadow(mut self, candidate: ModSecurity, stats: ShadowStats) -> Self {
+        self.shadow = Some(Shadow::new(candidate, stats));
+        self
+    }
+
+    /// Invoke an async callback for logged rule matches at or above the severity.
+    ///
+    /// Alerts are raised independently of blocking and the callback is
+    /// spawned in the background so it never delays the response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_modsecurity::{Middleware, ModSecurity, Severity};
+    ///
+    /// let mw = Middleware::new(ModSecurity::new()).alert(Severity::Critical, |alert| async move {
+    ///     eprintln!("waf alert {:?}: {:?}", alert.rule.id, alert.rule.message);
+    /// });
+    /// ```
+    pub fn alert<F, Fut>(mut self, severity: Severity, callback: F) -> Self
+    where
+        F: Fn(Alert) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.alerter = Some(Alerter::new(severity, callback));
+        self
     }
 }
 
@@ -68,7 +207,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(ModSecurityService(Rc::new(ModSecurityInner {
             service: Rc::new(service),
-            modsecurity: Rc::clone(&self.0),
+            modsecurity: Rc::clone(&self.modsecurity),
+            request_status: self.request_status,
+            response_status: self.response_status,
+            alerter: self.alerter.clone(),
+            deny_list: self.deny_list.clone(),
+            bypass: self.bypass.clone(),
+            shadow: self.shadow.clone(),
+            id_header: self.id_header.clone(),
         }))))
     }
 }
@@ -26,14 +26,31 @@
 //! # Requirements
 //!
 //! This crate requires `libmodsecurity` >= 3.0.6 to be installed on your system.
+//!
+//! # Features
+//!
+//! - `crs`: Download, verify and load a pinned
+//!   [OWASP Core Rule Set](https://coreruleset.org/) release using [`CoreRuleSet`].
+mod alert;
 mod builder;
+mod bypass;
+#[cfg(feature = "crs")]
+mod crs;
+mod deny;
 mod error;
 mod factory;
 mod modsecurity;
 mod service;
+mod shadow;
 
+pub use alert::{Alert, RuleMatch, Severity};
 pub use builder::Builder;
+pub use bypass::BypassCache;
+#[cfg(feature = "crs")]
+pub use crs::CoreRuleSet;
+pub use deny::DenyList;
 pub use error::Error;
 pub use factory::Middleware;
 pub use modsecurity::{Intervention, ModSecurity, Transaction};
 pub use service::ModSecurityService;
+pub use shadow::ShadowStats;
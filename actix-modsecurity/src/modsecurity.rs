@@ -1,21 +1,34 @@
-use std::path::Path;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
+use actix_common::{TransactionId, body::BodyBuffer};
 use actix_http::Response;
+use actix_web::web::{Bytes, BytesMut};
 use actix_web::{
     HttpMessage, HttpRequest, HttpResponse,
     body::{BodyStream, BoxBody, to_bytes_limited},
     dev::{Payload, ServiceRequest},
+    error::PayloadError,
     http::{StatusCode, Version, header},
 };
+use futures_util::StreamExt;
 
 use crate::{builder::Builder, error::Error, factory::Middleware};
 
 const CONNECTION_INFO: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
 
+/// Callback receiving the log messages of a transaction
+pub(crate) type LogFn = Arc<dyn Fn(&str) + Send + Sync>;
+
 #[derive(Clone, Default)]
 struct TransactionConfig {
     max_request_body: Option<usize>,
     max_response_body: Option<usize>,
+    early_inspection: Option<usize>,
+    shadow: bool,
 }
 
 /// Actix-Web compatible wrapper on [`ModSecurity`](modsecurity::ModSecurity)
@@ -23,6 +36,7 @@ pub struct ModSecurity {
     config: TransactionConfig,
     rules: modsecurity::Rules,
     security: modsecurity::ModSecurity,
+    version: DefaultHasher,
 }
 
 impl ModSecurity {
@@ -37,6 +51,7 @@ impl ModSecurity {
         Self {
             config: TransactionConfig::default(),
             rules: modsecurity::Rules::new(),
+            version: DefaultHasher::new(),
             security: modsecurity::ModSecurity::builder()
                 .with_log_callbacks()
                 .with_connector_info(CONNECTION_INFO)
@@ -63,6 +78,7 @@ impl ModSecurity {
     /// ```
     pub fn add_rules(&mut self, rules: &str) -> Result<&mut Self, Error> {
         self.rules.add_plain(rules)?;
+        rules.hash(&mut self.version);
         Ok(self)
     }
 
@@ -77,10 +93,105 @@ impl ModSecurity {
     /// security.add_rules_file("/path/to/rules.conf").expect("Failed to add rules from file");
     /// ```
     pub fn add_rules_file<P: AsRef<Path>>(&mut self, file: P) -> Result<&mut Self, Error> {
-        self.rules.add_file(file)?;
+        self.rules.add_file(&file)?;
+        match std::fs::read(&file) {
+            Ok(contents) => contents.hash(&mut self.version),
+            Err(_) => file.as_ref().hash(&mut self.version),
+        }
         Ok(self)
     }
 
+    /// Fingerprint of the rules loaded into the set.
+    ///
+    /// Changes whenever different rules are loaded, so it can be used to
+    /// confirm which rule version a running service is enforcing.
+    pub fn rules_version(&self) -> String {
+        format!("{:016x}", self.version.finish())
+    }
+
+    /// Set the default actions of rules within the specified phase.
+    ///
+    /// Equivalent of the `SecDefaultAction` directive and only applies to
+    /// rules added afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_modsecurity::ModSecurity;
+    ///
+    /// let mut security = ModSecurity::new();
+    /// security
+    ///     .set_default_action(2, "log,auditlog,deny,status:403")
+    ///     .expect("invalid default action");
+    /// ```
+    pub fn set_default_action(&mut self, phase: u8, actions: &str) -> Result<&mut Self, Error> {
+        if !(1..=5).contains(&phase) {
+            return Err(Error::InvalidDirective(phase.to_string()));
+        }
+        let actions = directive_value(actions)?;
+        self.add_rules(&format!("SecDefaultAction \"phase:{phase},{actions}\"\n"))
+    }
+
+    /// Enable inspection of request bodies.
+    ///
+    /// Equivalent of the `SecRequestBodyAccess` directive.
+    pub fn set_request_body_access(&mut self, enable: bool) -> Result<&mut Self, Error> {
+        self.add_rules(&format!("SecRequestBodyAccess {}\n", on_off(enable)))
+    }
+
+    /// Enable inspection of response bodies.
+    ///
+    /// Equivalent of the `SecResponseBodyAccess` directive.
+    pub fn set_response_body_access(&mut self, enable: bool) -> Result<&mut Self, Error> {
+        self.add_rules(&format!("SecResponseBodyAccess {}\n", on_off(enable)))
+    }
+
+    /// Set the response mime-types whose bodies are inspected.
+    ///
+    /// Equivalent of the `SecResponseBodyMimeType` directive.
+    pub fn set_response_body_mime_types<'a, I>(&mut self, mimes: I) -> Result<&mut Self, Error>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mimes = mimes
+            .into_iter()
+            .map(|mime| match mime.contains(char::is_whitespace) {
+                true => Err(Error::InvalidDirective(mime.to_owned())),
+                false => directive_value(mime),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.add_rules(&format!("SecResponseBodyMimeType {}\n", mimes.join(" ")))
+    }
+
+    /// Set the OWASP Core Rule Set paranoia level.
+    ///
+    /// Sets `tx.paranoia_level` and `tx.blocking_paranoia_level` and must be
+    /// configured before the Core Rule Set is added.
+    pub fn set_paranoia_level(&mut self, level: u8) -> Result<&mut Self, Error> {
+        if !(1..=4).contains(&level) {
+            return Err(Error::InvalidDirective(level.to_string()));
+        }
+        self.add_rules(&format!(
+            "SecAction \"id:900000,phase:1,pass,t:none,nolog,\
+             setvar:tx.paranoia_level={level},setvar:tx.blocking_paranoia_level={level}\"\n"
+        ))
+    }
+
+    /// Set the OWASP Core Rule Set inbound and outbound anomaly score thresholds.
+    ///
+    /// Must be configured before the Core Rule Set is added.
+    pub fn set_anomaly_thresholds(
+        &mut self,
+        inbound: u32,
+        outbound: u32,
+    ) -> Result<&mut Self, Error> {
+        self.add_rules(&format!(
+            "SecAction \"id:900110,phase:1,pass,t:none,nolog,\
+             setvar:tx.inbound_anomaly_score_threshold={inbound},\
+             setvar:tx.outbound_anomaly_score_threshold={outbound}\"\n"
+        ))
+    }
+
     /// Configure Max request body size allowed to be loaded into memory for processing.
     ///
     /// This avoids out-of-memory errors and potential security-risks from attackers
@@ -99,15 +210,85 @@ impl ModSecurity {
         self
     }
 
+    /// Configure a single early probe of the first bytes of large request bodies.
+    ///
+    /// Request body chunks are always passed to the transaction as they are
+    /// received, and reading stops once an intervention such as an exceeded
+    /// `SecRequestBodyLimit` triggers. Request body rules however only run
+    /// once the complete body is received.
+    ///
+    /// Once the specified number of bytes is received, the partial body is
+    /// scored a single time in a separate transaction, which also repeats the
+    /// connection, URI and header phases, and the request is rejected without
+    /// reading the remainder if a disruptive action triggers. The complete
+    /// body is still buffered and inspected once fully received.
+    ///
+    /// Default is disabled.
+    pub fn set_early_inspection(&mut self, early_inspection: Option<usize>) -> &mut Self {
+        self.config.early_inspection = early_inspection;
+        self
+    }
+
+    /// Mark the rules as a shadow candidate whose interventions are only
+    /// logged for comparison
+    pub(crate) fn set_shadow(&mut self, shadow: bool) -> &mut Self {
+        self.config.shadow = shadow;
+        self
+    }
+
     /// Creates a configured LibModSecurity Transaction with the configured rules.
     pub fn transaction(&self) -> Result<Transaction, Error> {
+        self.build_transaction(None, None)
+    }
+
+    /// Creates a configured LibModSecurity Transaction passing each generated
+    /// log message to the specified callback.
+    pub fn transaction_with_logging<F>(&self, log: F) -> Result<Transaction<'_>, Error>
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.build_transaction(None, Some(Arc::new(log)))
+    }
+
+    /// Creates a configured LibModSecurity Transaction using the request
+    /// [`TransactionId`] as its unique id, so audit logs and rule messages
+    /// reference the same id as every other layer.
+    pub(crate) fn identified_transaction<'a>(
+        &'a self,
+        id: &'a TransactionId,
+        log: Option<LogFn>,
+    ) -> Result<Transaction<'a>, Error> {
+        self.build_transaction(Some(id.as_str()), log)
+    }
+
+    fn build_transaction<'a>(
+        &'a self,
+        id: Option<&'a str>,
+        log: Option<LogFn>,
+    ) -> Result<Transaction<'a>, Error> {
+        let builder = self.security.transaction_builder().with_rules(&self.rules);
+        let builder = match id {
+            Some(id) => builder.with_id(id),
+            None => builder,
+        };
+        let transaction = match log.clone() {
+            Some(log) => builder
+                .with_logging(move |msg| {
+                    if let Some(msg) = msg {
+                        log(msg)
+                    }
+                })
+                .build()?,
+            None => builder.build()?,
+        };
         Ok(Transaction {
             config: self.config.clone(),
-            transaction: self
-                .security
-                .transaction_builder()
-                .with_rules(&self.rules)
-                .build()?,
+            security: self,
+            log,
+            early: None,
+            request_body: Bytes::new(),
+            response_body: Bytes::new(),
+            transaction,
         })
     }
 
@@ -138,6 +319,19 @@ impl Default for ModSecurity {
     }
 }
 
+#[inline]
+fn on_off(enable: bool) -> &'static str {
+    if enable { "On" } else { "Off" }
+}
+
+/// Reject values which would escape the generated directive
+fn directive_value(value: &str) -> Result<&str, Error> {
+    match value.contains(['"', '\\', '\n', '\r']) {
+        true => Err(Error::InvalidDirective(value.to_owned())),
+        false => Ok(value),
+    }
+}
+
 #[inline]
 fn version_str(v: Version) -> &'static str {
     match v {
@@ -151,9 +345,14 @@ fn version_str(v: Version) -> &'static str {
 }
 
 #[inline]
-fn intervention_response(intv: &modsecurity::Intervention) -> Result<HttpResponse, Error> {
-    if let Some(log) = intv.log() {
-        tracing::error!("{log}");
+fn intervention_response(
+    intv: &modsecurity::Intervention,
+    shadow: bool,
+) -> Result<HttpResponse, Error> {
+    match intv.log() {
+        Some(log) if shadow => tracing::debug!("shadow: {log}"),
+        Some(log) => tracing::error!("{log}"),
+        None => {}
     }
     if let Some(url) = intv.url() {
         let mut res = HttpResponse::TemporaryRedirect();
@@ -167,6 +366,11 @@ fn intervention_response(intv: &modsecurity::Intervention) -> Result<HttpRespons
 /// Actix-Web compatible wrapper on [`Tranaction`](modsecurity::Transaction)
 pub struct Transaction<'a> {
     config: TransactionConfig,
+    security: &'a ModSecurity,
+    log: Option<LogFn>,
+    early: Option<Intervention>,
+    request_body: Bytes,
+    response_body: Bytes,
     transaction: modsecurity::Transaction<'a>,
 }
 
@@ -178,7 +382,7 @@ impl<'a> Transaction<'a> {
     /// **NOTE**: Remember to check for a possible intervention using
     /// [`Transaction::intervention()`] after calling this method.
     pub fn process_connection(&mut self, req: &HttpRequest) -> Result<(), Error> {
-        let Some(caddr) = req.peer_addr() else {
+        let Some(caddr) = actix_common::client_addr(req) else {
             tracing::warn!("missing client-address. cannot scan connection");
             return Ok(());
         };
@@ -194,6 +398,9 @@ impl<'a> Transaction<'a> {
 
     /// Perform the analysis on the URI and all the query string variables.
     ///
+    /// The URI is normalized first using the
+    /// [`Normalizer`](actix_common::Normalizer) registered as app-data.
+    ///
     /// This should be called at the very beginning of a request process.
     ///
     /// **NOTE**: Remember to check for a possible intervention using
@@ -201,7 +408,7 @@ impl<'a> Transaction<'a> {
     #[inline]
     pub fn process_uri(&mut self, req: &HttpRequest) -> Result<(), Error> {
         Ok(self.transaction.process_uri(
-            &req.uri().to_string(),
+            &actix_common::normalized_uri(req).to_string(),
             req.method().as_str(),
             version_str(req.version()),
         )?)
@@ -229,11 +436,106 @@ impl<'a> Transaction<'a> {
     /// **NOTE**: Remember to check for a possible intervention using
     /// [`Transaction::intervention()`] after calling this method.
     pub async fn process_request_body(&mut self, payload: Payload) -> Result<Payload, Error> {
+        self.read_request_body(payload, None).await
+    }
+
+    /// Score the first bytes of the request body in a separate transaction
+    ///
+    /// The log messages of a blocking probe are passed to the logging
+    /// callback of this transaction, so alerts and the deny list see early
+    /// blocks like any other. Messages of a passing probe are dropped since
+    /// the complete body is inspected again.
+    fn probe_request_body(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+    ) -> Result<Option<Intervention>, Error> {
+        let logs: Arc<Mutex<Vec<String>>> = Arc::default();
+        let mut probe = self.security.transaction_with_logging({
+            let logs = Arc::clone(&logs);
+            move |msg| logs.lock().expect("poisoned lock").push(msg.to_owned())
+        })?;
+        probe.process_connection(req)?;
+        probe.process_uri(req)?;
+        probe.process_request_headers(req)?;
+        probe.transaction.append_request_body(body)?;
+        probe.transaction.process_request_body()?;
+        let intv = probe.intervention()?;
+        if intv.is_some()
+            && let Some(log) = self.log.as_ref()
+        {
+            let logs = std::mem::take(&mut *logs.lock().expect("poisoned lock"));
+            logs.iter().for_each(|msg| log(msg));
+        }
+        Ok(intv)
+    }
+
+    /// Append a chunk of the request body and check for an intervention
+    ///
+    /// The received bytes are probed a single time once the early
+    /// inspection size is reached.
+    fn feed_request_body(
+        &mut self,
+        data: &[u8],
+        head: &mut BytesMut,
+        early: Option<(&HttpRequest, usize)>,
+    ) -> Result<Option<Intervention>, Error> {
+        self.transaction.append_request_body(data)?;
+        if let Some(intv) = self.intervention()? {
+            return Ok(Some(intv));
+        }
+        let Some((req, size)) = early else {
+            return Ok(None);
+        };
+        if head.len() >= size {
+            return Ok(None);
+        }
+        head.extend_from_slice(data);
+        if head.len() < size {
+            return Ok(None);
+        }
+        self.probe_request_body(req, head).or_else(|err| {
+            tracing::warn!("early body inspection failed: {err}");
+            Ok(None)
+        })
+    }
+
+    /// Feed the request body to the transaction as it is received
+    async fn read_request_body(
+        &mut self,
+        payload: Payload,
+        req: Option<&HttpRequest>,
+    ) -> Result<Payload, Error> {
         let max = self.config.max_request_body.unwrap_or(u16::MAX as usize);
-        let stream = BodyStream::new(payload);
-        let body = to_bytes_limited(stream, max).await??;
-        self.transaction.append_request_body(&body)?;
+        let early = req.zip(self.config.early_inspection);
+
+        let mut head = BytesMut::new();
+        let mut stopped = None;
+        let stream = payload.map(|chunk| {
+            let Ok(data) = &chunk else {
+                return chunk;
+            };
+            match self.feed_request_body(data, &mut head, early) {
+                Ok(None) => chunk,
+                result => {
+                    stopped = Some(result);
+                    Err(PayloadError::Incomplete(None))
+                }
+            }
+        });
+        let body = to_bytes_limited(BodyStream::new(stream), max).await;
+        match stopped {
+            Some(Ok(intv)) => {
+                self.early = intv;
+                return Ok(Payload::None);
+            }
+            Some(Err(err)) => return Err(err),
+            None => {}
+        }
+
+        let body = body??;
         self.transaction.process_request_body()?;
+        self.request_body = body.clone();
 
         let (_, mut payload) = actix_http::h1::Payload::create(true);
         payload.unread_data(body);
@@ -256,7 +558,8 @@ impl<'a> Transaction<'a> {
         self.process_connection(req.request())?;
         self.process_uri(req.request())?;
         self.process_request_headers(req.request())?;
-        let payload = self.process_request_body(req.take_payload()).await?;
+        let payload = req.take_payload();
+        let payload = self.read_request_body(payload, Some(req.request())).await?;
         req.set_payload(payload);
         Ok(())
     }
@@ -283,10 +586,64 @@ impl<'a> Transaction<'a> {
     /// [`Transaction::intervention()`] after calling this method.
     pub async fn process_response_body(&mut self, body: BoxBody) -> Result<BoxBody, Error> {
         let max = self.config.max_response_body.unwrap_or(u16::MAX as usize);
-        let body = to_bytes_limited(body, max).await??;
+        let buffer = BodyBuffer::from_body(body, max);
+        let body = buffer
+            .capture()
+            .await
+            .map_err(|err| Error::ResponseBodyError(Box::new(err)))?;
         self.transaction.append_response_body(&body)?;
         self.transaction.process_response_body()?;
-        Ok(BoxBody::new(body))
+        self.response_body = body;
+        Ok(buffer.body())
+    }
+
+    /// Request body buffered while processing the request phases
+    #[inline]
+    pub(crate) fn request_body(&self) -> Bytes {
+        self.request_body.clone()
+    }
+
+    /// Response body buffered while processing the response phases
+    #[inline]
+    pub(crate) fn response_body(&self) -> Bytes {
+        self.response_body.clone()
+    }
+
+    /// Process the request phases using a body buffered by another transaction
+    ///
+    /// The request payload is never read, so the request is left untouched.
+    pub(crate) fn process_buffered_request(
+        &mut self,
+        req: &HttpRequest,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        self.process_connection(req)?;
+        self.process_uri(req)?;
+        self.process_request_headers(req)?;
+        let max = self.config.max_request_body.unwrap_or(u16::MAX as usize);
+        if body.len() > max {
+            return Err(Error::RequestBodyError(PayloadError::Overflow));
+        }
+        self.transaction.append_request_body(body)?;
+        Ok(self.transaction.process_request_body()?)
+    }
+
+    /// Process the response phases using a body buffered by another transaction
+    ///
+    /// The response body is never read, so the response is left untouched.
+    pub(crate) fn process_buffered_response<T>(
+        &mut self,
+        res: &HttpResponse<T>,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        self.process_response_headers(res)?;
+        let max = self.config.max_response_body.unwrap_or(u16::MAX as usize);
+        if body.len() > max {
+            let err = Box::new(PayloadError::Overflow);
+            return Err(Error::ResponseBodyError(err));
+        }
+        self.transaction.append_response_body(body)?;
+        Ok(self.transaction.process_response_body()?)
     }
 
     /// Processes *ALL* rules in the response phase for this transaction.
@@ -311,10 +668,13 @@ impl<'a> Transaction<'a> {
     /// An intervention is triggered when a rule is matched and the
     /// corresponding action is disruptive.
     pub fn intervention(&mut self) -> Result<Option<Intervention>, Error> {
+        if let Some(intv) = self.early.take() {
+            return Ok(Some(intv));
+        }
         let Some(intv) = self.transaction.intervention() else {
             return Ok(None);
         };
-        let response = intervention_response(&intv)?;
+        let response = intervention_response(&intv, self.config.shadow)?;
         Ok(Some(Intervention {
             message: intv.log().map(|s| s.to_owned()),
             url: intv.url().map(|u| u.to_owned()),
@@ -1,14 +1,21 @@
 use std::ops::Deref;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
+use actix_common::{TransactionId, metrics::Timer};
 use actix_web::{
-    Error as ActixError,
+    Error as ActixError, HttpResponse,
     body::BoxBody,
     dev::{Service, ServiceRequest, ServiceResponse, forward_ready},
+    http::{StatusCode, header::HeaderName},
 };
 use futures_core::future::LocalBoxFuture;
 
-use crate::modsecurity::ModSecurity;
+use crate::alert::Alerter;
+use crate::bypass::BypassCache;
+use crate::deny::{self, DenyList};
+use crate::modsecurity::{Intervention, LogFn, ModSecurity, Transaction};
+use crate::shadow::Shadow;
 
 /// Assembled LibModSecurity service
 #[derive(Clone)]
@@ -25,6 +32,21 @@ impl<S> Deref for ModSecurityService<S> {
 pub struct ModSecurityInner<S> {
     pub(crate) service: Rc<S>,
     pub(crate) modsecurity: Rc<ModSecurity>,
+    pub(crate) request_status: Option<StatusCode>,
+    pub(crate) response_status: Option<StatusCode>,
+    pub(crate) alerter: Option<Alerter>,
+    pub(crate) deny_list: Option<DenyList>,
+    pub(crate) bypass: Option<BypassCache>,
+    pub(crate) shadow: Option<Shadow>,
+    pub(crate) id_header: HeaderName,
+}
+
+/// Build the intervention response with an optional status override
+fn intervention_response(intv: Intervention, status: Option<StatusCode>) -> HttpResponse {
+    match status {
+        Some(status) if intv.url().is_none() => HttpResponse::new(status),
+        _ => intv.into(),
+    }
 }
 
 impl<S> Service<ServiceRequest> for ModSecurityService<S>
@@ -38,25 +60,123 @@ where
 
     forward_ready!(service);
 
-    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+    fn call(&self, req: ServiceRequest) -> Self::Future {
         let this = Rc::clone(&self.0);
         Box::pin(async move {
-            let mut transaction = this.modsecurity.transaction()?;
-            transaction.process_request(&mut req).await?;
+            let timer = Timer::start("modsecurity", "", req.request());
+            let res = this.serve(req).await;
+            timer.finish(&res);
+            res
+        })
+    }
+}
+
+impl<S> ModSecurityInner<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = ActixError> + 'static,
+{
+    /// Check the client deny list and bypass cache and inspect the request,
+    /// dispatching alerts
+    async fn serve(&self, mut req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        let id = TransactionId::resolve(req.request(), &self.id_header);
+        if !req.headers().contains_key(&self.id_header)
+            && let Some(value) = id.header_value()
+        {
+            req.headers_mut().insert(self.id_header.clone(), value);
+        }
+
+        let client = actix_common::client_addr(req.request()).map(|addr| addr.ip());
+        if let Some(deny) = self.deny_list.as_ref()
+            && client.is_some_and(|ip| deny.is_blocked(ip))
+        {
+            tracing::debug!("transaction {id} rejected by deny list");
+            let status = self.request_status.unwrap_or(StatusCode::FORBIDDEN);
+            return Ok(req.into_response(HttpResponse::new(status)));
+        }
+
+        let bypass = self
+            .bypass
+            .as_ref()
+            .and_then(|cache| Some((cache, cache.key(req.request())?)));
+        if let Some((cache, key)) = bypass.as_ref()
+            && cache.is_bypassed(key)
+        {
+            return self.service.call(req).await;
+        }
+
+        if self.alerter.is_none() && self.deny_list.is_none() && bypass.is_none() {
+            let transaction = self.modsecurity.identified_transaction(&id, None)?;
+            return self.inspect(transaction, req).await.map(|(res, _)| res);
+        }
+
+        let logs: Arc<Mutex<Vec<String>>> = Arc::default();
+        let log: LogFn = Arc::new({
+            let logs = Arc::clone(&logs);
+            move |msg: &str| logs.lock().expect("poisoned lock").push(msg.to_owned())
+        });
+        let transaction = self.modsecurity.identified_transaction(&id, Some(log))?;
+        let http_req = req.request().clone();
+        let result = self.inspect(transaction, req).await;
 
-            if let Some(intv) = transaction.intervention()? {
-                return Ok(req.into_response(intv));
+        let logs = std::mem::take(&mut *logs.lock().expect("poisoned lock"));
+        if let Some(deny) = self.deny_list.as_ref()
+            && let Some(ip) = client
+        {
+            deny.record(ip, &logs);
+        }
+        if let Some((cache, key)) = bypass {
+            let clean = result.as_ref().is_ok_and(|(res, blocked)| {
+                let status = res.status();
+                !*blocked && (status.is_success() || status == StatusCode::NOT_MODIFIED)
+            });
+            cache.record(key, clean && deny::anomaly_score(&logs) == 0);
+        }
+        if let Some(alerter) = self.alerter.as_ref() {
+            let blocked = result.as_ref().is_ok_and(|(_, blocked)| *blocked);
+            alerter.dispatch(&http_req, logs, blocked);
+        }
+        result.map(|(res, _)| res)
+    }
+
+    /// Run the request and response phases returning the response and if it was blocked
+    ///
+    /// The candidate rules of the shadow, if any, evaluate the same phases
+    /// and their decision is compared once the enforced decision is known.
+    async fn inspect(
+        &self,
+        mut transaction: Transaction<'_>,
+        mut req: ServiceRequest,
+    ) -> Result<(ServiceResponse, bool), ActixError> {
+        let mut shadow = self.shadow.as_ref().map(Shadow::transaction);
+        transaction.process_request(&mut req).await?;
+        if let Some(shadow) = shadow.as_mut() {
+            shadow.process_request(req.request(), &transaction.request_body());
+        }
+
+        if let Some(intv) = transaction.intervention()? {
+            if let Some(shadow) = shadow {
+                shadow.finish(req.request(), &self.id_header, true);
             }
+            let res = intervention_response(intv, self.request_status);
+            return Ok((req.into_response(res), true));
+        }
 
-            let res = this.service.call(req).await?;
+        let res = self.service.call(req).await?;
 
-            let (http_req, mut http_res) = res.into_parts();
-            http_res = transaction.process_response(http_res).await?;
+        let (http_req, mut http_res) = res.into_parts();
+        http_res = transaction.process_response(http_res).await?;
+        let intv = transaction.intervention()?;
+        if let Some(mut shadow) = shadow {
+            shadow.process_response(&http_res, &transaction.response_body());
+            shadow.finish(&http_req, &self.id_header, intv.is_some());
+        }
 
-            match transaction.intervention()? {
-                Some(intv) => Ok(ServiceResponse::new(http_req, intv.into())),
-                None => Ok(ServiceResponse::new(http_req, http_res)),
+        match intv {
+            Some(intv) => {
+                let res = intervention_response(intv, self.response_status);
+                Ok((ServiceResponse::new(http_req, res), true))
             }
-        })
+            None => Ok((ServiceResponse::new(http_req, http_res), false)),
+        }
     }
 }
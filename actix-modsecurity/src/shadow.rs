@@ -0,0 +1,200 @@
+//! Shadow Evaluation of a Candidate Rule Set
+
+use std::{
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use actix_common::TransactionId;
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::{StatusCode, header::HeaderName},
+};
+
+use crate::error::Error;
+use crate::modsecurity::{ModSecurity, Transaction};
+
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicU64,
+    divergences: AtomicU64,
+    candidate_blocks: AtomicU64,
+    candidate_allows: AtomicU64,
+    failures: AtomicU64,
+}
+
+/// Decision counters of a shadow evaluated candidate rule set
+///
+/// Clones share the same counters, so a single instance may be passed to
+/// the middleware of every worker and read from elsewhere.
+///
+/// # Examples
+///
+/// ```
+/// use actix_modsecurity::{Middleware, ModSecurity, ShadowStats};
+///
+/// let stats = ShadowStats::default();
+/// let mw = Middleware::new(ModSecurity::new()).shadow(ModSecurity::new(), stats.clone());
+/// assert_eq!(stats.divergences(), 0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ShadowStats(Arc<Counters>);
+
+impl ShadowStats {
+    /// Number of requests evaluated by both rule sets.
+    #[inline]
+    pub fn requests(&self) -> u64 {
+        self.0.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests where the rule sets disagreed on blocking.
+    #[inline]
+    pub fn divergences(&self) -> u64 {
+        self.0.divergences.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests only the candidate rule set would have blocked.
+    #[inline]
+    pub fn candidate_blocks(&self) -> u64 {
+        self.0.candidate_blocks.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests only the enforced rule set blocked.
+    #[inline]
+    pub fn candidate_allows(&self) -> u64 {
+        self.0.candidate_allows.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests the candidate rule set failed to evaluate.
+    #[inline]
+    pub fn failures(&self) -> u64 {
+        self.0.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// Candidate rule set evaluated alongside the enforced rules
+#[derive(Clone)]
+pub(crate) struct Shadow {
+    modsecurity: Rc<ModSecurity>,
+    stats: ShadowStats,
+}
+
+impl Shadow {
+    pub(crate) fn new(mut modsecurity: ModSecurity, stats: ShadowStats) -> Self {
+        modsecurity.set_shadow(true);
+        Self {
+            modsecurity: Rc::new(modsecurity),
+            stats,
+        }
+    }
+
+    /// Begin a candidate transaction, counting failures instead of raising them
+    pub(crate) fn transaction(&self) -> ShadowTransaction<'_> {
+        let state = match self.modsecurity.transaction() {
+            Ok(transaction) => State::Pending(transaction),
+            Err(err) => State::Failed(err),
+        };
+        ShadowTransaction {
+            stats: &self.stats,
+            version: self.modsecurity.rules_version(),
+            state,
+        }
+    }
+}
+
+enum State<'a> {
+    Pending(Transaction<'a>),
+    Blocked(StatusCode, Option<String>),
+    Failed(Error),
+}
+
+/// In-flight evaluation of a request by the candidate rule set
+///
+/// Interventions are only recorded and never applied to the request.
+pub(crate) struct ShadowTransaction<'a> {
+    stats: &'a ShadowStats,
+    version: String,
+    state: State<'a>,
+}
+
+impl ShadowTransaction<'_> {
+    /// Check the transaction for an intervention once a phase completed
+    fn check(&mut self) {
+        let State::Pending(transaction) = &mut self.state else {
+            return;
+        };
+        self.state = match transaction.intervention() {
+            Ok(Some(intv)) => State::Blocked(intv.status(), intv.log().map(str::to_owned)),
+            Ok(None) => return,
+            Err(err) => State::Failed(err),
+        };
+    }
+
+    /// Record a candidate failure in place of its decision
+    fn fail(&mut self, result: Result<(), Error>) {
+        if let Err(err) = result {
+            self.state = State::Failed(err);
+        }
+    }
+
+    /// Run the candidate request phases on a copy of the buffered body
+    ///
+    /// Failures are recorded and never affect the request.
+    pub(crate) fn process_request(&mut self, req: &HttpRequest, body: &[u8]) {
+        if let State::Pending(transaction) = &mut self.state {
+            let result = transaction.process_buffered_request(req, body);
+            self.fail(result);
+        }
+        self.check();
+    }
+
+    /// Run the candidate response phases on a copy of the buffered body
+    ///
+    /// Failures are recorded and never affect the response.
+    pub(crate) fn process_response(&mut self, res: &HttpResponse, body: &[u8]) {
+        if let State::Pending(transaction) = &mut self.state {
+            let result = transaction.process_buffered_response(res, body);
+            self.fail(result);
+        }
+        self.check();
+    }
+
+    /// Compare the candidate decision with the enforced decision
+    pub(crate) fn finish(self, req: &HttpRequest, id_header: &HeaderName, blocked: bool) {
+        let counters = &self.stats.0;
+        let (candidate, log) = match self.state {
+            State::Pending(_) => (None, None),
+            State::Blocked(status, log) => (Some(status), log),
+            State::Failed(err) => {
+                counters.failures.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("shadow rules {} failed: {err}", self.version);
+                return;
+            }
+        };
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        if blocked == candidate.is_some() {
+            return;
+        }
+
+        counters.divergences.fetch_add(1, Ordering::Relaxed);
+        match blocked {
+            true => counters.candidate_allows.fetch_add(1, Ordering::Relaxed),
+            false => counters.candidate_blocks.fetch_add(1, Ordering::Relaxed),
+        };
+        let id = TransactionId::resolve(req, id_header);
+        tracing::warn!(
+            target: "actix_modsecurity::shadow",
+            transaction = %id,
+            method = %req.method(),
+            uri = %req.uri(),
+            enforced = if blocked { "block" } else { "allow" },
+            candidate = ?candidate,
+            rules = %self.version,
+            log = log.as_deref().unwrap_or_default(),
+            "shadow rule set decision diverged"
+        );
+    }
+}
@@ -1,5 +1,12 @@
-use actix_modsecurity::{Middleware, ModSecurity};
-use actix_web::test::{self, TestRequest};
+use std::{cell::RefCell, rc::Rc};
+
+use actix_modsecurity::{
+    Alert, BypassCache, DenyList, Middleware, ModSecurity, Severity, ShadowStats,
+};
+use actix_web::{
+    http::StatusCode,
+    test::{self, TestRequest},
+};
 
 mod common;
 
@@ -42,3 +49,413 @@ async fn test_middleware() {
     let res = test::call_service(&srv, req).await;
     assert_eq!(res.status().to_string(), "403 Forbidden");
 }
+
+#[actix_web::test]
+async fn test_middleware_status_override() {
+    common::setup();
+
+    let mut security = ModSecurity::new();
+    security.add_rules(RULES).expect("Failed to add rules");
+
+    let mw = Middleware::new(security).request_status(StatusCode::FORBIDDEN);
+    let srv = test::init_service(actix_web::App::new().wrap(mw)).await;
+
+    let req = TestRequest::with_uri("/admin").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_middleware_alert() {
+    common::setup();
+
+    let mut security = ModSecurity::new();
+    security
+        .add_rules(
+            r#"
+SecRuleEngine On
+SecRule REQUEST_URI "@rx passwd" "id:10,phase:1,pass,log,msg:'Path traversal',severity:CRITICAL"
+SecRule REQUEST_URI "@rx passwd" "id:11,phase:1,pass,log,severity:NOTICE"
+"#,
+        )
+        .expect("Failed to add rules");
+
+    let alerts: Rc<RefCell<Vec<Alert>>> = Rc::default();
+    let mw = Middleware::new(security).alert(Severity::Critical, {
+        let alerts = Rc::clone(&alerts);
+        move |alert| {
+            let alerts = Rc::clone(&alerts);
+            async move { alerts.borrow_mut().push(alert) }
+        }
+    });
+    let srv = test::init_service(actix_web::App::new().wrap(mw)).await;
+
+    let req = TestRequest::with_uri("/etc/passwd")
+        .insert_header(("X-Request-ID", "abc123"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    actix_web::rt::task::yield_now().await;
+
+    let alerts = alerts.borrow();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule.id.as_deref(), Some("10"));
+    assert_eq!(alerts[0].rule.severity, Some(Severity::Critical));
+    assert!(!alerts[0].blocked);
+    // the transaction id is used as the modsecurity unique id
+    assert_eq!(alerts[0].transaction_id.as_deref(), Some("abc123"));
+    assert!(alerts[0].rule.log.contains("[unique_id \"abc123\"]"));
+}
+
+#[actix_web::test]
+async fn test_builder_directives() {
+    common::setup();
+
+    let security = ModSecurity::builder()
+        .rules("SecRuleEngine On\n")
+        .and_then(|b| b.request_body_access(true))
+        .and_then(|b| b.response_body_mime_types(["text/plain", "text/html"]))
+        .and_then(|b| b.default_action(1, "log,deny,status:418"))
+        .and_then(|b| b.rules(r#"SecRule REQUEST_URI "@rx teapot" "id:2,phase:1,block""#))
+        .expect("Failed to add rules")
+        .build();
+    assert!(ModSecurity::builder().default_action(9, "deny").is_err());
+    assert!(ModSecurity::builder().paranoia_level(2).is_ok());
+    assert!(ModSecurity::builder().default_action(1, "deny\"").is_err());
+
+    let srv = test::init_service(actix_web::App::new().wrap(security.middleware())).await;
+    let req = TestRequest::with_uri("/teapot").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+}
+
+#[actix_web::test]
+async fn test_early_body_inspection() {
+    common::setup();
+
+    let security = ModSecurity::builder()
+        .early_inspection(Some(16))
+        .max_request_size(Some(1024 * 1024))
+        .rules(
+            r#"
+SecRuleEngine On
+SecRequestBodyAccess On
+SecRule REQUEST_BODY "@contains evil" "id:3,phase:2,deny,status:403"
+"#,
+        )
+        .expect("Failed to add rules")
+        .build();
+
+    let srv = test::init_service(actix_web::App::new().wrap(security.middleware())).await;
+    let body = format!("evil payload {}", "x".repeat(512 * 1024));
+    let req = TestRequest::post().uri("/").set_payload(body).to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_request_body_limit() {
+    common::setup();
+
+    let security = ModSecurity::builder()
+        .max_request_size(Some(1024 * 1024))
+        .rules(
+            r#"
+SecRuleEngine On
+SecRequestBodyAccess On
+SecRequestBodyLimit 64
+SecRequestBodyLimitAction Reject
+"#,
+        )
+        .expect("Failed to add rules")
+        .build();
+
+    // chunks are fed to the transaction as they are received
+    let srv = test::init_service(actix_web::App::new().wrap(security.middleware())).await;
+    let req = TestRequest::post()
+        .uri("/")
+        .set_payload("x".repeat(512 * 1024))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_early_body_inspection_alert() {
+    common::setup();
+
+    let security = ModSecurity::builder()
+        .early_inspection(Some(16))
+        .max_request_size(Some(1024 * 1024))
+        .rules(
+            r#"
+SecRuleEngine On
+SecRequestBodyAccess On
+SecRule REQUEST_BODY "@contains evil" "id:4,phase:2,deny,status:403,log,msg:'Evil body',severity:CRITICAL"
+"#,
+        )
+        .expect("Failed to add rules")
+        .build();
+
+    let peer: std::net::SocketAddr = "192.0.2.2:4000".parse().unwrap();
+    let deny = DenyList::new(5);
+    let alerts: Rc<RefCell<Vec<Alert>>> = Rc::default();
+    let mw = Middleware::new(security)
+        .deny_list(deny.clone())
+        .alert(Severity::Critical, {
+            let alerts = Rc::clone(&alerts);
+            move |alert| {
+                let alerts = Rc::clone(&alerts);
+                async move { alerts.borrow_mut().push(alert) }
+            }
+        });
+    let srv = test::init_service(actix_web::App::new().wrap(mw)).await;
+
+    let body = format!("evil payload {}", "x".repeat(512 * 1024));
+    let req = TestRequest::post()
+        .uri("/")
+        .peer_addr(peer)
+        .set_payload(body)
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    actix_web::rt::task::yield_now().await;
+
+    // early blocks reach the same alert and deny list handling
+    let alerts = alerts.borrow();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule.id.as_deref(), Some("4"));
+    assert!(alerts[0].blocked);
+    assert!(deny.is_blocked(peer.ip()));
+}
+
+#[actix_web::test]
+async fn test_deny_list() {
+    common::setup();
+
+    let mut security = ModSecurity::new();
+    security
+        .add_rules(
+            r#"
+SecRuleEngine On
+SecRule REQUEST_URI "@rx scan" "id:20,phase:1,pass,log,severity:CRITICAL"
+"#,
+        )
+        .expect("Failed to add rules");
+
+    let peer: std::net::SocketAddr = "192.0.2.1:4000".parse().unwrap();
+    let deny = DenyList::new(10);
+    let mw = Middleware::new(security).deny_list(deny.clone());
+    let srv = test::init_service(actix_web::App::new().wrap(mw)).await;
+
+    for _ in 0..2 {
+        let req = TestRequest::with_uri("/scan").peer_addr(peer).to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+    assert!(deny.is_blocked(peer.ip()));
+
+    let req = TestRequest::with_uri("/").peer_addr(peer).to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    deny.unblock(peer.ip());
+    let req = TestRequest::with_uri("/").peer_addr(peer).to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_bypass_cache() {
+    common::setup();
+
+    let mut security = ModSecurity::new();
+    security
+        .add_rules(
+            r#"
+SecRuleEngine On
+SecRule REQUEST_HEADERS:X-Evil "@rx ." "id:30,phase:1,deny,status:403"
+"#,
+        )
+        .expect("Failed to add rules");
+
+    let bypass = BypassCache::new(std::time::Duration::from_secs(60)).passes(2);
+    let mw = Middleware::new(security).bypass_cache(bypass.clone());
+    let app = actix_web::App::new()
+        .wrap(mw)
+        .default_service(actix_web::web::to(|| async { "asset" }));
+    let srv = test::init_service(app).await;
+
+    let evil = TestRequest::with_uri("/app.js").insert_header(("X-Evil", "1"));
+    let res = test::call_service(&srv, evil.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    for _ in 0..2 {
+        let req = TestRequest::with_uri("/app.js").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    assert_eq!(bypass.bypassed().len(), 1);
+
+    let evil = TestRequest::with_uri("/app.js").insert_header(("X-Evil", "1"));
+    let res = test::call_service(&srv, evil.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let evil = TestRequest::with_uri("/app.js")
+        .insert_header(("Host", "other.example.com"))
+        .insert_header(("X-Evil", "1"));
+    let res = test::call_service(&srv, evil.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let evil = TestRequest::with_uri("/app.js?v=2").insert_header(("X-Evil", "1"));
+    let res = test::call_service(&srv, evil.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let evil = TestRequest::post()
+        .uri("/app.js")
+        .insert_header(("X-Evil", "1"))
+        .set_payload("body");
+    let res = test::call_service(&srv, evil.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // bodiless requests of other methods are never bypassed
+    for _ in 0..2 {
+        let req = TestRequest::post().uri("/app.js").to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    let evil = TestRequest::post()
+        .uri("/app.js")
+        .insert_header(("X-Evil", "1"));
+    let res = test::call_service(&srv, evil.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(bypass.bypassed().len(), 1);
+
+    bypass.clear();
+    let evil = TestRequest::with_uri("/app.js").insert_header(("X-Evil", "1"));
+    let res = test::call_service(&srv, evil.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_transaction_id() {
+    common::setup();
+
+    let mut security = ModSecurity::new();
+    security.add_rules(RULES).expect("Failed to add rules");
+
+    let mw = Middleware::new(security).transaction_id_header("X-Correlation-ID");
+    let app = actix_web::App::new()
+        .wrap(mw)
+        .default_service(actix_web::web::to(
+            |req: actix_web::HttpRequest| async move {
+                let id = actix_common::transaction_id(&req).expect("missing transaction id");
+                let header = req.headers().get("X-Correlation-ID").cloned();
+                assert_eq!(
+                    header.as_ref().and_then(|h| h.to_str().ok()),
+                    Some(id.as_str())
+                );
+                id.to_string()
+            },
+        ));
+    let srv = test::init_service(app).await;
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-Correlation-ID", "abc123"))
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "abc123");
+
+    let req = TestRequest::with_uri("/").to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body.len(), 24);
+}
+
+#[actix_web::test]
+async fn test_shadow_rules() {
+    common::setup();
+
+    let mut current = ModSecurity::new();
+    current.add_rules(RULES).expect("Failed to add rules");
+    let mut candidate = ModSecurity::new();
+    candidate
+        .add_rules(
+            r#"
+SecRuleEngine On
+SecRule REQUEST_URI "@rx admin" "id:1,phase:1,deny,status:401"
+SecRule REQUEST_URI "@rx wp-login" "id:2,phase:1,deny,status:403"
+"#,
+        )
+        .expect("Failed to add rules");
+
+    let stats = ShadowStats::default();
+    let mw = Middleware::new(current).shadow(candidate, stats.clone());
+    let app = actix_web::App::new()
+        .wrap(mw)
+        .default_service(actix_web::web::to(|| async { "ok" }));
+    let srv = test::init_service(app).await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = TestRequest::with_uri("/admin").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(stats.divergences(), 0);
+
+    let req = TestRequest::with_uri("/wp-login.php").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(stats.candidate_blocks(), 1);
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-Client-Port", 22))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(stats.candidate_allows(), 1);
+
+    assert_eq!(stats.requests(), 4);
+    assert_eq!(stats.divergences(), 2);
+    assert_eq!(stats.failures(), 0);
+}
+
+#[actix_web::test]
+async fn test_shadow_failure_is_isolated() {
+    common::setup();
+
+    let mut current = ModSecurity::new();
+    current.add_rules(RULES).expect("Failed to add rules");
+    let mut candidate = ModSecurity::new();
+    candidate.add_rules(RULES).expect("Failed to add rules");
+    candidate.set_max_request_size(Some(4));
+    candidate.set_max_response_size(Some(4));
+
+    let stats = ShadowStats::default();
+    let mw = Middleware::new(current).shadow(candidate, stats.clone());
+    let app = actix_web::App::new()
+        .wrap(mw)
+        .route(
+            "/echo",
+            actix_web::web::post().to(|body: actix_web::web::Bytes| async move { body }),
+        )
+        .default_service(actix_web::web::to(|| async { "a response over the limit" }));
+    let srv = test::init_service(app).await;
+
+    let req = TestRequest::post()
+        .uri("/echo")
+        .set_payload("a request over the limit")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "a request over the limit");
+    assert_eq!(stats.failures(), 1);
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "a response over the limit");
+    assert_eq!(stats.failures(), 2);
+    assert_eq!(stats.divergences(), 0);
+}
@@ -0,0 +1,99 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    net::TcpListener,
+};
+
+use actix_modsecurity::{CoreRuleSet, Error};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+
+const VERSION: &str = "4.0.0";
+const RULES: &str = "SecRule REQUEST_URI \"@rx admin\" \"id:1,phase:1,deny\"\n";
+
+/// Build a release archive containing a single rules file
+fn release_archive() -> Vec<u8> {
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    let mut builder = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(RULES.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(
+            &mut header,
+            format!("coreruleset-{VERSION}/rules/REQUEST-901.conf"),
+            RULES.as_bytes(),
+        )
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Serve the archive to every request on a random local port
+fn serve(archive: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(Result::ok) {
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                archive.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&archive);
+        }
+    });
+    format!("http://{addr}/release.tar.gz")
+}
+
+#[test]
+fn test_checksum_mismatch() {
+    let cache = TempDir::new().unwrap();
+    let url = serve(release_archive());
+
+    let wrong = format!("{:x}", Sha256::digest(b"another release"));
+    let err = CoreRuleSet::new(VERSION, &wrong)
+        .url(&url)
+        .cache_dir(cache.path())
+        .fetch()
+        .unwrap_err();
+    assert!(matches!(err, Error::ChecksumMismatch(_)), "{err}");
+    assert!(fs::read_dir(cache.path()).unwrap().next().is_none());
+}
+
+#[test]
+fn test_tampered_cache() {
+    let cache = TempDir::new().unwrap();
+    let archive = release_archive();
+    let sha256 = format!("{:x}", Sha256::digest(&archive));
+    let crs = CoreRuleSet::new(VERSION, &sha256)
+        .url(&serve(archive))
+        .cache_dir(cache.path());
+
+    let dir = crs.fetch().expect("failed to fetch release");
+    let rules = dir.join("rules/REQUEST-901.conf");
+    assert_eq!(fs::read_to_string(&rules).unwrap(), RULES);
+
+    // modified and planted rules are replaced by the verified archive
+    fs::write(&rules, "SecRuleEngine Off\n").unwrap();
+    fs::write(
+        dir.join("rules/REQUEST-000-PLANTED.conf"),
+        "SecRuleEngine Off\n",
+    )
+    .unwrap();
+    let dir = crs.fetch().expect("failed to fetch release");
+    assert_eq!(fs::read_to_string(&rules).unwrap(), RULES);
+    assert!(!dir.join("rules/REQUEST-000-PLANTED.conf").exists());
+
+    // a tampered archive is discarded and downloaded again
+    let cached = cache.path().join(format!("coreruleset-{VERSION}.tar.gz"));
+    fs::write(&cached, b"tampered").unwrap();
+    crs.fetch().expect("failed to fetch release");
+    assert_eq!(fs::read_to_string(&rules).unwrap(), RULES);
+    assert_eq!(
+        format!("{:x}", Sha256::digest(fs::read(&cached).unwrap())),
+        sha256
+    );
+}
@@ -0,0 +1,67 @@
+use actix_revproxy::RevProxy;
+use actix_web::{
+    App, HttpResponse, HttpServer, body,
+    dev::{Payload, Service},
+    http::KeepAlive,
+    rt::{self, System},
+    test::{self, TestRequest},
+    web::{self, Bytes},
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use futures_util::stream;
+
+async fn echo(body: Bytes) -> HttpResponse {
+    HttpResponse::Ok().body(body)
+}
+
+fn forward_body(c: &mut Criterion) {
+    let system = System::new();
+    let srv = system.block_on(async {
+        let server = HttpServer::new(|| {
+            App::new()
+                .app_data(web::PayloadConfig::new(16 << 20))
+                .default_service(web::to(echo))
+        })
+        .workers(1)
+        // reused upstream connections break on large bodies under load
+        .keep_alive(KeepAlive::Disabled)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))
+        .expect("failed to bind upstream");
+        let url = format!("http://{}", server.addrs()[0]);
+        rt::spawn(server.run());
+        test::init_service(App::new().service(RevProxy::new("/", url))).await
+    });
+
+    let mut group = c.benchmark_group("revproxy_forward");
+    for (body_size, chunk_size) in [(1_024, 1_024), (1 << 20, 16_384)] {
+        let body = Bytes::from(vec![b'x'; body_size]);
+        let chunks: Vec<_> = body
+            .chunks(chunk_size)
+            .map(|chunk| body.slice_ref(chunk))
+            .collect();
+        group.throughput(Throughput::Bytes(body_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new(format!("chunk_{chunk_size}"), body_size),
+            &chunks,
+            |b, chunks| {
+                b.iter(|| {
+                    system.block_on(async {
+                        let chunks = chunks.clone().into_iter().map(Ok);
+                        let payload: Payload = Payload::Stream {
+                            payload: Box::pin(stream::iter(chunks)),
+                        };
+                        let (req, _) = TestRequest::post().to_request().replace_payload(payload);
+                        let res = srv.call(req).await.expect("proxy failed");
+                        let body = body::to_bytes(res.into_body()).await.expect("invalid body");
+                        assert_eq!(body.len(), body_size);
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, forward_body);
+criterion_main!(benches);
@@ -0,0 +1,226 @@
+//! Request Tee to an Audit Sink for Compliance Recording
+
+use std::{
+    cell::RefCell, fs::OpenOptions, io::Write, net::SocketAddr, path::PathBuf, rc::Rc, sync::mpsc,
+    time::SystemTime,
+};
+
+use actix_web::{
+    HttpRequest,
+    http::{Method, Uri, header::HeaderMap},
+    web::{Bytes, BytesMut},
+};
+
+use crate::Masker;
+
+/// Default maximum number of request body bytes kept per audit record
+const DEFAULT_MAX_BODY: usize = 64 * 1024;
+
+/// Metadata and (truncated) body of a single proxied request
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct AuditRecord {
+    /// Time the request was received
+    pub time: SystemTime,
+    /// Downstream client address
+    pub peer: Option<SocketAddr>,
+    /// Request method
+    pub method: Method,
+    /// Original downstream request uri
+    pub uri: Uri,
+    /// Upstream request uri
+    pub upstream: Uri,
+    /// Original downstream request headers
+    pub headers: HeaderMap,
+    /// Request body bytes up to the configured maximum
+    pub body: Bytes,
+    /// Total size of the request body forwarded upstream
+    pub body_size: usize,
+    /// Transaction ID correlating the request across services
+    pub transaction_id: Option<String>,
+}
+
+/// Destination for [`AuditRecord`]s
+///
+/// Records are delivered once the request body has been forwarded.
+/// Implementations should hand records off without blocking, such as by
+/// sending them over a channel to a background writer.
+pub trait AuditSink {
+    fn record(&self, record: AuditRecord);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(AuditRecord),
+{
+    #[inline]
+    fn record(&self, record: AuditRecord) {
+        self(record)
+    }
+}
+
+/// [`AuditSink`] appending records to a file from a background thread
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{Audit, FileSink, RevProxy};
+///
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+///     .audit(Audit::new(FileSink::new("/tmp/audit.log")).max_body(1024));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileSink(mpsc::Sender<AuditRecord>);
+
+impl FileSink {
+    /// Spawn a background writer appending to the specified file.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let (tx, rx) = mpsc::channel::<AuditRecord>();
+        std::thread::spawn(move || {
+            let mut file = match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => file,
+                Err(err) => return tracing::error!("failed to open audit log {path:?}: {err}"),
+            };
+            for record in rx {
+                if let Err(err) = file.write_all(format_record(&record).as_bytes()) {
+                    tracing::error!("failed to write audit log {path:?}: {err}");
+                }
+            }
+        });
+        Self(tx)
+    }
+}
+
+impl AuditSink for FileSink {
+    fn record(&self, record: AuditRecord) {
+        if self.0.send(record).is_err() {
+            tracing::error!("audit log writer stopped. dropping record");
+        }
+    }
+}
+
+/// Render a record as a plain-text log entry
+fn format_record(record: &AuditRecord) -> String {
+    let time = record
+        .time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let peer = record
+        .peer
+        .map(|peer| peer.to_string())
+        .unwrap_or_else(|| "<unknown>".to_owned());
+    let id = record.transaction_id.as_deref().unwrap_or("-");
+    let mut entry = format!(
+        "== {time} {peer} [{id}] {} {} -> {}\n",
+        record.method, record.uri, record.upstream
+    );
+    for (name, value) in record.headers.iter() {
+        entry.push_str(&format!("{name}: {}\n", value.as_bytes().escape_ascii()));
+    }
+    entry.push_str(&format!(
+        "== body ({} bytes, {} recorded)\n{}\n",
+        record.body_size,
+        record.body.len(),
+        record.body.escape_ascii()
+    ));
+    entry
+}
+
+/// Request tee configuration for [`RevProxy::audit`](crate::RevProxy::audit)
+#[derive(Clone)]
+pub struct Audit {
+    sink: Rc<dyn AuditSink>,
+    masker: Option<Rc<dyn Masker>>,
+    max_body: usize,
+}
+
+impl Audit {
+    /// Tee proxied requests to the specified sink.
+    pub fn new<S: AuditSink + 'static>(sink: S) -> Self {
+        Self {
+            sink: Rc::new(sink),
+            masker: None,
+            max_body: DEFAULT_MAX_BODY,
+        }
+    }
+
+    /// Mask sensitive data within each record before it reaches the sink.
+    ///
+    /// See [`Redactor`](crate::Redactor) for the default implementation.
+    ///
+    /// Default is no masking.
+    pub fn masker<M: Masker + 'static>(mut self, masker: M) -> Self {
+        self.masker = Some(Rc::new(masker));
+        self
+    }
+
+    /// Set the maximum number of request body bytes kept per record.
+    ///
+    /// Default is 64KiB.
+    pub fn max_body(mut self, max_body: usize) -> Self {
+        self.max_body = max_body;
+        self
+    }
+
+    /// Begin recording a single request
+    pub(crate) fn start(&self, req: &HttpRequest, upstream: &Uri) -> AuditTap {
+        AuditTap(Rc::new(RefCell::new(Pending {
+            sink: self.sink.clone(),
+            masker: self.masker.clone(),
+            max_body: self.max_body,
+            body: BytesMut::new(),
+            record: Some(AuditRecord {
+                time: SystemTime::now(),
+                peer: req.peer_addr(),
+                method: req.method().clone(),
+                uri: req.uri().clone(),
+                upstream: upstream.clone(),
+                headers: req.headers().clone(),
+                body: Bytes::new(),
+                body_size: 0,
+                transaction_id: actix_common::transaction_id(req).map(|id| id.to_string()),
+            }),
+        })))
+    }
+}
+
+/// Active audit record shared with the forwarded request body
+///
+/// The record is delivered once all references are dropped.
+#[derive(Clone)]
+pub(crate) struct AuditTap(Rc<RefCell<Pending>>);
+
+impl AuditTap {
+    /// Copy a chunk of the request body forwarded upstream
+    pub(crate) fn body(&self, data: &[u8]) {
+        let mut pending = self.0.borrow_mut();
+        let remaining = pending.max_body.saturating_sub(pending.body.len());
+        let copy = &data[..data.len().min(remaining)];
+        pending.body.extend_from_slice(copy);
+        if let Some(record) = pending.record.as_mut() {
+            record.body_size += data.len();
+        }
+    }
+}
+
+struct Pending {
+    sink: Rc<dyn AuditSink>,
+    masker: Option<Rc<dyn Masker>>,
+    max_body: usize,
+    body: BytesMut,
+    record: Option<AuditRecord>,
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            record.body = std::mem::take(&mut self.body).freeze();
+            if let Some(masker) = self.masker.as_ref() {
+                masker.mask(&mut record);
+            }
+            self.sink.record(record);
+        }
+    }
+}
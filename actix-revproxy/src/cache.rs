@@ -0,0 +1,672 @@
+//! Shared HTTP Response Cache for Proxied Responses
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+use actix_common::body::BodyBuffer;
+use actix_web::{
+    HttpRequest, HttpResponse,
+    body::{BodySize, BoxBody, MessageBody},
+    http::{
+        Method, StatusCode,
+        header::{self, HeaderMap, HeaderName, HeaderValue, HttpDate},
+    },
+    web::{Bytes, BytesMut},
+};
+
+/// Default maximum number of cached responses
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+/// Default maximum size of a single cached response body
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Headers kept in `304 Not Modified` responses (RFC 9110 section 15.4.5)
+const NOT_MODIFIED_HEADERS: [HeaderName; 5] = [
+    header::CACHE_CONTROL,
+    header::CONTENT_LOCATION,
+    header::DATE,
+    header::ETAG,
+    header::EXPIRES,
+];
+
+/// `Warning` added to stale responses served on upstream failure (RFC 7234 section 5.5)
+const REVALIDATION_FAILED: &str = "111 - \"Revalidation Failed\"";
+
+/// Response status codes cacheable by default (RFC 9110 section 15.1)
+const CACHEABLE_STATUS: [u16; 10] = [200, 203, 204, 300, 301, 404, 405, 410, 414, 501];
+
+/// Parsed `Cache-Control` directives
+#[derive(Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+    stale_while_revalidate: Option<u64>,
+    stale_if_error: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cc = Self::default();
+        let directives = headers
+            .get_all(header::CACHE_CONTROL)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for directive in directives {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name, Some(value.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+            let seconds = value.and_then(|v| v.parse::<u64>().ok());
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => cc.no_store = true,
+                "no-cache" => cc.no_cache = true,
+                "private" => cc.private = true,
+                "max-age" => cc.max_age = seconds,
+                "s-maxage" => cc.s_maxage = seconds,
+                "stale-while-revalidate" => cc.stale_while_revalidate = seconds,
+                "stale-if-error" => cc.stale_if_error = seconds,
+                _ => {}
+            }
+        }
+        cc
+    }
+}
+
+/// Request header values selecting a cached variant
+type Variant = Vec<(HeaderName, Option<HeaderValue>)>;
+
+/// Custom cache key builder
+pub type CacheKeyFn = Arc<dyn Fn(&HttpRequest) -> String + Send + Sync>;
+
+/// Weak entity tag derived from the response body using FNV-1a
+fn weak_etag(body: &[u8]) -> HeaderValue {
+    let hash = body.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    let etag = format!("W/\"{:x}-{hash:016x}\"", body.len());
+    HeaderValue::from_str(&etag).expect("invalid etag")
+}
+
+/// Check if any entity tag in `If-None-Match` weakly matches the tag
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    let etag = opaque(etag);
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// Parse an http-date header value
+fn http_date(headers: &HeaderMap, name: header::HeaderName) -> Option<SystemTime> {
+    let value = headers.get(name)?.to_str().ok()?;
+    HttpDate::from_str(value).ok().map(SystemTime::from)
+}
+
+/// Cached response along with its freshness information
+#[derive(Clone)]
+pub(crate) struct Entry {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored: Instant,
+    initial_age: Duration,
+    lifetime: Duration,
+    stale_while_revalidate: Duration,
+    stale_if_error: Duration,
+    variant: Variant,
+    revalidating: Arc<AtomicBool>,
+}
+
+impl Entry {
+    /// Check if the request selects this cached variant
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.variant
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+
+    /// Check if the entry is still worth keeping
+    fn is_usable(&self) -> bool {
+        self.is_fresh() || self.can_serve_revalidating() || self.can_serve_on_error()
+    }
+
+    /// Current age of the response including time spent upstream
+    fn age(&self) -> Duration {
+        self.initial_age + self.stored.elapsed()
+    }
+
+    /// Staleness of the response past its freshness lifetime
+    fn staleness(&self) -> Option<Duration> {
+        self.age().checked_sub(self.lifetime)
+    }
+
+    #[inline]
+    pub(crate) fn is_fresh(&self) -> bool {
+        self.age() < self.lifetime
+    }
+
+    /// Check if the stale response may be served while revalidating
+    #[inline]
+    pub(crate) fn can_serve_revalidating(&self) -> bool {
+        self.staleness()
+            .is_some_and(|stale| stale < self.stale_while_revalidate)
+    }
+
+    /// Check if the stale response may be served on upstream failure
+    #[inline]
+    pub(crate) fn can_serve_on_error(&self) -> bool {
+        self.staleness()
+            .is_some_and(|stale| stale < self.stale_if_error)
+    }
+
+    /// Claim the background revalidation of the entry
+    ///
+    /// Returns false if a revalidation is already in progress.
+    #[inline]
+    pub(crate) fn start_revalidation(&self) -> bool {
+        !self.revalidating.swap(true, Ordering::AcqRel)
+    }
+
+    /// Release the background revalidation claim
+    #[inline]
+    pub(crate) fn finish_revalidation(&self) {
+        self.revalidating.store(false, Ordering::Release);
+    }
+
+    /// Build a `304 Not Modified` response if the request validator
+    /// matches the cached `ETag`
+    fn not_modified(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        let etag = self.headers.get(header::ETAG)?.to_str().ok()?;
+        let matched = req
+            .headers()
+            .get_all(header::IF_NONE_MATCH)
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| etag_matches(value, etag));
+        if !matched {
+            return None;
+        }
+        let mut res = HttpResponse::NotModified().finish();
+        let headers = res.headers_mut();
+        for name in NOT_MODIFIED_HEADERS {
+            if let Some(value) = self.headers.get(&name) {
+                headers.insert(name, value.clone());
+            }
+        }
+        for value in self.headers.get_all(header::VARY) {
+            headers.append(header::VARY, value.clone());
+        }
+        headers.insert(header::AGE, HeaderValue::from(self.age().as_secs()));
+        Some(res)
+    }
+
+    /// Build a response from the cached entry with an updated `Age`
+    pub(crate) fn response(&self) -> HttpResponse {
+        let mut res = HttpResponse::with_body(self.status, self.body.clone());
+        *res.headers_mut() = self.headers.clone();
+        res.headers_mut()
+            .insert(header::AGE, HeaderValue::from(self.age().as_secs()));
+        res.map_into_boxed_body()
+    }
+}
+
+/// Shared HTTP cache for proxied responses
+///
+/// Behaves like a shared HTTP cache: freshness is determined by
+/// `s-maxage`, `max-age` or `Expires`, cached responses carry an updated
+/// `Age` header, and the `stale-while-revalidate` and `stale-if-error`
+/// extensions are honored. Stale responses served while revalidating are
+/// refreshed in the background, and a grace period may keep serving stale
+/// responses when the upstream fails.
+///
+/// Responses which are private, marked `no-store`/`no-cache`, set cookies,
+/// include `Vary: *`, or have no explicit freshness are never stored.
+/// Responses with a `Vary` header are stored as separate variants selected
+/// by the listed request headers.
+///
+/// Cache keys default to the request scheme, host and uri, such as
+/// `https://example.com/index.html`, and may be extended with request
+/// headers, normalized query ordering, or replaced entirely. Cookies are
+/// never part of the default key.
+///
+/// Weak `ETag` validators may be generated for cached responses whose
+/// upstream sent none, answering matching `If-None-Match` requests with
+/// `304 Not Modified` without contacting the upstream.
+///
+/// Clones share the same storage, allowing purges at runtime.
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{ResponseCache, RevProxy};
+///
+/// let cache = ResponseCache::new()
+///     .max_entries(512)
+///     .key_header("Accept-Language")
+///     .normalize_query(true);
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080").cache(cache.clone());
+///
+/// cache.purge_prefix("https://example.com/static/");
+/// ```
+#[derive(Clone)]
+pub struct ResponseCache {
+    entries: Arc<Mutex<HashMap<String, Vec<Entry>>>>,
+    max_entries: usize,
+    max_body_size: usize,
+    key_headers: Vec<HeaderName>,
+    normalize_query: bool,
+    key_fn: Option<CacheKeyFn>,
+    etag: bool,
+    grace: Duration,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            key_headers: Vec::new(),
+            normalize_query: false,
+            key_fn: None,
+            etag: false,
+            grace: Duration::ZERO,
+        }
+    }
+}
+
+impl ResponseCache {
+    /// Construct a new empty response cache.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of cached responses.
+    ///
+    /// Default is 1024.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set the maximum body size of a cached response.
+    ///
+    /// Default is 1MiB.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Include the value of the specified request header in the cache key.
+    pub fn key_header(mut self, name: &str) -> Self {
+        match HeaderName::try_from(name) {
+            Ok(name) => self.key_headers.push(name),
+            Err(_) => tracing::warn!("invalid cache key header {name:?}"),
+        }
+        self
+    }
+
+    /// Sort query parameters so differently ordered queries share an entry.
+    ///
+    /// Default is disabled.
+    pub fn normalize_query(mut self, normalize: bool) -> Self {
+        self.normalize_query = normalize;
+        self
+    }
+
+    /// Generate weak `ETag` validators for cached responses without one.
+    ///
+    /// Requests carrying a matching `If-None-Match` validator are answered
+    /// from the cache with `304 Not Modified`.
+    ///
+    /// Default is disabled.
+    pub fn etag(mut self, etag: bool) -> Self {
+        self.etag = etag;
+        self
+    }
+
+    /// Serve stale responses for up to the grace period past their
+    /// freshness lifetime when the upstream fails.
+    ///
+    /// Applies to responses without an explicit `stale-if-error` directive.
+    /// Stale responses served on failure carry a
+    /// `Warning: 111 - "Revalidation Failed"` header.
+    ///
+    /// Default is zero, honoring only upstream `stale-if-error` directives.
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Replace the default cache key with a custom function.
+    ///
+    /// Keys passed to [`purge`](Self::purge) and
+    /// [`purge_prefix`](Self::purge_prefix) must use the same format.
+    pub fn key_fn<F>(mut self, key_fn: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> String + Send + Sync + 'static,
+    {
+        self.key_fn = Some(Arc::new(key_fn));
+        self
+    }
+
+    /// Number of currently cached responses including variants.
+    pub fn len(&self) -> usize {
+        let entries = self.entries.lock().expect("poisoned lock");
+        entries.values().map(Vec::len).sum()
+    }
+
+    /// Check if the cache is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every cached response.
+    pub fn clear(&self) {
+        self.entries.lock().expect("poisoned lock").clear();
+    }
+
+    /// Remove every variant cached under the specified key.
+    ///
+    /// Returns the number of removed responses.
+    pub fn purge(&self, key: &str) -> usize {
+        let mut entries = self.entries.lock().expect("poisoned lock");
+        entries
+            .remove(key)
+            .map(|variants| variants.len())
+            .unwrap_or(0)
+    }
+
+    /// Remove every response cached under keys starting with the specified prefix.
+    ///
+    /// Returns the number of removed responses.
+    pub fn purge_prefix(&self, prefix: &str) -> usize {
+        let mut entries = self.entries.lock().expect("poisoned lock");
+        let mut removed = 0;
+        entries.retain(|key, variants| match key.starts_with(prefix) {
+            true => {
+                removed += variants.len();
+                false
+            }
+            false => true,
+        });
+        removed
+    }
+
+    /// Cache key of the request when it may be served from the cache
+    pub(crate) fn key(&self, req: &HttpRequest) -> Option<String> {
+        if req.method() != Method::GET || req.headers().contains_key(header::AUTHORIZATION) {
+            return None;
+        }
+        if CacheControl::parse(req.headers()).no_store {
+            return None;
+        }
+        if let Some(key_fn) = self.key_fn.as_ref() {
+            return Some(key_fn(req));
+        }
+
+        let uri = req.uri();
+        let info = req.connection_info();
+        let mut key = format!("{}://{}{}", info.scheme(), info.host(), uri.path());
+        if let Some(query) = uri.query().filter(|query| !query.is_empty()) {
+            key.push('?');
+            match self.normalize_query {
+                true => {
+                    let mut pairs: Vec<_> = query.split('&').collect();
+                    pairs.sort_unstable();
+                    key.push_str(&pairs.join("&"));
+                }
+                false => key.push_str(query),
+            }
+        }
+        for name in self.key_headers.iter() {
+            let values: Vec<_> = req
+                .headers()
+                .get_all(name)
+                .filter_map(|value| value.to_str().ok())
+                .collect();
+            key.push_str(&format!("|{name}={}", values.join(",")));
+        }
+        Some(key)
+    }
+
+    /// Check if the request demands revalidation with the upstream
+    pub(crate) fn bypass(&self, req: &HttpRequest) -> bool {
+        CacheControl::parse(req.headers()).no_cache
+    }
+
+    /// Retrieve the cached response variant matching the request
+    pub(crate) fn lookup(&self, key: &str, req: &HttpRequest) -> Option<Entry> {
+        let entries = self.entries.lock().expect("poisoned lock");
+        let variants = entries.get(key)?;
+        variants
+            .iter()
+            .find(|entry| entry.matches(req.headers()))
+            .cloned()
+    }
+
+    /// Build the response for a cached entry
+    ///
+    /// Conditional requests are answered with `304 Not Modified` when
+    /// etag handling is enabled and the cached validator matches.
+    pub(crate) fn serve(&self, entry: &Entry, req: &HttpRequest) -> HttpResponse {
+        self.etag
+            .then(|| entry.not_modified(req))
+            .flatten()
+            .unwrap_or_else(|| entry.response())
+    }
+
+    /// Build the response for a stale entry served on upstream failure
+    pub(crate) fn serve_stale(&self, entry: &Entry, req: &HttpRequest) -> HttpResponse {
+        let mut res = self.serve(entry, req);
+        res.headers_mut().append(
+            header::WARNING,
+            HeaderValue::from_static(REVALIDATION_FAILED),
+        );
+        res
+    }
+
+    /// Build a new cache entry when the response is cacheable
+    fn entry(&self, req: &HttpRequest, res: &HttpResponse<BoxBody>) -> Option<Entry> {
+        let headers = res.headers();
+        if !CACHEABLE_STATUS.contains(&res.status().as_u16())
+            || headers.contains_key(header::SET_COOKIE)
+        {
+            return None;
+        }
+        let mut variant = Variant::new();
+        let vary = headers
+            .get_all(header::VARY)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        for name in vary {
+            let name = HeaderName::try_from(name).ok()?;
+            let value = req.headers().get(&name).cloned();
+            variant.push((name, value));
+        }
+        let cc = CacheControl::parse(headers);
+        if cc.no_store || cc.no_cache || cc.private {
+            return None;
+        }
+
+        let now = SystemTime::now();
+        let date = http_date(headers, header::DATE);
+        let lifetime = match cc.s_maxage.or(cc.max_age) {
+            Some(seconds) => Duration::from_secs(seconds),
+            None => {
+                let expires = http_date(headers, header::EXPIRES)?;
+                expires.duration_since(date.unwrap_or(now)).ok()?
+            }
+        };
+
+        let age = headers
+            .get(header::AGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let apparent_age = date
+            .and_then(|date| now.duration_since(date).ok())
+            .unwrap_or_default();
+
+        Some(Entry {
+            status: res.status(),
+            headers: headers.clone(),
+            body: Bytes::new(),
+            stored: Instant::now(),
+            initial_age: age.max(apparent_age),
+            lifetime,
+            stale_while_revalidate: Duration::from_secs(cc.stale_while_revalidate.unwrap_or(0)),
+            stale_if_error: cc
+                .stale_if_error
+                .map(Duration::from_secs)
+                .unwrap_or(self.grace),
+            variant,
+            revalidating: Arc::default(),
+        })
+    }
+
+    /// Insert a complete entry evicting old responses as required
+    fn insert(&self, key: String, mut entry: Entry) {
+        if self.max_entries == 0 {
+            return;
+        }
+        if self.etag && entry.status == StatusCode::OK && !entry.headers.contains_key(header::ETAG)
+        {
+            let etag = weak_etag(&entry.body);
+            entry.headers.insert(header::ETAG, etag);
+        }
+        let mut entries = self.entries.lock().expect("poisoned lock");
+        let count = entries.values().map(Vec::len).sum::<usize>();
+        if count >= self.max_entries {
+            entries.retain(|_, variants| {
+                variants.retain(Entry::is_usable);
+                !variants.is_empty()
+            });
+            let count = entries.values().map(Vec::len).sum::<usize>();
+            let oldest = entries
+                .iter()
+                .filter(|_| count >= self.max_entries)
+                .flat_map(|(key, variants)| {
+                    variants
+                        .iter()
+                        .enumerate()
+                        .map(move |(idx, entry)| (entry.stored, key, idx))
+                })
+                .min()
+                .map(|(_, key, idx)| (key.clone(), idx));
+            if let Some((key, idx)) = oldest {
+                let variants = entries.get_mut(&key).expect("missing cache key");
+                variants.remove(idx);
+                if variants.is_empty() {
+                    entries.remove(&key);
+                }
+            }
+        }
+
+        tracing::debug!("caching response for {key}");
+        let variants = entries.entry(key).or_default();
+        variants.retain(|cached| cached.variant != entry.variant);
+        variants.push(entry);
+    }
+
+    /// Store the response once its body has been streamed to the client
+    pub(crate) fn fill(
+        &self,
+        key: String,
+        req: &HttpRequest,
+        res: HttpResponse<BoxBody>,
+    ) -> HttpResponse<BoxBody> {
+        let Some(entry) = self.entry(req, &res) else {
+            return res;
+        };
+        if let BodySize::Sized(size) = res.body().size()
+            && size > self.max_body_size as u64
+        {
+            return res;
+        }
+        let cache = self.clone();
+        res.map_body(move |_, body| {
+            BoxBody::new(CacheFill {
+                body,
+                buf: BytesMut::new(),
+                fill: Some((cache, key, entry)),
+            })
+        })
+    }
+
+    /// Store a fully buffered response
+    pub(crate) async fn store(&self, key: String, req: &HttpRequest, res: HttpResponse<BoxBody>) {
+        let Some(mut entry) = self.entry(req, &res) else {
+            return;
+        };
+        match BodyBuffer::from_body(res.into_body(), self.max_body_size)
+            .capture()
+            .await
+        {
+            Ok(body) => {
+                entry.body = body;
+                self.insert(key, entry);
+            }
+            _ => tracing::debug!("revalidated response for {key} not cacheable"),
+        }
+    }
+}
+
+/// Response body copying streamed chunks into the cache once complete
+struct CacheFill {
+    body: BoxBody,
+    buf: BytesMut,
+    fill: Option<(ResponseCache, String, Entry)>,
+}
+
+impl MessageBody for CacheFill {
+    type Error = Box<dyn std::error::Error>;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        let item = std::task::ready!(Pin::new(&mut this.body).poll_next(cx));
+        match item.as_ref() {
+            Some(Ok(chunk)) => {
+                let oversized = this.fill.as_ref().is_some_and(|(cache, _, _)| {
+                    this.buf.len() + chunk.len() > cache.max_body_size
+                });
+                match oversized {
+                    true => this.fill = None,
+                    false => this.buf.extend_from_slice(chunk),
+                }
+            }
+            Some(Err(_)) => this.fill = None,
+            None => {
+                if let Some((cache, key, mut entry)) = this.fill.take() {
+                    entry.body = std::mem::take(&mut this.buf).freeze();
+                    cache.insert(key, entry);
+                }
+            }
+        }
+        Poll::Ready(item)
+    }
+}
@@ -0,0 +1,136 @@
+//! Forwarding of mTLS Client Certificate Details to Upstreams
+
+use std::str::FromStr;
+
+use actix_common::ClientCert;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// Header names used to forward client certificate details upstream
+///
+/// Incoming copies of every configured header are always removed so
+/// downstream clients cannot spoof certificate details.
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{ClientCertHeaders, RevProxy};
+///
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+///     .forward_client_cert(ClientCertHeaders::new().cert(None).subject(Some("X-Client-DN")));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClientCertHeaders {
+    cert: Option<HeaderName>,
+    subject: Option<HeaderName>,
+    issuer: Option<HeaderName>,
+    serial: Option<HeaderName>,
+    verify: Option<HeaderName>,
+}
+
+impl Default for ClientCertHeaders {
+    fn default() -> Self {
+        Self {
+            cert: Some(HeaderName::from_static("x-ssl-client-cert")),
+            subject: Some(HeaderName::from_static("x-ssl-client-s-dn")),
+            issuer: Some(HeaderName::from_static("x-ssl-client-i-dn")),
+            serial: Some(HeaderName::from_static("x-ssl-client-serial")),
+            verify: Some(HeaderName::from_static("x-ssl-client-verify")),
+        }
+    }
+}
+
+fn header_name(name: Option<&str>) -> Option<HeaderName> {
+    name.map(|name| HeaderName::from_str(name).expect("invalid client cert header name"))
+}
+
+impl ClientCertHeaders {
+    /// Construct the default header set.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the header carrying the url-encoded PEM certificate.
+    ///
+    /// Default is `X-SSL-Client-Cert`. `None` disables the header.
+    pub fn cert(mut self, name: Option<&str>) -> Self {
+        self.cert = header_name(name);
+        self
+    }
+
+    /// Set the header carrying the subject distinguished name.
+    ///
+    /// Default is `X-SSL-Client-S-DN`. `None` disables the header.
+    pub fn subject(mut self, name: Option<&str>) -> Self {
+        self.subject = header_name(name);
+        self
+    }
+
+    /// Set the header carrying the issuer distinguished name.
+    ///
+    /// Default is `X-SSL-Client-I-DN`. `None` disables the header.
+    pub fn issuer(mut self, name: Option<&str>) -> Self {
+        self.issuer = header_name(name);
+        self
+    }
+
+    /// Set the header carrying the certificate serial number.
+    ///
+    /// Default is `X-SSL-Client-Serial`. `None` disables the header.
+    pub fn serial(mut self, name: Option<&str>) -> Self {
+        self.serial = header_name(name);
+        self
+    }
+
+    /// Set the header carrying the verification result.
+    ///
+    /// Default is `X-SSL-Client-Verify`. `None` disables the header.
+    pub fn verify(mut self, name: Option<&str>) -> Self {
+        self.verify = header_name(name);
+        self
+    }
+
+    /// Replace any client supplied headers with the connection certificate details
+    pub(crate) fn apply(&self, cert: Option<&ClientCert>, headers: &mut HeaderMap) {
+        let names = [
+            &self.cert,
+            &self.subject,
+            &self.issuer,
+            &self.serial,
+            &self.verify,
+        ];
+        for name in names.into_iter().flatten() {
+            headers.remove(name);
+        }
+
+        let verify = cert.map(|cert| cert.verify_status()).unwrap_or("NONE");
+        let values = [
+            (&self.cert, cert.map(|cert| cert.escaped_pem())),
+            (
+                &self.subject,
+                cert.and_then(|cert| cert.subject_dn()).map(str::to_owned),
+            ),
+            (
+                &self.issuer,
+                cert.and_then(|cert| cert.issuer_dn()).map(str::to_owned),
+            ),
+            (
+                &self.serial,
+                cert.and_then(|cert| cert.serial_number())
+                    .map(str::to_owned),
+            ),
+            (&self.verify, Some(verify.to_owned())),
+        ];
+        for (name, value) in values {
+            let (Some(name), Some(value)) = (name, value) else {
+                continue;
+            };
+            match HeaderValue::from_str(&value) {
+                Ok(value) => {
+                    headers.insert(name.clone(), value);
+                }
+                Err(_) => tracing::warn!("invalid client cert header value {name:?}: {value:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Custom [`awc`] Connector Dialing Through [`actix_upstream`]
+
+use std::sync::{Arc, RwLock};
+
+use actix_service::Service;
+use actix_tls::connect::{ConnectError, ConnectInfo, Connection};
+use actix_upstream::{Connector, EgressProxy, SockStream, StreamAddr};
+use awc::http::Uri;
+use futures_core::future::LocalBoxFuture;
+
+/// [`awc`] connector service dialing upstreams via [`actix_upstream`].
+///
+/// Connectors created with [`UpstreamConnector::new`] always dial a fixed
+/// upstream address, allowing the proxy to reach unix socket upstreams while
+/// the request uri continues to be built from the resolution uri.
+///
+/// Connectors created with [`UpstreamConnector::resolve`] dial the host of
+/// each request uri, so dual-stack upstream hostnames are connected using
+/// happy-eyeballs and the configured [`IpPreference`](actix_upstream::IpPreference).
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::UpstreamConnector;
+///
+/// let connector = awc::Connector::new()
+///     .connector(UpstreamConnector::new("unix:///var/run/app.sock".parse().unwrap()));
+/// let client = awc::Client::builder().connector(connector).finish();
+/// ```
+#[derive(Clone, Debug)]
+pub struct UpstreamConnector(Arc<RwLock<Target>>);
+
+#[derive(Debug)]
+struct Target {
+    addr: Option<StreamAddr>,
+    connector: Connector,
+}
+
+impl UpstreamConnector {
+    /// Create a new connector for the specified upstream address.
+    pub fn new(addr: StreamAddr) -> Self {
+        Self(Arc::new(RwLock::new(Target {
+            addr: Some(addr),
+            connector: Connector::default(),
+        })))
+    }
+
+    /// Create a new connector dialing the host of each request uri.
+    pub fn resolve() -> Self {
+        Self(Arc::new(RwLock::new(Target {
+            addr: None,
+            connector: Connector::default(),
+        })))
+    }
+
+    /// Override the [`Connector`] used to dial the upstream.
+    ///
+    /// Default is [`Connector::new()`](actix_upstream::Connector::new)
+    pub fn connector(self, connector: Connector) -> Self {
+        self.set_connector(connector);
+        self
+    }
+
+    /// Tunnel TCP upstream connections through the specified [`EgressProxy`].
+    pub fn proxy(self, proxy: EgressProxy) -> Self {
+        self.set_proxy(proxy);
+        self
+    }
+
+    /// Change the upstream address for all subsequent connections.
+    pub fn set_addr(&self, addr: StreamAddr) {
+        self.0.write().expect("poisoned lock").addr = Some(addr);
+    }
+
+    /// Replace the [`Connector`] for all subsequent connections.
+    pub fn set_connector(&self, connector: Connector) {
+        self.0.write().expect("poisoned lock").connector = connector;
+    }
+
+    /// Tunnel all subsequent TCP connections through the specified [`EgressProxy`].
+    pub fn set_proxy(&self, proxy: EgressProxy) {
+        let mut target = self.0.write().expect("poisoned lock");
+        target.connector = target.connector.clone().proxy(proxy);
+    }
+}
+
+impl Service<ConnectInfo<Uri>> for UpstreamConnector {
+    type Response = Connection<Uri, SockStream>;
+    type Error = ConnectError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: ConnectInfo<Uri>) -> Self::Future {
+        let (addr, connector) = {
+            let target = self.0.read().expect("poisoned lock");
+            (target.addr.clone(), target.connector.clone())
+        };
+        let addr = addr.unwrap_or_else(|| StreamAddr::Dns {
+            host: req
+                .hostname()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_owned(),
+            port: req.port(),
+            refresh: false,
+        });
+        Box::pin(async move {
+            let io = connector.connect(&addr).await.map_err(ConnectError::Io)?;
+            Ok(Connection::new(req.request().clone(), io))
+        })
+    }
+}
@@ -0,0 +1,196 @@
+//! Runtime Control Handle for Reverse Proxy Services
+
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use actix_common::Maintenance;
+use awc::http::Uri;
+
+use crate::{
+    Connector, StreamAddr, UpstreamConnector, UpstreamSubscriber,
+    upstream::{UpstreamStatus, Upstreams},
+};
+
+pub(crate) struct ControlState {
+    upstreams: Upstreams,
+    timeout: Option<Duration>,
+    upstream: Option<UpstreamConnector>,
+    maintenance: Option<Maintenance>,
+}
+
+/// Runtime control over a [`RevProxy`](crate::RevProxy) service upstream
+///
+/// Changes apply to all services sharing the handle without restarting
+/// the server. Established keep-alive connections are reused until they
+/// expire. Removed upstreams stop receiving new requests and are dropped
+/// once their in-flight requests complete.
+///
+/// External controllers such as a service mesh sidecar can
+/// [`subscribe`](Self::subscribe) to upstream events and push upstream
+/// sets and health back through the same handle. Upstreams of a
+/// [`RouteTable`](crate::RouteTable) or fallback are not covered.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_revproxy::RevProxy;
+///
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080");
+/// let control = proxy.control_handle();
+///
+/// control.set_upstream("http://127.0.0.1:8081").unwrap();
+/// control.set_timeout(Duration::from_secs(10));
+/// ```
+#[derive(Clone)]
+pub struct ControlHandle(Arc<RwLock<ControlState>>);
+
+impl ControlHandle {
+    pub(crate) fn new(resolve: Uri) -> Self {
+        Self(Arc::new(RwLock::new(ControlState {
+            upstreams: Upstreams::new(resolve),
+            timeout: None,
+            upstream: None,
+            maintenance: None,
+        })))
+    }
+
+    #[inline]
+    pub(crate) fn set_upstream_connector(&self, connector: UpstreamConnector) {
+        self.0.write().expect("poisoned lock").upstream = Some(connector);
+    }
+
+    #[inline]
+    pub(crate) fn set_maintenance_mode(&self, maintenance: Maintenance) {
+        self.0.write().expect("poisoned lock").maintenance = Some(maintenance);
+    }
+
+    /// [`Maintenance`] mode of the proxy, if configured.
+    pub fn maintenance(&self) -> Option<Maintenance> {
+        self.0.read().expect("poisoned lock").maintenance.clone()
+    }
+
+    /// Switch maintenance mode on or off.
+    ///
+    /// Only applies to proxies configured with
+    /// [`RevProxy::maintenance`](crate::RevProxy::maintenance).
+    pub fn set_maintenance(&self, enabled: bool) {
+        match self.0.read().expect("poisoned lock").maintenance.as_ref() {
+            Some(maintenance) => maintenance.set_enabled(enabled),
+            None => tracing::warn!("proxy has no maintenance mode. ignoring toggle"),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn upstream_set(&self) -> Upstreams {
+        self.0.read().expect("poisoned lock").upstreams.clone()
+    }
+
+    /// Current primary upstream resolution uri.
+    pub fn upstream(&self) -> Option<Uri> {
+        self.upstream_set().uris().into_iter().next()
+    }
+
+    /// Current upstream resolution uris excluding draining upstreams.
+    pub fn upstreams(&self) -> Vec<Uri> {
+        self.upstream_set().uris()
+    }
+
+    /// Health and load of every upstream including draining upstreams.
+    pub fn upstream_status(&self) -> Vec<UpstreamStatus> {
+        self.upstream_set().status()
+    }
+
+    /// Change the upstream resolution uri, draining any other upstreams.
+    pub fn set_upstream<U: TryInto<Uri>>(&self, uri: U) -> Result<(), U::Error> {
+        self.upstream_set().replace(vec![uri.try_into()?]);
+        Ok(())
+    }
+
+    /// Replace the set of upstream resolution uris, draining any removed upstreams.
+    pub fn set_upstreams<U: TryInto<Uri>>(&self, uris: Vec<U>) -> Result<(), U::Error> {
+        let uris = uris
+            .into_iter()
+            .map(|uri| uri.try_into())
+            .collect::<Result<_, _>>()?;
+        self.upstream_set().replace(uris);
+        Ok(())
+    }
+
+    /// Add an upstream resolution uri to balance requests across.
+    pub fn add_upstream<U: TryInto<Uri>>(&self, uri: U) -> Result<(), U::Error> {
+        self.upstream_set().add(uri.try_into()?);
+        Ok(())
+    }
+
+    /// Drain an upstream resolution uri and remove it once idle.
+    pub fn remove_upstream<U: TryInto<Uri>>(&self, uri: U) -> Result<(), U::Error> {
+        self.upstream_set().remove(&uri.try_into()?);
+        Ok(())
+    }
+
+    /// Mark an upstream down for the fail timeout or return it to the rotation.
+    ///
+    /// Lets external health checkers push their verdict, alongside the
+    /// passive failure detection of the proxy itself.
+    pub fn set_upstream_health<U: TryInto<Uri>>(
+        &self,
+        uri: U,
+        healthy: bool,
+    ) -> Result<(), U::Error> {
+        self.upstream_set().set_health(&uri.try_into()?, healthy);
+        Ok(())
+    }
+
+    /// Subscribe to selection and health events of the upstream set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_revproxy::{RevProxy, UpstreamEvent, UpstreamEventKind};
+    ///
+    /// let proxy = RevProxy::new("/", "http://127.0.0.1:8080");
+    /// proxy.control_handle().subscribe(|event: &UpstreamEvent| {
+    ///     if event.kind == UpstreamEventKind::MarkedDown {
+    ///         eprintln!("upstream {} is down", event.uri);
+    ///     }
+    /// });
+    /// ```
+    pub fn subscribe<S: UpstreamSubscriber + 'static>(&self, subscriber: S) {
+        self.upstream_set().subscribe(Arc::new(subscriber));
+    }
+
+    /// Current upstream response timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.0.read().expect("poisoned lock").timeout
+    }
+
+    /// Change the upstream response timeout.
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.0.write().expect("poisoned lock").timeout = Some(timeout);
+    }
+
+    /// Change the fixed upstream socket address.
+    ///
+    /// Only applies to proxies configured with
+    /// [`RevProxy::upstream_addr`](crate::RevProxy::upstream_addr) or
+    /// [`RevProxy::upstream_connector`](crate::RevProxy::upstream_connector).
+    pub fn set_upstream_addr(&self, addr: StreamAddr) {
+        match self.0.read().expect("poisoned lock").upstream.as_ref() {
+            Some(upstream) => upstream.set_addr(addr),
+            None => tracing::warn!("proxy has no upstream connector. ignoring address"),
+        }
+    }
+
+    /// Replace the [`Connector`] used to dial the fixed upstream socket address.
+    ///
+    /// Use this to update connect timeouts and TLS settings.
+    pub fn set_connector(&self, connector: Connector) {
+        match self.0.read().expect("poisoned lock").upstream.as_ref() {
+            Some(upstream) => upstream.set_connector(connector),
+            None => tracing::warn!("proxy has no upstream connector. ignoring connector"),
+        }
+    }
+}
@@ -1,7 +1,11 @@
 //! Error and Result Types
 
-use actix_web::{ResponseError, error::QueryPayloadError};
-use awc::http::header::{InvalidHeaderValue, ToStrError};
+use actix_common::{ErrorKind, GatewayError};
+use actix_web::{HttpResponse, ResponseError, body::BoxBody, error::QueryPayloadError};
+use awc::{
+    error::{ConnectError, SendRequestError},
+    http::header::{InvalidHeaderValue, ToStrError},
+};
 use derive_more::{Display, Error, From};
 
 /// Errors which occur when processing Reverse Proxy Requests/Responses
@@ -22,6 +26,21 @@ pub enum Error {
 
     /// Failed to build uri error
     UriError(UriError),
+
+    /// Every upstream was removed from the proxy
+    #[from(skip)]
+    #[display("No upstream available")]
+    NoUpstream,
+
+    /// Upstream response headers exceed the configured limits
+    #[from(skip)]
+    #[display("Upstream response headers too large")]
+    HeadersTooLarge,
+
+    /// Upstream response body does not match its announced digest
+    #[from(skip)]
+    #[display("Upstream response failed integrity verification")]
+    IntegrityMismatch,
 }
 
 /// Errors which occur when building a combined proxied request uri
@@ -43,16 +62,49 @@ pub enum UriError {
     RequestError(awc::error::HttpError),
 }
 
+impl GatewayError for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) | Self::NoUpstream => ErrorKind::UpstreamConnect,
+            Self::FailedRequest(err) => match err {
+                SendRequestError::Timeout | SendRequestError::Connect(ConnectError::Timeout) => {
+                    ErrorKind::UpstreamTimeout
+                }
+                SendRequestError::Url(_) => ErrorKind::Config,
+                SendRequestError::Connect(_) => ErrorKind::UpstreamConnect,
+                _ => ErrorKind::ProtocolViolation,
+            },
+            Self::HeadersTooLarge | Self::IntegrityMismatch => ErrorKind::ProtocolViolation,
+            Self::InvalidHeader(_) | Self::InvalidHeaderValue(_) => ErrorKind::Internal,
+            Self::UriError(err) => err.kind(),
+        }
+    }
+}
+
 impl ResponseError for Error {
-    /// Returns `500 Internal Server Error`.
+    /// Returns the status code of the error's [`ErrorKind`].
     fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::INTERNAL_SERVER_ERROR
+        self.kind().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        self.gateway_response()
+    }
+}
+
+impl GatewayError for UriError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Config
     }
 }
 
 impl ResponseError for UriError {
-    /// Returns `500 Internal Server Error`.
+    /// Returns the status code of the error's [`ErrorKind`].
     fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::INTERNAL_SERVER_ERROR
+        self.kind().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        self.gateway_response()
     }
 }
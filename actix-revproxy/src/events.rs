@@ -0,0 +1,67 @@
+//! Upstream Selection and Health Events
+
+use std::sync::Arc;
+
+use awc::http::Uri;
+
+/// Kind of change reported by an [`UpstreamEvent`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UpstreamEventKind {
+    /// Upstream was chosen for a request.
+    Selected,
+    /// Upstream failed and is avoided for the fail timeout.
+    MarkedDown,
+    /// Upstream failure left no healthy upstream in the set.
+    CircuitOpened,
+    /// Upstream returned to the rotation after its fail timeout.
+    Recovered,
+    /// Upstream was added to the set.
+    Added,
+    /// Upstream stopped receiving new requests before removal.
+    Draining,
+    /// Drained upstream was removed from the set.
+    Removed,
+}
+
+/// Change in the selection or health of a single upstream
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpstreamEvent {
+    /// Kind of change.
+    pub kind: UpstreamEventKind,
+    /// Upstream resolution uri.
+    pub uri: Uri,
+}
+
+/// Receiver of [`UpstreamEvent`]s for external load balancer control
+///
+/// Events are delivered synchronously on the worker handling the request
+/// after the upstream set was updated, so implementations should hand
+/// events off without blocking, such as by sending them over a channel.
+/// Subscribers may push updates back through the
+/// [`ControlHandle`](crate::ControlHandle) in response to an event.
+pub trait UpstreamSubscriber: Send + Sync {
+    fn notify(&self, event: &UpstreamEvent);
+}
+
+impl<F> UpstreamSubscriber for F
+where
+    F: Fn(&UpstreamEvent) + Send + Sync,
+{
+    #[inline]
+    fn notify(&self, event: &UpstreamEvent) {
+        self(event)
+    }
+}
+
+/// Shared list of registered subscribers
+pub(crate) type Subscribers = Vec<Arc<dyn UpstreamSubscriber>>;
+
+/// Deliver the events to every subscriber
+pub(crate) fn publish(subscribers: &Subscribers, events: &[UpstreamEvent]) {
+    for event in events {
+        for subscriber in subscribers {
+            subscriber.notify(event);
+        }
+    }
+}
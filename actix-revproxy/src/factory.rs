@@ -1,5 +1,6 @@
-use std::{fmt::Debug, rc::Rc, str::FromStr};
+use std::{fmt::Debug, rc::Rc, str::FromStr, time::Duration};
 
+use actix_common::{AllowedMethods, Concurrency, Cors, ErrorPages, Maintenance, Template};
 use actix_service::ServiceFactory;
 use actix_web::{
     Error,
@@ -12,9 +13,15 @@ use awc::{
 };
 use futures_core::future::LocalBoxFuture;
 
-use crate::service::HeaderVec;
+use crate::{
+    Audit, ClientCertHeaders, Connector, ControlHandle, EgressProxy, HeaderLimits, HeaderPolicy,
+    Integrity, ResponseCache, RouteTable, StreamAddr, UpstreamConnector,
+    service::{HeaderTemplates, HeaderVec},
+};
 
-use super::service::{ProxyService, ProxyServiceInner};
+use super::service::{Fallback, ProxyService, ProxyServiceInner};
+use super::throttle::RateLimit;
+use super::upstream::Upstreams;
 
 /// Reverse Proxy service
 ///
@@ -29,15 +36,47 @@ use super::service::{ProxyService, ProxyServiceInner};
 /// let app = App::new()
 ///     .service(RevProxy::new("/", "http://127.0.0.1:8080"));
 /// ```
+/// Callback constructing the actix-web-client used by each worker
+pub type ClientFactory = Rc<dyn Fn() -> Client>;
+
+/// Source of the actix-web-client used by each worker
+#[derive(Clone, Default)]
+enum ClientSource {
+    #[default]
+    Default,
+    Shared(Rc<Client>),
+    Factory(ClientFactory),
+    Upstream(UpstreamConnector),
+}
+
 #[derive(Clone)]
 pub struct RevProxy {
     mount_path: String,
     guards: Vec<Rc<dyn Guard>>,
-    client: Rc<Client>,
-    resolve: Uri,
+    client: ClientSource,
+    control: ControlHandle,
     change_host: bool,
+    webdav: bool,
     header_up: HeaderVec,
+    header_templates: HeaderTemplates,
     header_down: HeaderVec,
+    header_policy: Option<HeaderPolicy>,
+    request_limits: Option<HeaderLimits>,
+    response_limits: Option<HeaderLimits>,
+    integrity: Option<Integrity>,
+    client_cert: Option<ClientCertHeaders>,
+    head_for_get: bool,
+    limit_rate: Option<u64>,
+    limit_rate_after: u64,
+    audit: Option<Audit>,
+    cache: Option<ResponseCache>,
+    routes: Option<RouteTable>,
+    fallbacks: Vec<Fallback>,
+    hedge: Option<Duration>,
+    concurrency: Option<Concurrency>,
+    methods: AllowedMethods,
+    cors: Option<Cors>,
+    error_pages: Option<ErrorPages>,
 }
 
 impl RevProxy {
@@ -56,11 +95,30 @@ impl RevProxy {
         Self {
             mount_path: mount_path.to_owned(),
             guards: Vec::new(),
-            client: Rc::new(awc::Client::new()),
-            resolve: uri.try_into().expect("invalid resolution uri"),
+            client: ClientSource::default(),
+            control: ControlHandle::new(uri.try_into().expect("invalid resolution uri")),
             change_host: false,
+            webdav: false,
             header_up: Vec::new(),
+            header_templates: Vec::new(),
             header_down: Vec::new(),
+            header_policy: None,
+            request_limits: None,
+            response_limits: None,
+            integrity: None,
+            client_cert: None,
+            head_for_get: false,
+            limit_rate: None,
+            limit_rate_after: 0,
+            audit: None,
+            cache: None,
+            routes: None,
+            fallbacks: Vec::new(),
+            hedge: None,
+            concurrency: None,
+            methods: AllowedMethods::default(),
+            cors: None,
+            error_pages: None,
         }
     }
 
@@ -90,10 +148,242 @@ impl RevProxy {
     ///
     /// Default is [`Client::new()`](awc::Client::new)
     pub fn with_client(mut self, client: Client) -> Self {
-        self.client = Rc::new(client);
+        self.client = ClientSource::Shared(Rc::new(client));
+        self
+    }
+
+    /// Construct the actix-web-client for each worker using the specified callback
+    ///
+    /// Use this to configure timeouts, a default user-agent, or a custom
+    /// [`awc::Connector`] for local address binding or chained proxies.
+    /// Overrides any configured client or upstream connector.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_revproxy::{RevProxy, awc};
+    ///
+    /// let proxy = RevProxy::new("/", "http://127.0.0.1:8080").client_factory(|| {
+    ///     awc::Client::builder()
+    ///         .timeout(Duration::from_secs(30))
+    ///         .add_default_header(("User-Agent", "my-proxy"))
+    ///         .finish()
+    /// });
+    /// ```
+    pub fn client_factory<F>(mut self, factory: F) -> Self
+    where
+        F: Fn() -> Client + 'static,
+    {
+        self.client = ClientSource::Factory(Rc::new(factory));
+        self
+    }
+
+    /// Dial upstream hostnames using the specified [`Connector`]
+    ///
+    /// Hostnames resolving to both IPv4 and IPv6 addresses are connected
+    /// using happy-eyeballs (RFC 8305) according to the connector
+    /// [`IpPreference`](crate::IpPreference). Overrides any configured client.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use actix_revproxy::{Connector, IpPreference, RevProxy};
+    ///
+    /// let proxy = RevProxy::new("/", "http://backend.internal:8080")
+    ///     .connector(Connector::new().ip_preference(IpPreference::Ipv4));
+    /// ```
+    pub fn connector(self, connector: Connector) -> Self {
+        self.upstream_connector(UpstreamConnector::resolve().connector(connector))
+    }
+
+    /// Dial upstream connections through an egress SOCKS5 or HTTP CONNECT proxy
+    ///
+    /// Applies to the configured upstream connector, or dials upstream
+    /// hostnames through the proxy otherwise. Overrides any configured client.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_revproxy::RevProxy;
+    ///
+    /// let proxy = RevProxy::new("/", "http://backend.internal:8080")
+    ///     .egress_proxy("socks5://proxy.corp:1080".parse().unwrap());
+    /// ```
+    pub fn egress_proxy(self, proxy: EgressProxy) -> Self {
+        match &self.client {
+            ClientSource::Upstream(connector) => {
+                connector.set_proxy(proxy);
+                self
+            }
+            _ => self.connector(Connector::new().proxy(proxy)),
+        }
+    }
+
+    /// Dial a fixed upstream socket address instead of the resolution uri host
+    ///
+    /// Use this to proxy to unix socket upstreams. The resolution uri is still
+    /// used to build the upstream request uri. Overrides any configured client.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_revproxy::RevProxy;
+    ///
+    /// let proxy = RevProxy::new("/", "http://localhost")
+    ///     .upstream_addr("unix:///var/run/app.sock".parse().unwrap());
+    /// ```
+    pub fn upstream_addr(self, addr: StreamAddr) -> Self {
+        self.upstream_connector(UpstreamConnector::new(addr))
+    }
+
+    /// Dial upstreams using the specified [`UpstreamConnector`]
+    ///
+    /// Overrides any configured client.
+    pub fn upstream_connector(mut self, connector: UpstreamConnector) -> Self {
+        self.control.set_upstream_connector(connector.clone());
+        self.client = ClientSource::Upstream(connector);
+        self
+    }
+
+    /// Construct the actix-web-client used by a single worker
+    fn build_client(&self) -> Rc<Client> {
+        match &self.client {
+            ClientSource::Default => Rc::new(Client::new()),
+            ClientSource::Shared(client) => client.clone(),
+            ClientSource::Factory(factory) => Rc::new(factory()),
+            ClientSource::Upstream(connector) => {
+                let connector = awc::Connector::new().connector(connector.clone());
+                Rc::new(Client::builder().connector(connector).finish())
+            }
+        }
+    }
+
+    /// Add another upstream resolution uri to balance requests across.
+    ///
+    /// Requests are sent to the upstream with the fewest in-flight requests.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_revproxy::RevProxy;
+    ///
+    /// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+    ///     .upstream("http://127.0.0.1:8081");
+    /// ```
+    pub fn upstream<U: TryInto<Uri>>(self, uri: U) -> Self
+    where
+        U::Error: Debug,
+    {
+        self.control
+            .add_upstream(uri)
+            .expect("invalid resolution uri");
+        self
+    }
+
+    /// Send requests matching a [`RouteTable`] to their routed upstreams.
+    ///
+    /// Requests matching no route are sent to the default upstreams.
+    ///
+    /// Default is no routes.
+    pub fn routes(mut self, routes: RouteTable) -> Self {
+        self.routes = Some(routes);
+        self
+    }
+
+    /// Retry requests against a secondary upstream when the response status matches.
+    ///
+    /// Fallbacks are tried in order of declaration, each against the
+    /// response of the previous attempt. Only requests without a body are
+    /// retried since request bodies are streamed and cannot be replayed.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_revproxy::RevProxy;
+    ///
+    /// // serve from the cdn origin and fall back to the application on 404
+    /// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+    ///     .fallback_on([404, 410], "http://127.0.0.1:8081");
+    /// ```
+    pub fn fallback_on<S, U>(mut self, statuses: S, uri: U) -> Self
+    where
+        S: IntoIterator<Item = u16>,
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        let uri = uri.try_into().expect("invalid fallback uri");
+        self.fallbacks.push(Fallback {
+            statuses: statuses.into_iter().collect(),
+            upstreams: Upstreams::new(uri),
+        });
+        self
+    }
+
+    /// Hedge slow requests by sending a duplicate to another upstream.
+    ///
+    /// When an upstream has not responded within `delay`, the request is
+    /// also sent to a different upstream and whichever responds first is
+    /// used, cancelling the other. Only idempotent requests without a body
+    /// are hedged, and only when more than one upstream is available.
+    ///
+    /// Default is disabled.
+    pub fn hedge(mut self, delay: Duration) -> Self {
+        self.hedge = Some(delay);
+        self
+    }
+
+    /// Ramp traffic to a recovered upstream up gradually over the specified duration.
+    ///
+    /// Default is disabled.
+    pub fn slow_start(self, slow_start: Duration) -> Self {
+        self.control.upstream_set().set_slow_start(slow_start);
+        self
+    }
+
+    /// Avoid an upstream for the specified duration after a failed connection.
+    ///
+    /// Default is 10 seconds.
+    pub fn fail_timeout(self, fail_timeout: Duration) -> Self {
+        self.control.upstream_set().set_fail_timeout(fail_timeout);
+        self
+    }
+
+    /// Limit the number of concurrent requests handled by the service.
+    ///
+    /// Default is unlimited.
+    pub fn concurrency(mut self, concurrency: Concurrency) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Render errors raised by the service using the specified pages.
+    ///
+    /// Default renders errors using their
+    /// [`ResponseError`](actix_web::ResponseError) implementation.
+    pub fn error_pages(mut self, pages: ErrorPages) -> Self {
+        self.error_pages = Some(pages);
+        self
+    }
+
+    /// Set the upstream response timeout.
+    ///
+    /// Default is the client timeout.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.control.set_timeout(timeout);
         self
     }
 
+    /// Share runtime control of the upstream with another handle.
+    ///
+    /// Use this to control proxies constructed separately for each worker
+    /// using a single [`ControlHandle`].
+    pub fn with_control(mut self, control: ControlHandle) -> Self {
+        self.control = control;
+        self
+    }
+
+    /// Returns a [`ControlHandle`] for runtime changes to the upstream.
+    #[inline]
+    pub fn control_handle(&self) -> ControlHandle {
+        self.control.clone()
+    }
+
     /// Configure proxy to change hostname to the upstream host
     ///
     /// Default is return the established hostname of the original request.
@@ -102,6 +392,30 @@ impl RevProxy {
         self
     }
 
+    /// Configure proxy to forward WebDAV requests (RFC 4918).
+    ///
+    /// Accepts the WebDAV methods in addition to the configured
+    /// [`methods`](Self::methods) and rewrites `Destination` headers of
+    /// `COPY` and `MOVE` requests addressing the public host to the
+    /// upstream. `Location` and `Destination` response headers naming the
+    /// upstream are rewritten back to the public host, so the internal
+    /// upstream authority never reaches the client.
+    ///
+    /// Default is disabled.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_revproxy::RevProxy;
+    ///
+    /// let proxy = RevProxy::new("/dav", "http://dav.internal:8080")
+    ///     .change_host()
+    ///     .webdav(true);
+    /// ```
+    pub fn webdav(mut self, enable: bool) -> Self {
+        self.webdav = enable;
+        self
+    }
+
     /// Append a header to include in the upstream request.
     pub fn upstream_header(mut self, name: &str, value: &str) -> Self {
         let Ok(name) = header::HeaderName::from_str(name) else {
@@ -116,6 +430,192 @@ impl RevProxy {
         self
     }
 
+    /// Set an upstream request header rendered from each request.
+    ///
+    /// The value is a [`Template`] whose placeholders such as `{uri}`,
+    /// `{method}`, `{client_ip}` and `{host}` are replaced with values of
+    /// the incoming request. Headers rendering to an empty value are
+    /// removed. Applied after [`upstream_header`](Self::upstream_header).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_revproxy::RevProxy;
+    ///
+    /// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+    ///     .set_request_header("X-Original-URI", "{uri}")
+    ///     .set_request_header("X-Real-IP", "{client_ip}");
+    /// ```
+    pub fn set_request_header(mut self, name: &str, template: &str) -> Self {
+        let Ok(name) = header::HeaderName::from_str(name) else {
+            tracing::warn!("invalid upstream header name {name:?}");
+            return self;
+        };
+        match Template::from_str(template) {
+            Ok(template) => self.header_templates.push((name, template)),
+            Err(err) => tracing::warn!("invalid upstream header template {name:?}: {err}"),
+        }
+        self
+    }
+
+    /// Send upstream `GET` requests as `HEAD` requests.
+    ///
+    /// Downstream clients receive the upstream status and headers with an
+    /// empty body. Use this for prefetch or health-check mounts where the
+    /// upstream body is never needed.
+    ///
+    /// Default is disabled.
+    pub fn head_for_get(mut self, head_for_get: bool) -> Self {
+        self.head_for_get = head_for_get;
+        self
+    }
+
+    /// Limit the transfer rate of each downstream response body in bytes per second.
+    ///
+    /// Similar to nginx `limit_rate`, preventing large downloads from
+    /// starving other clients.
+    ///
+    /// Default is unlimited.
+    pub fn limit_rate(mut self, bytes_per_sec: u64) -> Self {
+        self.limit_rate = Some(bytes_per_sec.max(1));
+        self
+    }
+
+    /// Send the specified number of bytes of each response before applying
+    /// [`limit_rate`](Self::limit_rate).
+    ///
+    /// Default is 0.
+    pub fn limit_rate_after(mut self, bytes: u64) -> Self {
+        self.limit_rate_after = bytes;
+        self
+    }
+
+    /// Cache upstream responses following HTTP caching semantics.
+    ///
+    /// See [`ResponseCache`] for which responses are stored.
+    ///
+    /// Default is disabled.
+    pub fn cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Tee request metadata and bodies to an [`Audit`] sink.
+    ///
+    /// Body bytes are copied as they are forwarded upstream and the record
+    /// is delivered once the request body completes.
+    ///
+    /// Default is disabled.
+    pub fn audit(mut self, audit: Audit) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Restrict the request methods forwarded to the upstream.
+    ///
+    /// Other methods are answered with `405 Method Not Allowed` and an
+    /// `Allow` header. Use [`AllowedMethods::any`] to forward every method.
+    ///
+    /// Default is `GET`, `HEAD`, `POST`, `PUT`, `PATCH`, `DELETE` and `OPTIONS`.
+    pub fn methods(mut self, methods: AllowedMethods) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Answer CORS preflight requests locally using the [`Cors`] policy.
+    ///
+    /// Preflights are never forwarded to the upstream, and responses to
+    /// cross-origin requests from allowed origins receive the matching
+    /// `Access-Control-Allow-*` headers.
+    ///
+    /// Default is disabled.
+    pub fn cors(mut self, cors: Cors) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Apply a [`HeaderPolicy`] to every downstream response.
+    ///
+    /// Headers appended with [`downstream_header`](Self::downstream_header)
+    /// are applied after the policy.
+    ///
+    /// Default is no policy.
+    pub fn header_policy(mut self, policy: HeaderPolicy) -> Self {
+        self.header_policy = Some(policy);
+        self
+    }
+
+    /// Apply the [`HeaderPolicy::hardened`] preset to every downstream response.
+    #[inline]
+    pub fn harden(self) -> Self {
+        self.header_policy(HeaderPolicy::hardened())
+    }
+
+    /// Answer requests with `503 Service Unavailable` while in maintenance.
+    ///
+    /// Switch maintenance mode at runtime using
+    /// [`ControlHandle::set_maintenance`] or a clone of the handle.
+    ///
+    /// Default is disabled.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_common::Maintenance;
+    /// use actix_revproxy::RevProxy;
+    ///
+    /// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+    ///     .maintenance(Maintenance::new().allow_ip("10.0.0.0/8"));
+    /// proxy.control_handle().set_maintenance(true);
+    /// ```
+    pub fn maintenance(self, maintenance: Maintenance) -> Self {
+        self.control.set_maintenance_mode(maintenance);
+        self
+    }
+
+    /// Limit the number and size of request headers forwarded upstream.
+    ///
+    /// Requests exceeding the [`HeaderLimits`] are answered with
+    /// `431 Request Header Fields Too Large`.
+    ///
+    /// Default is unlimited.
+    pub fn request_header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.request_limits = Some(limits);
+        self
+    }
+
+    /// Limit the number and size of upstream response headers.
+    ///
+    /// Responses exceeding the [`HeaderLimits`] are replaced with
+    /// `502 Bad Gateway`.
+    ///
+    /// Default is unlimited.
+    pub fn response_header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.response_limits = Some(limits);
+        self
+    }
+
+    /// Verify upstream response bodies against their announced digests.
+    ///
+    /// Responses failing [`Integrity`] verification are replaced with
+    /// `502 Bad Gateway` when buffered, or aborted when streamed.
+    ///
+    /// Default is disabled.
+    pub fn integrity(mut self, integrity: Integrity) -> Self {
+        self.integrity = Some(integrity);
+        self
+    }
+
+    /// Forward mTLS client certificate details to the upstream as headers.
+    ///
+    /// Details are read from the [`ClientCert`](actix_common::ClientCert)
+    /// attached to the connection. Client supplied copies of the configured
+    /// headers are always stripped.
+    ///
+    /// Default is disabled.
+    pub fn forward_client_cert(mut self, headers: ClientCertHeaders) -> Self {
+        self.client_cert = Some(headers);
+        self
+    }
+
     /// Append a header to include in the downstream response.
     pub fn downstream_header(mut self, name: &str, value: &str) -> Self {
         let Ok(name) = header::HeaderName::from_str(name) else {
@@ -165,11 +665,37 @@ impl ServiceFactory<ServiceRequest> for RevProxy {
 
     fn new_service(&self, _: ()) -> Self::Future {
         let inner = ProxyServiceInner {
-            client: self.client.clone(),
-            resolve: self.resolve.clone(),
+            mount_path: self.mount_path.clone(),
+            client: self.build_client(),
+            control: self.control.clone(),
             change_host: self.change_host,
+            webdav: self.webdav,
             header_up: self.header_up.clone(),
+            header_templates: self.header_templates.clone(),
             header_down: self.header_down.clone(),
+            header_policy: self.header_policy.clone(),
+            request_limits: self.request_limits,
+            maintenance: self.control.maintenance(),
+            response_limits: self.response_limits,
+            integrity: self.integrity,
+            client_cert: self.client_cert.clone(),
+            head_for_get: self.head_for_get,
+            limit_rate: self.limit_rate.map(|rate| RateLimit {
+                rate,
+                burst: self.limit_rate_after,
+            }),
+            audit: self.audit.clone(),
+            cache: self.cache.clone(),
+            routes: self.routes.clone(),
+            fallbacks: self.fallbacks.clone(),
+            hedge: self.hedge,
+            concurrency: self.concurrency.clone(),
+            methods: match self.webdav {
+                true => self.methods.clone().webdav(),
+                false => self.methods.clone(),
+            },
+            cors: self.cors.clone(),
+            error_pages: self.error_pages.clone(),
         };
         Box::pin(async move { Ok(ProxyService(Rc::new(inner))) })
     }
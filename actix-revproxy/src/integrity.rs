@@ -0,0 +1,326 @@
+//! Upstream Response Integrity Verification
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    HttpResponse,
+    body::{self, BodySize, BoxBody, MessageBody},
+    http::{
+        StatusCode,
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+    },
+    web::Bytes,
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::error::Error;
+
+/// Default maximum size of responses buffered for verification
+const DEFAULT_BUFFER_LIMIT: usize = 8 * 1024 * 1024;
+
+/// `Digest` response header (RFC 3230)
+const DIGEST: HeaderName = HeaderName::from_static("digest");
+
+/// `Content-Digest` response header (RFC 9530)
+const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+
+/// `Content-MD5` response header (RFC 1864)
+const CONTENT_MD5: HeaderName = HeaderName::from_static("content-md5");
+
+/// Hash algorithm of a response digest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "md5" => Some(Self::Md5),
+            "sha-256" => Some(Self::Sha256),
+            "sha-512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+}
+
+/// Running hash of a response body
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Md5 => Self::Md5(Md5::new()),
+            Algorithm::Sha256 => Self::Sha256(Sha256::new()),
+            Algorithm::Sha512 => Self::Sha512(Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Md5(hasher) => hasher.finalize().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+            Self::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Digest values announced by the upstream response headers
+#[derive(Default)]
+struct Expected(Vec<(Algorithm, Vec<u8>)>);
+
+impl Expected {
+    /// Collect every supported digest from the response headers
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut expected = Vec::new();
+        let decode = |value: &str| BASE64_STANDARD.decode(value.trim()).ok();
+        for value in headers.get_all(CONTENT_MD5) {
+            if let Some(hash) = value.to_str().ok().and_then(decode) {
+                expected.push((Algorithm::Md5, hash));
+            }
+        }
+        for (name, structured) in [(DIGEST, false), (CONTENT_DIGEST, true)] {
+            let items = headers
+                .get_all(name)
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','));
+            for item in items {
+                let Some((algorithm, value)) = item.split_once('=') else {
+                    continue;
+                };
+                let value = match structured {
+                    true => value.trim().trim_matches(':'),
+                    false => value,
+                };
+                if let (Some(algorithm), Some(hash)) = (Algorithm::parse(algorithm), decode(value))
+                {
+                    expected.push((algorithm, hash));
+                }
+            }
+        }
+        Self(expected)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn hashers(&self) -> Vec<(Algorithm, Hasher)> {
+        let mut hashers: Vec<(Algorithm, Hasher)> = Vec::new();
+        for (algorithm, _) in self.0.iter() {
+            if !hashers.iter().any(|(existing, _)| existing == algorithm) {
+                hashers.push((*algorithm, Hasher::new(*algorithm)));
+            }
+        }
+        hashers
+    }
+
+    /// Check the computed hashes against every announced digest
+    fn matches(&self, computed: &[(Algorithm, Vec<u8>)]) -> bool {
+        self.0.iter().all(|(algorithm, hash)| {
+            computed
+                .iter()
+                .any(|(computed, value)| computed == algorithm && value == hash)
+        })
+    }
+}
+
+/// Verification of upstream response bodies against announced digests
+///
+/// Responses carrying a `Content-MD5`, `Digest` (RFC 3230) or
+/// `Content-Digest` (RFC 9530) header with a supported algorithm
+/// (`md5`, `sha-256`, `sha-512`) are hashed before they reach the client.
+/// Responses with a known length within the buffer limit are buffered and
+/// replaced with `502 Bad Gateway` on mismatch. Larger or chunked
+/// responses are verified while streaming, and the client connection is
+/// aborted if the body does not match, since the status was already sent.
+///
+/// Digests are computed over the body as sent by the upstream, before any
+/// content-coding is removed. `206 Partial Content` responses are never
+/// verified, since their digests describe the full representation.
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{Integrity, RevProxy};
+///
+/// let proxy = RevProxy::new("/", "http://artifacts.internal:8080")
+///     .integrity(Integrity::new().buffer_limit(64 * 1024 * 1024).add_digest(true));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Integrity {
+    buffer_limit: usize,
+    add_digest: bool,
+}
+
+impl Default for Integrity {
+    fn default() -> Self {
+        Self {
+            buffer_limit: DEFAULT_BUFFER_LIMIT,
+            add_digest: false,
+        }
+    }
+}
+
+impl Integrity {
+    /// Construct a new verifier with default settings.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum size of responses buffered to be verified before sending.
+    ///
+    /// Default is 8MiB.
+    pub fn buffer_limit(mut self, bytes: usize) -> Self {
+        self.buffer_limit = bytes;
+        self
+    }
+
+    /// Add a `Digest: sha-256=...` header to buffered responses without one.
+    ///
+    /// Default is disabled.
+    pub fn add_digest(mut self, enable: bool) -> Self {
+        self.add_digest = enable;
+        self
+    }
+
+    /// Verify the upstream response body against its announced digests
+    pub(crate) async fn verify(&self, res: HttpResponse) -> Result<HttpResponse, Error> {
+        // announced digests cover the full representation, not the range
+        if res.status() == StatusCode::PARTIAL_CONTENT {
+            return Ok(res);
+        }
+        let expected = Expected::parse(res.headers());
+        let missing_digest = self.add_digest && !res.headers().contains_key(DIGEST);
+        if expected.is_empty() && !missing_digest {
+            return Ok(res);
+        }
+        // proxied bodies are streamed so the length is taken from the headers
+        let length = res
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        let buffered = match res.body().size() {
+            BodySize::None => return Ok(res),
+            BodySize::Sized(size) => size <= self.buffer_limit as u64,
+            BodySize::Stream => length.is_some_and(|size| size <= self.buffer_limit as u64),
+        };
+        if !buffered {
+            if expected.is_empty() {
+                return Ok(res);
+            }
+            return Ok(res.map_body(|_, body| BoxBody::new(VerifiedBody::new(body, expected))));
+        }
+
+        let (mut res, body) = res.into_parts();
+        let body = match body::to_bytes_limited(body, self.buffer_limit).await {
+            Ok(Ok(body)) => body,
+            Ok(Err(err)) => return Err(Error::Io(std::io::Error::other(err.to_string()))),
+            Err(_) => {
+                return Err(Error::Io(std::io::Error::other(
+                    "body exceeds buffer limit",
+                )));
+            }
+        };
+        let mut hashers = expected.hashers();
+        if missing_digest && !hashers.iter().any(|(alg, _)| *alg == Algorithm::Sha256) {
+            hashers.push((Algorithm::Sha256, Hasher::new(Algorithm::Sha256)));
+        }
+        let computed: Vec<_> = hashers
+            .into_iter()
+            .map(|(algorithm, mut hasher)| {
+                hasher.update(&body);
+                (algorithm, hasher.finalize())
+            })
+            .collect();
+        if !expected.matches(&computed) {
+            return Err(Error::IntegrityMismatch);
+        }
+        if missing_digest
+            && let Some((_, hash)) = computed.iter().find(|(alg, _)| *alg == Algorithm::Sha256)
+        {
+            let value = format!("sha-256={}", BASE64_STANDARD.encode(hash));
+            let value = HeaderValue::from_str(&value)?;
+            res.headers_mut().insert(DIGEST, value);
+        }
+        Ok(res.set_body(BoxBody::new(body)))
+    }
+}
+
+/// Response body verified against the announced digests while streaming
+struct VerifiedBody {
+    body: BoxBody,
+    expected: Expected,
+    hashers: Vec<(Algorithm, Hasher)>,
+}
+
+impl VerifiedBody {
+    fn new(body: BoxBody, expected: Expected) -> Self {
+        let hashers = expected.hashers();
+        Self {
+            body,
+            expected,
+            hashers,
+        }
+    }
+}
+
+impl MessageBody for VerifiedBody {
+    type Error = Box<dyn std::error::Error>;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        match std::task::ready!(Pin::new(&mut this.body).poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                for (_, hasher) in this.hashers.iter_mut() {
+                    hasher.update(&chunk);
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            None => {
+                let computed: Vec<_> = std::mem::take(&mut this.hashers)
+                    .into_iter()
+                    .map(|(algorithm, hasher)| (algorithm, hasher.finalize()))
+                    .collect();
+                match computed.is_empty() || this.expected.matches(&computed) {
+                    true => Poll::Ready(None),
+                    false => {
+                        tracing::error!("streamed upstream response failed integrity verification");
+                        Poll::Ready(Some(Err(Error::IntegrityMismatch.into())))
+                    }
+                }
+            }
+            other => Poll::Ready(other),
+        }
+    }
+}
@@ -1,7 +1,35 @@
+mod audit;
+mod cache;
+mod cert;
+mod connector;
+mod control;
 pub mod error;
+mod events;
 mod factory;
+mod integrity;
+mod limits;
+mod policy;
 pub mod proxy;
+mod redact;
+mod routes;
 mod service;
+mod throttle;
+mod upstream;
+mod webdav;
 
-pub use factory::RevProxy;
+pub use actix_upstream::{Connector, EgressProxy, IpPreference, ProxyProtocol, StreamAddr};
+pub use audit::{Audit, AuditRecord, AuditSink, FileSink};
+pub use awc;
+pub use cache::{CacheKeyFn, ResponseCache};
+pub use cert::ClientCertHeaders;
+pub use connector::UpstreamConnector;
+pub use control::ControlHandle;
+pub use events::{UpstreamEvent, UpstreamEventKind, UpstreamSubscriber};
+pub use factory::{ClientFactory, RevProxy};
+pub use integrity::Integrity;
+pub use limits::HeaderLimits;
+pub use policy::HeaderPolicy;
+pub use redact::{Masker, Redactor};
+pub use routes::RouteTable;
 pub use service::ProxyService;
+pub use upstream::UpstreamStatus;
@@ -0,0 +1,69 @@
+//! Header Count and Size Limits for Proxied Messages
+
+use awc::http::header::HeaderMap;
+
+/// Limits on the number and total size of message headers
+///
+/// The size of a header is the length of its name and value. Requests
+/// exceeding the limits are answered with
+/// `431 Request Header Fields Too Large` without contacting the upstream,
+/// while upstream responses exceeding them fail with `502 Bad Gateway`.
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{HeaderLimits, RevProxy};
+///
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+///     .request_header_limits(HeaderLimits::new().max_count(64).max_size(16 * 1024))
+///     .response_header_limits(HeaderLimits::new().max_size(32 * 1024));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeaderLimits {
+    max_count: Option<usize>,
+    max_size: Option<usize>,
+}
+
+impl HeaderLimits {
+    /// Construct limits which allow any headers.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of headers.
+    ///
+    /// Default is unlimited.
+    pub fn max_count(mut self, count: usize) -> Self {
+        self.max_count = Some(count);
+        self
+    }
+
+    /// Limit the total size of header names and values in bytes.
+    ///
+    /// Default is unlimited.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Check if the headers exceed any of the limits
+    pub(crate) fn exceeded(&self, headers: &HeaderMap) -> bool {
+        if self.max_count.is_some_and(|max| headers.len() > max) {
+            tracing::warn!("header count {} exceeds limit", headers.len());
+            return true;
+        }
+        let Some(max) = self.max_size else {
+            return false;
+        };
+        let size: usize = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        if size > max {
+            tracing::warn!("header size {size} exceeds limit of {max} bytes");
+            return true;
+        }
+        false
+    }
+}
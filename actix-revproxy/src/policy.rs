@@ -0,0 +1,161 @@
+//! Egress Header Policy for Proxied Responses
+
+use std::net::{IpAddr, SocketAddr};
+
+use awc::http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// Default `Strict-Transport-Security` value used by [`HeaderPolicy::hardened`]
+const DEFAULT_HSTS: &str = "max-age=31536000; includeSubDomains";
+
+/// Headers commonly leaking upstream implementation details
+const FINGERPRINT_HEADERS: [&str; 5] = [
+    "server",
+    "x-powered-by",
+    "x-aspnet-version",
+    "x-aspnetmvc-version",
+    "x-generator",
+];
+
+/// Response header allow/strip policy applied to proxied responses
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{HeaderPolicy, RevProxy};
+///
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080")
+///     .header_policy(HeaderPolicy::hardened().strip("X-Debug-Token"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HeaderPolicy {
+    strip: Vec<HeaderName>,
+    allow: Option<Vec<HeaderName>>,
+    strip_internal_ips: bool,
+    defaults: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl HeaderPolicy {
+    /// Construct an empty policy which leaves responses unchanged.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Construct a hardened preset policy.
+    ///
+    /// Strips `Server`, `X-Powered-By` and similar fingerprinting headers,
+    /// strips headers exposing internal ip addresses and adds
+    /// `X-Content-Type-Options: nosniff` and `Strict-Transport-Security`
+    /// when missing.
+    pub fn hardened() -> Self {
+        let policy = FINGERPRINT_HEADERS
+            .iter()
+            .fold(Self::new(), |policy, name| policy.strip(name));
+        policy
+            .strip_internal_ips(true)
+            .default_header("X-Content-Type-Options", "nosniff")
+            .default_header("Strict-Transport-Security", DEFAULT_HSTS)
+    }
+
+    /// Remove the specified header from responses.
+    pub fn strip(mut self, name: &str) -> Self {
+        match HeaderName::try_from(name) {
+            Ok(name) => self.strip.push(name),
+            Err(_) => tracing::warn!("invalid policy header name {name:?}"),
+        }
+        self
+    }
+
+    /// Only pass through the specified header.
+    ///
+    /// Calling this at least once removes every header that was not
+    /// explicitly allowed or added as a default.
+    ///
+    /// Default allows all headers.
+    pub fn allow(mut self, name: &str) -> Self {
+        match HeaderName::try_from(name) {
+            Ok(name) => self.allow.get_or_insert_default().push(name),
+            Err(_) => tracing::warn!("invalid policy header name {name:?}"),
+        }
+        self
+    }
+
+    /// Remove headers containing private, loopback or link-local ip addresses.
+    ///
+    /// Default is disabled.
+    pub fn strip_internal_ips(mut self, strip: bool) -> Self {
+        self.strip_internal_ips = strip;
+        self
+    }
+
+    /// Add the specified header to responses which do not already include it.
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        match (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            (Ok(name), Ok(value)) => self.defaults.push((name, value)),
+            _ => tracing::warn!("invalid policy header {name:?}: {value:?}"),
+        }
+        self
+    }
+
+    /// Apply the policy to the specified response headers.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Some(allow) = self.allow.as_ref() {
+            let names: Vec<_> = headers
+                .keys()
+                .filter(|name| !allow.contains(name))
+                .cloned()
+                .collect();
+            for name in names {
+                headers.remove(name);
+            }
+        }
+        for name in self.strip.iter() {
+            headers.remove(name);
+        }
+        if self.strip_internal_ips {
+            let names: Vec<_> = headers
+                .iter()
+                .filter(|(_, value)| value.to_str().is_ok_and(contains_internal_ip))
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in names {
+                tracing::debug!("stripping header {name:?} exposing internal ip");
+                headers.remove(name);
+            }
+        }
+        for (name, value) in self.defaults.iter() {
+            if !headers.contains_key(name) {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Check if the ip address is only reachable within a private network
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            None => ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local(),
+        },
+    }
+}
+
+/// Check if the header value includes any internal ip address
+fn contains_internal_ip(value: &str) -> bool {
+    value
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '=' | '"' | '/'))
+        .filter(|token| !token.is_empty())
+        .filter_map(|token| {
+            token
+                .parse::<IpAddr>()
+                .ok()
+                .or_else(|| token.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+                .or_else(|| {
+                    let token = token.trim_start_matches('[').split(']').next()?;
+                    token.parse::<IpAddr>().ok()
+                })
+        })
+        .any(is_internal)
+}
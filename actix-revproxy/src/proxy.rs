@@ -1,8 +1,15 @@
 //! Actix-Web Proxy Utilities
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 use actix_web::{
     HttpMessage, HttpResponse,
+    body::{BodySize, MessageBody},
     web::{Bytes, Query},
 };
 use awc::{
@@ -20,6 +27,7 @@ use crate::error::{Error, UriError};
 const HOP_HEADERS: [HeaderName; 9] = [
     header::CONNECTION,
     header::TE,
+    // trailers are neither decoded by awc nor sent by actix-web
     header::TRAILER,
     header::PROXY_AUTHENTICATE,
     header::PROXY_AUTHORIZATION,
@@ -29,6 +37,13 @@ const HOP_HEADERS: [HeaderName; 9] = [
     header::HeaderName::from_static("proxy-connection"),
 ];
 
+const FORWARDED_HEADERS: [HeaderName; 4] = [
+    header::FORWARDED,
+    header::X_FORWARDED_FOR,
+    header::X_FORWARDED_HOST,
+    header::X_FORWARDED_PROTO,
+];
+
 /// Trait for Converting [`actix_web::HttpRequest`] to [`awc::ClientRequest`]
 pub trait ClientReq {
     type Error;
@@ -41,6 +56,31 @@ pub trait ServerRes {
     type Error;
 
     fn server_response(self) -> Result<HttpResponse, Self::Error>;
+
+    /// Convert into a body-less response for a `HEAD` request
+    ///
+    /// The upstream body is never read and `Content-Length` is preserved.
+    fn head_response(self) -> Result<HttpResponse, Self::Error>;
+}
+
+/// Body-less response body advertising the upstream `Content-Length`
+struct HeadBody(u64);
+
+impl MessageBody for HeadBody {
+    type Error = Infallible;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.0)
+    }
+
+    #[inline]
+    fn poll_next(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Poll::Ready(None)
+    }
 }
 
 impl ClientReq for actix_web::HttpRequest {
@@ -77,6 +117,27 @@ where
         remove_hop_headers(http_res.headers_mut());
         Ok(http_res)
     }
+
+    fn head_response(self) -> Result<HttpResponse, Self::Error> {
+        let mut builder = actix_web::HttpResponseBuilder::new(self.status());
+        for header in self.headers() {
+            builder.append_header(header);
+        }
+
+        let length = self
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        let mut http_res = match length {
+            Some(length) => builder.body(HeadBody(length)),
+            None => builder.body(actix_web::body::None::new()),
+        };
+
+        remove_connection_headers(http_res.headers_mut())?;
+        remove_hop_headers(http_res.headers_mut());
+        Ok(http_res)
+    }
 }
 
 type QueryMap = Query<HashMap<String, String>>;
@@ -132,6 +193,14 @@ pub fn remove_connection_headers(headers: &mut HeaderMap) -> Result<(), Error> {
     Ok(())
 }
 
+/// Remove `Forwarded` and `X-Forwarded-*` headers from request
+#[inline]
+pub fn remove_forwarded_headers(headers: &mut HeaderMap) {
+    for header in FORWARDED_HEADERS {
+        headers.remove(header);
+    }
+}
+
 /// Update/Insert forward header with connection information
 ///
 /// # Examples
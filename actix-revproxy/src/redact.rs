@@ -0,0 +1,203 @@
+//! Masking of Sensitive Data Within Audit Records
+
+use std::str::FromStr;
+
+use actix_web::{
+    http::{
+        Uri,
+        header::{self, HeaderName, HeaderValue},
+    },
+    web::Bytes,
+};
+
+use crate::AuditRecord;
+
+/// Replacement value for masked headers and arguments
+const MASK: &str = "***";
+
+/// Rewrites an [`AuditRecord`] before it is delivered to the sink
+pub trait Masker {
+    fn mask(&self, record: &mut AuditRecord);
+}
+
+impl<F> Masker for F
+where
+    F: Fn(&mut AuditRecord),
+{
+    #[inline]
+    fn mask(&self, record: &mut AuditRecord) {
+        self(record)
+    }
+}
+
+/// Default [`Masker`] for credentials, card numbers and configured arguments
+///
+/// Masks the `Authorization`, `Proxy-Authorization` and `Cookie` headers
+/// and any payment card numbers found in the uri or body by default.
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{Audit, FileSink, Redactor, RevProxy};
+///
+/// let audit = Audit::new(FileSink::new("/tmp/audit.log"))
+///     .masker(Redactor::new().header("X-Api-Key").arg("password"));
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080").audit(audit);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Redactor {
+    headers: Vec<HeaderName>,
+    args: Vec<String>,
+    card_numbers: bool,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self {
+            headers: vec![
+                header::AUTHORIZATION,
+                header::PROXY_AUTHORIZATION,
+                header::COOKIE,
+            ],
+            args: Vec::new(),
+            card_numbers: true,
+        }
+    }
+}
+
+impl Redactor {
+    /// Construct the default redactor.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mask the value of the specified header.
+    pub fn header(mut self, name: &str) -> Self {
+        match HeaderName::from_str(name) {
+            Ok(name) => self.headers.push(name),
+            Err(_) => tracing::warn!("invalid redacted header name {name:?}"),
+        }
+        self
+    }
+
+    /// Mask the value of the specified query or urlencoded form argument.
+    pub fn arg(mut self, name: &str) -> Self {
+        self.args.push(name.to_owned());
+        self
+    }
+
+    /// Mask all but the last four digits of payment card numbers.
+    ///
+    /// Default is enabled.
+    pub fn card_numbers(mut self, enable: bool) -> Self {
+        self.card_numbers = enable;
+        self
+    }
+
+    /// Mask configured arguments within an urlencoded string
+    fn mask_args(&self, encoded: &str) -> String {
+        encoded
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if self.args.iter().any(|arg| arg == name) => {
+                    format!("{name}={MASK}")
+                }
+                _ => pair.to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    fn mask_uri(&self, uri: &Uri) -> Option<Uri> {
+        let query = uri.query()?;
+        let mut masked = self.mask_args(query);
+        if self.card_numbers {
+            masked = String::from_utf8_lossy(&mask_card_numbers(masked.as_bytes())).into_owned();
+        }
+        let path = uri.path();
+        Uri::from_str(&format!("{path}?{masked}")).ok()
+    }
+}
+
+impl Masker for Redactor {
+    fn mask(&self, record: &mut AuditRecord) {
+        for name in self.headers.iter() {
+            if record.headers.contains_key(name) {
+                let mask = HeaderValue::from_static(MASK);
+                record.headers.insert(name.clone(), mask);
+            }
+        }
+
+        if let Some(uri) = self.mask_uri(&record.uri) {
+            record.uri = uri;
+        }
+        if let Some(uri) = self.mask_uri(&record.upstream) {
+            record.upstream = uri;
+        }
+
+        let form = record
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|ctype| ctype.starts_with("application/x-www-form-urlencoded"));
+        let mut body = record.body.to_vec();
+        if form && !self.args.is_empty() {
+            body = self.mask_args(&String::from_utf8_lossy(&body)).into_bytes();
+        }
+        if self.card_numbers {
+            body = mask_card_numbers(&body);
+        }
+        record.body = Bytes::from(body);
+    }
+}
+
+/// Validate a payment card number checksum
+fn luhn(digits: &[u8]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, digit)| {
+            let digit = (digit - b'0') as u32;
+            match idx % 2 {
+                1 if digit > 4 => digit * 2 - 9,
+                1 => digit * 2,
+                _ => digit,
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Mask all but the last four digits of card numbers within the data
+///
+/// Numbers may be separated by single spaces or dashes.
+fn mask_card_numbers(data: &[u8]) -> Vec<u8> {
+    let mut masked = data.to_vec();
+    let mut idx = 0;
+    while idx < data.len() {
+        if !data[idx].is_ascii_digit() || (idx > 0 && data[idx - 1].is_ascii_digit()) {
+            idx += 1;
+            continue;
+        }
+        let mut end = idx;
+        let mut positions = Vec::new();
+        while end < data.len() {
+            match data[end] {
+                b'0'..=b'9' => positions.push(end),
+                b' ' | b'-' if data.get(end + 1).is_some_and(u8::is_ascii_digit) => {}
+                _ => break,
+            }
+            end += 1;
+        }
+        let digits: Vec<u8> = positions.iter().map(|pos| data[*pos]).collect();
+        if (13..=19).contains(&digits.len()) && luhn(&digits) {
+            for pos in &positions[..positions.len() - 4] {
+                masked[*pos] = b'*';
+            }
+        }
+        idx = end.max(idx + 1);
+    }
+    masked
+}
@@ -0,0 +1,130 @@
+//! Declarative Route Table Mapping Requests to Upstreams
+
+use std::fmt::Debug;
+
+use actix_common::PathPrefix;
+use actix_web::HttpRequest;
+use awc::http::Uri;
+
+use crate::upstream::Upstreams;
+
+/// Host pattern matched against the request host
+#[derive(Clone, Debug)]
+enum HostPattern {
+    Any,
+    Exact(String),
+    Suffix(String),
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Self {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(suffix) => Self::Suffix(format!(".{suffix}")),
+            None => Self::Exact(pattern),
+        }
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(pattern) => host == pattern,
+            Self::Suffix(suffix) => host.ends_with(suffix.as_str()),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Route {
+    host: HostPattern,
+    prefix: PathPrefix,
+    upstreams: Upstreams,
+}
+
+/// Table of path prefixes and host patterns mapped to upstreams
+///
+/// The most specific matching route is selected for each request: the
+/// longest matching path prefix wins and routes with a host pattern win
+/// over routes without one. Path prefixes are matched against the full
+/// request path on segment boundaries. Requests matching no route are
+/// sent to the proxy default upstream.
+///
+/// # Examples
+///
+/// ```
+/// use actix_revproxy::{RevProxy, RouteTable};
+///
+/// let routes = RouteTable::new()
+///     .prefix("/api", "http://127.0.0.1:8081")
+///     .prefix("/auth", "http://127.0.0.1:8082")
+///     .host("*.static.example.com", "http://127.0.0.1:8083");
+/// let proxy = RevProxy::new("/", "http://127.0.0.1:8080").routes(routes);
+/// ```
+#[derive(Clone, Default)]
+pub struct RouteTable(Vec<Route>);
+
+impl RouteTable {
+    /// Construct an empty route table.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push<U: TryInto<Uri>>(mut self, host: HostPattern, prefix: &str, uri: U) -> Self
+    where
+        U::Error: Debug,
+    {
+        let uri = uri.try_into().expect("invalid route uri");
+        self.0.push(Route {
+            host,
+            prefix: PathPrefix::new(prefix),
+            upstreams: Upstreams::new(uri),
+        });
+        self
+    }
+
+    /// Route requests starting with the path prefix to the upstream.
+    pub fn prefix<U: TryInto<Uri>>(self, prefix: &str, uri: U) -> Self
+    where
+        U::Error: Debug,
+    {
+        self.push(HostPattern::Any, prefix, uri)
+    }
+
+    /// Route requests for the host pattern to the upstream.
+    ///
+    /// Patterns starting with `*.` match any subdomain.
+    pub fn host<U: TryInto<Uri>>(self, host: &str, uri: U) -> Self
+    where
+        U::Error: Debug,
+    {
+        self.push(HostPattern::parse(host), "", uri)
+    }
+
+    /// Route requests for the host pattern starting with the path prefix to the upstream.
+    pub fn host_prefix<U: TryInto<Uri>>(self, host: &str, prefix: &str, uri: U) -> Self
+    where
+        U::Error: Debug,
+    {
+        self.push(HostPattern::parse(host), prefix, uri)
+    }
+
+    /// Select the upstreams of the most specific route matching the request
+    pub(crate) fn select(&self, req: &HttpRequest) -> Option<Upstreams> {
+        let info = req.connection_info();
+        let host = info.host().to_ascii_lowercase();
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if port.parse::<u16>().is_ok() => name.to_owned(),
+            _ => host,
+        };
+        let path = req.uri().path();
+        self.0
+            .iter()
+            .filter(|route| route.host.matches(&host) && route.prefix.matches(path))
+            .max_by_key(|route| {
+                let has_host = !matches!(route.host, HostPattern::Any);
+                (route.prefix.as_str().len(), has_host)
+            })
+            .map(|route| route.upstreams.clone())
+    }
+}
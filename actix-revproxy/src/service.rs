@@ -1,21 +1,72 @@
-use std::{ops::Deref, rc::Rc};
+use std::{
+    ops::Deref,
+    pin::pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
 
+#[cfg(feature = "opentelemetry")]
+use actix_common::telemetry::{SpanKind, SpanScope};
+use actix_common::{
+    AllowedMethods, Concurrency, Cors, ErrorPages, Maintenance, Template, TransactionId,
+    TrustedProxies, X_REQUEST_ID, metrics::Timer,
+};
 use actix_web::{
-    HttpRequest,
+    HttpRequest, HttpResponse,
     body::BoxBody,
-    dev::{self, Service, ServiceRequest, ServiceResponse},
+    dev::{Service, ServiceRequest, ServiceResponse},
     error::Error as ActixError,
 };
 use awc::{
     Client, ClientRequest,
-    http::{Uri, header},
+    error::SendRequestError,
+    http::{Method, StatusCode, Uri, header},
 };
 use futures_core::future::LocalBoxFuture;
+use futures_util::{
+    StreamExt,
+    future::{Either, select},
+};
 
+use crate::cache::Entry;
 use crate::error::Error;
 use crate::proxy::*;
+use crate::throttle::{RateLimit, ThrottledBody};
+use crate::upstream::{Lease, LeasedBody, Upstreams};
+use crate::webdav::{rewrite_destination, rewrite_response};
+use crate::{
+    Audit, ClientCertHeaders, ControlHandle, HeaderLimits, HeaderPolicy, Integrity, ResponseCache,
+    RouteTable,
+};
 
 pub type HeaderVec = Vec<(header::HeaderName, header::HeaderValue)>;
+pub type HeaderTemplates = Vec<(header::HeaderName, Template)>;
+
+/// Check if the request carries a body
+fn has_body(req: &ServiceRequest) -> bool {
+    req.headers().contains_key(header::TRANSFER_ENCODING)
+        || req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .is_some_and(|value| value.as_bytes() != b"0")
+}
+
+/// Check if the request may be duplicated to a second upstream
+fn is_hedgeable(req: &ServiceRequest) -> bool {
+    let idempotent = matches!(
+        *req.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+    );
+    idempotent && !has_body(req)
+}
+
+/// Secondary upstream tried when the response status matches
+#[derive(Clone)]
+pub(crate) struct Fallback {
+    pub(crate) statuses: Vec<u16>,
+    pub(crate) upstreams: Upstreams,
+}
 
 /// Assembled reverse-proxy service
 #[derive(Clone)]
@@ -24,15 +75,28 @@ pub struct ProxyService(pub(crate) Rc<ProxyServiceInner>);
 impl ProxyService {
     /// Convert [`actix_web::HttpRequest`] into [`awc::ClientRequest`]
     #[inline]
-    fn prepare_request(&self, req: &HttpRequest) -> Result<ClientRequest, Error> {
+    fn prepare_request(&self, req: &HttpRequest, upstream: &Uri) -> Result<ClientRequest, Error> {
         let info = req.connection_info().clone();
-        let uri = combine_uri(&self.resolve, req.uri())?;
+        let uri = combine_uri(upstream, req.uri())?;
 
         let mut request = req.client_req(&self.client, uri)?.no_decompress();
+        if let Some(timeout) = self.control.timeout() {
+            request = request.timeout(timeout);
+        }
         if !self.change_host {
             request = request.insert_header((header::HOST, info.host()))
         }
 
+        // discard forwarding headers supplied by untrusted peers
+        if let Some(proxies) = req.app_data::<TrustedProxies>() {
+            let trusted = req
+                .peer_addr()
+                .is_some_and(|addr| proxies.is_trusted(addr.ip()));
+            if !trusted {
+                remove_forwarded_headers(request.headers_mut());
+            }
+        }
+
         if let Some(addr) = req.peer_addr() {
             let ip = addr.ip().to_string();
             let proto = request.get_uri().scheme_str().unwrap_or("http").to_owned();
@@ -42,14 +106,317 @@ impl ProxyService {
             update_forwarded(request.headers_mut(), header::X_FORWARDED_FOR, ip)?;
         }
 
+        if let Some(headers) = self.client_cert.as_ref() {
+            let cert = actix_common::client_cert(req);
+            headers.apply(cert.as_ref(), request.headers_mut());
+        }
+
         for (name, value) in self.header_up.clone() {
             match value.is_empty() {
                 true => request.headers_mut().remove(name),
                 false => request.headers_mut().insert(name, value),
             };
         }
+        for (name, template) in self.header_templates.iter() {
+            let value = template.render(req);
+            if value.is_empty() {
+                request.headers_mut().remove(name);
+                continue;
+            }
+            match header::HeaderValue::from_str(&value) {
+                Ok(value) => {
+                    request.headers_mut().insert(name.clone(), value);
+                }
+                Err(_) => tracing::warn!("invalid templated header value {name:?}: {value:?}"),
+            }
+        }
+
+        if self.webdav {
+            let request_uri = request.get_uri().clone();
+            rewrite_destination(req, request.headers_mut(), upstream, &request_uri)?;
+        }
+
+        #[cfg(feature = "opentelemetry")]
+        actix_common::telemetry::inject_headers(req, request.headers_mut());
         Ok(request)
     }
+
+    /// Forward the request and retry bodiless requests against fallback upstreams
+    async fn forward_fallback(&self, req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        let upstreams = self
+            .routes
+            .as_ref()
+            .and_then(|routes| routes.select(req.request()))
+            .unwrap_or_else(|| self.control.upstream_set());
+        if self.fallbacks.is_empty() || has_body(&req) {
+            return self.forward(req, upstreams).await;
+        }
+
+        let http_req = req.request().clone();
+        let mut res = self.forward(req, upstreams).await?;
+        for fallback in self.fallbacks.iter() {
+            if !fallback.statuses.contains(&res.status().as_u16()) {
+                continue;
+            }
+            tracing::debug!("upstream responded {}. trying fallback", res.status());
+            let req = ServiceRequest::from_request(http_req.clone());
+            res = self.forward(req, fallback.upstreams.clone()).await?;
+        }
+        Ok(res)
+    }
+
+    /// Forward the request to an upstream, hedging idempotent requests
+    /// against a second upstream when the first is slow to respond
+    async fn forward(
+        &self,
+        req: ServiceRequest,
+        upstreams: Upstreams,
+    ) -> Result<ServiceResponse, ActixError> {
+        let lease = upstreams
+            .lease()
+            .ok_or(Error::NoUpstream)
+            .inspect_err(|err| tracing::error!("{err}"))?;
+        let Some(delay) = self.hedge.filter(|_| is_hedgeable(&req)) else {
+            return self.forward_to(req, lease).await;
+        };
+
+        let http_req = req.request().clone();
+        let primary_uri = lease.uri().clone();
+        let primary = pin!(self.forward_to(req, lease));
+        let delay = pin!(actix_web::rt::time::sleep(delay));
+        let primary = match select(primary, delay).await {
+            Either::Left((res, _)) => return res,
+            Either::Right((_, primary)) => primary,
+        };
+        let Some(lease) = upstreams.lease_except(Some(&primary_uri)) else {
+            return primary.await;
+        };
+
+        tracing::debug!("upstream {primary_uri} slow. hedging to {}", lease.uri());
+        let hedge = pin!(self.forward_to(ServiceRequest::from_request(http_req), lease));
+        match select(primary, hedge).await {
+            Either::Left((Ok(res), _)) | Either::Right((Ok(res), _)) => Ok(res),
+            Either::Left((Err(_), other)) => other.await,
+            Either::Right((Err(_), other)) => other.await,
+        }
+    }
+
+    /// Forward the request to the leased upstream and convert the response
+    async fn forward_to(
+        &self,
+        req: ServiceRequest,
+        lease: Lease,
+    ) -> Result<ServiceResponse, ActixError> {
+        let (http_req, payload) = req.into_parts();
+
+        let addr = http_req
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| "<unknown>".to_owned());
+        actix_common::metrics::set_upstream(&http_req, lease.uri().to_string());
+        let mut request = self
+            .prepare_request(&http_req, lease.uri())
+            .inspect_err(|err| tracing::error!("invalid request: {err:?}"))?;
+        let head = http_req.method() == Method::HEAD;
+        let head_for_get = self.head_for_get && http_req.method() == Method::GET;
+        if head_for_get {
+            request = request.method(Method::HEAD);
+        }
+
+        let id = TransactionId::resolve(&http_req, &X_REQUEST_ID);
+        if !request.headers().contains_key(X_REQUEST_ID)
+            && let Some(value) = id.header_value()
+        {
+            request = request.insert_header((X_REQUEST_ID, value));
+        }
+
+        tracing::debug!(
+            "{addr} [{id}] {:?} {:?}",
+            http_req.method(),
+            request.get_uri()
+        );
+        tracing::trace!(?addr, ?request);
+        let request_uri = request.get_uri().clone();
+        let tap = self
+            .audit
+            .as_ref()
+            .map(|audit| audit.start(&http_req, request.get_uri()));
+        let payload = payload.inspect(move |item| {
+            if let (Some(tap), Ok(data)) = (&tap, item) {
+                tap.body(data);
+            }
+        });
+        let response = request
+            .send_stream(payload)
+            .await
+            .map_err(Error::FailedRequest)
+            .inspect_err(|err| {
+                if let Error::FailedRequest(SendRequestError::Connect(_)) = err {
+                    lease.fail();
+                }
+                tracing::error!("request failed: {err:?}")
+            })?;
+        tracing::trace!(?addr, ?response);
+        if let Some(limits) = self.response_limits.as_ref()
+            && limits.exceeded(response.headers())
+        {
+            tracing::error!("{addr} [{id}] upstream response headers exceed limits");
+            return Err(Error::HeadersTooLarge.into());
+        }
+
+        let mut http_res = match head || head_for_get {
+            true => response.head_response(),
+            false => response.server_response(),
+        }
+        .inspect_err(|err| tracing::error!("invalid response: {err:?}"))?;
+        if let Some(integrity) = self.integrity.as_ref()
+            && !(head || head_for_get)
+        {
+            http_res = integrity
+                .verify(http_res)
+                .await
+                .inspect_err(|err| tracing::error!("{addr} [{id}] {err}"))?;
+        }
+        if head_for_get {
+            http_res.headers_mut().remove(header::CONTENT_LENGTH);
+            http_res = http_res.set_body(BoxBody::new(()));
+        }
+        if let Some(policy) = self.header_policy.as_ref() {
+            policy.apply(http_res.headers_mut());
+        }
+        for (name, value) in self.header_down.clone() {
+            match value.is_empty() {
+                true => http_res.headers_mut().remove(name),
+                false => http_res.headers_mut().insert(name, value),
+            };
+        }
+        if self.webdav {
+            rewrite_response(&http_req, http_res.headers_mut(), &request_uri)?;
+        }
+        // hold the upstream lease until the response body is complete
+        let mount = (!head).then(|| self.mount_path.clone());
+        let mut http_res =
+            http_res.map_body(|_, body| BoxBody::new(LeasedBody::new(body, lease, mount)));
+        if let Some(limit) = self.limit_rate {
+            http_res = http_res.map_body(|_, body| BoxBody::new(ThrottledBody::new(body, limit)));
+        }
+        Ok(ServiceResponse::new(http_req, http_res))
+    }
+}
+
+impl ProxyService {
+    /// Serve the request from the response cache when possible
+    async fn respond(&self, req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        let Some(cache) = self.cache.as_ref() else {
+            return self.forward_fallback(req).await;
+        };
+        let Some(key) = cache.key(req.request()) else {
+            return self.forward_fallback(req).await;
+        };
+
+        let entry = cache
+            .lookup(&key, req.request())
+            .filter(|_| !cache.bypass(req.request()));
+        if let Some(entry) = entry.as_ref() {
+            if entry.is_fresh() {
+                let res = cache.serve(entry, req.request());
+                return Ok(req.into_response(res));
+            }
+            if entry.can_serve_revalidating() {
+                if entry.start_revalidation() {
+                    let this = self.clone();
+                    let http_req = req.request().clone();
+                    let (key, entry) = (key.clone(), entry.clone());
+                    actix_web::rt::spawn(
+                        async move { this.revalidate(http_req, key, entry).await },
+                    );
+                }
+                let res = cache.serve(entry, req.request());
+                return Ok(req.into_response(res));
+            }
+        }
+
+        let http_req = req.request().clone();
+        let stale = entry.filter(Entry::can_serve_on_error);
+        match (self.forward_fallback(req).await, stale) {
+            (Ok(res), Some(stale)) if res.status().is_server_error() => {
+                tracing::warn!("upstream error {}. serving stale {key}", res.status());
+                let res = cache.serve_stale(&stale, &http_req);
+                Ok(ServiceResponse::new(http_req, res))
+            }
+            (Err(err), Some(stale)) => {
+                tracing::warn!("upstream error {err}. serving stale {key}");
+                let res = cache.serve_stale(&stale, &http_req);
+                Ok(ServiceResponse::new(http_req, res))
+            }
+            (Ok(res), _) => {
+                let (http_req, http_res) = res.into_parts();
+                let http_res = cache.fill(key, &http_req, http_res);
+                Ok(ServiceResponse::new(http_req, http_res))
+            }
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    /// Respond to the request once a concurrency permit is acquired
+    async fn serve(&self, mut req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        if let Some(res) = self
+            .maintenance
+            .as_ref()
+            .and_then(|maintenance| maintenance.check(req.request()))
+        {
+            return Ok(req.into_response(res));
+        }
+        if let Some(res) = self.cors.as_ref().and_then(|cors| cors.preflight(&req)) {
+            return Ok(req.into_response(res));
+        }
+        if let Some(res) = self.methods.check(&mut req) {
+            return Ok(req.into_response(res));
+        }
+        if let Some(limits) = self.request_limits.as_ref()
+            && limits.exceeded(req.headers())
+        {
+            let res = HttpResponse::build(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE).finish();
+            return Ok(req.into_response(res));
+        }
+        let origin = self
+            .cors
+            .as_ref()
+            .and_then(|_| req.headers().get(header::ORIGIN).cloned());
+        let _permit = match self.concurrency.as_ref() {
+            Some(concurrency) => Some(concurrency.acquire().await?),
+            None => None,
+        };
+        #[cfg(feature = "opentelemetry")]
+        let scope = SpanScope::enter(req.request(), "revproxy", SpanKind::Client);
+        let mut res = self.respond(req).await;
+        #[cfg(feature = "opentelemetry")]
+        scope.exit(&res);
+        if let (Some(cors), Ok(res)) = (self.cors.as_ref(), res.as_mut()) {
+            cors.apply(origin.as_ref(), res.response_mut());
+        }
+        res
+    }
+
+    /// Refresh a stale cache entry in the background
+    async fn revalidate(&self, req: HttpRequest, key: String, entry: Entry) {
+        let Some(cache) = self.cache.as_ref() else {
+            return;
+        };
+        tracing::debug!("revalidating {key}");
+        match self
+            .forward_fallback(ServiceRequest::from_request(req))
+            .await
+        {
+            Ok(res) if !res.status().is_server_error() => {
+                let (req, res) = res.into_parts();
+                cache.store(key, &req, res).await;
+            }
+            Ok(res) => tracing::warn!("failed to revalidate {key}: {}", res.status()),
+            Err(err) => tracing::warn!("failed to revalidate {key}: {err}"),
+        }
+        entry.finish_revalidation();
+    }
 }
 
 impl Deref for ProxyService {
@@ -61,11 +428,31 @@ impl Deref for ProxyService {
 }
 
 pub struct ProxyServiceInner {
+    pub(crate) mount_path: String,
     pub(crate) client: Rc<Client>,
-    pub(crate) resolve: Uri,
+    pub(crate) control: ControlHandle,
     pub(crate) change_host: bool,
+    pub(crate) webdav: bool,
     pub(crate) header_up: HeaderVec,
+    pub(crate) header_templates: HeaderTemplates,
     pub(crate) header_down: HeaderVec,
+    pub(crate) header_policy: Option<HeaderPolicy>,
+    pub(crate) request_limits: Option<HeaderLimits>,
+    pub(crate) maintenance: Option<Maintenance>,
+    pub(crate) response_limits: Option<HeaderLimits>,
+    pub(crate) integrity: Option<Integrity>,
+    pub(crate) client_cert: Option<ClientCertHeaders>,
+    pub(crate) head_for_get: bool,
+    pub(crate) limit_rate: Option<RateLimit>,
+    pub(crate) audit: Option<Audit>,
+    pub(crate) cache: Option<ResponseCache>,
+    pub(crate) routes: Option<RouteTable>,
+    pub(crate) fallbacks: Vec<Fallback>,
+    pub(crate) hedge: Option<Duration>,
+    pub(crate) concurrency: Option<Concurrency>,
+    pub(crate) methods: AllowedMethods,
+    pub(crate) cors: Option<Cors>,
+    pub(crate) error_pages: Option<ErrorPages>,
 }
 
 impl Service<ServiceRequest> for ProxyService {
@@ -73,40 +460,26 @@ impl Service<ServiceRequest> for ProxyService {
     type Error = ActixError;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    dev::always_ready!();
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.concurrency.as_ref() {
+            Some(concurrency) => concurrency.poll_ready(cx).map_err(ActixError::from),
+            None => Poll::Ready(Ok(())),
+        }
+    }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let this = self.clone();
         Box::pin(async move {
-            let (http_req, payload) = req.into_parts();
-
-            let addr = http_req
-                .peer_addr()
-                .map(|addr| addr.to_string())
-                .unwrap_or_else(|| "<unknown>".to_owned());
-            let request = this
-                .prepare_request(&http_req)
-                .inspect_err(|err| tracing::error!("invalid request: {err:?}"))?;
-
-            tracing::debug!("{addr} {:?} {:?}", http_req.method(), request.get_uri());
-            tracing::trace!(?addr, ?request);
-            let response = request
-                .send_stream(payload)
-                .await
-                .map_err(Error::FailedRequest)
-                .inspect_err(|err| tracing::error!("request failed: {err:?}"))?;
-            tracing::trace!(?addr, ?response);
-
-            let mut http_res = response
-                .server_response()
-                .inspect_err(|err| tracing::error!("invalid response: {err:?}"))?;
-            for (name, value) in this.header_down.clone() {
-                match value.is_empty() {
-                    true => http_res.headers_mut().remove(name),
-                    false => http_res.headers_mut().insert(name, value),
-                };
-            }
-            Ok(ServiceResponse::new(http_req, http_res))
+            let timer = Timer::start("revproxy", &this.mount_path, req.request());
+            let res = match this.error_pages.as_ref() {
+                Some(pages) => {
+                    let path = req.path().to_owned();
+                    pages.recover(&path, this.serve(req).await)
+                }
+                None => this.serve(req).await,
+            };
+            timer.finish(&res);
+            res
         })
     }
 }
@@ -0,0 +1,97 @@
+//! Outbound Bandwidth Throttling for Proxied Response Bodies
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::{BodySize, BoxBody, MessageBody},
+    rt::time::{Sleep, sleep},
+    web::Bytes,
+};
+
+/// Bandwidth limit applied to each proxied response body
+///
+/// Similar to nginx `limit_rate` and `limit_rate_after`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RateLimit {
+    pub(crate) rate: u64,
+    pub(crate) burst: u64,
+}
+
+/// Response body throttled to a fixed number of bytes per second
+pub(crate) struct ThrottledBody {
+    body: BoxBody,
+    limit: RateLimit,
+    pending: Bytes,
+    sent: u64,
+    start: Instant,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl ThrottledBody {
+    pub(crate) fn new(body: BoxBody, limit: RateLimit) -> Self {
+        Self {
+            body,
+            limit,
+            pending: Bytes::new(),
+            sent: 0,
+            start: Instant::now(),
+            sleep: None,
+        }
+    }
+
+    /// Number of bytes allowed to be sent right now
+    fn allowed(&self) -> u64 {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let budget = self.limit.burst + (self.limit.rate as f64 * elapsed) as u64;
+        budget.saturating_sub(self.sent)
+    }
+}
+
+impl MessageBody for ThrottledBody {
+    type Error = Box<dyn std::error::Error>;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(sleep) = this.sleep.as_mut() {
+                std::task::ready!(sleep.as_mut().poll(cx));
+                this.sleep = None;
+            }
+
+            if this.pending.is_empty() {
+                match std::task::ready!(Pin::new(&mut this.body).poll_next(cx)) {
+                    Some(Ok(chunk)) => this.pending = chunk,
+                    other => return Poll::Ready(other),
+                }
+                continue;
+            }
+
+            // send slices of roughly 100ms worth of bandwidth
+            let slice = (this.limit.rate / 10).max(1);
+            let want = (this.pending.len() as u64).min(slice);
+            let allowed = this.allowed();
+            if allowed < want {
+                let wait = (want - allowed) as f64 / this.limit.rate as f64;
+                this.sleep = Some(Box::pin(sleep(Duration::from_secs_f64(wait))));
+                continue;
+            }
+
+            let size = allowed.min(this.pending.len() as u64);
+            this.sent += size;
+            return Poll::Ready(Some(Ok(this.pending.split_to(size as usize))));
+        }
+    }
+}
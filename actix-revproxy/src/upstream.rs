@@ -0,0 +1,441 @@
+//! Upstream Set with Passive Health, Slow-Start and Connection Draining
+
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_common::metrics::{self, CLIENT_ABORTS, Labels};
+use actix_web::{
+    body::{BodySize, BoxBody, MessageBody},
+    web::Bytes,
+};
+use awc::http::Uri;
+
+use crate::events::{self, Subscribers, UpstreamEvent, UpstreamEventKind, UpstreamSubscriber};
+
+/// Default duration an upstream is avoided after a failed request
+const DEFAULT_FAIL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Minimum traffic share of an upstream at the start of a slow-start ramp
+const MIN_SLOW_START_WEIGHT: f64 = 0.05;
+
+struct Backend {
+    uri: Uri,
+    in_flight: usize,
+    aborted: u64,
+    failed_at: Option<Instant>,
+    recovered_at: Option<Instant>,
+    draining: bool,
+}
+
+impl Backend {
+    fn new(uri: Uri) -> Self {
+        Self {
+            uri,
+            in_flight: 0,
+            aborted: 0,
+            failed_at: None,
+            recovered_at: None,
+            draining: false,
+        }
+    }
+
+    /// Traffic weight in `(0, 1]` accounting for any active slow-start ramp
+    fn weight(&self, slow_start: Option<Duration>) -> f64 {
+        match (slow_start, self.recovered_at) {
+            (Some(ramp), Some(recovered)) if recovered.elapsed() < ramp => {
+                let progress = recovered.elapsed().as_secs_f64() / ramp.as_secs_f64();
+                progress.max(MIN_SLOW_START_WEIGHT)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+struct State {
+    backends: Vec<Backend>,
+    slow_start: Option<Duration>,
+    fail_timeout: Duration,
+    next: usize,
+    subscribers: Subscribers,
+}
+
+/// Build an event of the specified kind
+#[inline]
+fn event(kind: UpstreamEventKind, uri: &Uri) -> UpstreamEvent {
+    UpstreamEvent {
+        kind,
+        uri: uri.clone(),
+    }
+}
+
+impl State {
+    /// Return avoided upstreams whose fail timeout has elapsed to the rotation
+    fn recover(&mut self, events: &mut Vec<UpstreamEvent>) {
+        let fail_timeout = self.fail_timeout;
+        for backend in self.backends.iter_mut() {
+            if backend
+                .failed_at
+                .is_some_and(|failed| failed.elapsed() >= fail_timeout)
+            {
+                tracing::info!("upstream {} recovered", backend.uri);
+                backend.failed_at = None;
+                backend.recovered_at = Some(Instant::now());
+                events.push(event(UpstreamEventKind::Recovered, &backend.uri));
+            }
+        }
+    }
+
+    /// Avoid the upstream for the fail timeout, opening the circuit of the
+    /// set once no healthy upstream remains
+    fn mark_down(&mut self, uri: &Uri) -> Vec<UpstreamEvent> {
+        let available = |state: &Self| {
+            state
+                .backends
+                .iter()
+                .any(|backend| !backend.draining && backend.failed_at.is_none())
+        };
+        let was_available = available(self);
+        let Some(backend) = self.backends.iter_mut().find(|b| b.uri == *uri) else {
+            return Vec::new();
+        };
+        backend.failed_at = Some(Instant::now());
+        backend.recovered_at = None;
+        let mut events = vec![event(UpstreamEventKind::MarkedDown, uri)];
+        if was_available && !available(self) {
+            tracing::warn!("no healthy upstream left after {uri} failed");
+            events.push(event(UpstreamEventKind::CircuitOpened, uri));
+        }
+        events
+    }
+
+    /// Remove draining upstreams without any remaining in-flight requests
+    fn sweep(&mut self, events: &mut Vec<UpstreamEvent>) {
+        self.backends.retain(|backend| {
+            let done = backend.draining && backend.in_flight == 0;
+            if done {
+                tracing::info!("upstream {} drained", backend.uri);
+                events.push(event(UpstreamEventKind::Removed, &backend.uri));
+            }
+            !done
+        });
+    }
+
+    /// Release the lock and deliver the events to every subscriber
+    fn publish(state: MutexGuard<'_, Self>, events: Vec<UpstreamEvent>) {
+        if events.is_empty() || state.subscribers.is_empty() {
+            return;
+        }
+        let subscribers = state.subscribers.clone();
+        drop(state);
+        events::publish(&subscribers, &events);
+    }
+}
+
+/// Snapshot of a single upstream within the set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpstreamStatus {
+    /// Upstream resolution uri.
+    pub uri: Uri,
+    /// Number of requests currently forwarded to the upstream.
+    pub in_flight: usize,
+    /// Number of responses abandoned by clients before completion.
+    pub aborted: u64,
+    /// Whether the upstream is outside of its fail timeout.
+    pub healthy: bool,
+    /// Whether the upstream is draining before removal.
+    pub draining: bool,
+}
+
+/// Shared set of upstreams selected for each proxied request
+///
+/// Upstreams are chosen by the fewest in-flight requests relative to their
+/// weight. Failed upstreams are avoided for the fail timeout and ramped back
+/// up over the slow-start window once they recover. Removed upstreams stop
+/// receiving requests and are dropped once their in-flight requests finish.
+#[derive(Clone)]
+pub(crate) struct Upstreams(Arc<Mutex<State>>);
+
+impl Upstreams {
+    pub(crate) fn new(uri: Uri) -> Self {
+        Self(Arc::new(Mutex::new(State {
+            backends: vec![Backend::new(uri)],
+            slow_start: None,
+            fail_timeout: DEFAULT_FAIL_TIMEOUT,
+            next: 0,
+            subscribers: Vec::new(),
+        })))
+    }
+
+    #[inline]
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.0.lock().expect("poisoned lock")
+    }
+
+    pub(crate) fn set_slow_start(&self, slow_start: Duration) {
+        self.state().slow_start = Some(slow_start);
+    }
+
+    pub(crate) fn set_fail_timeout(&self, fail_timeout: Duration) {
+        self.state().fail_timeout = fail_timeout;
+    }
+
+    pub(crate) fn subscribe(&self, subscriber: Arc<dyn UpstreamSubscriber>) {
+        self.state().subscribers.push(subscriber);
+    }
+
+    /// Active upstreams in order of declaration
+    pub(crate) fn uris(&self) -> Vec<Uri> {
+        self.state()
+            .backends
+            .iter()
+            .filter(|backend| !backend.draining)
+            .map(|backend| backend.uri.clone())
+            .collect()
+    }
+
+    /// Health and load of every upstream including draining ones
+    pub(crate) fn status(&self) -> Vec<UpstreamStatus> {
+        let state = self.state();
+        state
+            .backends
+            .iter()
+            .map(|backend| UpstreamStatus {
+                uri: backend.uri.clone(),
+                in_flight: backend.in_flight,
+                aborted: backend.aborted,
+                healthy: backend
+                    .failed_at
+                    .is_none_or(|failed| failed.elapsed() >= state.fail_timeout),
+                draining: backend.draining,
+            })
+            .collect()
+    }
+
+    /// Add an upstream or cancel draining of an existing one
+    pub(crate) fn add(&self, uri: Uri) {
+        let mut state = self.state();
+        let added = match state.backends.iter_mut().find(|backend| backend.uri == uri) {
+            Some(backend) => std::mem::replace(&mut backend.draining, false),
+            None => {
+                state.backends.push(Backend::new(uri.clone()));
+                true
+            }
+        };
+        let events = match added {
+            true => vec![event(UpstreamEventKind::Added, &uri)],
+            false => Vec::new(),
+        };
+        State::publish(state, events);
+    }
+
+    /// Drain an upstream and remove it once idle
+    pub(crate) fn remove(&self, uri: &Uri) {
+        let mut state = self.state();
+        let mut events = Vec::new();
+        if let Some(backend) = state.backends.iter_mut().find(|b| b.uri == *uri)
+            && !backend.draining
+        {
+            tracing::info!("draining upstream {uri}");
+            backend.draining = true;
+            events.push(event(UpstreamEventKind::Draining, uri));
+        }
+        state.sweep(&mut events);
+        State::publish(state, events);
+    }
+
+    /// Mark an upstream down for the fail timeout or return it to the rotation
+    pub(crate) fn set_health(&self, uri: &Uri, healthy: bool) {
+        let mut state = self.state();
+        let Some(backend) = state.backends.iter_mut().find(|b| b.uri == *uri) else {
+            return;
+        };
+        let events = match (healthy, backend.failed_at.is_some()) {
+            (true, true) => {
+                tracing::info!("upstream {uri} marked up");
+                backend.failed_at = None;
+                backend.recovered_at = Some(Instant::now());
+                vec![event(UpstreamEventKind::Recovered, uri)]
+            }
+            (false, _) => {
+                tracing::info!("upstream {uri} marked down");
+                state.mark_down(uri)
+            }
+            (true, false) => return,
+        };
+        State::publish(state, events);
+    }
+
+    /// Replace the set of upstreams, draining any no longer present
+    pub(crate) fn replace(&self, uris: Vec<Uri>) {
+        let current = self.uris();
+        for uri in current.iter().filter(|uri| !uris.contains(uri)) {
+            self.remove(uri);
+        }
+        for uri in uris {
+            self.add(uri);
+        }
+    }
+
+    /// Select an upstream for a single request
+    #[inline]
+    pub(crate) fn lease(&self) -> Option<Lease> {
+        self.lease_except(None)
+    }
+
+    /// Select an upstream for a single request other than the excluded one
+    pub(crate) fn lease_except(&self, exclude: Option<&Uri>) -> Option<Lease> {
+        let mut state = self.state();
+        let mut events = Vec::new();
+        state.recover(&mut events);
+
+        let slow_start = state.slow_start;
+        let count = state.backends.len();
+        let start = state.next;
+        state.next = state.next.wrapping_add(1);
+
+        // prefer healthy upstreams but fall back to failed ones over none
+        let candidates = (0..count)
+            .map(|n| (start + n) % count)
+            .filter(|i| !state.backends[*i].draining)
+            .filter(|i| exclude.is_none_or(|uri| state.backends[*i].uri != *uri));
+        let score = |i: &usize| {
+            let backend = &state.backends[*i];
+            let load = (backend.in_flight + 1) as f64 / backend.weight(slow_start);
+            (backend.failed_at.is_some(), load)
+        };
+        let Some(index) =
+            candidates.min_by(|a, b| score(a).partial_cmp(&score(b)).expect("nan score"))
+        else {
+            State::publish(state, events);
+            return None;
+        };
+
+        let backend = &mut state.backends[index];
+        backend.in_flight += 1;
+        let uri = backend.uri.clone();
+        events.push(event(UpstreamEventKind::Selected, &uri));
+        State::publish(state, events);
+        Some(Lease {
+            uri,
+            upstreams: self.clone(),
+        })
+    }
+}
+
+/// Upstream selected for a single in-flight request
+pub(crate) struct Lease {
+    uri: Uri,
+    upstreams: Upstreams,
+}
+
+impl Lease {
+    #[inline]
+    pub(crate) fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Avoid the upstream for the fail timeout
+    pub(crate) fn fail(&self) {
+        let mut state = self.upstreams.state();
+        if !state.backends.iter().any(|b| b.uri == self.uri) {
+            return;
+        }
+        tracing::warn!("upstream {} failed", self.uri);
+        let events = state.mark_down(&self.uri);
+        State::publish(state, events);
+    }
+
+    /// Count a response abandoned by the client
+    fn abort(&self) {
+        let mut state = self.upstreams.state();
+        if let Some(backend) = state.backends.iter_mut().find(|b| b.uri == self.uri) {
+            backend.aborted += 1;
+        }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let mut state = self.upstreams.state();
+        if let Some(backend) = state.backends.iter_mut().find(|b| b.uri == self.uri) {
+            backend.in_flight = backend.in_flight.saturating_sub(1);
+        }
+        let mut events = Vec::new();
+        state.sweep(&mut events);
+        State::publish(state, events);
+    }
+}
+
+/// Response body holding an upstream [`Lease`] until fully streamed
+///
+/// Dropping the body before it completed, such as when the client
+/// disconnects mid-response, drops the upstream stream immediately so no
+/// more of the upstream body is read, and counts the abandoned response
+/// against the upstream and the [`CLIENT_ABORTS`] metric.
+pub(crate) struct LeasedBody {
+    body: BoxBody,
+    lease: Lease,
+    mount: Option<String>,
+    done: bool,
+}
+
+impl LeasedBody {
+    /// Hold the lease until the body completes
+    ///
+    /// Abandoned bodies are only detected when the mount path is given.
+    pub(crate) fn new(body: BoxBody, lease: Lease, mount: Option<String>) -> Self {
+        let done = matches!(body.size(), BodySize::None | BodySize::Sized(0));
+        Self {
+            body,
+            lease,
+            mount,
+            done,
+        }
+    }
+}
+
+impl Drop for LeasedBody {
+    fn drop(&mut self) {
+        let Some(mount) = self.mount.as_deref().filter(|_| !self.done) else {
+            return;
+        };
+        // closes the unfinished upstream connection rather than releasing
+        // it back to the pool
+        drop(std::mem::replace(&mut self.body, BoxBody::new(())));
+        tracing::debug!("client abandoned response from {}", self.lease.uri);
+        self.lease.abort();
+        let upstream = self.lease.uri.to_string();
+        let labels = Labels {
+            service: "revproxy",
+            mount,
+            upstream: &upstream,
+        };
+        metrics::increment(CLIENT_ABORTS, &labels);
+    }
+}
+
+impl MessageBody for LeasedBody {
+    type Error = Box<dyn std::error::Error>;
+
+    #[inline]
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    #[inline]
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        let item = std::task::ready!(Pin::new(&mut this.body).poll_next(cx));
+        if !matches!(item, Some(Ok(_))) {
+            this.done = true;
+        }
+        Poll::Ready(item)
+    }
+}
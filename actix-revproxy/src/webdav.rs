@@ -0,0 +1,106 @@
+//! WebDAV Destination and Location Rewriting
+
+use actix_web::{
+    HttpRequest,
+    http::{
+        Uri,
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+    },
+};
+
+use crate::error::Error;
+use crate::proxy::combine_uri;
+
+/// `Destination` request header of `COPY` and `MOVE` (RFC 4918 section 10.3)
+pub(crate) const DESTINATION: HeaderName = HeaderName::from_static("destination");
+
+/// Scheme and authority of a uri
+fn origin(uri: &Uri) -> Option<String> {
+    let authority = uri.authority()?;
+    Some(format!(
+        "{}://{authority}",
+        uri.scheme_str().unwrap_or("http")
+    ))
+}
+
+/// Rewrite a `Destination` addressing the public host to the upstream
+///
+/// The destination is resolved against the upstream the same way as the
+/// request uri and addressed to the host the upstream receives, so
+/// servers comparing it against the request host accept the request.
+/// Destinations for other hosts are left unchanged.
+pub(crate) fn rewrite_destination(
+    req: &HttpRequest,
+    headers: &mut HeaderMap,
+    upstream: &Uri,
+    request_uri: &Uri,
+) -> Result<(), Error> {
+    let Some(value) = headers.get(DESTINATION) else {
+        return Ok(());
+    };
+    let Ok(destination) = value.to_str()?.parse::<Uri>() else {
+        tracing::debug!("invalid webdav destination {value:?}");
+        return Ok(());
+    };
+    let info = req.connection_info();
+    if destination
+        .authority()
+        .is_some_and(|authority| !authority.as_str().eq_ignore_ascii_case(info.host()))
+    {
+        tracing::debug!("leaving cross-server webdav destination {destination}");
+        return Ok(());
+    }
+
+    let combined = combine_uri(upstream, &destination)?;
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .map(str::to_owned)
+        .or_else(|| {
+            request_uri
+                .authority()
+                .map(|authority| authority.to_string())
+        })
+        .unwrap_or_else(|| info.host().to_owned());
+    let scheme = request_uri.scheme_str().unwrap_or("http");
+    let path = combined.path_and_query().map_or("/", |path| path.as_str());
+    let value = format!("{scheme}://{host}{path}");
+    tracing::trace!("rewrote webdav destination {destination} to {value}");
+    headers.insert(DESTINATION, HeaderValue::from_str(&value)?);
+    Ok(())
+}
+
+/// Rewrite `Location` and `Destination` response headers naming the
+/// upstream origin to the public origin of the request
+pub(crate) fn rewrite_response(
+    req: &HttpRequest,
+    headers: &mut HeaderMap,
+    request_uri: &Uri,
+) -> Result<(), Error> {
+    let Some(upstream) = origin(request_uri) else {
+        return Ok(());
+    };
+    let public = {
+        let info = req.connection_info();
+        format!("{}://{}", info.scheme(), info.host())
+    };
+    for name in [header::LOCATION, DESTINATION] {
+        let Some(value) = headers.get(&name).and_then(|value| value.to_str().ok()) else {
+            continue;
+        };
+        let Some(rest) = strip_origin(value, &upstream) else {
+            continue;
+        };
+        let value = HeaderValue::from_str(&format!("{public}{rest}"))?;
+        headers.insert(name, value);
+    }
+    Ok(())
+}
+
+/// Strip the origin from the uri when it matches case-insensitively
+fn strip_origin<'a>(uri: &'a str, origin: &str) -> Option<&'a str> {
+    let prefix = uri.get(..origin.len())?;
+    let rest = &uri[origin.len()..];
+    let boundary = rest.is_empty() || rest.starts_with(['/', '?', '#']);
+    (prefix.eq_ignore_ascii_case(origin) && boundary).then_some(rest)
+}
@@ -0,0 +1,83 @@
+use std::{
+    future::poll_fn,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use actix_revproxy::RevProxy;
+use actix_web::{
+    App, HttpResponse,
+    body::MessageBody,
+    rt::time::sleep,
+    test::{self, TestRequest},
+    web::{self, Bytes},
+};
+
+mod common;
+
+/// Flag raised once the upstream response stream is dropped
+struct Cancelled(Arc<AtomicBool>);
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Start a local upstream streaming an endless response body
+fn start_upstream(cancelled: Arc<AtomicBool>) -> std::net::SocketAddr {
+    common::spawn_upstream(move || {
+        let cancelled = cancelled.clone();
+        App::new().route(
+            "/stream",
+            web::get().to(move || {
+                let guard = Cancelled(cancelled.clone());
+                let stream = futures_util::stream::unfold(guard, |guard| async move {
+                    sleep(Duration::from_millis(10)).await;
+                    Some((Ok::<_, std::io::Error>(Bytes::from_static(b"chunk")), guard))
+                });
+                async move { HttpResponse::Ok().streaming(stream) }
+            }),
+        )
+    })
+}
+
+#[actix_web::test]
+async fn client_abort() {
+    common::setup();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let addr = start_upstream(cancelled.clone());
+
+    let proxy = RevProxy::new("/", format!("http://{addr}"));
+    let control = proxy.control_handle();
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::with_uri("/stream").to_request();
+    let res = test::call_service(&srv, req).await;
+    let mut body = res.into_body();
+    let chunk = poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await;
+    assert_eq!(
+        chunk.expect("missing chunk").ok(),
+        Some(Bytes::from_static(b"chunk"))
+    );
+    assert!(!cancelled.load(Ordering::SeqCst));
+
+    // the client disconnects mid-response
+    drop(body);
+    let status = control.upstream_status();
+    assert_eq!(status[0].aborted, 1);
+    assert_eq!(status[0].in_flight, 0);
+
+    // the upstream stops streaming once the connection is closed
+    for _ in 0..100 {
+        if cancelled.load(Ordering::SeqCst) {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert!(cancelled.load(Ordering::SeqCst));
+}
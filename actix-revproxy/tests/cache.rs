@@ -0,0 +1,119 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use actix_revproxy::{ResponseCache, RevProxy};
+use actix_web::{
+    App, HttpResponse,
+    http::{StatusCode, header},
+    test::{self, TestRequest},
+    web,
+};
+
+mod common;
+
+/// Start a local upstream counting every request it answers
+fn start_upstream() -> std::net::SocketAddr {
+    let hits = Arc::new(AtomicUsize::new(0));
+    common::spawn_upstream(move || {
+        let hits = hits.clone();
+        App::new().route(
+            "/page",
+            web::get().to(move || {
+                let hit = hits.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    HttpResponse::Ok()
+                        .insert_header(("Cache-Control", "max-age=60"))
+                        .insert_header(("Vary", "Accept-Language"))
+                        .body(hit.to_string())
+                }
+            }),
+        )
+    })
+}
+
+/// Request for `/page` on the host selecting the language variant
+fn page(host: &str, lang: &str) -> TestRequest {
+    TestRequest::with_uri("/page")
+        .insert_header(("Host", host))
+        .insert_header(("Accept-Language", lang))
+}
+
+#[actix_web::test]
+async fn cache_hosts() {
+    common::setup();
+    let addr = start_upstream();
+
+    let cache = ResponseCache::new().max_entries(3);
+    let proxy = RevProxy::new("/", format!("http://{addr}")).cache(cache.clone());
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let fetch = async |host: &str, lang: &str| {
+        let req = page(host, lang).to_request();
+        test::call_and_read_body(&srv, req).await
+    };
+
+    // identical paths on different hosts never share an entry
+    assert_eq!(fetch("a.example.com", "en").await, "1");
+    assert_eq!(fetch("b.example.com", "en").await, "2");
+    assert_eq!(fetch("a.example.com", "en").await, "1");
+    assert_eq!(fetch("b.example.com", "en").await, "2");
+
+    // variants are stored per host
+    assert_eq!(fetch("a.example.com", "de").await, "3");
+    assert_eq!(fetch("b.example.com", "en").await, "2");
+    assert_eq!(cache.len(), 3);
+
+    // eviction drops the oldest variant only
+    assert_eq!(fetch("b.example.com", "de").await, "4");
+    assert_eq!(cache.len(), 3);
+    assert_eq!(fetch("a.example.com", "de").await, "3");
+    assert_eq!(fetch("a.example.com", "en").await, "5");
+
+    assert_eq!(cache.purge_prefix("http://b.example.com/"), 1);
+    assert_eq!(fetch("a.example.com", "de").await, "3");
+    assert_eq!(fetch("b.example.com", "de").await, "6");
+}
+
+#[actix_web::test]
+async fn cache_etag() {
+    common::setup();
+    let addr = start_upstream();
+
+    let cache = ResponseCache::new().etag(true);
+    let proxy = RevProxy::new("/", format!("http://{addr}")).cache(cache);
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    // the validator is generated once the response was cached
+    let res = test::call_service(&srv, page("example.com", "en").to_request()).await;
+    assert!(res.headers().get(header::ETAG).is_none());
+    assert_eq!(test::read_body(res).await, "1");
+
+    let res = test::call_service(&srv, page("example.com", "en").to_request()).await;
+    let etag = res
+        .headers()
+        .get(header::ETAG)
+        .expect("missing etag")
+        .clone();
+    assert!(etag.to_str().unwrap().starts_with("W/\""));
+    assert_eq!(test::read_body(res).await, "1");
+
+    let req = page("example.com", "en")
+        .insert_header((header::IF_NONE_MATCH, etag.clone()))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers().get(header::ETAG), Some(&etag));
+    assert_eq!(
+        res.headers().get(header::CACHE_CONTROL).unwrap(),
+        "max-age=60"
+    );
+    assert!(test::read_body(res).await.is_empty());
+
+    let req = page("example.com", "en")
+        .insert_header((header::IF_NONE_MATCH, "W/\"0-0\""))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "1");
+}
@@ -1,8 +1,12 @@
-use std::sync::Once;
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::Once,
+};
 
 use actix_web::{
-    body::{self, BoxBody},
-    dev::ServiceResponse,
+    App, Error, HttpServer,
+    body::{self, BoxBody, MessageBody},
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
 };
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
@@ -29,3 +33,28 @@ pub async fn get_body(res: ServiceResponse<BoxBody>) -> String {
         .expect("invalid body")
         .to_string()
 }
+
+/// Spawn an upstream server running the app on a random local port
+#[allow(dead_code)]
+pub fn spawn_upstream<F, T, B>(factory: F) -> SocketAddr
+where
+    F: Fn() -> App<T> + Send + Clone + 'static,
+    T: ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<B>,
+            Error = Error,
+            InitError = (),
+        > + 'static,
+    B: MessageBody + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = HttpServer::new(factory)
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+    actix_web::rt::spawn(server);
+    addr
+}
@@ -0,0 +1,45 @@
+use std::sync::mpsc;
+
+use actix_revproxy::{RevProxy, UpstreamEvent, UpstreamEventKind};
+use actix_web::{
+    App,
+    test::{self, TestRequest},
+};
+
+mod common;
+
+#[actix_web::test]
+async fn circuit_opened() {
+    common::setup();
+
+    // nothing listens on either address so every request fails to connect
+    let proxy = RevProxy::new("/", "http://127.0.0.1:1").upstream("http://127.0.0.1:2");
+    let control = proxy.control_handle();
+    let (tx, rx) = mpsc::channel();
+    control.subscribe(move |event: &UpstreamEvent| {
+        let _ = tx.send(event.kind);
+    });
+
+    control
+        .set_upstream_health("http://127.0.0.1:1", false)
+        .unwrap();
+    let events: Vec<_> = rx.try_iter().collect();
+    assert_eq!(events, vec![UpstreamEventKind::MarkedDown]);
+
+    // the last healthy upstream failing opens the circuit
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::with_uri("/").to_request();
+    let _ = test::try_call_service(&srv, req).await;
+    let events: Vec<_> = rx.try_iter().collect();
+    assert!(events.ends_with(&[
+        UpstreamEventKind::MarkedDown,
+        UpstreamEventKind::CircuitOpened,
+    ]));
+
+    // failures while the circuit is open are not reported again
+    let req = TestRequest::with_uri("/").to_request();
+    let _ = test::try_call_service(&srv, req).await;
+    let events: Vec<_> = rx.try_iter().collect();
+    assert!(events.contains(&UpstreamEventKind::MarkedDown));
+    assert!(!events.contains(&UpstreamEventKind::CircuitOpened));
+}
@@ -0,0 +1,93 @@
+use actix_revproxy::{HeaderLimits, RevProxy};
+use actix_web::{
+    App, HttpRequest, HttpResponse,
+    http::StatusCode,
+    test::{self, TestRequest},
+    web,
+};
+
+mod common;
+
+/// Start a local upstream echoing the templated request headers
+fn start_upstream() -> std::net::SocketAddr {
+    common::spawn_upstream(|| {
+        App::new().default_service(web::to(|req: HttpRequest| async move {
+            let header = |name| {
+                req.headers()
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("-")
+                    .to_owned()
+            };
+            HttpResponse::Ok().body(format!(
+                "{} {} {}",
+                header("x-original-uri"),
+                header("x-real-ip"),
+                header("x-forwarded-method"),
+            ))
+        }))
+    })
+}
+
+#[actix_web::test]
+async fn templated_headers() {
+    common::setup();
+    let addr = start_upstream();
+
+    let proxy = RevProxy::new("/app", format!("http://{addr}"))
+        .set_request_header("X-Original-URI", "{uri}")
+        .set_request_header("X-Real-IP", "{client_ip}")
+        .set_request_header("X-Forwarded-Method", "{method}");
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::with_uri("/app/index.html?page=2")
+        .peer_addr("192.0.2.7:5000".parse().unwrap())
+        .insert_header(("X-Real-IP", "203.0.113.1"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert!(res.status().is_success());
+    let body = test::read_body(res).await;
+    assert_eq!(body, "/app/index.html?page=2 192.0.2.7 GET");
+}
+
+#[actix_web::test]
+async fn header_limits() {
+    common::setup();
+    let addr = start_upstream();
+
+    let limits = HeaderLimits::new().max_count(2).max_size(64);
+    let proxy = RevProxy::new("/", format!("http://{addr}")).request_header_limits(limits);
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-Real-IP", "203.0.113.1"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // oversized requests are rejected without contacting the upstream
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-One", "1"))
+        .insert_header(("X-Two", "2"))
+        .insert_header(("X-Three", "3"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-Large", "x".repeat(64)))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+    // oversized upstream responses fail as a bad gateway
+    let limits = HeaderLimits::new().max_count(1);
+    let proxy = RevProxy::new("/", format!("http://{addr}")).response_header_limits(limits);
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::with_uri("/").to_request();
+    let status = match test::try_call_service(&srv, req).await {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
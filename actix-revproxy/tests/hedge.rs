@@ -0,0 +1,62 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use actix_revproxy::RevProxy;
+use actix_web::{
+    App, HttpResponse,
+    rt::time::sleep,
+    test::{self, TestRequest},
+    web,
+};
+
+mod common;
+
+/// Start a local upstream where every other request is slow to respond
+fn start_upstream(hits: Arc<AtomicUsize>) -> std::net::SocketAddr {
+    common::spawn_upstream(move || {
+        let hits = hits.clone();
+        App::new().default_service(web::to(move || {
+            let hit = hits.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if hit % 2 == 1 {
+                    sleep(Duration::from_millis(500)).await;
+                    return HttpResponse::Ok().body("slow");
+                }
+                HttpResponse::Ok().body("fast")
+            }
+        }))
+    })
+}
+
+#[actix_web::test]
+async fn hedge_slow_requests() {
+    common::setup();
+    let hits = Arc::new(AtomicUsize::new(0));
+    let first = start_upstream(hits.clone());
+    let second = start_upstream(hits.clone());
+
+    let proxy = RevProxy::new("/", format!("http://{first}"))
+        .upstream(format!("http://{second}"))
+        .hedge(Duration::from_millis(50));
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    // the duplicate sent to the other upstream answers first
+    let start = Instant::now();
+    let req = TestRequest::with_uri("/page").to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "fast");
+    assert!(start.elapsed() < Duration::from_millis(500));
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    // requests with a body are never duplicated
+    let req = TestRequest::post()
+        .uri("/page")
+        .set_payload("data")
+        .to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "slow");
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
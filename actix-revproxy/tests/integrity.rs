@@ -0,0 +1,75 @@
+use actix_revproxy::{Integrity, RevProxy};
+use actix_web::{
+    App, HttpResponse,
+    http::StatusCode,
+    test::{self, TestRequest},
+    web,
+};
+
+mod common;
+
+/// Base64 MD5 of `hello`
+const HELLO_MD5: &str = "XUFAKrxLKna5cZ2REBfFkg==";
+/// Base64 MD5 of `world`
+const WORLD_MD5: &str = "fXkwN6B2AYZXSwKC8vQ15w==";
+/// Base64 SHA-256 of `hello world`
+const HELLO_WORLD_SHA256: &str = "uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=";
+
+/// Start a local upstream announcing matching and mismatching digests
+fn start_upstream() -> std::net::SocketAddr {
+    common::spawn_upstream(|| {
+        App::new()
+            .route(
+                "/match",
+                web::get().to(|| async {
+                    HttpResponse::Ok()
+                        .insert_header(("Content-MD5", HELLO_MD5))
+                        .body("hello")
+                }),
+            )
+            .route(
+                "/mismatch",
+                web::get().to(|| async {
+                    HttpResponse::Ok()
+                        .insert_header(("Content-MD5", WORLD_MD5))
+                        .body("hello")
+                }),
+            )
+            .route(
+                "/partial",
+                web::get().to(|| async {
+                    HttpResponse::PartialContent()
+                        .insert_header(("Content-Range", "bytes 0-4/11"))
+                        .insert_header(("Digest", format!("sha-256={HELLO_WORLD_SHA256}")))
+                        .body("hello")
+                }),
+            )
+    })
+}
+
+#[actix_web::test]
+async fn integrity_verification() {
+    common::setup();
+    let addr = start_upstream();
+
+    let proxy = RevProxy::new("/", format!("http://{addr}")).integrity(Integrity::new());
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::with_uri("/match").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "hello");
+
+    let req = TestRequest::with_uri("/mismatch").to_request();
+    let status = match test::try_call_service(&srv, req).await {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+
+    // range responses carry the digest of the full representation
+    let req = TestRequest::with_uri("/partial").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(test::read_body(res).await, "hello");
+}
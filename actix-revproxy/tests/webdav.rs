@@ -0,0 +1,78 @@
+use actix_revproxy::RevProxy;
+use actix_web::{
+    App, HttpRequest, HttpResponse,
+    http::{Method, StatusCode, header},
+    test::{self, TestRequest},
+    web,
+};
+
+mod common;
+
+/// Start a local WebDAV-like upstream echoing the `Destination` header
+fn start_upstream() -> std::net::SocketAddr {
+    common::spawn_upstream(|| {
+        App::new().default_service(web::to(|req: HttpRequest| async move {
+            match req.method().as_str() {
+                "PROPFIND" => HttpResponse::build(StatusCode::MULTI_STATUS).finish(),
+                "MOVE" => {
+                    let addr = req.app_config().local_addr();
+                    let destination = req.headers().get("destination").unwrap();
+                    HttpResponse::Created()
+                        .insert_header((header::LOCATION, format!("http://{addr}/dav/new")))
+                        .body(destination.to_str().unwrap().to_owned())
+                }
+                _ => HttpResponse::Ok().finish(),
+            }
+        }))
+    })
+}
+
+#[actix_web::test]
+async fn webdav_methods() {
+    common::setup();
+    let addr = start_upstream();
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+
+    let proxy = RevProxy::new("", format!("http://{addr}"));
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::default()
+        .method(propfind.clone())
+        .uri("/dav/")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    let proxy = RevProxy::new("", format!("http://{addr}")).webdav(true);
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::default()
+        .method(propfind)
+        .uri("/dav/")
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::MULTI_STATUS);
+}
+
+#[actix_web::test]
+async fn webdav_destination() {
+    common::setup();
+    let addr = start_upstream();
+
+    let proxy = RevProxy::new("", format!("http://{addr}"))
+        .change_host()
+        .webdav(true);
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::default()
+        .method(Method::from_bytes(b"MOVE").unwrap())
+        .uri("/dav/a.txt")
+        .insert_header((header::HOST, "files.example.com"))
+        .insert_header(("Destination", "http://files.example.com/dav/b.txt"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(
+        res.headers().get(header::LOCATION).unwrap(),
+        "http://files.example.com/dav/new"
+    );
+    let body = common::get_body(res).await;
+    assert_eq!(body, format!("http://{addr}/dav/b.txt"));
+}
@@ -0,0 +1,22 @@
+use actix_rewrite::Engine;
+use actix_web::test::TestRequest;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+fn rewrite_prefilter(c: &mut Criterion) {
+    let rules: String = (0..50)
+        .map(|n| format!("RewriteRule ^/legacy/{n}/(.*) /v2/{n}/$1 [L]\n"))
+        .collect();
+    let engine = Engine::new().rules(&rules).expect("failed to load rules");
+
+    let mut group = c.benchmark_group("rewrite_prefilter");
+    for path in ["/assets/app.js", "/legacy/49/index.html"] {
+        let req = TestRequest::with_uri(path).to_http_request();
+        group.bench_with_input(BenchmarkId::from_parameter(path), &req, |b, req| {
+            b.iter(|| engine.rewrite(req).expect("rewrite failed"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, rewrite_prefilter);
+criterion_main!(benches);
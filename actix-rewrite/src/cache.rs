@@ -0,0 +1,221 @@
+//! Rewrite Result Caching
+
+use std::{
+    cell::RefCell,
+    num::NonZeroUsize,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use actix_web::{
+    HttpRequest,
+    http::header::{self, HeaderName},
+};
+use lru::LruCache;
+
+/// Default maximum number of cached rewrite outcomes
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Rewrite outcome of the rules for a single input
+#[derive(Clone, Debug)]
+pub(crate) enum Outcome {
+    Uri(String),
+    Redirect(String, u16),
+    Status(u16),
+}
+
+impl From<mod_rewrite::Rewrite> for Outcome {
+    fn from(value: mod_rewrite::Rewrite) -> Self {
+        match value {
+            mod_rewrite::Rewrite::Uri(uri) => Self::Uri(uri),
+            mod_rewrite::Rewrite::EndUri(uri) => Self::Uri(uri),
+            mod_rewrite::Rewrite::Redirect(uri, sc) => Self::Redirect(uri, sc),
+            mod_rewrite::Rewrite::StatusCode(sc) => Self::Status(sc),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+/// Point-in-time statistics of a [`ResultCache`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResultCacheStats {
+    /// Number of rewrites answered from the cache.
+    pub hits: u64,
+    /// Number of rewrites evaluating the rules.
+    pub misses: u64,
+    /// Number of times cached outcomes were discarded on rule changes.
+    pub invalidations: u64,
+}
+
+impl ResultCacheStats {
+    /// Share of rewrites answered from the cache between 0 and 1.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// Bounded cache of rewrite outcomes keyed by normalized uri
+///
+/// Outcomes are cached per request method, host, normalized uri, the
+/// values of any custom variables, and the configured request headers. Rules with
+/// conditions on any other input, such as `%{REMOTE_ADDR}` or
+/// `%{TIME}`, must not be used with the cache unless the inputs are added
+/// as key headers. The least recently used outcome is evicted once the
+/// cache is full, and all outcomes are discarded whenever the rules
+/// change through toggles or provided rules.
+///
+/// Every worker keeps its own outcomes while statistics are shared by all
+/// clones of the handle.
+///
+/// # Examples
+///
+/// ```
+/// use actix_rewrite::{Engine, ResultCache};
+///
+/// let cache = ResultCache::new().capacity(4096).key_header("Accept-Language");
+/// let engine = Engine::new()
+///     .result_cache(cache.clone())
+///     .rules("RewriteRule ^/old/(.*) /new/$1 [L]")
+///     .expect("failed to process rules");
+///
+/// println!("hit rate: {:.2}", cache.stats().hit_rate());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ResultCache {
+    capacity: usize,
+    key_headers: Vec<HeaderName>,
+    counters: Arc<Counters>,
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            key_headers: Vec::new(),
+            counters: Arc::default(),
+        }
+    }
+}
+
+impl ResultCache {
+    /// Construct a new result cache configuration.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of cached outcomes per worker.
+    ///
+    /// Default is 1024.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Include the value of the specified request header in the cache key.
+    pub fn key_header(mut self, name: &str) -> Self {
+        match HeaderName::try_from(name) {
+            Ok(name) => self.key_headers.push(name),
+            Err(_) => tracing::warn!("invalid rewrite cache key header {name:?}"),
+        }
+        self
+    }
+
+    /// Current statistics across all workers.
+    pub fn stats(&self) -> ResultCacheStats {
+        ResultCacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            invalidations: self.counters.invalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Build the cache key for the request
+    fn key(&self, req: &HttpRequest, uri: &str, values: &[String]) -> String {
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| req.uri().host())
+            .unwrap_or_default();
+        let mut key = format!("{} {host} {uri}", req.method());
+        for value in values {
+            key.push_str(&format!("|{value}"));
+        }
+        for name in self.key_headers.iter() {
+            let values: Vec<_> = req
+                .headers()
+                .get_all(name)
+                .filter_map(|value| value.to_str().ok())
+                .collect();
+            key.push_str(&format!("|{name}={}", values.join(",")));
+        }
+        key
+    }
+}
+
+/// Result cache configuration alongside the outcomes cached by an engine
+#[derive(Clone, Debug)]
+pub(crate) struct Results {
+    cache: ResultCache,
+    outcomes: Option<RefCell<LruCache<String, Outcome>>>,
+}
+
+impl Results {
+    pub(crate) fn new(cache: ResultCache) -> Self {
+        let outcomes =
+            NonZeroUsize::new(cache.capacity).map(|cap| RefCell::new(LruCache::new(cap)));
+        Self { cache, outcomes }
+    }
+
+    /// Build the cache key for the request
+    #[inline]
+    pub(crate) fn key(&self, req: &HttpRequest, uri: &str, values: &[String]) -> String {
+        self.cache.key(req, uri, values)
+    }
+
+    /// Retrieve the cached outcome for the key
+    pub(crate) fn get(&self, key: &str) -> Option<Outcome> {
+        let counters = &self.cache.counters;
+        let outcome = self
+            .outcomes
+            .as_ref()
+            .and_then(|outcomes| outcomes.borrow_mut().get(key).cloned());
+        match outcome.is_some() {
+            true => counters.hits.fetch_add(1, Ordering::Relaxed),
+            false => counters.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        outcome
+    }
+
+    /// Cache the outcome evicting the least recently used as required
+    pub(crate) fn insert(&self, key: String, outcome: Outcome) {
+        if let Some(outcomes) = self.outcomes.as_ref() {
+            outcomes.borrow_mut().put(key, outcome);
+        }
+    }
+
+    /// Discard all cached outcomes after the rules changed
+    pub(crate) fn clear(&self) {
+        let Some(outcomes) = self.outcomes.as_ref() else {
+            return;
+        };
+        let mut outcomes = outcomes.borrow_mut();
+        if !outcomes.is_empty() {
+            outcomes.clear();
+            let counters = &self.cache.counters;
+            counters.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Custom Responses for Denied Requests
+
+use actix_http::StatusCode;
+use actix_web::{HttpResponse, http::header};
+
+#[derive(Clone, Copy, Debug)]
+enum Format {
+    Html,
+    Json,
+}
+
+/// Custom response body template for requests denied by a rule,
+/// such as those ending in `[F]` (Forbidden) or `[G]` (Gone).
+///
+/// Templates may contain the following placeholders which are
+/// escaped according to the template format:
+///
+/// - `{status}`: Numeric status code
+/// - `{reason}`: Canonical status reason
+/// - `{path}`: Request path
+/// - `{rule}`: ID or text of the rule that denied the request
+///
+/// # Examples
+///
+/// ```
+/// use actix_web::http::StatusCode;
+/// use actix_rewrite::{DenyTemplate, Engine};
+///
+/// let engine = Engine::new().deny_template(
+///     StatusCode::FORBIDDEN,
+///     DenyTemplate::json(r#"{"error": "{reason}", "path": "{path}"}"#),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct DenyTemplate {
+    format: Format,
+    template: String,
+}
+
+impl DenyTemplate {
+    /// Create a new `text/html` response template.
+    pub fn html<S: Into<String>>(template: S) -> Self {
+        Self {
+            format: Format::Html,
+            template: template.into(),
+        }
+    }
+
+    /// Create a new `application/json` response template.
+    pub fn json<S: Into<String>>(template: S) -> Self {
+        Self {
+            format: Format::Json,
+            template: template.into(),
+        }
+    }
+
+    fn escape(&self, value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            match (self.format, c) {
+                (Format::Html, '&') => escaped.push_str("&amp;"),
+                (Format::Html, '<') => escaped.push_str("&lt;"),
+                (Format::Html, '>') => escaped.push_str("&gt;"),
+                (Format::Html, '"') => escaped.push_str("&quot;"),
+                (Format::Html, '\'') => escaped.push_str("&#39;"),
+                (Format::Json, '"') => escaped.push_str("\\\""),
+                (Format::Json, '\\') => escaped.push_str("\\\\"),
+                (Format::Json, c) if c.is_control() => {
+                    escaped.push_str(&format!("\\u{:04x}", c as u32))
+                }
+                (_, c) => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    /// Render the template into a response
+    pub(crate) fn render(
+        &self,
+        status: StatusCode,
+        path: &str,
+        rule: Option<&str>,
+    ) -> HttpResponse {
+        let values = [
+            ("{status}", status.as_str().to_owned()),
+            (
+                "{reason}",
+                self.escape(status.canonical_reason().unwrap_or("")),
+            ),
+            ("{path}", self.escape(path)),
+            ("{rule}", self.escape(rule.unwrap_or("unknown"))),
+        ];
+        let mut body = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            body.push_str(&rest[..start]);
+            rest = &rest[start..];
+            match values.iter().find(|(key, _)| rest.starts_with(key)) {
+                Some((key, value)) => {
+                    body.push_str(value);
+                    rest = &rest[key.len()..];
+                }
+                None => {
+                    body.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        body.push_str(rest);
+        let content_type = match self.format {
+            Format::Html => header::ContentType::html(),
+            Format::Json => header::ContentType::json(),
+        };
+        HttpResponse::build(status)
+            .insert_header(content_type)
+            .body(body)
+    }
+}
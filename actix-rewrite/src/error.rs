@@ -1,6 +1,7 @@
 //! Error and Result module
 
-use actix_web::ResponseError;
+use actix_common::{ErrorKind, GatewayError};
+use actix_web::{HttpResponse, ResponseError, body::BoxBody};
 use derive_more::{Display, Error, From};
 
 /// Errors which occur when processing Reverse Proxy Requests/Responses
@@ -33,11 +34,27 @@ pub enum Error {
 
     #[display("Failed to build http request uri")]
     RequestError(actix_web::error::HttpError),
+
+    #[display("Rule provider failed to fetch rules")]
+    ProviderError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl GatewayError for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::IoError(_) | Self::RuleError(_) | Self::ProviderError(_) => ErrorKind::Config,
+            _ => ErrorKind::Internal,
+        }
+    }
 }
 
 impl ResponseError for Error {
-    /// Returns `500 Internal Server Error`.
+    /// Returns the status code of the error's [`ErrorKind`].
     fn status_code(&self) -> actix_web::http::StatusCode {
-        actix_web::http::StatusCode::INTERNAL_SERVER_ERROR
+        self.kind().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        self.gateway_response()
     }
 }
@@ -29,13 +29,31 @@ use crate::service::{RewriteInner, RewriteService};
 ///
 /// let app = App::new().wrap(Middleware::new(engine));
 /// ```
-pub struct Middleware(Rc<Engine>);
+pub struct Middleware {
+    engine: Rc<Engine>,
+    root: bool,
+}
 
 impl Middleware {
     /// Creates a new `mod_rewrite` middleware instance
     #[inline]
     pub fn new(engine: Engine) -> Self {
-        Self(Rc::new(engine))
+        Self {
+            engine: Rc::new(engine),
+            root: false,
+        }
+    }
+
+    /// Creates a new `mod_rewrite` middleware instance intended
+    /// to be registered as the outermost [`App::wrap`](actix_web::App::wrap).
+    ///
+    /// See [`Engine::into_root_transform`] for more details.
+    #[inline]
+    pub fn root(engine: Engine) -> Self {
+        Self {
+            engine: Rc::new(engine),
+            root: true,
+        }
     }
 }
 
@@ -60,7 +78,8 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(RewriteService(Rc::new(RewriteInner {
             service: Rc::new(service),
-            engine: self.0.clone(),
+            engine: self.engine.clone(),
+            root: self.root,
         }))))
     }
 }
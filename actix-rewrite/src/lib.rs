@@ -17,20 +17,44 @@
 //!   .wrap(engine.middleware());
 //! ```
 //!
+//! # Middleware Ordering
+//!
+//! Route selection happens after all [`App::wrap`](actix_web::App::wrap)
+//! middleware has run, so rewrites influence which route handles the
+//! request only when the engine wraps the entire application. Rewrites
+//! applied via [`Scope::wrap`](actix_web::Scope::wrap) or
+//! [`Resource::wrap`](actix_web::Resource::wrap) happen after route
+//! selection, unless the application is wrapped by
+//! [`Engine::into_root_transform`] as the last `wrap` call, which
+//! dispatches requests rewritten by nested engines again.
+//!
 //! # Documentation
 //!
 //! Information regarding the Rewrite expression language can be found in the [mod_rewrite manual](https://httpd.apache.org/docs/current/mod/mod_rewrite.html).
 //!
 //! Documentation for this crate can be found on [docs.rs](https://docs.rs/actix-modrewrite).
+mod cache;
+mod deny;
 mod error;
 mod factory;
+mod loops;
+mod provider;
 mod rewrite;
+mod rules;
 mod service;
+mod target;
 pub mod util;
+mod vars;
 
+pub use cache::{ResultCache, ResultCacheStats};
+pub use deny::DenyTemplate;
 pub use error::Error;
 pub use factory::Middleware;
+pub use provider::{FileProvider, MemoryProvider, ProvidedRules, RuleProvider, RuleSet};
 pub use rewrite::{Engine, Rewrite};
+pub use rules::RuleToggles;
 pub use service::RewriteService;
+pub use target::TargetNormalizer;
+pub use vars::VarProvider;
 
 pub use mod_rewrite::context::ServerCtx;
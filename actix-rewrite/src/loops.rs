@@ -0,0 +1,139 @@
+//! Rewrite and Redirect Loop Detection
+
+use actix_http::StatusCode;
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse,
+    http::header::{self, HeaderMap, HeaderValue},
+};
+
+use crate::rewrite::Rewrite;
+
+/// Cookie counting consecutive external redirects
+const REDIRECT_COOKIE: &str = "rewrite-redirects";
+
+/// Lifetime of the redirect counting cookie in seconds
+const REDIRECT_COOKIE_MAX_AGE: u64 = 60;
+
+/// Uris a request was rewritten to so far
+#[derive(Clone, Debug)]
+struct RewriteHistory(Vec<String>);
+
+/// Request target of an uri as compared between rewrites
+#[inline]
+fn target(uri: &actix_http::Uri) -> &str {
+    uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/")
+}
+
+/// Detection of requests caught in rewrite or redirect loops
+#[derive(Clone, Debug)]
+pub(crate) struct LoopDetector {
+    pub(crate) redirect_limit: Option<usize>,
+    pub(crate) reject_self_redirects: bool,
+    pub(crate) status: StatusCode,
+}
+
+impl Default for LoopDetector {
+    fn default() -> Self {
+        Self {
+            redirect_limit: None,
+            reject_self_redirects: false,
+            status: StatusCode::LOOP_DETECTED,
+        }
+    }
+}
+
+impl LoopDetector {
+    /// Number of consecutive redirects counted for the client
+    ///
+    /// Always `None` unless a redirect limit is configured.
+    pub(crate) fn redirect_count(&self, req: &HttpRequest) -> Option<usize> {
+        self.redirect_limit?;
+        req.headers()
+            .get_all(header::COOKIE)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == REDIRECT_COOKIE)
+            .and_then(|(_, count)| count.parse().ok())
+    }
+
+    /// Clear the redirect counter once a request is served without redirect
+    pub(crate) fn clear(&self, headers: &mut HeaderMap) {
+        let cookie = format!("{REDIRECT_COOKIE}=; Max-Age=0; Path=/; HttpOnly");
+        if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+            headers.append(header::SET_COOKIE, cookie);
+        }
+    }
+
+    /// Build the response for a request caught in a loop
+    fn fail(&self, req: &HttpRequest, reason: &str) -> HttpResponse {
+        let addr = actix_common::client_addr(req)
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        tracing::warn!(
+            "{addr} {} {:?} {reason}. responding with {}",
+            req.method(),
+            req.uri(),
+            self.status
+        );
+        HttpResponse::new(self.status)
+    }
+
+    /// Reject the request if the client exceeded the redirect limit
+    pub(crate) fn check(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        let limit = self.redirect_limit?;
+        let count = self.redirect_count(req)?;
+        (count >= limit).then(|| self.fail(req, &format!("redirect loop after {count} redirects")))
+    }
+
+    /// Record the rewrite in the request history and replace it with a
+    /// failure response if it completes a loop
+    pub(crate) fn track(&self, req: &HttpRequest, rewrite: Rewrite) -> Rewrite {
+        let before = target(req.uri()).to_owned();
+        match rewrite {
+            Rewrite::Uri(uri) => {
+                let after = target(&uri);
+                if after == before {
+                    return Rewrite::Uri(uri);
+                }
+                // restart the history when the request was restored in the
+                // meantime rather than rewritten further
+                let history = req.extensions_mut().remove::<RewriteHistory>();
+                let mut history = history
+                    .filter(|history| history.0.last() == Some(&before))
+                    .unwrap_or_else(|| RewriteHistory(vec![before]));
+                if history.0.iter().any(|seen| seen == after) {
+                    let reason = format!("rewrite loop {:?} -> {after:?}", history.0);
+                    return Rewrite::Response(self.fail(req, &reason));
+                }
+                history.0.push(after.to_owned());
+                req.extensions_mut().insert(history);
+                Rewrite::Uri(uri)
+            }
+            Rewrite::Redirect(mut res) => {
+                let location = res
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|value| value.to_str().ok());
+                if self.reject_self_redirects
+                    && location.is_some_and(|location| {
+                        location == before || location == req.uri().to_string()
+                    })
+                {
+                    return Rewrite::Response(self.fail(req, "redirect to itself"));
+                }
+                if self.redirect_limit.is_some() {
+                    let count = self.redirect_count(req).unwrap_or(0) + 1;
+                    let cookie = format!(
+                        "{REDIRECT_COOKIE}={count}; Max-Age={REDIRECT_COOKIE_MAX_AGE}; Path=/; HttpOnly"
+                    );
+                    if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+                        res.headers_mut().append(header::SET_COOKIE, cookie);
+                    }
+                }
+                Rewrite::Redirect(res)
+            }
+            rewrite => rewrite,
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Rewrite Rules Sourced at Runtime
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc, RwLock, Weak,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{
+    error::Error,
+    rules::{self, RuleBlock},
+};
+
+/// Rule expressions fetched from a [`RuleProvider`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuleSet {
+    /// Rewrite rule expressions.
+    pub rules: String,
+    /// Tag identifying this version of the rules.
+    pub etag: Option<String>,
+}
+
+impl RuleSet {
+    /// Construct a new rule set from rule expressions.
+    pub fn new<S: Into<String>>(rules: S) -> Self {
+        Self {
+            rules: rules.into(),
+            etag: None,
+        }
+    }
+
+    /// Tag identifying this version of the rules.
+    pub fn etag<S: Into<String>>(mut self, etag: S) -> Self {
+        self.etag = Some(etag.into());
+        self
+    }
+}
+
+/// Source of rewrite rules fetched periodically at runtime
+///
+/// Implement this trait to manage rules in a database or configuration
+/// service without redeploying. See [`ProvidedRules`] for how fetched
+/// rules are applied to an [`Engine`](crate::Engine).
+pub trait RuleProvider: Send + 'static {
+    /// Fetch the current rules.
+    ///
+    /// `etag` is the tag of the rules fetched last, if any. Return
+    /// `Ok(None)` when the rules are unchanged since then.
+    fn fetch(&self, etag: Option<&str>) -> impl Future<Output = Result<Option<RuleSet>, Error>>;
+}
+
+/// Provider serving rules held in memory
+///
+/// Clones share the same rules, so they may be replaced at runtime
+/// through any clone.
+///
+/// # Examples
+///
+/// ```
+/// use actix_rewrite::MemoryProvider;
+///
+/// let provider = MemoryProvider::new("RewriteRule ^/old /new [R=301]");
+/// provider.set("RewriteRule ^/old /newer [R=301]");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MemoryProvider(Arc<RwLock<(String, usize)>>);
+
+impl MemoryProvider {
+    /// Construct a new provider serving the specified rules.
+    pub fn new<S: Into<String>>(rules: S) -> Self {
+        Self(Arc::new(RwLock::new((rules.into(), 0))))
+    }
+
+    /// Replace the rules served by the provider.
+    pub fn set<S: Into<String>>(&self, rules: S) {
+        let mut inner = self.0.write().expect("poisoned lock");
+        *inner = (rules.into(), inner.1 + 1);
+    }
+}
+
+impl RuleProvider for MemoryProvider {
+    async fn fetch(&self, etag: Option<&str>) -> Result<Option<RuleSet>, Error> {
+        let (rules, version) = self.0.read().expect("poisoned lock").clone();
+        let version = version.to_string();
+        if etag == Some(version.as_str()) {
+            return Ok(None);
+        }
+        Ok(Some(RuleSet::new(rules).etag(version)))
+    }
+}
+
+/// Provider reading rules from a file
+///
+/// The file is only re-read when its modification time or size changed.
+#[derive(Clone, Debug)]
+pub struct FileProvider(PathBuf);
+
+impl FileProvider {
+    /// Construct a new provider reading the specified file.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self(path.as_ref().to_owned())
+    }
+}
+
+impl RuleProvider for FileProvider {
+    async fn fetch(&self, etag: Option<&str>) -> Result<Option<RuleSet>, Error> {
+        let meta = std::fs::metadata(&self.0)?;
+        let modified = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let tag = format!(
+            "{}.{}-{}",
+            modified.as_secs(),
+            modified.subsec_nanos(),
+            meta.len()
+        );
+        if etag == Some(tag.as_str()) {
+            return Ok(None);
+        }
+        let rules = std::fs::read_to_string(&self.0)?;
+        Ok(Some(RuleSet::new(rules).etag(tag)))
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    blocks: RwLock<Vec<RuleBlock>>,
+    etag: RwLock<Option<String>>,
+    generation: AtomicUsize,
+}
+
+/// Shared handle holding rules fetched from a [`RuleProvider`]
+///
+/// Provided rules are evaluated after the rules configured on the
+/// [`Engine`](crate::Engine) and replace the previously provided rules
+/// entirely on every change. Engines pick up changes on their next
+/// request, and rule sets failing to compile are logged and ignored.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use actix_web::{App, HttpServer};
+/// use actix_rewrite::{Engine, FileProvider, ProvidedRules};
+///
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     let rules = ProvidedRules::new();
+///     rules.watch(FileProvider::new("redirects.conf"), Duration::from_secs(30));
+///
+///     HttpServer::new(move || {
+///         let engine = Engine::new().provided_rules(rules.clone());
+///         App::new().wrap(engine.middleware())
+///     })
+///     .bind(("127.0.0.1", 8080))?
+///     .run()
+///     .await
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ProvidedRules(Arc<Shared>);
+
+impl ProvidedRules {
+    /// Construct a new handle without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch the rules from the provider and apply them if changed.
+    ///
+    /// Returns `true` if new rules were applied.
+    pub async fn refresh<P: RuleProvider>(&self, provider: &P) -> Result<bool, Error> {
+        let etag = self.etag();
+        let Some(set) = provider.fetch(etag.as_deref()).await? else {
+            return Ok(false);
+        };
+        if set.etag.is_some() && set.etag == etag {
+            return Ok(false);
+        }
+        *self.0.blocks.write().expect("poisoned lock") = rules::parse(&set.rules);
+        *self.0.etag.write().expect("poisoned lock") = set.etag;
+        self.0.generation.fetch_add(1, Ordering::AcqRel);
+        Ok(true)
+    }
+
+    /// Refresh the rules from the provider at the specified interval.
+    ///
+    /// The rules are fetched immediately and then polled on a dedicated
+    /// thread, which exits once every clone of the handle is dropped.
+    pub fn watch<P: RuleProvider>(&self, provider: P, interval: Duration) -> JoinHandle<()> {
+        let shared = Arc::downgrade(&self.0);
+        std::thread::spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                while let Some(rules) = Weak::upgrade(&shared).map(Self) {
+                    match rules.refresh(&provider).await {
+                        Ok(true) => tracing::info!("reloaded provided rewrite rules"),
+                        Ok(false) => {}
+                        Err(err) => tracing::error!("failed to fetch rewrite rules: {err}"),
+                    }
+                    drop(rules);
+                    actix_web::rt::time::sleep(interval).await;
+                }
+            })
+        })
+    }
+
+    /// Tag of the currently applied rules.
+    pub fn etag(&self) -> Option<String> {
+        self.0.etag.read().expect("poisoned lock").clone()
+    }
+
+    /// Current rules generation, bumped on every change.
+    #[inline]
+    pub fn generation(&self) -> usize {
+        self.0.generation.load(Ordering::Acquire)
+    }
+
+    /// Snapshot of the currently provided rule blocks.
+    pub(crate) fn blocks(&self) -> Vec<RuleBlock> {
+        self.0.blocks.read().expect("poisoned lock").clone()
+    }
+}
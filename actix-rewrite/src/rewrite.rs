@@ -1,13 +1,36 @@
 //! Utilities for Actix-Web Rewrite Actions
 
-use std::path::Path;
+use std::{cell::RefCell, collections::HashMap, path::Path, sync::Arc};
 
 use actix_http::{StatusCode, Uri};
-use actix_web::http::header;
-use actix_web::{HttpRequest, HttpResponse};
+use actix_web::http::header::{self, TryIntoHeaderValue};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
 use mod_rewrite::context::{EngineCtx, ServerCtx};
 
-use crate::Middleware;
+use crate::{
+    DenyTemplate, Middleware, ProvidedRules, ResultCache, TargetNormalizer,
+    cache::{Outcome, Results},
+    loops::LoopDetector,
+    rules::{self, PreFilter, RuleBlock, RuleToggles},
+    vars::{Template, VarProvider, VarRef, Vars},
+};
+
+/// Max number of engines compiled for distinct custom variable values
+const MAX_VARIANTS: usize = 64;
+
+/// Offset basis of the 64-bit FNV-1a hash
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// Fold bytes into a 64-bit FNV-1a hash
+///
+/// Unlike the std hashers the result is stable across builds and
+/// processes, keeping redirect validators valid between restarts.
+#[inline]
+fn fnv1a(bytes: &[u8], hash: u64) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
 
 use super::error::Error;
 use super::util;
@@ -19,11 +42,38 @@ pub enum Rewrite {
     Response(HttpResponse),
 }
 
+/// Compiled rules alongside the toggle generation they reflect
+#[derive(Clone)]
+struct Compiled {
+    generation: usize,
+    engine: mod_rewrite::Engine,
+    deny_rules: Vec<(String, mod_rewrite::Engine)>,
+    filter: PreFilter,
+    template: Template,
+    refs: Vec<VarRef>,
+}
+
 #[derive(Clone)]
 /// Actix-Web compatible wrapper on [`Engine`](mod_rewrite::Engine)
+///
+/// Rules may be tagged with a `# id: <name>` comment and toggled at
+/// runtime using [`Engine::toggles`]. Disabled rules are replaced with
+/// a no-op rule rather than removed, so `[S=n]` skip counts and `[C]`
+/// chains spanning them are evaluated exactly as Apache would.
 pub struct Engine {
-    engine: mod_rewrite::Engine,
+    engine: RefCell<Compiled>,
     srv_ctx: ServerCtx,
+    max_iterations: Option<usize>,
+    blocks: Vec<RuleBlock>,
+    toggles: RuleToggles,
+    provided: Option<ProvidedRules>,
+    loops: LoopDetector,
+    results: Option<Results>,
+    targets: TargetNormalizer,
+    templates: HashMap<StatusCode, DenyTemplate>,
+    caching: HashMap<StatusCode, header::CacheControl>,
+    vars: Vars,
+    variants: RefCell<HashMap<Vec<String>, mod_rewrite::Engine>>,
 }
 
 impl Engine {
@@ -32,9 +82,186 @@ impl Engine {
     /// See [`mod_rewrite::Engine`](mod_rewrite::Engine) for more details.
     pub fn new() -> Self {
         Self {
-            engine: mod_rewrite::Engine::default(),
+            engine: RefCell::new(Compiled {
+                generation: 0,
+                engine: mod_rewrite::Engine::default(),
+                deny_rules: Vec::new(),
+                filter: PreFilter::default(),
+                template: Template::default(),
+                refs: Vec::new(),
+            }),
             srv_ctx: ServerCtx::default(),
+            max_iterations: None,
+            blocks: Vec::new(),
+            toggles: RuleToggles::default(),
+            provided: None,
+            loops: LoopDetector::default(),
+            results: None,
+            targets: TargetNormalizer::default(),
+            templates: HashMap::new(),
+            caching: HashMap::new(),
+            vars: Vars::default(),
+            variants: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Combined generation of the rule toggles and provided rules
+    #[inline]
+    fn generation(&self) -> usize {
+        let provided = self.provided.as_ref().map(|p| p.generation());
+        self.toggles
+            .generation()
+            .wrapping_add(provided.unwrap_or_default())
+    }
+
+    /// Compile the rule blocks into a new engine honoring rule toggles
+    fn compile(&self, blocks: &[RuleBlock]) -> Result<Compiled, Error> {
+        let generation = self.generation();
+        let disabled = self.toggles.disabled();
+        let blocks = match self.provided.as_ref() {
+            Some(provided) => &[blocks, &provided.blocks()].concat(),
+            None => blocks,
+        };
+        let source = rules::render(blocks, &disabled);
+        let refs = self.vars.references(&source);
+        let template = Template::new(&source, &refs);
+        let unset = vec![String::new(); refs.len()];
+        let mut engine = self.new_engine();
+        engine.add_rules(&template.expand(&unset))?;
+
+        let mut deny_rules = vec![];
+        for (label, preceding) in rules::deny_rules(blocks, &disabled) {
+            let source = rules::render(preceding, &disabled);
+            let refs = self.vars.references(&source);
+            let unset = vec![String::new(); refs.len()];
+            let mut engine = self.new_engine();
+            engine.add_rules(&Template::new(&source, &refs).expand(&unset))?;
+            deny_rules.push((label, engine));
+        }
+        Ok(Compiled {
+            generation,
+            engine,
+            deny_rules,
+            filter: PreFilter::new(blocks, &disabled),
+            template,
+            refs,
+        })
+    }
+
+    fn new_engine(&self) -> mod_rewrite::Engine {
+        let engine = mod_rewrite::Engine::default();
+        match self.max_iterations {
+            Some(iterations) => engine.max_iterations(iterations),
+            None => engine,
+        }
+    }
+
+    /// Build the engine context used to evaluate the request
+    fn context(&self, req: &HttpRequest) -> Result<EngineCtx, Error> {
+        Ok(EngineCtx::default()
+            .with_env()
+            .with_time()
+            .with_ctx(util::request_ctx(req))
+            .with_ctx(util::fill_server_ctx(self.srv_ctx.clone(), req)?))
+    }
+
+    /// Find the `[F]` or `[G]` rule responsible for denying the request.
+    ///
+    /// Each deny rule is re-evaluated together with every rule preceding
+    /// it against the original request uri, so the first deny rule reached
+    /// in enforcement order is reported, honoring earlier rules that end
+    /// evaluation or rewrite the uri beforehand.
+    fn denied_by(
+        &self,
+        compiled: &Compiled,
+        req: &HttpRequest,
+        uri: &str,
+        status: StatusCode,
+    ) -> Option<String> {
+        compiled.deny_rules.iter().find_map(|(label, engine)| {
+            let mut ctx = self.context(req).ok()?;
+            match engine.rewrite_ctx(uri, &mut ctx).ok()? {
+                mod_rewrite::Rewrite::StatusCode(sc) if sc == status.as_u16() => {
+                    Some(label.clone())
+                }
+                _ => None,
+            }
+        })
+    }
+
+    /// Build the response for a request denied by the rules
+    /// and record which rule denied it.
+    fn deny(
+        &self,
+        compiled: &Compiled,
+        req: &HttpRequest,
+        uri: &str,
+        status: StatusCode,
+    ) -> HttpResponse {
+        let rule = self.denied_by(compiled, req, uri, status);
+        let addr = actix_common::client_addr(req)
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        tracing::warn!(
+            "{addr} {} {:?} denied with {status} by rule {:?}",
+            req.method(),
+            req.uri(),
+            rule.as_deref().unwrap_or("unknown")
+        );
+        match self.templates.get(&status) {
+            Some(template) => template.render(status, req.path(), rule.as_deref()),
+            None => HttpResponse::new(status),
+        }
+    }
+
+    /// Apply the configured caching headers to a response generated by
+    /// the rules, answering conditional redirects with `304 Not Modified`.
+    fn cache(&self, req: &HttpRequest, mut res: HttpResponse) -> HttpResponse {
+        let status = res.status();
+        let Some(cache) = self.caching.get(&status) else {
+            return res;
+        };
+        let etag = match status.is_redirection() {
+            true => res.headers().get(header::LOCATION).map(|location| {
+                let hash = fnv1a(&status.as_u16().to_be_bytes(), FNV_OFFSET);
+                let hash = fnv1a(location.as_bytes(), hash);
+                header::EntityTag::new_strong(format!("{hash:016x}"))
+            }),
+            false => None,
+        };
+        if let Some(etag) = etag {
+            let fresh = match req.get_header::<header::IfNoneMatch>() {
+                Some(header::IfNoneMatch::Any) => true,
+                Some(header::IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+                None => false,
+            };
+            if fresh {
+                let mut not_modified = HttpResponse::NotModified().finish();
+                let headers = not_modified.headers_mut();
+                for (name, value) in res.headers() {
+                    if !matches!(
+                        *name,
+                        header::CONTENT_TYPE | header::CONTENT_LENGTH | header::TRANSFER_ENCODING
+                    ) {
+                        headers.append(name.clone(), value.clone());
+                    }
+                }
+                res = not_modified;
+            }
+            match header::ETag(etag).try_into_value() {
+                Ok(value) => {
+                    res.headers_mut().insert(header::ETAG, value);
+                }
+                Err(err) => tracing::error!("invalid redirect etag: {err:?}"),
+            }
+        }
+        match cache.clone().try_into_value() {
+            Ok(value) => {
+                res.headers_mut().insert(header::CACHE_CONTROL, value);
+            }
+            Err(err) => tracing::error!("invalid cache-control for {status}: {err:?}"),
         }
+        res
     }
 
     /// Configure max number of loops over entire ruleset during
@@ -43,7 +270,157 @@ impl Engine {
     /// See [`mod_rewrite::Engine::max_iterations`](mod_rewrite::Engine::max_iterations)
     /// for more details.
     pub fn max_iterations(mut self, iterations: usize) -> Self {
-        self.engine = self.engine.max_iterations(iterations);
+        self.max_iterations = Some(iterations);
+        let compiled = self.engine.get_mut();
+        compiled.engine = compiled.engine.clone().max_iterations(iterations);
+        self
+    }
+
+    /// Configure how targets produced by the rules are encoded and
+    /// normalized before use.
+    ///
+    /// See [`TargetNormalizer`](crate::TargetNormalizer) for more details.
+    pub fn target_normalizer(mut self, targets: TargetNormalizer) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Cache rewrite outcomes of identical requests to avoid evaluating
+    /// the rules again.
+    ///
+    /// See [`ResultCache`](crate::ResultCache) for more details.
+    pub fn result_cache(mut self, cache: ResultCache) -> Self {
+        self.results = Some(Results::new(cache));
+        self
+    }
+
+    /// Fail requests caught in an external redirect loop once a client
+    /// was redirected by the rules `limit` times in a row.
+    ///
+    /// Redirects carry a short-lived cookie counting consecutive redirects,
+    /// which is cleared once a request is served without a redirect.
+    /// Internal rewrites revisiting an uri within the same request are
+    /// always rejected.
+    ///
+    /// Default is no limit on external redirects.
+    pub fn redirect_limit(mut self, limit: usize) -> Self {
+        self.loops.redirect_limit = Some(limit);
+        self
+    }
+
+    /// Reject redirects pointing at the request uri itself as a loop.
+    ///
+    /// Such redirects are not necessarily loops, as rules may depend on
+    /// conditions such as cookies or headers changed by the response.
+    ///
+    /// Default is disabled.
+    pub fn reject_self_redirects(mut self, enable: bool) -> Self {
+        self.loops.reject_self_redirects = enable;
+        self
+    }
+
+    /// Set the status of responses to requests caught in a rewrite or
+    /// redirect loop.
+    ///
+    /// Default is `508 Loop Detected`.
+    pub fn loop_status(mut self, status: StatusCode) -> Self {
+        self.loops.status = status;
+        self
+    }
+
+    /// Respond with a custom body when rules deny a request with the
+    /// given status, such as `403 Forbidden` for `[F]` or `410 Gone`
+    /// for `[G]`.
+    ///
+    /// Default is an empty body.
+    pub fn deny_template(mut self, status: StatusCode, template: DenyTemplate) -> Self {
+        self.templates.insert(status, template);
+        self
+    }
+
+    /// Attach a `Cache-Control` header to redirects and responses
+    /// generated by the rules with the given status.
+    ///
+    /// Redirects with a configured policy also carry an `ETag` derived
+    /// from their status and location, and requests with a matching
+    /// `If-None-Match` header are answered with `304 Not Modified`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_web::http::{StatusCode, header::{CacheControl, CacheDirective}};
+    /// use actix_rewrite::Engine;
+    ///
+    /// let engine = Engine::new().cache_control(
+    ///     StatusCode::MOVED_PERMANENTLY,
+    ///     CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(86400)]),
+    /// );
+    /// ```
+    pub fn cache_control(mut self, status: StatusCode, cache: header::CacheControl) -> Self {
+        self.caching.insert(status, cache);
+        self
+    }
+
+    /// Register a provider for a custom `%{NAMESPACE:key}` variable
+    /// namespace evaluated at request time.
+    ///
+    /// References to the namespace are expanded before the rules are
+    /// evaluated, and an engine is compiled and cached for every distinct
+    /// combination of values, so providers should return values with low
+    /// cardinality such as feature flags or tenant names. Values are
+    /// restricted to alphanumeric characters and `-._~:/,=@+` and unset
+    /// variables expand to an empty string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_rewrite::Engine;
+    ///
+    /// let engine = Engine::new()
+    ///     .var_provider("FLAG", |_req, key| match key {
+    ///         "new-ui" => Some("on".to_owned()),
+    ///         _ => None,
+    ///     })
+    ///     .rules(r#"
+    ///         RewriteCond %{FLAG:new-ui} =on
+    ///         RewriteRule ^/app/(.*) /app-v2/$1 [L]
+    ///     "#)
+    ///     .expect("failed to process rules");
+    /// ```
+    pub fn var_provider<P>(mut self, namespace: &str, provider: P) -> Self
+    where
+        P: VarProvider + 'static,
+    {
+        self.vars.insert(namespace, Arc::new(provider));
+        // force recompile to collect references to the new namespace
+        self.engine.get_mut().generation = usize::MAX;
+        self
+    }
+
+    /// Register a provider for a custom variable namespace backed
+    /// by application data of type `T` registered via
+    /// [`App::app_data`](actix_web::App::app_data) as [`web::Data<T>`].
+    ///
+    /// Variables are unset when the data is not registered.
+    /// See [`Engine::var_provider`] for more details.
+    pub fn data_provider<T, F>(self, namespace: &str, lookup: F) -> Self
+    where
+        T: 'static,
+        F: Fn(&T, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.var_provider(namespace, move |req: &HttpRequest, key: &str| {
+            req.app_data::<web::Data<T>>()
+                .and_then(|data| lookup(data.get_ref(), key))
+        })
+    }
+
+    /// Evaluate rules fetched at runtime after the configured rules.
+    ///
+    /// See [`ProvidedRules`](crate::ProvidedRules) for more details.
+    pub fn provided_rules(mut self, rules: ProvidedRules) -> Self {
+        self.provided = Some(rules);
+        // force recompile to include the provided rules
+        self.engine.get_mut().generation = usize::MAX;
         self
     }
 
@@ -59,7 +436,10 @@ impl Engine {
     /// See [`mod_rewrite::Engine::add_rules`](mod_rewrite::Engine::add_rules)
     /// for more details.
     pub fn add_rules(&mut self, rules: &str) -> Result<&mut Self, Error> {
-        self.engine.add_rules(rules)?;
+        let mut blocks = self.blocks.clone();
+        blocks.extend(rules::parse(rules));
+        *self.engine.get_mut() = self.compile(&blocks)?;
+        self.blocks = blocks;
         Ok(self)
     }
 
@@ -86,28 +466,148 @@ impl Engine {
         Ok(self)
     }
 
+    /// Handle used to enable or disable rules by ID at runtime.
+    ///
+    /// See [`RuleToggles`](crate::RuleToggles) for more details.
+    #[inline]
+    pub fn toggles(&self) -> RuleToggles {
+        self.toggles.clone()
+    }
+
+    /// IDs assigned to rules using `# id: <name>` comments.
+    pub fn rule_ids(&self) -> impl Iterator<Item = &str> {
+        rules::ids(&self.blocks)
+    }
+
+    /// Version of the rule set currently in effect.
+    ///
+    /// The version is a stable hash of the rendered rules, including any
+    /// provided rules and rules disabled at runtime, so it only changes
+    /// when the enforced rules change.
+    pub fn rules_version(&self) -> String {
+        let disabled = self.toggles.disabled();
+        let blocks = match self.provided.as_ref() {
+            Some(provided) => &[&self.blocks[..], &provided.blocks()].concat(),
+            None => &self.blocks[..],
+        };
+        let source = rules::render(blocks, &disabled);
+        format!("{:016x}", fnv1a(source.as_bytes(), FNV_OFFSET))
+    }
+
     /// Evaluates the given [`HttpRequest`](actix_web::HttpRequest) against
     /// the engine rules and returns a [`Rewrite`] response.
+    ///
+    /// The URI is normalized first using the
+    /// [`Normalizer`](actix_common::Normalizer) registered as app-data.
+    /// Requests no rule pattern could possibly match are returned
+    /// unchanged without evaluating the engine.
+    ///
+    /// Requests caught in a rewrite or redirect loop are answered with
+    /// the configured [`Engine::loop_status`].
     pub fn rewrite(&self, req: &HttpRequest) -> Result<Rewrite, Error> {
-        let mut ctx = EngineCtx::default()
-            .with_env()
-            .with_time()
-            .with_ctx(util::request_ctx(req))
-            .with_ctx(util::fill_server_ctx(self.srv_ctx.clone(), req)?);
-        Ok(
-            match self.engine.rewrite_ctx(&req.uri().to_string(), &mut ctx)? {
-                mod_rewrite::Rewrite::Uri(uri) => Rewrite::Uri(util::recode(uri)?),
-                mod_rewrite::Rewrite::EndUri(uri) => Rewrite::Uri(util::recode(uri)?),
-                mod_rewrite::Rewrite::Redirect(uri, sc) => Rewrite::Redirect(
-                    HttpResponse::build(StatusCode::from_u16(sc)?)
-                        .insert_header((header::LOCATION, uri))
-                        .body(""),
-                ),
-                mod_rewrite::Rewrite::StatusCode(sc) => {
-                    Rewrite::Response(HttpResponse::new(StatusCode::from_u16(sc)?))
+        if let Some(res) = self.loops.check(req) {
+            return Ok(Rewrite::Response(res));
+        }
+        let rewrite = self.evaluate(req)?;
+        Ok(self.loops.track(req, rewrite))
+    }
+
+    /// Redirect loop detection configured for the engine
+    #[inline]
+    pub(crate) fn loops(&self) -> &LoopDetector {
+        &self.loops
+    }
+
+    /// Evaluate the rules against the request
+    fn evaluate(&self, req: &HttpRequest) -> Result<Rewrite, Error> {
+        let generation = self.generation();
+        if self.engine.borrow().generation != generation {
+            match self.compile(&self.blocks) {
+                Ok(compiled) => {
+                    *self.engine.borrow_mut() = compiled;
+                    self.variants.borrow_mut().clear();
+                    if let Some(results) = self.results.as_ref() {
+                        results.clear();
+                    }
+                }
+                Err(err) if self.provided.is_some() => {
+                    tracing::error!("invalid provided rules, keeping previous rules: {err}");
+                    self.engine.borrow_mut().generation = generation;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        let engine = self.engine.borrow();
+
+        let normalized = actix_common::normalized_uri(req);
+        let uri = normalized.to_string();
+        if !engine.filter.matches(&normalized, &uri) {
+            return Ok(Rewrite::Uri(normalized));
+        }
+
+        let values = self.vars.resolve(req, &engine.refs);
+        let key = self
+            .results
+            .as_ref()
+            .map(|results| results.key(req, &uri, &values));
+        let cached = key
+            .as_ref()
+            .zip(self.results.as_ref())
+            .and_then(|(key, results)| results.get(key));
+        let outcome = match cached {
+            Some(outcome) => outcome,
+            None => {
+                let outcome = self.outcome(&engine, req, &uri, &values)?;
+                if let (Some(key), Some(results)) = (key, self.results.as_ref()) {
+                    results.insert(key, outcome.clone());
+                }
+                outcome
+            }
+        };
+        Ok(match outcome {
+            Outcome::Uri(uri) => Rewrite::Uri(self.targets.uri(&uri)?),
+            Outcome::Redirect(uri, sc) => {
+                let res = HttpResponse::build(StatusCode::from_u16(sc)?)
+                    .insert_header((header::LOCATION, self.targets.encode(&uri)))
+                    .body("");
+                Rewrite::Redirect(self.cache(req, res))
+            }
+            Outcome::Status(sc) => {
+                let status = StatusCode::from_u16(sc)?;
+                let res = self.deny(&engine, req, &uri, status);
+                Rewrite::Response(self.cache(req, res))
+            }
+        })
+    }
+
+    /// Evaluate the compiled rules using the resolved custom variables
+    fn outcome(
+        &self,
+        engine: &Compiled,
+        req: &HttpRequest,
+        uri: &str,
+        values: &[String],
+    ) -> Result<Outcome, Error> {
+        let mut ctx = self.context(req)?;
+        let rewrite = match engine.refs.is_empty() {
+            true => engine.engine.rewrite_ctx(uri, &mut ctx)?,
+            false => {
+                let mut variants = self.variants.borrow_mut();
+                if !variants.contains_key(&values) {
+                    if variants.len() >= MAX_VARIANTS {
+                        tracing::warn!(
+                            "rule variants exceeded limit of {MAX_VARIANTS}, recompiling all variants"
+                        );
+                        variants.clear();
+                    }
+                    let mut variant = self.new_engine();
+                    variant.add_rules(&engine.template.expand(values))?;
+                    variants.insert(values.to_vec(), variant);
                 }
-            },
-        )
+                variants[values].rewrite_ctx(uri, &mut ctx)?
+            }
+        };
+        Ok(rewrite.into())
     }
 
     /// Converts Engine Instance into Actix-Web Middleware
@@ -128,6 +628,47 @@ impl Engine {
     pub fn middleware(self) -> Middleware {
         self.into()
     }
+
+    /// Converts Engine Instance into Actix-Web Middleware intended
+    /// to be registered at the application root.
+    ///
+    /// When registered as the outermost [`App::wrap`](actix_web::App::wrap)
+    /// (the last `wrap` call) the rules are applied before any route is
+    /// selected. Engines registered via [`Scope::wrap`](actix_web::Scope::wrap)
+    /// or [`Resource::wrap`](actix_web::Resource::wrap) below a root transform
+    /// hand requests whose path they rewrite back to the root transform,
+    /// which dispatches them again so the rewritten path selects the route.
+    ///
+    /// Requests are dispatched at most 10 times before being answered
+    /// with `500 Internal Server Error`. Middleware registered between the
+    /// root transform and the nested engine sees every dispatch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_web::{App, web};
+    /// use actix_rewrite::Engine;
+    ///
+    /// async fn v2() -> &'static str {
+    ///     "v2"
+    /// }
+    ///
+    /// let root = Engine::new()
+    ///     .rules("RewriteRule ^/v1/(.*) /v2/$1 [L]")
+    ///     .expect("failed to process rules");
+    /// let legacy = Engine::new()
+    ///     .rules("RewriteRule ^/legacy/(.*) /v2/$1 [L]")
+    ///     .expect("failed to process rules");
+    ///
+    /// let app = App::new()
+    ///     .service(web::scope("/legacy").wrap(legacy.middleware()))
+    ///     .service(web::scope("/v2").default_service(web::to(v2)))
+    ///     .wrap(root.into_root_transform());
+    /// ```
+    #[inline]
+    pub fn into_root_transform(self) -> Middleware {
+        Middleware::root(self)
+    }
 }
 
 impl Default for Engine {
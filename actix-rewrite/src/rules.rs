@@ -0,0 +1,293 @@
+//! Rule Source Tracking for Runtime Toggles
+
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use actix_http::Uri;
+
+/// No-op rule substituted for disabled rules.
+///
+/// Always matches without substitution, so `[S=n]` skip counts and
+/// `[C]` chains around the disabled rule keep their original meaning.
+const PLACEHOLDER: &str = "RewriteRule ^ -";
+
+/// Single `RewriteRule` alongside its preceding `RewriteCond` lines.
+#[derive(Clone, Debug)]
+pub(crate) struct RuleBlock {
+    id: Option<String>,
+    source: String,
+    chained: bool,
+    deny: bool,
+}
+
+/// Check if a `RewriteRule` line contains the specified flag.
+fn has_flag(line: &str, short: &str, long: &str) -> bool {
+    line.trim_end()
+        .strip_suffix(']')
+        .and_then(|line| line.rsplit_once('['))
+        .map(|(_, flags)| {
+            flags.split(',').any(|flag| {
+                let flag = flag.trim();
+                flag.eq_ignore_ascii_case(short) || flag.eq_ignore_ascii_case(long)
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Check if the line is a `RewriteRule` directive.
+fn is_rule(line: &str) -> bool {
+    line.split_whitespace()
+        .next()
+        .map(|word| {
+            word.eq_ignore_ascii_case("RewriteRule") || word.eq_ignore_ascii_case("Rewrite")
+        })
+        .unwrap_or(false)
+}
+
+/// Split rule expressions into blocks keyed by an optional
+/// `# id: <name>` comment directly preceding the block.
+pub(crate) fn parse(rules: &str) -> Vec<RuleBlock> {
+    let mut blocks = Vec::new();
+    let mut id = None;
+    let mut source = String::new();
+    for line in rules.lines().map(str::trim) {
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(name) = comment.trim().strip_prefix("id:") {
+                flush(&mut blocks, &mut source);
+                id = Some(name.trim().to_owned());
+            }
+            continue;
+        }
+        source.push_str(line);
+        source.push('\n');
+        if is_rule(line) {
+            blocks.push(RuleBlock {
+                id: id.take(),
+                chained: has_flag(line, "C", "chain"),
+                deny: has_flag(line, "F", "forbidden") || has_flag(line, "G", "gone"),
+                source: std::mem::take(&mut source),
+            });
+        }
+    }
+    flush(&mut blocks, &mut source);
+    blocks
+}
+
+/// Push any remaining directives not belonging to a rule as an untagged block.
+fn flush(blocks: &mut Vec<RuleBlock>, source: &mut String) {
+    if !source.trim().is_empty() {
+        blocks.push(RuleBlock {
+            id: None,
+            chained: false,
+            deny: false,
+            source: std::mem::take(source),
+        });
+    }
+}
+
+/// Render rule blocks back into rule expressions,
+/// replacing disabled rules with a placeholder.
+pub(crate) fn render(blocks: &[RuleBlock], disabled: &HashSet<String>) -> String {
+    let mut rules = String::new();
+    for block in blocks {
+        match block.id.as_ref().is_some_and(|id| disabled.contains(id)) {
+            true if block.chained => rules.push_str(&format!("{PLACEHOLDER} [C]\n")),
+            true => rules.push_str(&format!("{PLACEHOLDER}\n")),
+            false => rules.push_str(&block.source),
+        }
+    }
+    rules
+}
+
+/// Collect enabled rules ending in `[F]` or `[G]` alongside a label
+/// identifying them, either their ID or the rule itself, and the blocks
+/// up to and including the rule in evaluation order.
+pub(crate) fn deny_rules<'a>(
+    blocks: &'a [RuleBlock],
+    disabled: &'a HashSet<String>,
+) -> impl Iterator<Item = (String, &'a [RuleBlock])> {
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, block)| block.deny)
+        .filter(|(_, block)| !block.id.as_ref().is_some_and(|id| disabled.contains(id)))
+        .map(|(n, block)| {
+            let label = match block.id.as_ref() {
+                Some(id) => id.clone(),
+                None => block.source.lines().last().unwrap_or_default().to_owned(),
+            };
+            (label, &blocks[..=n])
+        })
+}
+
+/// Check if the pattern contains an unescaped `|` outside of any group,
+/// in which case no single branch must match.
+fn has_alternation(pattern: &str) -> bool {
+    let mut depth = 0usize;
+    let mut class = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            ']' if class => class = false,
+            _ if class => {}
+            '[' => class = true,
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Extract the literal text any match of the rule pattern must contain,
+/// alongside whether the pattern is anchored to the start of the uri.
+///
+/// Returns `None` when no such literal can be determined.
+fn pattern_literal(line: &str) -> Option<(String, bool)> {
+    if has_flag(line, "NC", "nocase") {
+        return None;
+    }
+    let pattern = line.split_whitespace().nth(1)?;
+    if pattern.starts_with('!') || pattern.starts_with('"') {
+        return None;
+    }
+    if has_alternation(pattern) {
+        return None;
+    }
+    let (anchored, pattern) = match pattern.strip_prefix('^') {
+        Some(pattern) => (true, pattern),
+        None => (false, pattern),
+    };
+    let mut literal = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some(escaped) if !escaped.is_ascii_alphanumeric() => escaped,
+                _ => break,
+            },
+            '.' | '^' | '$' | '%' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' => {
+                break;
+            }
+            c => c,
+        };
+        if matches!(chars.peek(), Some('*' | '?' | '{')) {
+            break;
+        }
+        literal.push(c);
+    }
+    Some((literal, anchored))
+}
+
+/// Cheap check run before evaluating the engine that rejects
+/// request uris no enabled rule could possibly match.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PreFilter(Option<Vec<(String, bool)>>);
+
+impl PreFilter {
+    /// Build a filter from the enabled rule blocks.
+    ///
+    /// The filter is disabled if any rule pattern has no usable literal.
+    pub(crate) fn new(blocks: &[RuleBlock], disabled: &HashSet<String>) -> Self {
+        Self(
+            blocks
+                .iter()
+                .filter(|block| !block.id.as_ref().is_some_and(|id| disabled.contains(id)))
+                .filter_map(|block| block.source.lines().last().filter(|line| is_rule(line)))
+                .map(pattern_literal)
+                .collect(),
+        )
+    }
+
+    /// Check if any rule could match the specified uri
+    ///
+    /// Anchored literals are checked against both the complete uri and
+    /// its path so absolute-form request uris are never skipped wrongly.
+    #[inline]
+    pub(crate) fn matches(&self, uri: &Uri, full: &str) -> bool {
+        let path = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or(full);
+        match self.0.as_ref() {
+            None => true,
+            Some(literals) => literals.iter().any(|(literal, anchored)| match anchored {
+                true => path.starts_with(literal.as_str()) || full.starts_with(literal.as_str()),
+                false => full.contains(literal.as_str()),
+            }),
+        }
+    }
+}
+
+/// Collect the IDs assigned to rule blocks.
+pub(crate) fn ids(blocks: &[RuleBlock]) -> impl Iterator<Item = &str> {
+    blocks.iter().filter_map(|block| block.id.as_deref())
+}
+
+/// Shared handle used to enable or disable rules by ID at runtime.
+///
+/// Rules are tagged with an ID using a `# id: <name>` comment on the
+/// line before the rule (or its conditions). The handle is shared
+/// between every clone of the [`Engine`](crate::Engine) it was taken
+/// from, including those running on other workers.
+///
+/// # Examples
+///
+/// ```
+/// use actix_rewrite::Engine;
+///
+/// let engine = Engine::new()
+///     .rules(r#"
+///         # id: legacy-blog
+///         RewriteRule ^/blog/(.*) /posts/$1 [L]
+///     "#)
+///     .expect("failed to process rules");
+///
+/// let toggles = engine.toggles();
+/// toggles.disable("legacy-blog");
+/// assert!(!toggles.is_enabled("legacy-blog"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RuleToggles {
+    disabled: Arc<RwLock<HashSet<String>>>,
+    generation: Arc<AtomicUsize>,
+}
+
+impl RuleToggles {
+    /// Enable the rule with the specified ID.
+    pub fn enable(&self, id: &str) {
+        self.disabled.write().expect("poisoned lock").remove(id);
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Disable the rule with the specified ID.
+    pub fn disable(&self, id: &str) {
+        self.disabled
+            .write()
+            .expect("poisoned lock")
+            .insert(id.to_owned());
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// Check if the rule with the specified ID is enabled.
+    pub fn is_enabled(&self, id: &str) -> bool {
+        !self.disabled.read().expect("poisoned lock").contains(id)
+    }
+
+    /// Current toggle generation, bumped on every change.
+    #[inline]
+    pub(crate) fn generation(&self) -> usize {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Snapshot of currently disabled rule IDs.
+    pub(crate) fn disabled(&self) -> HashSet<String> {
+        self.disabled.read().expect("poisoned lock").clone()
+    }
+}
@@ -1,8 +1,10 @@
 use std::{ops::Deref, rc::Rc};
 
+use actix_common::metrics::Timer;
 use actix_web::{
+    HttpMessage, HttpResponse,
     body::BoxBody,
-    dev::{Path, Service, ServiceRequest, ServiceResponse, Url, forward_ready},
+    dev::{Path, Payload, Service, ServiceRequest, ServiceResponse, Url, forward_ready},
     error::Error as ActixError,
 };
 use futures_core::future::LocalBoxFuture;
@@ -10,6 +12,16 @@ use futures_core::future::LocalBoxFuture;
 use super::rewrite::{Engine, Rewrite};
 use super::util;
 
+/// Most dispatches of a single request by a root transform
+const MAX_DISPATCH: usize = 10;
+
+/// Marks requests passing through a root transform
+#[derive(Clone, Copy)]
+struct Dispatcher;
+
+/// Body of a request handed back to the root transform
+struct Redispatch(Payload);
+
 /// Assembled `mod_rewrite` service
 #[derive(Clone)]
 pub struct RewriteService<S>(pub(crate) Rc<RewriteInner<S>>);
@@ -25,6 +37,7 @@ impl<S> Deref for RewriteService<S> {
 pub struct RewriteInner<S> {
     pub(crate) service: Rc<S>,
     pub(crate) engine: Rc<Engine>,
+    pub(crate) root: bool,
 }
 
 impl<S> Service<ServiceRequest> for RewriteService<S>
@@ -38,25 +51,76 @@ where
 
     forward_ready!(service);
 
-    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+    fn call(&self, req: ServiceRequest) -> Self::Future {
         let this = Rc::clone(&self.0);
         Box::pin(async move {
-            let after = match this
-                .engine
-                .rewrite(req.request())
-                .inspect_err(|err| tracing::error!("rewrite failed {err:?}"))?
-            {
-                Rewrite::Uri(uri) => uri,
-                Rewrite::Redirect(res) => return Ok(req.into_response(res)),
-                Rewrite::Response(res) => return Ok(req.into_response(res)),
-            };
+            let timer = Timer::start("rewrite", "", req.request());
+            let res = this.serve(req).await;
+            timer.finish(&res);
+            res
+        })
+    }
+}
 
-            let uri = util::join_uri(req.uri(), &after)
-                .inspect_err(|err| tracing::error!("url join failed: {err:?}"))?;
-            req.head_mut().uri = uri.clone();
-            *req.match_info_mut() = Path::new(Url::new(uri));
+impl<S> RewriteInner<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = ActixError> + 'static,
+{
+    /// Rewrite the request uri before passing it to the wrapped service
+    async fn serve(&self, mut req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        if self.root {
+            req.extensions_mut().insert(Dispatcher);
+        }
+        util::record_request_line(req.request());
+        let counted = self.engine.loops().redirect_count(req.request()).is_some();
+        let after = match self
+            .engine
+            .rewrite(req.request())
+            .inspect_err(|err| tracing::error!("rewrite failed {err:?}"))?
+        {
+            Rewrite::Uri(uri) => uri,
+            Rewrite::Redirect(res) => return Ok(req.into_response(res)),
+            Rewrite::Response(res) => return Ok(req.into_response(res)),
+        };
 
-            this.service.call(req).await
-        })
+        let uri = util::join_uri(req.uri(), &after)
+            .inspect_err(|err| tracing::error!("url join failed: {err:?}"))?;
+        let moved = uri.path() != req.uri().path();
+        req.head_mut().uri = uri.clone();
+        *req.match_info_mut() = Path::new(Url::new(uri));
+
+        // let the root transform select the route for the rewritten path
+        if moved && !self.root && req.extensions().contains::<Dispatcher>() {
+            let (http_req, payload) = req.into_parts();
+            http_req.extensions_mut().insert(Redispatch(payload));
+            return Ok(ServiceResponse::new(
+                http_req,
+                HttpResponse::NotFound().finish(),
+            ));
+        }
+
+        let mut res = match self.root {
+            true => self.dispatch(req).await?,
+            false => self.service.call(req).await?,
+        };
+        if counted {
+            self.engine.loops().clear(res.headers_mut());
+        }
+        Ok(res)
+    }
+    /// Call the wrapped service again for requests handed back by nested engines
+    async fn dispatch(&self, mut req: ServiceRequest) -> Result<ServiceResponse, ActixError> {
+        for _ in 0..MAX_DISPATCH {
+            let res = self.service.call(req).await?;
+            let Some(Redispatch(payload)) = res.request().extensions_mut().remove() else {
+                return Ok(res);
+            };
+            let (http_req, _) = res.into_parts();
+            req = ServiceRequest::from_parts(http_req, payload);
+            let uri = req.uri().clone();
+            *req.match_info_mut() = Path::new(Url::new(uri));
+        }
+        tracing::error!("rewrite dispatch exceeded limit of {MAX_DISPATCH}");
+        Ok(req.into_response(HttpResponse::InternalServerError().finish()))
     }
 }
@@ -0,0 +1,232 @@
+//! Encoding and Normalization of Rewritten Targets
+
+use std::str::FromStr;
+
+use actix_common::Normalizer;
+use actix_http::Uri;
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+
+use crate::error::Error;
+
+/// Characters escaped within rewritten paths and fragments
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}')
+    .add(b'|')
+    .add(b'\\')
+    .add(b'^');
+
+/// Characters escaped within rewritten query strings
+const QUERY: &AsciiSet = &PATH.add(b'#');
+
+/// Components of a rewritten target
+struct Parts<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Parts<'a> {
+    fn parse(target: &'a str) -> Self {
+        let (rest, fragment) = match target.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (target, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (scheme, authority, path) = match rest.split_once("://") {
+            Some((scheme, rest)) if !scheme.is_empty() && !scheme.contains('/') => {
+                let idx = rest.find('/').unwrap_or(rest.len());
+                (Some(scheme), Some(&rest[..idx]), &rest[idx..])
+            }
+            _ => (None, None, rest),
+        };
+        Self {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+}
+
+/// Convert the host of an authority to its ASCII form using punycode
+fn ascii_authority(authority: &str) -> String {
+    let (userinfo, hostport) = match authority.rsplit_once('@') {
+        Some((userinfo, hostport)) => (Some(userinfo), hostport),
+        None => (None, authority),
+    };
+    let (host, port) = match hostport.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => (host, Some(port)),
+        _ => (hostport, None),
+    };
+    let host = match host.is_ascii() {
+        true => host.to_owned(),
+        false => idna::domain_to_ascii(host).unwrap_or_else(|_| {
+            tracing::warn!("invalid internationalized host {host:?}");
+            utf8_percent_encode(host, PATH).to_string()
+        }),
+    };
+    let mut ascii = String::with_capacity(authority.len());
+    if let Some(userinfo) = userinfo {
+        ascii.push_str(&utf8_percent_encode(userinfo, PATH).to_string());
+        ascii.push('@');
+    }
+    ascii.push_str(&host);
+    if let Some(port) = port {
+        ascii.push(':');
+        ascii.push_str(port);
+    }
+    ascii
+}
+
+/// Collapse runs of slashes within the path into a single slash
+fn merge_slashes(path: &str) -> String {
+    let mut merged = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c == '/' && merged.ends_with('/') {
+            continue;
+        }
+        merged.push(c);
+    }
+    merged
+}
+
+/// Encoding and normalization applied to targets produced by the rules
+///
+/// Legacy rules frequently produce targets containing raw Unicode, which
+/// is not valid within an uri or a `Location` header. Targets are
+/// converted from IRIs to URIs by percent-encoding non-ASCII characters
+/// as UTF-8 and converting internationalized hosts to punycode. Existing
+/// `%xx` escapes are preserved.
+///
+/// # Examples
+///
+/// ```
+/// use actix_rewrite::TargetNormalizer;
+///
+/// let targets = TargetNormalizer::new().merge_slashes(true);
+/// assert_eq!(targets.encode("/café//menu/../carte"), "/caf%C3%A9/carte");
+/// assert_eq!(targets.encode("https://bücher.example/"), "https://xn--bcher-kva.example/");
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct TargetNormalizer {
+    encode_unicode: bool,
+    punycode_host: bool,
+    merge_slashes: bool,
+    remove_dot_segments: bool,
+}
+
+impl Default for TargetNormalizer {
+    fn default() -> Self {
+        Self {
+            encode_unicode: true,
+            punycode_host: true,
+            merge_slashes: false,
+            remove_dot_segments: true,
+        }
+    }
+}
+
+impl TargetNormalizer {
+    /// Construct a new normalizer with the default options.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Percent-encode non-ASCII and unsafe characters as UTF-8.
+    ///
+    /// Default is enabled.
+    pub fn encode_unicode(mut self, enable: bool) -> Self {
+        self.encode_unicode = enable;
+        self
+    }
+
+    /// Convert internationalized hosts to punycode.
+    ///
+    /// Default is enabled.
+    pub fn punycode_host(mut self, enable: bool) -> Self {
+        self.punycode_host = enable;
+        self
+    }
+
+    /// Collapse duplicate slashes within the path.
+    ///
+    /// Default is disabled.
+    pub fn merge_slashes(mut self, enable: bool) -> Self {
+        self.merge_slashes = enable;
+        self
+    }
+
+    /// Remove `.` and `..` path segments.
+    ///
+    /// Default is enabled.
+    pub fn remove_dot_segments(mut self, enable: bool) -> Self {
+        self.remove_dot_segments = enable;
+        self
+    }
+
+    /// Normalize the path of a target
+    fn path(&self, path: &str) -> String {
+        let mut path = match self.merge_slashes {
+            true => merge_slashes(path),
+            false => path.to_owned(),
+        };
+        if self.remove_dot_segments {
+            path = Normalizer::new()
+                .decode_percent(false)
+                .merge_slashes(false)
+                .path(&path);
+        }
+        match self.encode_unicode {
+            true => utf8_percent_encode(&path, PATH).to_string(),
+            false => path,
+        }
+    }
+
+    /// Encode and normalize a target, keeping any fragment.
+    pub fn encode(&self, target: &str) -> String {
+        let parts = Parts::parse(target);
+        let mut encoded = String::with_capacity(target.len());
+        if let Some(scheme) = parts.scheme {
+            encoded.push_str(scheme);
+            encoded.push_str("://");
+        }
+        if let Some(authority) = parts.authority {
+            match self.punycode_host {
+                true => encoded.push_str(&ascii_authority(authority)),
+                false => encoded.push_str(authority),
+            }
+        }
+        encoded.push_str(&self.path(parts.path));
+        let parts = [('?', parts.query, QUERY), ('#', parts.fragment, PATH)];
+        for (sep, part, set) in parts {
+            let Some(part) = part else {
+                continue;
+            };
+            encoded.push(sep);
+            match self.encode_unicode {
+                true => encoded.push_str(&utf8_percent_encode(part, set).to_string()),
+                false => encoded.push_str(part),
+            }
+        }
+        encoded
+    }
+
+    /// Encode and normalize a target into an [`Uri`], dropping any fragment.
+    pub fn uri(&self, target: &str) -> Result<Uri, Error> {
+        let target = target.split_once('#').map(|(t, _)| t).unwrap_or(target);
+        Ok(Uri::from_str(&self.encode(target))?)
+    }
+}
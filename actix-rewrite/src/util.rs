@@ -3,27 +3,65 @@
 use std::{collections::HashMap, str::FromStr};
 
 use actix_http::Uri;
-use actix_web::{HttpRequest, web::Query};
+use actix_web::{HttpMessage, HttpRequest, web::Query};
 use mod_rewrite::context::{RequestCtx, ServerCtx};
 
 use super::error::Error;
 
 type QueryMap = Query<HashMap<String, String>>;
 
-#[inline]
-pub(crate) fn recode(uri: String) -> Result<Uri, Error> {
-    Ok(Uri::from_str(&uri)?)
+/// Request line as received before any rewriting
+#[derive(Clone, Debug)]
+struct OriginalRequest {
+    line: String,
+    path: String,
+}
+
+impl OriginalRequest {
+    fn new(req: &HttpRequest) -> Self {
+        let target = req
+            .uri()
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/");
+        Self {
+            line: format!("{} {target} {:?}", req.method(), req.version()),
+            path: req.uri().path().to_owned(),
+        }
+    }
+}
+
+/// Record the request line before the request uri is rewritten.
+///
+/// `%{THE_REQUEST}` and `%{REQUEST_URI}` keep reflecting the recorded
+/// request line for every later rewrite pass. Only the first call for a
+/// request has any effect.
+pub fn record_request_line(req: &HttpRequest) {
+    if !req.extensions().contains::<OriginalRequest>() {
+        req.extensions_mut().insert(OriginalRequest::new(req));
+    }
 }
 
 /// Build [`mod_rewrite::context::RequestCtx`]
 /// using [`HttpRequest`] data.
+///
+/// Like Apache, `%{THE_REQUEST}` holds the full request line and
+/// `%{REQUEST_URI}` the path without the query string, both exactly as
+/// received and without decoding.
 pub fn request_ctx(req: &HttpRequest) -> RequestCtx {
+    let uri = actix_common::normalized_uri(req);
+    let original = req
+        .extensions()
+        .get::<OriginalRequest>()
+        .cloned()
+        .unwrap_or_else(|| OriginalRequest::new(req));
     RequestCtx::default()
         .path_info(req.match_info().unprocessed())
-        .request_uri(req.uri().to_string())
+        .request_uri(original.path)
+        .the_request(original.line)
         .request_method(req.method().to_string())
-        .query_string(req.uri().query().unwrap_or(""))
-        .maybe_remote_addr(req.peer_addr())
+        .query_string(uri.query().unwrap_or(""))
+        .maybe_remote_addr(actix_common::client_addr(req))
         .expect("invalid peer address")
 }
 
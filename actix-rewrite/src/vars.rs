@@ -0,0 +1,183 @@
+//! Custom Variable Namespaces for Rule Expressions
+
+use std::{collections::HashMap, sync::Arc};
+
+use actix_web::HttpRequest;
+
+/// Provider of values for a custom `%{NAMESPACE:key}` variable namespace.
+///
+/// Implemented for any `Fn(&HttpRequest, &str) -> Option<String>` closure.
+/// See [`Engine::var_provider`](crate::Engine::var_provider) for more details.
+pub trait VarProvider: Send + Sync {
+    fn var(&self, req: &HttpRequest, key: &str) -> Option<String>;
+}
+
+impl<F> VarProvider for F
+where
+    F: Fn(&HttpRequest, &str) -> Option<String> + Send + Sync,
+{
+    #[inline]
+    fn var(&self, req: &HttpRequest, key: &str) -> Option<String> {
+        (self)(req, key)
+    }
+}
+
+/// Reference to a custom variable within the rule expressions
+pub(crate) type VarRef = (String, String);
+
+/// Registered custom variable providers keyed by namespace
+#[derive(Clone, Default)]
+pub(crate) struct Vars(HashMap<String, Arc<dyn VarProvider>>);
+
+impl Vars {
+    #[inline]
+    pub(crate) fn insert(&mut self, namespace: &str, provider: Arc<dyn VarProvider>) {
+        self.0.insert(namespace.to_owned(), provider);
+    }
+
+    /// Collect unique references to registered namespaces within the rules
+    pub(crate) fn references(&self, rules: &str) -> Vec<VarRef> {
+        let mut refs = vec![];
+        let mut rest = rules;
+        while let Some(start) = rest.find("%{") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            if let Some((namespace, key)) = rest[..end].split_once(':') {
+                let var = (namespace.to_owned(), key.to_owned());
+                if self.0.contains_key(namespace) && !refs.contains(&var) {
+                    refs.push(var);
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        refs
+    }
+
+    /// Resolve the values of the referenced variables for the request
+    pub(crate) fn resolve(&self, req: &HttpRequest, refs: &[VarRef]) -> Vec<String> {
+        refs.iter()
+            .map(|(namespace, key)| {
+                self.0
+                    .get(namespace)
+                    .and_then(|provider| provider.var(req, key))
+                    .map(|value| sanitize(&value))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+/// Restrict values to characters which cannot alter rule syntax
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || "-._~:/,=@+".contains(*c))
+        .collect()
+}
+
+/// Segment of a rule argument
+#[derive(Clone)]
+enum Segment {
+    Text(String),
+    Var(usize),
+}
+
+/// Line of the rules split into arguments when it references variables
+#[derive(Clone)]
+enum Line {
+    Plain(String),
+    Args(Vec<Vec<Segment>>),
+}
+
+/// Rules tokenized once so each variant only substitutes resolved values.
+#[derive(Clone, Default)]
+pub(crate) struct Template(Vec<Line>);
+
+impl Template {
+    /// Split the rules on references to the specified variables
+    pub(crate) fn new(rules: &str, refs: &[VarRef]) -> Self {
+        let lines = rules
+            .lines()
+            .map(|line| match !refs.is_empty() && line.contains("%{") {
+                true => Line::Args(
+                    line.split_whitespace()
+                        .map(|arg| segments(arg, refs))
+                        .collect(),
+                ),
+                false => Line::Plain(line.to_owned()),
+            })
+            .collect();
+        Self(lines)
+    }
+
+    /// Expand the variable references using the resolved values.
+    ///
+    /// Arguments left empty by an unset variable are replaced with `""`.
+    pub(crate) fn expand(&self, values: &[String]) -> String {
+        let mut expanded = String::new();
+        for line in self.0.iter() {
+            match line {
+                Line::Plain(line) => expanded.push_str(line),
+                Line::Args(args) => {
+                    let args: Vec<_> = args
+                        .iter()
+                        .map(|segments| {
+                            let arg: String = segments
+                                .iter()
+                                .map(|segment| match segment {
+                                    Segment::Text(text) => text.as_str(),
+                                    Segment::Var(idx) => values[*idx].as_str(),
+                                })
+                                .collect();
+                            match arg.is_empty() {
+                                true => "\"\"".to_owned(),
+                                false => arg,
+                            }
+                        })
+                        .collect();
+                    expanded.push_str(&args.join(" "));
+                }
+            }
+            expanded.push('\n');
+        }
+        expanded
+    }
+}
+
+/// Split a rule argument into text and variable reference segments
+fn segments(arg: &str, refs: &[VarRef]) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find("%{") {
+        let name = &rest[start + 2..];
+        let var = name.find('}').and_then(|end| {
+            let (namespace, key) = name[..end].split_once(':')?;
+            let idx = refs
+                .iter()
+                .position(|(ns, k)| ns == namespace && k == key)?;
+            Some((idx, end))
+        });
+        match var {
+            Some((idx, end)) => {
+                text.push_str(&rest[..start]);
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Var(idx));
+                rest = &name[end + 1..];
+            }
+            None => {
+                text.push_str(&rest[..start + 2]);
+                rest = name;
+            }
+        }
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    segments
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use actix_http::header::{self, HeaderValue};
-use actix_rewrite::Engine;
+use actix_rewrite::{Engine, Rewrite};
 use actix_web::{
     HttpRequest, HttpResponse, Responder, body, get,
     test::{self, TestRequest},
@@ -63,3 +63,502 @@ async fn basic_rewrite() {
     assert_eq!(json.query.get("a"), Some(&"b".to_string()));
     assert_eq!(json.query.get("page"), Some(&"1/2/3".to_string()));
 }
+
+#[actix_web::test]
+async fn rule_toggles() {
+    let engine = Engine::new()
+        .rules(
+            r#"
+        # id: legacy
+        Rewrite /old/([\w/]*) /index.php?page=$1 [L]
+    "#,
+        )
+        .expect("failed to load rules");
+    assert_eq!(engine.rule_ids().collect::<Vec<_>>(), vec!["legacy"]);
+
+    let toggles = engine.toggles();
+    let version = engine.rules_version();
+    let status = engine.clone();
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/old/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+
+    toggles.disable("legacy");
+    let req = TestRequest::with_uri("/old/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "404 Not Found");
+    assert_ne!(status.rules_version(), version);
+
+    // the version follows the enforced rules rather than the toggle count
+    toggles.enable("legacy");
+    let req = TestRequest::with_uri("/old/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+    assert_eq!(status.rules_version(), version);
+}
+
+#[actix_web::test]
+async fn deny_template() {
+    use actix_http::StatusCode;
+    use actix_rewrite::DenyTemplate;
+
+    let engine = Engine::new()
+        .deny_template(
+            StatusCode::FORBIDDEN,
+            DenyTemplate::json(r#"{"error": "{reason}", "rule": "{rule}"}"#),
+        )
+        .rules(
+            r#"
+        # id: block-admin
+        Rewrite /admin/(.*) - [F]
+    "#,
+        )
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/admin/panel").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "403 Forbidden");
+    assert_eq!(
+        res.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("application/json"))
+    );
+
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(data, r#"{"error": "Forbidden", "rule": "block-admin"}"#);
+}
+
+#[actix_web::test]
+async fn deny_attribution() {
+    use actix_http::StatusCode;
+    use actix_rewrite::DenyTemplate;
+
+    let engine = Engine::new()
+        .deny_template(StatusCode::FORBIDDEN, DenyTemplate::html("{rule}"))
+        .rules(
+            r#"
+        # id: rename-old
+        Rewrite ^/old/(.*) /admin/$1
+        # id: block-old
+        Rewrite ^/old/ - [F]
+        # id: block-admin
+        Rewrite ^/admin/ - [F]
+    "#,
+        )
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    // the uri is rewritten before the first deny rule is evaluated
+    let req = TestRequest::with_uri("/old/panel").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(data, "block-admin");
+}
+
+#[actix_web::test]
+async fn prefilter_passthrough() {
+    let engine = Engine::new()
+        .rules("Rewrite ^/legacy/(.*) /index.php?page=$1 [L]")
+        .expect("failed to load rules");
+
+    let req = TestRequest::with_uri("/index.php?a=b").to_http_request();
+    match engine.rewrite(&req).expect("rewrite failed") {
+        Rewrite::Uri(uri) => assert_eq!(uri.to_string(), "/index.php?a=b"),
+        _ => panic!("unexpected rewrite"),
+    }
+}
+
+#[actix_web::test]
+async fn prefilter_alternation() {
+    let engine = Engine::new()
+        .rules("Rewrite ^/legacy/(.*)|^/old/(.*) /index.php [L]")
+        .expect("failed to load rules");
+
+    for uri in ["/legacy/page", "/old/page"] {
+        let req = TestRequest::with_uri(uri).to_http_request();
+        match engine.rewrite(&req).expect("rewrite failed") {
+            Rewrite::Uri(uri) => assert_eq!(uri.path(), "/index.php"),
+            _ => panic!("unexpected rewrite"),
+        }
+    }
+}
+
+#[actix_web::test]
+async fn rewrite_before_routing() {
+    async fn v2(req: HttpRequest) -> impl Responder {
+        format!("v2 {}", req.match_info().unprocessed())
+    }
+
+    let engine = Engine::new()
+        .rules("Rewrite ^/v1/(.*) /v2/$1 [L]")
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .service(web::scope("/v2").default_service(web::to(v2)))
+            .wrap(engine.middleware()),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/v1/users").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(data, "v2 /users");
+}
+
+#[actix_web::test]
+async fn root_transform() {
+    async fn v2(req: HttpRequest) -> impl Responder {
+        format!("v2 {}", req.match_info().unprocessed())
+    }
+    async fn legacy() -> impl Responder {
+        "legacy"
+    }
+
+    let app = || {
+        let legacy_engine = Engine::new()
+            .rules("RewriteRule ^/legacy/(.*) /v2/$1 [L]")
+            .expect("failed to load rules");
+        actix_web::App::new()
+            .service(
+                web::scope("/legacy")
+                    .wrap(legacy_engine.middleware())
+                    .default_service(web::to(legacy)),
+            )
+            .service(web::scope("/v2").default_service(web::to(v2)))
+    };
+
+    // scoped rewrites cannot change the selected route on their own
+    let srv = test::init_service(app()).await;
+    let req = TestRequest::with_uri("/legacy/users").to_request();
+    let res = test::call_service(&srv, req).await;
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(data, "legacy");
+
+    // the root transform dispatches the rewritten request again
+    let srv = test::init_service(app().wrap(Engine::new().into_root_transform())).await;
+    let req = TestRequest::with_uri("/legacy/users").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+    let data = body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(data, "v2 /users");
+
+    // endless rewrites between scopes are stopped
+    let looping = Engine::new()
+        .rules("RewriteRule ^/a/(.*) /b/$1 [L]\nRewriteRule ^/b/(.*) /a/$1 [L]")
+        .expect("failed to load rules");
+    let srv = test::init_service(
+        actix_web::App::new()
+            .service(web::scope("").wrap(looping.middleware()))
+            .wrap(Engine::new().into_root_transform()),
+    )
+    .await;
+    let req = TestRequest::with_uri("/a/x").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "500 Internal Server Error");
+}
+
+#[actix_web::test]
+async fn custom_vars() {
+    struct Flags(Vec<&'static str>);
+
+    let engine = Engine::new()
+        .data_provider("FLAG", |flags: &Flags, key| {
+            Some(if flags.0.contains(&key) { "on" } else { "off" }.to_owned())
+        })
+        .rules(
+            r#"
+        RewriteCond %{FLAG:new-index} =on
+        Rewrite /one/([\w/]*) /index.php?page=$1 [L]
+    "#,
+        )
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(Flags(vec!["new-index"])))
+            .wrap(engine.clone().middleware())
+            .service(index),
+    )
+    .await;
+    let req = TestRequest::with_uri("/one/1").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .app_data(web::Data::new(Flags(vec![])))
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+    let req = TestRequest::with_uri("/one/1").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "404 Not Found");
+}
+
+#[actix_web::test]
+async fn redirect_caching() {
+    use actix_http::StatusCode;
+    use actix_web::http::header::{CacheControl, CacheDirective};
+
+    let engine = Engine::new()
+        .cache_control(
+            StatusCode::MOVED_PERMANENTLY,
+            CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(3600)]),
+        )
+        .rules("Rewrite /go/(.*) /index.php?page=$1 [R=301]")
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/go/docs").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "301 Moved Permanently");
+    assert_eq!(
+        res.headers().get(header::CACHE_CONTROL),
+        Some(&HeaderValue::from_static("public, max-age=3600"))
+    );
+    let etag = res
+        .headers()
+        .get(header::ETAG)
+        .expect("missing etag")
+        .clone();
+    // validators are stable across processes and builds
+    assert_eq!(etag, "\"d896d668f5e727c0\"");
+
+    let req = TestRequest::with_uri("/go/docs")
+        .insert_header((header::IF_NONE_MATCH, etag.clone()))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "304 Not Modified");
+    assert_eq!(res.headers().get(header::ETAG), Some(&etag));
+    assert_eq!(
+        res.headers().get(header::LOCATION),
+        Some(&HeaderValue::from_static("/index.php?page=docs"))
+    );
+    assert_eq!(
+        res.headers().get(header::CACHE_CONTROL),
+        Some(&HeaderValue::from_static("public, max-age=3600"))
+    );
+
+    let req = TestRequest::with_uri("/go/other")
+        .insert_header((header::IF_NONE_MATCH, etag))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "301 Moved Permanently");
+}
+
+#[actix_web::test]
+async fn the_request() {
+    let engine = Engine::new()
+        .rules(
+            r#"
+        RewriteCond %{THE_REQUEST} ^GET\s/legacy\?id=1\sHTTP/1\.1$
+        Rewrite /legacy /index.php?page=legacy [L]
+        RewriteCond %{REQUEST_URI} ^/old%20name$
+        Rewrite /old /index.php?page=old [L]
+    "#,
+        )
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/legacy?id=1").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+
+    let req = TestRequest::with_uri("/legacy?id=2").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "404 Not Found");
+
+    let req = TestRequest::with_uri("/old%20name").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+}
+
+#[actix_web::test]
+async fn provided_rules() {
+    use actix_rewrite::{MemoryProvider, ProvidedRules};
+
+    let provider = MemoryProvider::new("Rewrite /old/([\\w/]*) /index.php?page=$1 [L]");
+    let rules = ProvidedRules::new();
+    assert!(rules.refresh(&provider).await.unwrap());
+    assert!(!rules.refresh(&provider).await.unwrap());
+
+    let engine = Engine::new().provided_rules(rules.clone());
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/old/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+
+    provider.set("Rewrite /new/([\\w/]*) /index.php?page=$1 [L]");
+    assert!(rules.refresh(&provider).await.unwrap());
+
+    let req = TestRequest::with_uri("/old/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "404 Not Found");
+
+    let req = TestRequest::with_uri("/new/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "200 OK");
+}
+
+#[actix_web::test]
+async fn redirect_loop() {
+    use actix_http::StatusCode;
+
+    let engine = Engine::new()
+        .redirect_limit(2)
+        .rules(
+            r#"
+        Rewrite /self /self [R=302]
+        Rewrite /ping /pong [R=302]
+        Rewrite /pong /ping [R=302]
+    "#,
+        )
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.clone().middleware())
+            .service(index),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/self").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FOUND);
+
+    let req = TestRequest::with_uri("/ping").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FOUND);
+    let cookie = res.headers().get(header::SET_COOKIE).unwrap();
+    assert!(cookie.to_str().unwrap().starts_with("rewrite-redirects=1;"));
+
+    let req = TestRequest::with_uri("/pong")
+        .insert_header((header::COOKIE, "rewrite-redirects=1"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FOUND);
+
+    let req = TestRequest::with_uri("/ping")
+        .insert_header((header::COOKIE, "rewrite-redirects=2"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::LOOP_DETECTED);
+}
+
+#[actix_web::test]
+async fn result_cache() {
+    use actix_rewrite::ResultCache;
+
+    let cache = ResultCache::new().capacity(1);
+    let engine = Engine::new()
+        .result_cache(cache.clone())
+        .rules(r#"Rewrite /one/([\w/]*) /index.php?page=$1 [L]"#)
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    for uri in ["/one/a", "/one/a", "/one/b", "/one/a"] {
+        let req = TestRequest::with_uri(uri).to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status().to_string(), "200 OK");
+    }
+
+    let stats = cache.stats();
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.misses, 3);
+    assert_eq!(stats.hit_rate(), 0.25);
+}
+
+#[actix_web::test]
+async fn result_cache_hosts() {
+    use actix_rewrite::ResultCache;
+
+    let cache = ResultCache::new();
+    let engine = Engine::new()
+        .result_cache(cache.clone())
+        .rules(
+            r#"
+        RewriteCond %{HTTP_HOST} ^admin\.example\.com$
+        RewriteRule ^/ - [F]
+    "#,
+        )
+        .expect("failed to load rules");
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.middleware())
+            .service(index),
+    )
+    .await;
+
+    for (host, status) in [
+        ("admin.example.com", "403 Forbidden"),
+        ("www.example.com", "404 Not Found"),
+        ("admin.example.com", "403 Forbidden"),
+    ] {
+        let req = TestRequest::with_uri("/")
+            .insert_header(("Host", host))
+            .to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status().to_string(), status, "{host}");
+    }
+    assert_eq!(cache.stats().hits, 1);
+
+    let srv = test::init_service(
+        actix_web::App::new()
+            .wrap(engine.reject_self_redirects(true).middleware())
+            .service(index),
+    )
+    .await;
+
+    let req = TestRequest::with_uri("/self").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::LOOP_DETECTED);
+}
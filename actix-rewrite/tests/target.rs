@@ -0,0 +1,51 @@
+use actix_rewrite::TargetNormalizer;
+
+#[test]
+fn encode_unicode() {
+    let targets = TargetNormalizer::new();
+    assert_eq!(targets.encode("/café/menu"), "/caf%C3%A9/menu");
+    assert_eq!(targets.encode("/a b?q=ü&x=1"), "/a%20b?q=%C3%BC&x=1");
+    assert_eq!(targets.encode("/already%20encoded"), "/already%20encoded");
+    assert_eq!(targets.encode("/page#süd"), "/page#s%C3%BCd");
+
+    let raw = TargetNormalizer::new().encode_unicode(false);
+    assert_eq!(raw.encode("/café"), "/café");
+}
+
+#[test]
+fn punycode_host() {
+    let targets = TargetNormalizer::new();
+    assert_eq!(
+        targets.encode("https://bücher.example:8443/straße"),
+        "https://xn--bcher-kva.example:8443/stra%C3%9Fe"
+    );
+    assert_eq!(
+        targets.encode("http://user@example.com/"),
+        "http://user@example.com/"
+    );
+
+    let raw = TargetNormalizer::new().punycode_host(false);
+    assert_eq!(raw.encode("https://bücher.example/"), "https://bücher.example/");
+}
+
+#[test]
+fn path_normalization() {
+    let targets = TargetNormalizer::new();
+    assert_eq!(targets.encode("/a/./b/../c"), "/a/c");
+    assert_eq!(targets.encode("/a//b"), "/a//b");
+
+    let merged = TargetNormalizer::new().merge_slashes(true);
+    assert_eq!(merged.encode("/a//b///c"), "/a/b/c");
+    assert_eq!(merged.encode("https://example.com//a"), "https://example.com/a");
+
+    let kept = TargetNormalizer::new().remove_dot_segments(false);
+    assert_eq!(kept.encode("/a/../b"), "/a/../b");
+}
+
+#[test]
+fn into_uri() {
+    let targets = TargetNormalizer::new();
+    let uri = targets.uri("/ünïcode?q=1#top").unwrap();
+    assert_eq!(uri.path(), "/%C3%BCn%C3%AFcode");
+    assert_eq!(uri.query(), Some("q=1"));
+}
@@ -0,0 +1,180 @@
+//! ACME HTTP-01 Challenge Responder
+//!
+//! Answers `/.well-known/acme-challenge/{token}` requests locally with the
+//! key authorization of pending challenges, so certificate issuance and
+//! renewal keep working while the FastCGI or proxy backends are down.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::App;
+//! use actix_services::{acme::AcmeChallenge, revproxy::RevProxy};
+//!
+//! let acme = AcmeChallenge::memory();
+//! acme.insert("LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0", "LoqXcYV8...key-auth");
+//!
+//! let app = App::new()
+//!     .service(acme.clone())
+//!     .service(RevProxy::new("/", "http://127.0.0.1:8080"));
+//! ```
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use actix_web::{
+    HttpRequest, HttpResponse,
+    dev::{AppService, HttpServiceFactory},
+    http::header,
+    web,
+};
+
+/// Path prefix of HTTP-01 challenge requests (RFC 8555 section 8.3)
+pub const CHALLENGE_PATH: &str = "/.well-known/acme-challenge";
+
+/// Source of the key authorizations of pending challenges
+#[derive(Clone, Debug)]
+enum TokenStore {
+    Memory(Arc<RwLock<HashMap<String, String>>>),
+    Directory(PathBuf),
+}
+
+/// Responder for ACME HTTP-01 challenges
+///
+/// Tokens are served from an in-memory store updated by an in-process
+/// ACME client, or from a directory where an external client such as
+/// `certbot --webroot` writes one file per token. Clones share the
+/// in-memory store. Unknown tokens are answered with `404 Not Found`,
+/// which a [`Link`](crate::chain::Link) passes on to the next link.
+///
+/// Register the responder before other services, or use
+/// [`AcmeChallenge::link`] as the first link of a chain.
+#[derive(Clone, Debug)]
+pub struct AcmeChallenge {
+    store: TokenStore,
+}
+
+impl AcmeChallenge {
+    /// Creates a new responder serving tokens inserted at runtime.
+    pub fn memory() -> Self {
+        Self {
+            store: TokenStore::Memory(Arc::default()),
+        }
+    }
+
+    /// Creates a new responder serving tokens from files within the directory.
+    ///
+    /// Each file is named after its token and contains the key authorization.
+    pub fn directory<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            store: TokenStore::Directory(path.into()),
+        }
+    }
+
+    /// Publish the key authorization for a pending challenge token.
+    ///
+    /// Ignored for directory backed responders.
+    pub fn insert<T: Into<String>, K: Into<String>>(&self, token: T, key_authorization: K) {
+        match &self.store {
+            TokenStore::Memory(tokens) => {
+                let mut tokens = tokens.write().expect("poisoned lock");
+                tokens.insert(token.into(), key_authorization.into());
+            }
+            TokenStore::Directory(_) => {
+                tracing::warn!("acme responder is directory backed. ignoring token")
+            }
+        }
+    }
+
+    /// Remove a challenge token once validation completed.
+    pub fn remove(&self, token: &str) {
+        if let TokenStore::Memory(tokens) = &self.store {
+            tokens.write().expect("poisoned lock").remove(token);
+        }
+    }
+
+    /// Retrieve the key authorization for the challenge token, if any.
+    pub async fn lookup(&self, token: &str) -> Option<String> {
+        if !is_valid_token(token) {
+            return None;
+        }
+        match &self.store {
+            TokenStore::Memory(tokens) => tokens.read().expect("poisoned lock").get(token).cloned(),
+            TokenStore::Directory(dir) => {
+                let path = dir.join(token);
+                let content = web::block(move || std::fs::read_to_string(path)).await;
+                match content {
+                    Ok(Ok(content)) => Some(content.trim().to_owned()),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Answer the challenge request from the token store
+    async fn respond(&self, req: HttpRequest) -> HttpResponse {
+        // links receive the path with the challenge prefix already stripped
+        let path = req.path();
+        let token = path
+            .rsplit_once(CHALLENGE_PATH)
+            .map_or(path, |(_, token)| token)
+            .trim_start_matches('/');
+        match self.lookup(token).await {
+            Some(key_authorization) => {
+                tracing::debug!("answering acme challenge {token}");
+                HttpResponse::Ok()
+                    .content_type("application/octet-stream")
+                    .insert_header((header::CACHE_CONTROL, "no-store"))
+                    .body(key_authorization)
+            }
+            None => HttpResponse::NotFound().finish(),
+        }
+    }
+
+    /// Convert into a [`Link`](crate::chain::Link) answering challenges
+    /// ahead of the remaining links of a chain.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_services::{
+    ///     acme::AcmeChallenge,
+    ///     chain::{Chain, Link},
+    ///     fastcgi::FastCGI,
+    /// };
+    ///
+    /// let chain = Chain::default()
+    ///     .link(AcmeChallenge::directory("/var/www/acme").link())
+    ///     .link(Link::new(FastCGI::new("", ".", "tcp://127.0.0.1:9000")));
+    /// ```
+    #[cfg(feature = "chain")]
+    pub fn link(self) -> crate::chain::Link {
+        crate::chain::Link::new(web::get().to(move |req: HttpRequest| {
+            let this = self.clone();
+            async move { this.respond(req).await }
+        }))
+        .prefix(CHALLENGE_PATH)
+    }
+}
+
+/// Check the token only contains base64url characters (RFC 8555 section 8.1)
+fn is_valid_token(token: &str) -> bool {
+    !token.is_empty()
+        && token
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+impl HttpServiceFactory for AcmeChallenge {
+    fn register(self, config: &mut AppService) {
+        let path = format!("{CHALLENGE_PATH}/{{token}}");
+        web::resource(path)
+            .route(web::get().to(move |req: HttpRequest| {
+                let this = self.clone();
+                async move { this.respond(req).await }
+            }))
+            .register(config)
+    }
+}
@@ -0,0 +1,611 @@
+//! Declarative configuration for workspace services.
+//!
+//! Mounts for FastCGI, reverse-proxies, and chains along with their rewrite
+//! rules and ModSecurity policies can be declared in a TOML or YAML file and
+//! registered onto an [`App`](actix_web::App) in a single call.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(all(feature = "toml", feature = "fastcgi", feature = "revproxy", feature = "rewrite"))]
+//! # {
+//! use actix_web::App;
+//! use actix_services::config::Config;
+//!
+//! let config = Config::from_toml(r#"
+//!     [[mount]]
+//!     path = "/api"
+//!     type = "proxy"
+//!     upstream = "http://127.0.0.1:8080"
+//!     change_host = true
+//!
+//!     [[mount]]
+//!     type = "chain"
+//!     rewrite = { rules = "RewriteRule /old/(.*) /new/$1 [R=301]" }
+//!
+//!     [[mount.links]]
+//!     type = "fastcgi"
+//!     root = "."
+//!     address = "tcp://127.0.0.1:9000"
+//!     index = ["index.php"]
+//!
+//!     [[mount.links]]
+//!     type = "proxy"
+//!     upstream = "http://127.0.0.1:8081"
+//! "#).expect("invalid config");
+//!
+//! let services = config.services().expect("failed to build services");
+//! let app = App::new().service(services);
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use actix_common::{ErrorKind, GatewayError};
+use actix_web::{
+    HttpResponse, ResponseError,
+    body::BoxBody,
+    dev::{AppService, HttpServiceFactory},
+    http::{StatusCode, Uri},
+    web,
+};
+use derive_more::{Display, Error, From};
+use serde::Deserialize;
+
+use crate::chain::{Chain, Link, next::IsStatus};
+
+/// Errors which occur when loading or building a [`Config`]
+#[derive(Debug, Display, From, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Failed to read configuration file
+    Io(std::io::Error),
+
+    /// Configuration file extension is not a supported format
+    #[display("Unsupported configuration format: {_0:?}")]
+    UnsupportedFormat(#[error(not(source))] PathBuf),
+
+    /// Failed to parse TOML configuration
+    #[cfg(feature = "toml")]
+    #[display("Invalid TOML configuration")]
+    Toml(toml::de::Error),
+
+    /// Failed to parse YAML configuration
+    #[cfg(feature = "yaml")]
+    #[display("Invalid YAML configuration")]
+    Yaml(serde_yaml::Error),
+
+    /// Upstream address could not be parsed
+    #[display("Invalid upstream uri: {_0:?}")]
+    #[from(skip)]
+    InvalidUpstream(#[error(not(source))] String),
+
+    /// Status code could not be parsed
+    #[display("Invalid status code: {_0}")]
+    #[from(skip)]
+    InvalidStatus(#[error(not(source))] u16),
+
+    /// Chain declared without any links
+    #[display("Chain mounted at {_0:?} contains no links")]
+    #[from(skip)]
+    EmptyChain(#[error(not(source))] String),
+
+    /// Rewrite rules failed to load
+    #[cfg(feature = "rewrite")]
+    #[display("Invalid rewrite rules")]
+    Rewrite(crate::rewrite::Error),
+
+    /// ModSecurity rules failed to load
+    #[cfg(feature = "modsecurity")]
+    #[display("Invalid modsecurity rules")]
+    ModSecurity(crate::modsecurity::Error),
+
+    /// Declared services failed to initialize
+    #[display("Services failed to initialize: {_0}")]
+    Init(crate::chain::InitError),
+}
+
+impl GatewayError for Error {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Config
+    }
+}
+
+impl ResponseError for Error {
+    /// Returns the status code of the error's [`ErrorKind`].
+    fn status_code(&self) -> StatusCode {
+        self.kind().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        self.gateway_response()
+    }
+}
+
+/// Top-level declarative service configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Config {
+    /// Services to mount, evaluated in order of declaration.
+    #[serde(default, rename = "mount")]
+    pub mounts: Vec<Mount>,
+}
+
+impl Config {
+    /// Parse configuration from a TOML string.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// Parse configuration from a YAML string.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(s: &str) -> Result<Self, Error> {
+        Ok(serde_yaml::from_str(s)?)
+    }
+
+    /// Read configuration from a file.
+    ///
+    /// The format is selected using the file extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        match ext {
+            #[cfg(feature = "toml")]
+            "toml" => Self::from_toml(&content),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Self::from_yaml(&content),
+            _ => Err(Error::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Build all declared mounts into registerable [`Services`].
+    pub fn services(&self) -> Result<Services, Error> {
+        let chains = self
+            .mounts
+            .iter()
+            .map(|mount| mount.build())
+            .collect::<Result<_, _>>()?;
+        Ok(Services(chains))
+    }
+
+    /// Build and register all declared mounts onto a [`web::ServiceConfig`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix_web::App;
+    /// use actix_services::config::Config;
+    ///
+    /// let config = Config::default();
+    /// let app = App::new().configure(|cfg| config.configure(cfg).expect("invalid config"));
+    /// ```
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) -> Result<(), Error> {
+        cfg.service(self.services()?);
+        Ok(())
+    }
+}
+
+/// Collection of services built from a [`Config`].
+///
+/// Each mount is assembled into a [`Chain`] so that middleware such as
+/// rewrite rules and ModSecurity policies can be scoped to the mount.
+pub struct Services(Vec<Chain>);
+
+impl Services {
+    /// Consume the collection and return the assembled chains.
+    #[inline]
+    pub fn into_inner(self) -> Vec<Chain> {
+        self.0
+    }
+}
+
+impl HttpServiceFactory for Services {
+    fn register(self, config: &mut AppService) {
+        for chain in self.0 {
+            chain.register(config);
+        }
+    }
+}
+
+/// Runtime control handle of a declared service
+#[derive(Clone)]
+pub(crate) enum Handle {
+    #[cfg(feature = "fastcgi")]
+    FastCGI(crate::fastcgi::ControlHandle),
+    #[cfg(feature = "revproxy")]
+    Proxy(crate::revproxy::ControlHandle),
+}
+
+/// Control handles shared between builds keyed by declaration identity
+///
+/// Services rebuilt from a registry reuse the existing handle for their
+/// identity so connection pools and upstream state survive a rebuild.
+#[derive(Clone, Default)]
+pub(crate) struct Handles(Arc<Mutex<HashMap<String, Handle>>>);
+
+impl Handles {
+    #[inline]
+    fn get(&self, key: &str) -> Option<Handle> {
+        self.0.lock().expect("poisoned lock").get(key).cloned()
+    }
+
+    #[inline]
+    fn insert(&self, key: &str, handle: Handle) {
+        self.0
+            .lock()
+            .expect("poisoned lock")
+            .insert(key.to_owned(), handle);
+    }
+
+    /// Drop handles of declarations no longer present
+    pub(crate) fn retain(&self, keys: &[String]) {
+        self.0
+            .lock()
+            .expect("poisoned lock")
+            .retain(|key, _| keys.contains(key));
+    }
+}
+
+/// Build stable keys for a list of declarations under the parent key
+///
+/// Declarations are keyed by their label rather than their position so
+/// inserting or reordering declarations keeps the handles of the others.
+/// Repeated labels are numbered in order of declaration.
+fn stable_keys<I>(parent: &str, labels: I) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    labels
+        .into_iter()
+        .map(|label| {
+            let count = seen.entry(label.clone()).or_default();
+            *count += 1;
+            match *count {
+                1 => format!("{parent}[{label}]"),
+                n => format!("{parent}[{label}#{n}]"),
+            }
+        })
+        .collect()
+}
+
+/// Stable keys of the declared mounts identified by their path
+pub(crate) fn mount_keys(mounts: &[Mount]) -> Vec<String> {
+    stable_keys("mount", mounts.iter().map(|mount| mount.path.clone()))
+}
+
+/// Stable keys of the declared links within the parent declaration
+fn link_keys(parent: &str, links: &[LinkConfig]) -> Vec<String> {
+    stable_keys(
+        &format!("{parent}.links"),
+        links.iter().map(LinkConfig::label),
+    )
+}
+
+/// A single mounted service declaration.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Mount {
+    /// Root URL at which the service is mounted.
+    ///
+    /// Defaults to an empty string which matches all requests.
+    #[serde(default)]
+    pub path: String,
+
+    /// Rewrite rules scoped to the mount.
+    #[cfg(feature = "rewrite")]
+    #[serde(default)]
+    pub rewrite: Option<RewriteConfig>,
+
+    /// ModSecurity policy scoped to the mount.
+    #[cfg(feature = "modsecurity")]
+    #[serde(default)]
+    pub modsecurity: Option<ModSecurityConfig>,
+
+    /// Service declaration.
+    #[serde(flatten)]
+    pub service: Service,
+}
+
+impl Mount {
+    /// Assemble the mount into a [`Chain`] instance.
+    ///
+    /// Mount scoped middleware is applied to an unprefixed inner chain
+    /// so the request path remains unchanged for all declared services.
+    pub fn build(&self) -> Result<Chain, Error> {
+        let chain = self.build_with("mount", &Handles::default())?;
+        Ok(Chain::new(&self.path).link(Link::from(chain)))
+    }
+
+    /// Assemble the unprefixed inner chain reusing control handles
+    /// registered for the declaration key.
+    pub(crate) fn build_with(&self, key: &str, handles: &Handles) -> Result<Chain, Error> {
+        let chain = match &self.service {
+            Service::Chain { links } => {
+                if links.is_empty() {
+                    return Err(Error::EmptyChain(self.path.clone()));
+                }
+                let mut chain = Chain::default();
+                for (link, key) in links.iter().zip(link_keys(key, links)) {
+                    chain.push_link(link.build_with(&key, handles)?);
+                }
+                chain
+            }
+            service => Chain::default().link(service.link(key, handles)?),
+        };
+        #[cfg(feature = "rewrite")]
+        let chain = match self.rewrite.as_ref() {
+            Some(rewrite) => chain.wrap(rewrite.build()?.middleware()),
+            None => chain,
+        };
+        #[cfg(feature = "modsecurity")]
+        let chain = match self.modsecurity.as_ref() {
+            Some(security) => chain.wrap(security.build()?.middleware()),
+            None => chain,
+        };
+        Ok(chain)
+    }
+}
+
+/// Declaration of a single service.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Service {
+    /// [`FastCGI`](crate::fastcgi::FastCGI) client service.
+    #[cfg(feature = "fastcgi")]
+    FastCGI {
+        /// Location on disk at which fastcgi script files are referenced.
+        root: PathBuf,
+        /// Unix/TCP socket address of the fastcgi service.
+        address: String,
+        /// Index files in order of priority.
+        #[serde(default)]
+        index: Vec<String>,
+    },
+
+    /// [`RevProxy`](crate::revproxy::RevProxy) reverse proxy service.
+    #[cfg(feature = "revproxy")]
+    Proxy {
+        /// Base uri the proxy resolves to.
+        upstream: String,
+        /// Change the hostname to the upstream host.
+        #[serde(default)]
+        change_host: bool,
+        /// Headers included in the upstream request.
+        #[serde(default)]
+        upstream_headers: Vec<(String, String)>,
+        /// Headers included in the downstream response.
+        #[serde(default)]
+        downstream_headers: Vec<(String, String)>,
+    },
+
+    /// Nested [`Chain`] service.
+    Chain {
+        /// Links evaluated in order of declaration.
+        #[serde(default)]
+        links: Vec<LinkConfig>,
+    },
+}
+
+impl Service {
+    /// Declared type of the service.
+    fn kind(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "fastcgi")]
+            Self::FastCGI { .. } => "fastcgi",
+            #[cfg(feature = "revproxy")]
+            Self::Proxy { .. } => "proxy",
+            Self::Chain { .. } => "chain",
+        }
+    }
+
+    /// Build declared service into a [`Link`].
+    fn link(&self, key: &str, handles: &Handles) -> Result<Link, Error> {
+        Ok(match self {
+            #[cfg(feature = "fastcgi")]
+            Self::FastCGI {
+                root,
+                address,
+                index,
+            } => {
+                let mut fastcgi = index
+                    .iter()
+                    .fold(crate::fastcgi::FastCGI::new("", root, address), |f, i| {
+                        f.index_file(i)
+                    });
+                match handles.get(key) {
+                    Some(Handle::FastCGI(control)) => fastcgi = fastcgi.with_control(control),
+                    _ => handles.insert(key, Handle::FastCGI(fastcgi.control_handle())),
+                }
+                Link::new(fastcgi)
+            }
+            #[cfg(feature = "revproxy")]
+            Self::Proxy {
+                upstream,
+                change_host,
+                upstream_headers,
+                downstream_headers,
+            } => {
+                let uri: Uri = upstream
+                    .parse()
+                    .map_err(|_| Error::InvalidUpstream(upstream.clone()))?;
+                let mut proxy = crate::revproxy::RevProxy::new("", uri);
+                if *change_host {
+                    proxy = proxy.change_host();
+                }
+                for (name, value) in upstream_headers {
+                    proxy = proxy.upstream_header(name, value);
+                }
+                for (name, value) in downstream_headers {
+                    proxy = proxy.downstream_header(name, value);
+                }
+                match handles.get(key) {
+                    Some(Handle::Proxy(control)) => proxy = proxy.with_control(control),
+                    _ => handles.insert(key, Handle::Proxy(proxy.control_handle())),
+                }
+                Link::new(proxy)
+            }
+            Self::Chain { links } => {
+                if links.is_empty() {
+                    return Err(Error::EmptyChain(String::new()));
+                }
+                let mut chain = Chain::default();
+                for (link, key) in links.iter().zip(link_keys(key, links)) {
+                    chain.push_link(link.build_with(&key, handles)?);
+                }
+                Link::from(chain)
+            }
+        })
+    }
+
+    /// Apply the declared upstream to control handles registered for the
+    /// declaration key, collecting every visited key.
+    pub(crate) fn apply(&self, key: &str, handles: &Handles, keys: &mut Vec<String>) {
+        keys.push(key.to_owned());
+        match (self, handles.get(key)) {
+            #[cfg(feature = "fastcgi")]
+            (Self::FastCGI { address, .. }, Some(Handle::FastCGI(control))) => {
+                match crate::fastcgi::StreamAddr::try_from(address.as_str()) {
+                    Ok(addr) => control.set_address(addr),
+                    Err(_) => tracing::error!("{key}: invalid fastcgi address {address:?}"),
+                }
+            }
+            #[cfg(feature = "revproxy")]
+            (Self::Proxy { upstream, .. }, Some(Handle::Proxy(control))) => {
+                if control.set_upstream(upstream.as_str()).is_err() {
+                    tracing::error!("{key}: invalid upstream uri {upstream:?}");
+                }
+            }
+            (Self::Chain { links }, _) => {
+                for (link, key) in links.iter().zip(link_keys(key, links)) {
+                    link.service.apply(&key, handles, keys);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Declaration of a single [`Link`] within a chain.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LinkConfig {
+    /// Identifier keeping the runtime state of the link across reloads.
+    ///
+    /// Defaults to the service type and prefix of the link, numbered
+    /// among links sharing both.
+    #[serde(default)]
+    pub id: Option<String>,
+
+    /// Match-prefix assigned to the link.
+    #[serde(default)]
+    pub prefix: String,
+
+    /// Status codes which forward the request to the next link.
+    ///
+    /// Defaults to the [`Link`] default behavior when empty.
+    #[serde(default)]
+    pub next: Vec<u16>,
+
+    /// Service declaration.
+    #[serde(flatten)]
+    pub service: Service,
+}
+
+impl LinkConfig {
+    /// Label identifying the declaration within its chain
+    fn label(&self) -> String {
+        match self.id.as_ref() {
+            Some(id) => id.clone(),
+            None => format!("{}:{}", self.service.kind(), self.prefix),
+        }
+    }
+
+    /// Assemble the declaration into a [`Link`] instance.
+    pub fn build(&self) -> Result<Link, Error> {
+        self.build_with("link", &Handles::default())
+    }
+
+    /// Assemble the declaration reusing control handles registered for
+    /// the declaration key.
+    pub(crate) fn build_with(&self, key: &str, handles: &Handles) -> Result<Link, Error> {
+        let mut link = self.service.link(key, handles)?.prefix(&self.prefix);
+        for code in self.next.iter().copied() {
+            let status = StatusCode::from_u16(code).map_err(|_| Error::InvalidStatus(code))?;
+            link = link.next(IsStatus(status));
+        }
+        Ok(link)
+    }
+}
+
+/// Rewrite rules declaration.
+#[cfg(feature = "rewrite")]
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RewriteConfig {
+    /// Inline rewrite rules.
+    #[serde(default)]
+    pub rules: Option<String>,
+    /// Files containing rewrite rules.
+    #[serde(default)]
+    pub rules_files: Vec<PathBuf>,
+    /// Max number of loops over the ruleset during rewrite.
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+}
+
+#[cfg(feature = "rewrite")]
+impl RewriteConfig {
+    /// Build declaration into a rewrite [`Engine`](crate::rewrite::Engine).
+    pub fn build(&self) -> Result<crate::rewrite::Engine, Error> {
+        let mut engine = crate::rewrite::Engine::new();
+        if let Some(iterations) = self.max_iterations {
+            engine = engine.max_iterations(iterations);
+        }
+        for path in self.rules_files.iter() {
+            engine.add_rules_file(path)?;
+        }
+        if let Some(rules) = self.rules.as_deref() {
+            engine.add_rules(rules)?;
+        }
+        Ok(engine)
+    }
+}
+
+/// ModSecurity policy declaration.
+#[cfg(feature = "modsecurity")]
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ModSecurityConfig {
+    /// Inline ModSecurity rules.
+    #[serde(default)]
+    pub rules: Option<String>,
+    /// Files containing ModSecurity rules.
+    #[serde(default)]
+    pub rules_files: Vec<PathBuf>,
+    /// Max request body size loaded into memory for processing.
+    #[serde(default)]
+    pub max_request_size: Option<usize>,
+    /// Max response body size loaded into memory for processing.
+    #[serde(default)]
+    pub max_response_size: Option<usize>,
+}
+
+#[cfg(feature = "modsecurity")]
+impl ModSecurityConfig {
+    /// Build declaration into a [`ModSecurity`](crate::modsecurity::ModSecurity) instance.
+    pub fn build(&self) -> Result<crate::modsecurity::ModSecurity, Error> {
+        let mut builder = crate::modsecurity::ModSecurity::builder()
+            .max_request_size(self.max_request_size)
+            .max_response_size(self.max_response_size);
+        for path in self.rules_files.iter() {
+            builder = builder.rules_file(path)?;
+        }
+        if let Some(rules) = self.rules.as_deref() {
+            builder = builder.rules(rules)?;
+        }
+        Ok(builder.build())
+    }
+}
@@ -0,0 +1,135 @@
+//! Collection of additional services and middleware for Actix-Web.
+//!
+//! Re-exports each crate within the workspace behind a feature flag so
+//! a single consistently versioned dependency can be used instead of
+//! tracking every crate separately.
+//!
+//! | Feature       | Crate                                                      | Default |
+//! | ------------- | ---------------------------------------------------------- | ------- |
+//! | `authn`       | [`actix-authn`](https://docs.rs/actix-authn)               |         |
+//! | `chain`       | [`actix-chain`](https://docs.rs/actix-chain)               | ✓       |
+//! | `fastcgi`     | [`actix-fastcgi`](https://docs.rs/actix-fastcgi)           | ✓       |
+//! | `modsecurity` | [`actix-modsecurity`](https://docs.rs/actix-modsecurity)   |         |
+//! | `revproxy`    | [`actix-revproxy`](https://docs.rs/actix-revproxy)         | ✓       |
+//! | `rewrite`     | [`actix-rewrite`](https://docs.rs/actix-rewrite)           | ✓       |
+//! | `sanitize`    | [`actix-sanitize`](https://docs.rs/actix-sanitize)         |         |
+//!
+//! Declarative configuration of mounts is available via the [`config`]
+//! module with the `toml` and/or `yaml` features.
+//!
+//! Zero-downtime reloads of a watched configuration file are available via
+//! the [`reload`] module with the `reload` feature.
+//!
+//! In-process FastCGI and HTTP stubs for integration tests are available via
+//! the [`testkit`] module with the `testkit` feature.
+//!
+//! Service errors are rendered as RFC 7807 `application/problem+json`
+//! responses with the `problem-details` feature.
+//!
+//! ACME HTTP-01 challenges can be answered ahead of the backends via the
+//! [`acme`] module with the `acme` feature.
+//!
+//! A consolidated JSON endpoint reporting the runtime state of services is
+//! available via the [`status`] module with the `status` feature.
+//!
+//! Per-tenant selection among pre-built service configurations keyed by
+//! `Host` or a header is available via the [`tenant`] module with the
+//! `tenant` feature.
+//!
+//! A PHP hosting preset combining static files, FastCGI with a front
+//! controller and sensitive file denial is available via the [`php`]
+//! module with the `php` feature.
+//!
+//! A ModSecurity protected reverse-proxy preset is available via the
+//! [`waf`] module with the `waf` feature.
+//!
+//! Request duration and size histograms shared by all services are recorded
+//! via the [`common::metrics`] facade, with sinks for the `prometheus` and
+//! `metrics` crates available with the features of the same name.
+//!
+//! W3C TraceContext and Baggage propagation with child spans per upstream
+//! call is available with the `opentelemetry` feature.
+//!
+//! # Example
+//!
+//! Services compose naturally. The example below rewrites legacy urls,
+//! attempts to serve PHP content via FastCGI, and falls back to an
+//! upstream web-server when the script is not found.
+//!
+//! ```
+//! use actix_web::App;
+//! use actix_services::{
+//!     chain::{Chain, Link},
+//!     fastcgi::FastCGI,
+//!     revproxy::RevProxy,
+//!     rewrite::Engine,
+//! };
+//!
+//! let mut engine = Engine::new();
+//! engine.add_rules(r#"
+//!     RewriteRule /legacy/(.*) /$1 [L]
+//! "#).expect("failed to process rules");
+//!
+//! let app = App::new()
+//!     .wrap(engine.middleware())
+//!     .service(
+//!         Chain::default()
+//!             .link(Link::new(FastCGI::new("", ".", "tcp://127.0.0.1:9000")))
+//!             .link(Link::new(RevProxy::new("", "http://127.0.0.1:8080")))
+//!     );
+//! ```
+
+#[cfg(feature = "acme")]
+pub mod acme;
+
+#[cfg(feature = "config")]
+pub mod config;
+
+#[cfg(feature = "authn")]
+#[doc(inline)]
+pub use actix_authn as authn;
+
+#[cfg(feature = "chain")]
+#[doc(inline)]
+pub use actix_chain as chain;
+
+#[cfg(feature = "status")]
+pub mod status;
+
+#[cfg(feature = "tenant")]
+pub mod tenant;
+
+#[cfg(feature = "testkit")]
+pub mod testkit;
+
+#[doc(inline)]
+pub use actix_common as common;
+
+#[cfg(feature = "fastcgi")]
+#[doc(inline)]
+pub use actix_fastcgi as fastcgi;
+
+#[cfg(feature = "modsecurity")]
+#[doc(inline)]
+pub use actix_modsecurity as modsecurity;
+
+#[cfg(feature = "php")]
+pub mod php;
+
+#[cfg(feature = "revproxy")]
+#[doc(inline)]
+pub use actix_revproxy as revproxy;
+
+#[cfg(feature = "reload")]
+pub mod reload;
+
+#[cfg(feature = "rewrite")]
+#[doc(inline)]
+pub use actix_rewrite as rewrite;
+
+#[cfg(feature = "sanitize")]
+#[doc(inline)]
+pub use actix_sanitize as sanitize;
+
+#[cfg(feature = "waf")]
+pub mod waf;
@@ -0,0 +1,239 @@
+//! Classic PHP Hosting Preset
+//!
+//! Assembles the most common deployment of this workspace into a single
+//! service: static files served directly from the document root, PHP
+//! scripts executed via FastCGI with a front controller for unmatched
+//! paths, and denial of dotfiles and other sensitive files.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::App;
+//! use actix_services::php::PhpSite;
+//!
+//! let app = App::new().service(
+//!     PhpSite::new("/var/www/html", "tcp://127.0.0.1:9000")
+//!         .deny(".twig")
+//!         .fastcgi(|fastcgi| fastcgi.pool_size(32)),
+//! );
+//! ```
+
+use std::{path::PathBuf, rc::Rc};
+
+use actix_files::Files;
+use actix_web::{
+    HttpResponse,
+    dev::{AppService, HttpServiceFactory},
+    guard::{GuardContext, fn_guard},
+    middleware::DefaultHeaders,
+    web,
+};
+use percent_encoding::percent_decode_str;
+
+use crate::{
+    chain::{Chain, Link},
+    fastcgi::FastCGI,
+};
+
+/// Default index and front controller script
+const DEFAULT_INDEX: &str = "index.php";
+
+/// Extensions of scripts which must never be served as static files
+const SCRIPT_EXTENSIONS: &[&str] = &[".php", ".php5", ".phps", ".pht", ".phtml", ".phar"];
+
+/// Path suffixes denied by default
+const DEFAULT_DENY: &[&str] = &[
+    ".bak",
+    ".inc",
+    ".ini",
+    ".log",
+    ".sql",
+    ".swp",
+    "~",
+    "/composer.json",
+    "/composer.lock",
+];
+
+/// Security headers added to every response
+const SECURITY_HEADERS: &[(&str, &str)] = &[
+    ("X-Content-Type-Options", "nosniff"),
+    ("X-Frame-Options", "SAMEORIGIN"),
+    ("Referrer-Policy", "strict-origin-when-cross-origin"),
+];
+
+/// Percent-decode the request path the same way [`Files`] does before
+/// opening a file, so encoded characters cannot bypass the path checks
+fn decoded_path(ctx: &GuardContext<'_>) -> String {
+    percent_decode_str(ctx.head().uri.path())
+        .decode_utf8_lossy()
+        .to_ascii_lowercase()
+}
+
+/// Check if the path addresses a hidden file or directory
+///
+/// The `/.well-known/` directory is allowed so ACME challenges and
+/// similar well-known resources keep working.
+fn is_hidden(path: &str) -> bool {
+    path.split('/')
+        .any(|segment| segment.starts_with('.') && segment != ".well-known")
+}
+
+/// Check if the path addresses a script, including `PATH_INFO` suffixes
+fn is_script(path: &str) -> bool {
+    SCRIPT_EXTENSIONS
+        .iter()
+        .any(|ext| path.ends_with(ext) || path.contains(&format!("{ext}/")))
+}
+
+/// PHP site serving static files and FastCGI scripts from a document root
+///
+/// Requests are handled in order by:
+///
+/// 1. A deny rule answering `403 Forbidden` for dotfiles (except
+///    `/.well-known/`), backups, logs and dependency manifests.
+/// 2. Static files within the root, never including script sources.
+/// 3. [`FastCGI`] with the `index.php` index file, running the
+///    `index.php` front controller for paths without a matching file.
+///
+/// The `X-Content-Type-Options`, `X-Frame-Options` and `Referrer-Policy`
+/// security headers are added to responses that do not already set them.
+pub struct PhpSite {
+    mount_path: String,
+    root: PathBuf,
+    fastcgi: FastCGI,
+    index: String,
+    front_controller: Option<String>,
+    static_files: bool,
+    security_headers: bool,
+    deny: Vec<String>,
+    #[cfg(feature = "rewrite")]
+    rewrite: Option<crate::rewrite::Engine>,
+}
+
+impl PhpSite {
+    /// Creates a new `PhpSite` serving the root via the FastCGI address.
+    pub fn new<P: Into<PathBuf>>(root: P, fastcgi_address: &str) -> Self {
+        let root = root.into();
+        Self {
+            mount_path: String::new(),
+            fastcgi: FastCGI::new("", root.clone(), fastcgi_address),
+            root,
+            index: DEFAULT_INDEX.to_owned(),
+            front_controller: Some(DEFAULT_INDEX.to_owned()),
+            static_files: true,
+            security_headers: true,
+            deny: DEFAULT_DENY.iter().map(|s| s.to_string()).collect(),
+            #[cfg(feature = "rewrite")]
+            rewrite: None,
+        }
+    }
+
+    /// Mount the site at the specified path.
+    ///
+    /// Default is the root path.
+    pub fn mount_path(mut self, mount_path: &str) -> Self {
+        self.mount_path = mount_path.to_owned();
+        self
+    }
+
+    /// Customize the underlying [`FastCGI`] instance.
+    pub fn fastcgi<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(FastCGI) -> FastCGI,
+    {
+        self.fastcgi = configure(self.fastcgi);
+        self
+    }
+
+    /// Set the index script run for directory requests.
+    ///
+    /// Default is `index.php`.
+    pub fn index_file<S: Into<String>>(mut self, index: S) -> Self {
+        self.index = index.into();
+        self
+    }
+
+    /// Set the script run for paths without a matching file.
+    ///
+    /// Pass `None` to answer such requests with `404 Not Found` instead.
+    ///
+    /// Default is `index.php`.
+    pub fn front_controller(mut self, script: Option<&str>) -> Self {
+        self.front_controller = script.map(str::to_owned);
+        self
+    }
+
+    /// Serve existing non-script files directly from the root.
+    ///
+    /// Default is enabled.
+    pub fn static_files(mut self, static_files: bool) -> Self {
+        self.static_files = static_files;
+        self
+    }
+
+    /// Add security headers to responses.
+    ///
+    /// Default is enabled.
+    pub fn security_headers(mut self, security_headers: bool) -> Self {
+        self.security_headers = security_headers;
+        self
+    }
+
+    /// Deny requests for paths ending with the specified suffix.
+    ///
+    /// Matching is case-insensitive and extends the default denied suffixes.
+    pub fn deny<S: Into<String>>(mut self, suffix: S) -> Self {
+        self.deny.push(suffix.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Apply rewrite rules before any request is handled.
+    #[cfg(feature = "rewrite")]
+    pub fn rewrite(mut self, engine: crate::rewrite::Engine) -> Self {
+        self.rewrite = Some(engine);
+        self
+    }
+
+    /// Assemble the preset into a [`Chain`] instance.
+    pub fn build(self) -> Chain {
+        let deny = Rc::new(self.deny);
+        let denied = fn_guard(move |ctx: &GuardContext<'_>| {
+            let path = decoded_path(ctx);
+            is_hidden(&path) || deny.iter().any(|suffix| path.ends_with(suffix.as_str()))
+        });
+
+        let mut chain = Chain::new(&self.mount_path)
+            .link(Link::new(web::to(HttpResponse::Forbidden)).guard(denied));
+        if self.static_files {
+            let files = Files::new("", self.root).prefer_utf8(true);
+            let is_static = fn_guard(|ctx: &GuardContext<'_>| !is_script(&decoded_path(ctx)));
+            chain = chain.link(Link::new(files).guard(is_static));
+        }
+
+        let mut fastcgi = self.fastcgi.index_file(self.index);
+        if let Some(script) = self.front_controller {
+            fastcgi = fastcgi.front_controller(script);
+        }
+        chain = chain.link(Link::new(fastcgi));
+
+        #[cfg(feature = "rewrite")]
+        if let Some(engine) = self.rewrite {
+            chain = chain.wrap(engine.middleware());
+        }
+        if self.security_headers {
+            let headers = SECURITY_HEADERS
+                .iter()
+                .fold(DefaultHeaders::new(), |headers, header| {
+                    headers.add(*header)
+                });
+            chain = chain.wrap(headers);
+        }
+        chain
+    }
+}
+
+impl HttpServiceFactory for PhpSite {
+    fn register(self, config: &mut AppService) {
+        self.build().register(config)
+    }
+}
@@ -0,0 +1,368 @@
+//! Zero-downtime configuration reload.
+//!
+//! A [`Reloader`] serves the mounts of a declarative [`Config`] file and
+//! re-reads the file whenever it changes. Upstream changes are applied to
+//! running FastCGI and reverse-proxy services via their control handles so
+//! established connection pools are kept, while any other change such as
+//! new mounts or rewrite rules rebuilds the mounts within each worker on
+//! its next request. In-flight requests complete on the services they
+//! started on.
+//!
+//! Invalid configuration is logged and ignored, leaving the running
+//! services untouched. Services failing to initialize after a reload are
+//! reported to the request triggering the rebuild, and the rebuild is
+//! retried on the next request.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use actix_web::{App, HttpServer};
+//! use actix_services::reload::Reloader;
+//!
+//! #[actix_web::main]
+//! async fn main() -> std::io::Result<()> {
+//!     let reloader = Reloader::new("services.toml").expect("invalid config");
+//!     reloader.watch(Duration::from_secs(2));
+//!
+//!     HttpServer::new(move || App::new().service(reloader.service()))
+//!         .bind(("127.0.0.1", 8080))?
+//!         .run()
+//!         .await
+//! }
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        Arc, RwLock, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
+
+use actix_service::{Service, ServiceFactory};
+use actix_web::{
+    Error as ActixError, HttpResponse,
+    dev::{AppService, HttpServiceFactory, ResourceDef, ServiceRequest, ServiceResponse},
+    guard, web,
+};
+use futures_core::future::LocalBoxFuture;
+
+use crate::{
+    chain::{Chain, ChainService, Link, next::Next},
+    config::{Config, Error, Handles, mount_keys},
+};
+
+/// Response guard that never forwards the request to the next mount
+struct Never;
+
+impl Next for Never {
+    #[inline]
+    fn next(&self, _res: &HttpResponse) -> bool {
+        false
+    }
+}
+
+/// Check if the request path is within the mount path
+fn within(mount: &str, path: &str) -> bool {
+    let mount = mount.trim_end_matches('/');
+    match path.strip_prefix(mount) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Summary of the mounts changed by a reload
+///
+/// Mounts are identified by their path.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// Paths of newly declared mounts.
+    pub added: Vec<String>,
+    /// Paths of mounts no longer declared.
+    pub removed: Vec<String>,
+    /// Paths of mounts whose declaration changed.
+    pub changed: Vec<String>,
+}
+
+impl ConfigDiff {
+    /// Compare the mounts of two configurations
+    fn new(old: &Config, new: &Config) -> Self {
+        let index = |config: &Config| {
+            config
+                .mounts
+                .iter()
+                .map(|mount| (mount.path.clone(), format!("{mount:?}")))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let (old, new) = (index(old), index(new));
+        let mut diff = Self::default();
+        for (path, mount) in new.iter() {
+            match old.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(prev) if prev != mount => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .keys()
+            .filter(|path| !new.contains_key(*path))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Check if no mount changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let groups = [
+            ("added", &self.added),
+            ("removed", &self.removed),
+            ("changed", &self.changed),
+        ];
+        let mut sep = "";
+        for (label, paths) in groups.into_iter().filter(|(_, p)| !p.is_empty()) {
+            write!(f, "{sep}{label}: {paths:?}")?;
+            sep = ", ";
+        }
+        Ok(())
+    }
+}
+
+struct Shared {
+    path: PathBuf,
+    config: RwLock<Config>,
+    generation: AtomicU64,
+    handles: Handles,
+}
+
+impl Shared {
+    /// Assemble all declared mounts into a single chain
+    fn build(&self) -> Result<Chain, Error> {
+        let config = self.config.read().expect("poisoned lock");
+        let mut chain = Chain::default();
+        for (mount, key) in config.mounts.iter().zip(mount_keys(&config.mounts)) {
+            let inner = mount.build_with(&key, &self.handles)?;
+            let path = mount.path.clone();
+            let link = Link::from(inner)
+                .guard(guard::fn_guard(move |ctx| {
+                    within(&path, ctx.head().uri.path())
+                }))
+                .next(Never);
+            chain.push_link(link);
+        }
+        if config.mounts.is_empty() {
+            chain.push_link(Link::new(web::to(HttpResponse::NotFound)));
+        }
+        Ok(chain)
+    }
+}
+
+/// Declarative configuration served with zero-downtime reloads.
+///
+/// Cloning the reloader shares the underlying configuration, so a single
+/// reloader can be moved into the [`HttpServer`](actix_web::HttpServer)
+/// factory and registered within every worker.
+#[derive(Clone)]
+pub struct Reloader(Arc<Shared>);
+
+impl Reloader {
+    /// Load the configuration file at the specified path.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let config = Config::from_file(&path)?;
+        config.services()?;
+        Ok(Self(Arc::new(Shared {
+            path,
+            config: RwLock::new(config),
+            generation: AtomicU64::new(0),
+            handles: Handles::default(),
+        })))
+    }
+
+    /// Currently applied configuration.
+    pub fn config(&self) -> Config {
+        self.0.config.read().expect("poisoned lock").clone()
+    }
+
+    /// Number of reloads applied so far.
+    pub fn generation(&self) -> u64 {
+        self.0.generation.load(Ordering::Acquire)
+    }
+
+    /// Re-read the configuration file and apply any changes.
+    ///
+    /// The new configuration is validated before being applied and is
+    /// discarded entirely if invalid.
+    pub fn reload(&self) -> Result<ConfigDiff, Error> {
+        let config = Config::from_file(&self.0.path)?;
+        config.services()?;
+
+        let mut keys = Vec::new();
+        for (mount, key) in config.mounts.iter().zip(mount_keys(&config.mounts)) {
+            mount.service.apply(&key, &self.0.handles, &mut keys);
+        }
+        self.0.handles.retain(&keys);
+
+        let mut current = self.0.config.write().expect("poisoned lock");
+        let diff = ConfigDiff::new(&current, &config);
+        *current = config;
+        drop(current);
+
+        self.0.generation.fetch_add(1, Ordering::AcqRel);
+        tracing::info!("reloaded {:?}: {diff}", self.0.path);
+        Ok(diff)
+    }
+
+    /// Watch the configuration file for changes, polling its modification
+    /// time at the specified interval.
+    ///
+    /// The watcher thread exits once every clone of the reloader is dropped.
+    pub fn watch(&self, interval: Duration) -> JoinHandle<()> {
+        let shared = Arc::downgrade(&self.0);
+        let mut last = modified(&self.0.path);
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(interval);
+                let Some(reloader) = Weak::upgrade(&shared).map(Self) else {
+                    return;
+                };
+                let current = modified(&reloader.0.path);
+                if current == last {
+                    continue;
+                }
+                last = current;
+                if let Err(err) = reloader.reload() {
+                    tracing::error!("failed to reload {:?}: {err}", reloader.0.path);
+                }
+            }
+        })
+    }
+
+    /// Service serving the declared mounts for registration with an `App`.
+    #[inline]
+    pub fn service(&self) -> Reloadable {
+        Reloadable(self.clone())
+    }
+}
+
+/// Last modification time of a file
+#[inline]
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Service factory serving the mounts of a [`Reloader`].
+///
+/// Unlike [`Services`](crate::config::Services), all mounts are served by
+/// a single service, and requests not matching any mount receive a
+/// `404 Not Found` response.
+///
+/// `Reloadable` service must be registered with `App::service()` method.
+#[derive(Clone)]
+pub struct Reloadable(Reloader);
+
+impl HttpServiceFactory for Reloadable {
+    fn register(self, config: &mut AppService) {
+        let rdef = if config.is_root() {
+            ResourceDef::root_prefix("")
+        } else {
+            ResourceDef::prefix("")
+        };
+        config.register_service(rdef, None, self, None)
+    }
+}
+
+impl ServiceFactory<ServiceRequest> for Reloadable {
+    type Response = ServiceResponse;
+    type Error = ActixError;
+    type Config = ();
+    type Service = ReloadService;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let shared = self.0.0.clone();
+        Box::pin(async move {
+            let generation = shared.generation.load(Ordering::Acquire);
+            let chain = shared
+                .build()
+                .map_err(|err| tracing::error!("failed to build services: {err}"))?;
+            let service = chain
+                .init()
+                .await
+                .map_err(|err| tracing::error!("failed to build services: {err}"))?;
+            Ok(ReloadService(Rc::new(ReloadInner {
+                shared,
+                current: RefCell::new((generation, service)),
+                building: Cell::new(false),
+            })))
+        })
+    }
+}
+
+/// Worker-local service rebuilt whenever the configuration is reloaded.
+#[derive(Clone)]
+pub struct ReloadService(Rc<ReloadInner>);
+
+struct ReloadInner {
+    shared: Arc<Shared>,
+    current: RefCell<(u64, ChainService)>,
+    building: Cell<bool>,
+}
+
+impl ReloadService {
+    /// Rebuild the services if the configuration changed since last built
+    ///
+    /// Failures are returned to the caller, leaving the previous services
+    /// in place until the rebuild succeeds.
+    async fn refresh(&self) -> Result<(), Error> {
+        let generation = self.0.shared.generation.load(Ordering::Acquire);
+        if self.0.current.borrow().0 == generation || self.0.building.replace(true) {
+            return Ok(());
+        }
+        let result = self.rebuild().await;
+        self.0.building.set(false);
+        let service =
+            result.inspect_err(|err| tracing::error!("failed to rebuild services: {err}"))?;
+        *self.0.current.borrow_mut() = (generation, service);
+        Ok(())
+    }
+
+    /// Build and initialize the currently declared services
+    async fn rebuild(&self) -> Result<ChainService, Error> {
+        let chain = self.0.shared.build()?;
+        Ok(chain.init().await?)
+    }
+}
+
+impl Service<ServiceRequest> for ReloadService {
+    type Response = ServiceResponse;
+    type Error = ActixError;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            this.refresh().await?;
+            let service = this.0.current.borrow().1.clone();
+            service.call(req).await
+        })
+    }
+}
@@ -0,0 +1,249 @@
+//! Consolidated Runtime Status Endpoint
+//!
+//! Reports the runtime state of the services within the workspace as
+//! a single JSON document for operations tooling: FastCGI and php-fpm
+//! pools, proxy upstream health, chain link statistics and circuit
+//! breakers, and the rule versions loaded into rewrite engines and
+//! ModSecurity.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::{App, guard};
+//! use actix_services::{
+//!     chain::{Chain, Link},
+//!     fastcgi::FastCGI,
+//!     revproxy::RevProxy,
+//!     status::Status,
+//! };
+//!
+//! let fastcgi = FastCGI::new("", ".", "tcp://127.0.0.1:9000");
+//! let proxy = RevProxy::new("", "http://127.0.0.1:8080");
+//! let status = Status::new("/_status")
+//!     .guard(guard::Header("X-Admin-Token", "secret"))
+//!     .fastcgi("php", fastcgi.control_handle())
+//!     .revproxy("backend", proxy.control_handle());
+//!
+//! let chain = Chain::default()
+//!     .link(Link::new(fastcgi))
+//!     .link(Link::new(proxy));
+//! let status = status.chain("site", chain.stats());
+//!
+//! let app = App::new().service(status).service(chain);
+//! ```
+
+use std::rc::Rc;
+
+use actix_web::{
+    HttpResponse,
+    dev::{AppService, HttpServiceFactory},
+    guard::{self, Guard},
+    web,
+};
+use serde_json::{Map, Value, json};
+
+/// Snapshot function producing the status of a single service
+type Report = Rc<dyn Fn() -> Value>;
+
+/// Mountable endpoint reporting the runtime state of registered services
+///
+/// Services are grouped by kind and keyed by the name they were
+/// registered with. The endpoint exposes operational details and
+/// should be protected using [`Status::guard`] or a middleware.
+pub struct Status {
+    path: String,
+    guards: Vec<Rc<dyn Guard>>,
+    sections: Vec<(&'static str, String, Report)>,
+}
+
+impl Status {
+    /// Creates a new `Status` endpoint mounted at the specified path.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            guards: Vec::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Adds a routing guard restricting access to the endpoint.
+    pub fn guard<G: Guard + 'static>(mut self, guard: G) -> Self {
+        self.guards.push(Rc::new(guard));
+        self
+    }
+
+    /// Register a custom status section under the specified kind and name.
+    pub fn section<F>(mut self, kind: &'static str, name: &str, report: F) -> Self
+    where
+        F: Fn() -> Value + 'static,
+    {
+        self.sections.push((kind, name.to_owned(), Rc::new(report)));
+        self
+    }
+
+    /// Report the address and connection pool of a FastCGI service.
+    #[cfg(feature = "fastcgi")]
+    pub fn fastcgi(self, name: &str, control: crate::fastcgi::ControlHandle) -> Self {
+        self.section("fastcgi", name, move || {
+            let pool = control.pool_status();
+            json!({
+                "address": format!("{:?}", control.address()),
+                "pool": {
+                    "max_size": pool.max_size,
+                    "size": pool.size,
+                    "available": pool.available,
+                    "waiting": pool.waiting,
+                },
+            })
+        })
+    }
+
+    /// Report the php-fpm pool statistics last retrieved by the probe.
+    ///
+    /// The statistics are refreshed by the probe itself, see
+    /// [`FpmProbe::poll`](crate::fastcgi::FpmProbe::poll).
+    #[cfg(feature = "fastcgi")]
+    pub fn php_fpm(self, name: &str, probe: crate::fastcgi::FpmProbe) -> Self {
+        self.section("php_fpm", name, move || {
+            let Some(status) = probe.last_status() else {
+                return Value::Null;
+            };
+            json!({
+                "pool": status.pool,
+                "process_manager": status.process_manager,
+                "start_since": status.start_since,
+                "accepted_conn": status.accepted_conn,
+                "listen_queue": status.listen_queue,
+                "max_listen_queue": status.max_listen_queue,
+                "idle_processes": status.idle_processes,
+                "active_processes": status.active_processes,
+                "total_processes": status.total_processes,
+                "max_active_processes": status.max_active_processes,
+                "max_children_reached": status.max_children_reached,
+                "slow_requests": status.slow_requests,
+            })
+        })
+    }
+
+    /// Report the upstream health and load of a reverse-proxy service.
+    #[cfg(feature = "revproxy")]
+    pub fn revproxy(self, name: &str, control: crate::revproxy::ControlHandle) -> Self {
+        self.section("revproxy", name, move || {
+            let upstreams: Vec<_> = control
+                .upstream_status()
+                .into_iter()
+                .map(|upstream| {
+                    json!({
+                        "uri": upstream.uri.to_string(),
+                        "in_flight": upstream.in_flight,
+                        "aborted": upstream.aborted,
+                        "healthy": upstream.healthy,
+                        "draining": upstream.draining,
+                    })
+                })
+                .collect();
+            json!({
+                "timeout_ms": control.timeout().map(|timeout| timeout.as_millis() as u64),
+                "upstreams": upstreams,
+            })
+        })
+    }
+
+    /// Report link statistics and circuit breakers of a chain.
+    ///
+    /// See [`Chain::stats`](crate::chain::Chain::stats).
+    #[cfg(feature = "chain")]
+    pub fn chain(self, name: &str, stats: crate::chain::ChainStats) -> Self {
+        self.section("chain", name, move || {
+            let links: Vec<_> = stats
+                .links()
+                .into_iter()
+                .map(|link| {
+                    json!({
+                        "prefix": link.prefix,
+                        "requests": link.requests,
+                        "errors": link.errors,
+                        "fallthroughs": link.fallthroughs,
+                        "circuit_trips": link.circuit_trips,
+                        "circuit_open": link.circuit_open,
+                    })
+                })
+                .collect();
+            json!({ "links": links })
+        })
+    }
+
+    /// Report the rule set version and rule toggles of a rewrite engine.
+    ///
+    /// See [`Engine::rules_version`](crate::rewrite::Engine::rules_version)
+    /// for how the version is derived.
+    #[cfg(feature = "rewrite")]
+    pub fn rewrite(self, name: &str, engine: &crate::rewrite::Engine) -> Self {
+        let engine = engine.clone();
+        let toggles = engine.toggles();
+        let ids: Vec<String> = engine.rule_ids().map(str::to_owned).collect();
+        self.section("rewrite", name, move || {
+            let rules: Vec<_> = ids
+                .iter()
+                .map(|id| json!({ "id": id, "enabled": toggles.is_enabled(id) }))
+                .collect();
+            json!({
+                "version": engine.rules_version(),
+                "rules": rules,
+            })
+        })
+    }
+
+    /// Report the version of the rules loaded into ModSecurity.
+    #[cfg(feature = "modsecurity")]
+    pub fn modsecurity(self, name: &str, security: &crate::modsecurity::ModSecurity) -> Self {
+        let version = security.rules_version();
+        self.section(
+            "modsecurity",
+            name,
+            move || json!({ "rules_version": version }),
+        )
+    }
+
+    /// Report whether a maintenance mode is enabled.
+    pub fn maintenance(self, name: &str, maintenance: actix_common::Maintenance) -> Self {
+        self.section(
+            "maintenance",
+            name,
+            move || json!({ "enabled": maintenance.is_enabled() }),
+        )
+    }
+
+    /// Build the status document of all registered services.
+    pub fn report(&self) -> Value {
+        report(&self.sections)
+    }
+}
+
+/// Group section reports by kind and name
+fn report(sections: &[(&'static str, String, Report)]) -> Value {
+    let mut kinds = Map::new();
+    for (kind, name, report) in sections {
+        let group = kinds
+            .entry(*kind)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(group) = group {
+            group.insert(name.clone(), report());
+        }
+    }
+    Value::Object(kinds)
+}
+
+impl HttpServiceFactory for Status {
+    fn register(self, config: &mut AppService) {
+        let sections = Rc::new(self.sections);
+        let mut resource = web::resource(self.path).route(web::get().to(move || {
+            let body = report(&sections);
+            async move { HttpResponse::Ok().json(body) }
+        }));
+        for g in self.guards {
+            resource = resource.guard(guard::fn_guard(move |ctx| g.check(ctx)));
+        }
+        resource.register(config)
+    }
+}
@@ -0,0 +1,254 @@
+//! Per-Tenant Service Selection
+//!
+//! Selects among multiple pre-built service configurations at request
+//! time using the request `Host` or a header, so many tenants can be
+//! hosted from a single registered service instead of one service per
+//! tenant.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::{App, HttpResponse, web};
+//! use actix_services::tenant::Tenants;
+//!
+//! let tenants = Tenants::new("/")
+//!     .tenant("blog.example.com", web::to(|| async { HttpResponse::Ok().body("blog") }))
+//!     .tenant("shop.example.com", web::to(|| async { HttpResponse::Ok().body("shop") }))
+//!     .default_tenant(web::to(HttpResponse::NotFound));
+//!
+//! let app = App::new().service(tenants);
+//! ```
+//!
+//! Services such as `FastCGI`, `RevProxy` or a ModSecurity protected
+//! `Chain` are registered the same way, allowing each tenant its own
+//! document root, upstream and WAF policy.
+
+use std::{collections::HashMap, fmt::Debug, rc::Rc};
+
+use actix_service::{IntoServiceFactory, Service, ServiceFactory, ServiceFactoryExt, boxed};
+use actix_web::{
+    Error, HttpResponse,
+    dev::{AppService, HttpServiceFactory, ResourceDef, ServiceRequest, ServiceResponse},
+    guard::Guard,
+    http::header::{self, HeaderName},
+};
+use futures_core::future::LocalBoxFuture;
+
+type HttpService = boxed::BoxService<ServiceRequest, ServiceResponse, Error>;
+type HttpNewService = boxed::BoxServiceFactory<(), ServiceRequest, ServiceResponse, Error, String>;
+
+#[inline]
+fn box_factory<F, U>(service: F) -> Rc<HttpNewService>
+where
+    F: IntoServiceFactory<U, ServiceRequest>,
+    U: ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = Error>
+        + 'static,
+    U::InitError: Debug,
+{
+    Rc::new(boxed::factory(
+        service
+            .into_factory()
+            .map_init_err(|err| format!("{err:?}")),
+    ))
+}
+
+/// Request attribute used to identify the tenant
+#[derive(Clone, Debug)]
+enum TenantKey {
+    Host,
+    Header(HeaderName),
+}
+
+impl TenantKey {
+    /// Extract the tenant key from the request
+    fn resolve(&self, req: &ServiceRequest) -> Option<String> {
+        match self {
+            Self::Host => {
+                let host = match req.uri().host() {
+                    Some(host) => host,
+                    None => req.headers().get(header::HOST)?.to_str().ok()?,
+                };
+                let host = match host.rsplit_once(':') {
+                    Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+                    _ => host,
+                };
+                Some(host.trim_end_matches('.').to_ascii_lowercase())
+            }
+            Self::Header(name) => req
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+        }
+    }
+}
+
+/// Service registry selecting a tenant service per request.
+///
+/// Tenants are keyed by the request `Host` (case-insensitive and without
+/// port) by default, or by the value of a header using
+/// [`Tenants::by_header`]. Requests for unknown tenants are passed to the
+/// [`Tenants::default_tenant`] service if configured, and answered with
+/// `404 Not Found` otherwise.
+///
+/// `Tenants` service must be registered with `App::service()` method.
+#[derive(Clone)]
+pub struct Tenants {
+    mount_path: String,
+    guards: Vec<Rc<dyn Guard>>,
+    key: TenantKey,
+    tenants: HashMap<String, Rc<HttpNewService>>,
+    default: Option<Rc<HttpNewService>>,
+}
+
+impl Tenants {
+    /// Creates new `Tenants` registry mounted at the specified path.
+    pub fn new(mount_path: &str) -> Self {
+        Self {
+            mount_path: mount_path.trim_end_matches('/').to_owned(),
+            guards: Vec::new(),
+            key: TenantKey::Host,
+            tenants: HashMap::new(),
+            default: None,
+        }
+    }
+
+    /// Adds a routing guard.
+    pub fn guard<G: Guard + 'static>(mut self, guard: G) -> Self {
+        self.guards.push(Rc::new(guard));
+        self
+    }
+
+    /// Identify tenants using the value of the specified header
+    /// instead of the request `Host`.
+    pub fn by_header(mut self, name: &str) -> Self {
+        match HeaderName::try_from(name) {
+            Ok(name) => self.key = TenantKey::Header(name),
+            Err(_) => tracing::error!("invalid tenant header: {name:?}"),
+        }
+        self
+    }
+
+    /// Register the service handling requests for the specified tenant.
+    ///
+    /// Any Actix-Web service factory can be passed such as `FastCGI`,
+    /// `RevProxy` or a `Chain`.
+    pub fn tenant<F, U>(mut self, key: &str, service: F) -> Self
+    where
+        F: IntoServiceFactory<U, ServiceRequest>,
+        U: ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = Error>
+            + 'static,
+        U::InitError: Debug,
+    {
+        let key = match self.key {
+            TenantKey::Host => key.to_ascii_lowercase(),
+            TenantKey::Header(_) => key.to_owned(),
+        };
+        self.tenants.insert(key, box_factory(service));
+        self
+    }
+
+    /// Register the service handling requests for unknown tenants.
+    pub fn default_tenant<F, U>(mut self, service: F) -> Self
+    where
+        F: IntoServiceFactory<U, ServiceRequest>,
+        U: ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse, Error = Error>
+            + 'static,
+        U::InitError: Debug,
+    {
+        self.default = Some(box_factory(service));
+        self
+    }
+}
+
+impl HttpServiceFactory for Tenants {
+    fn register(mut self, config: &mut AppService) {
+        let guards = if self.guards.is_empty() {
+            None
+        } else {
+            let guards = std::mem::take(&mut self.guards);
+            Some(
+                guards
+                    .into_iter()
+                    .map(|guard| -> Box<dyn Guard> { Box::new(guard) })
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let rdef = if config.is_root() {
+            ResourceDef::root_prefix(&self.mount_path)
+        } else {
+            ResourceDef::prefix(&self.mount_path)
+        };
+
+        config.register_service(rdef, guards, self, None)
+    }
+}
+
+impl ServiceFactory<ServiceRequest> for Tenants {
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Config = ();
+    type Service = TenantService;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            let mut tenants = HashMap::with_capacity(this.tenants.len());
+            for (key, factory) in this.tenants.iter() {
+                let service = factory
+                    .new_service(())
+                    .await
+                    .map_err(|err| tracing::error!("tenant {key:?} failed to initialize: {err}"))?;
+                tenants.insert(key.clone(), service);
+            }
+            let default = match this.default.as_ref() {
+                Some(factory) => Some(factory.new_service(()).await.map_err(|err| {
+                    tracing::error!("default tenant failed to initialize: {err}")
+                })?),
+                None => None,
+            };
+            Ok(TenantService(Rc::new(TenantInner {
+                key: this.key,
+                tenants,
+                default,
+            })))
+        })
+    }
+}
+
+/// Assembled tenant registry service.
+#[derive(Clone)]
+pub struct TenantService(Rc<TenantInner>);
+
+struct TenantInner {
+    key: TenantKey,
+    tenants: HashMap<String, HttpService>,
+    default: Option<HttpService>,
+}
+
+impl Service<ServiceRequest> for TenantService {
+    type Response = ServiceResponse;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let key = self.0.key.resolve(&req);
+        let service = key
+            .as_ref()
+            .and_then(|key| self.0.tenants.get(key))
+            .or(self.0.default.as_ref());
+        match service {
+            Some(service) => service.call(req),
+            None => {
+                tracing::debug!("no tenant matched {key:?}");
+                let res = req.into_response(HttpResponse::NotFound().finish());
+                Box::pin(async move { Ok(res) })
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! In-process FastCGI and HTTP stubs for integration testing.
+//!
+//! Allows behaviors such as chain fallthrough, retries and FastCGI parameter
+//! mapping to be tested without external services like php-fpm.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::{App, HttpResponse, test, web};
+//! use actix_services::{
+//!     chain::{Chain, Link},
+//!     fastcgi::FastCGI,
+//!     revproxy::RevProxy,
+//!     testkit::{FastCGIStub, HttpStub},
+//! };
+//!
+//! # actix_web::rt::System::new().block_on(async {
+//! let fastcgi = FastCGIStub::start(|_| "Status: 404\r\n\r\n".to_owned()).await.unwrap();
+//! let upstream = HttpStub::start(|cfg: &mut web::ServiceConfig| {
+//!     cfg.default_service(web::to(|| async { HttpResponse::Ok().body("upstream") }));
+//! })
+//! .unwrap();
+//!
+//! let chain = Chain::default()
+//!     .link(Link::new(FastCGI::new("", ".", &fastcgi.address())))
+//!     .link(Link::new(RevProxy::new("", upstream.url(""))));
+//! let srv = test::init_service(App::new().service(chain)).await;
+//!
+//! let res = test::call_service(&srv, test::TestRequest::default().to_request()).await;
+//! assert!(res.status().is_success());
+//! # upstream.stop().await;
+//! # });
+//! ```
+
+use std::{collections::HashMap, io, net::SocketAddr, rc::Rc};
+
+use actix_web::{
+    App, HttpServer,
+    dev::ServerHandle,
+    rt::{
+        self,
+        net::{TcpListener, TcpStream},
+    },
+    web,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    task::JoinHandle,
+};
+
+const FCGI_VERSION: u8 = 1;
+const FCGI_BEGIN_REQUEST: u8 = 1;
+const FCGI_END_REQUEST: u8 = 3;
+const FCGI_PARAMS: u8 = 4;
+const FCGI_STDIN: u8 = 5;
+const FCGI_STDOUT: u8 = 6;
+const FCGI_KEEP_CONN: u8 = 1;
+
+/// Request received by a [`FastCGIStub`]
+#[derive(Clone, Debug, Default)]
+pub struct StubRequest {
+    /// FastCGI parameters sent by the client
+    pub params: HashMap<String, String>,
+    /// Collected request body
+    pub body: Vec<u8>,
+}
+
+/// In-process mock FastCGI responder
+///
+/// The handler receives each [`StubRequest`] and returns the raw CGI output
+/// including headers, for example `"Status: 200\r\nContent-Type: text/plain\r\n\r\nhello"`.
+///
+/// The responder runs on the current runtime and stops when dropped.
+pub struct FastCGIStub {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl FastCGIStub {
+    /// Start a new responder listening on a random local port.
+    pub async fn start<F>(handler: F) -> io::Result<Self>
+    where
+        F: Fn(StubRequest) -> String + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let handler = Rc::new(handler);
+        let task = rt::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                rt::spawn(async move {
+                    let _ = serve_fastcgi(stream, &*handler).await;
+                });
+            }
+        });
+        Ok(Self { addr, task })
+    }
+
+    /// Socket address of the responder.
+    #[inline]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Address of the responder for use with [`FastCGI::new`](actix_fastcgi::FastCGI::new).
+    #[inline]
+    pub fn address(&self) -> String {
+        format!("tcp://{}", self.addr)
+    }
+}
+
+impl Drop for FastCGIStub {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// In-process stub upstream HTTP server
+///
+/// Routes are configured the same as an [`App`] using
+/// [`App::configure`](actix_web::App::configure).
+pub struct HttpStub {
+    addr: SocketAddr,
+    handle: ServerHandle,
+}
+
+impl HttpStub {
+    /// Start a new server listening on a random local port.
+    pub fn start<F>(config: F) -> io::Result<Self>
+    where
+        F: Fn(&mut web::ServiceConfig) + Send + Clone + 'static,
+    {
+        let server = HttpServer::new(move || App::new().configure(config.clone()))
+            .workers(1)
+            .disable_signals()
+            .bind(("127.0.0.1", 0))?;
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        rt::spawn(server);
+        Ok(Self { addr, handle })
+    }
+
+    /// Socket address of the server.
+    #[inline]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Build a url for the specified path on the server.
+    #[inline]
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// Gracefully stop the server.
+    pub async fn stop(self) {
+        self.handle.stop(true).await
+    }
+}
+
+/// Serve FastCGI requests on the specified connection.
+async fn serve_fastcgi<F>(mut stream: TcpStream, handler: &F) -> io::Result<()>
+where
+    F: Fn(StubRequest) -> String,
+{
+    let mut request = StubRequest::default();
+    let mut params = Vec::new();
+    let mut keep_conn = false;
+    loop {
+        let mut header = [0u8; 8];
+        if stream.read_exact(&mut header).await.is_err() {
+            return Ok(());
+        }
+        let kind = header[1];
+        let id = u16::from_be_bytes([header[2], header[3]]);
+        let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+        let mut content = vec![0u8; length + header[6] as usize];
+        stream.read_exact(&mut content).await?;
+        content.truncate(length);
+
+        match kind {
+            FCGI_BEGIN_REQUEST => {
+                keep_conn = content.get(2).is_some_and(|flags| flags & FCGI_KEEP_CONN != 0);
+                request = StubRequest::default();
+                params.clear();
+            }
+            FCGI_PARAMS if content.is_empty() => request.params = parse_params(&params),
+            FCGI_PARAMS => params.extend(content),
+            FCGI_STDIN if !content.is_empty() => request.body.extend(content),
+            FCGI_STDIN => {
+                let output = handler(std::mem::take(&mut request));
+                for chunk in output.as_bytes().chunks(u16::MAX as usize) {
+                    write_record(&mut stream, FCGI_STDOUT, id, chunk).await?;
+                }
+                write_record(&mut stream, FCGI_STDOUT, id, &[]).await?;
+                write_record(&mut stream, FCGI_END_REQUEST, id, &[0; 8]).await?;
+                if !keep_conn {
+                    return stream.shutdown().await;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Write a single FastCGI record to the stream.
+async fn write_record(stream: &mut TcpStream, kind: u8, id: u16, content: &[u8]) -> io::Result<()> {
+    let [id_hi, id_lo] = id.to_be_bytes();
+    let [len_hi, len_lo] = (content.len() as u16).to_be_bytes();
+    let header = [FCGI_VERSION, kind, id_hi, id_lo, len_hi, len_lo, 0, 0];
+    stream.write_all(&header).await?;
+    stream.write_all(content).await
+}
+
+/// Decode FastCGI name-value pairs.
+fn parse_params(mut data: &[u8]) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    while let (Some(name), Some(value)) = (read_length(&mut data), read_length(&mut data)) {
+        if data.len() < name + value {
+            break;
+        }
+        let (key, rest) = data.split_at(name);
+        let (value, rest) = rest.split_at(value);
+        params.insert(
+            String::from_utf8_lossy(key).into_owned(),
+            String::from_utf8_lossy(value).into_owned(),
+        );
+        data = rest;
+    }
+    params
+}
+
+/// Decode a FastCGI name-value length prefix.
+fn read_length(data: &mut &[u8]) -> Option<usize> {
+    let first = *data.first()?;
+    if first & 0x80 == 0 {
+        *data = &data[1..];
+        return Some(first as usize);
+    }
+    let bytes: [u8; 4] = data.get(..4)?.try_into().ok()?;
+    *data = &data[4..];
+    Some((u32::from_be_bytes(bytes) & 0x7fff_ffff) as usize)
+}
@@ -0,0 +1,129 @@
+//! ModSecurity Protected Reverse-Proxy Preset
+//!
+//! Wires the [`ModSecurity`] middleware in front of a [`RevProxy`] mount so
+//! both the request and the proxied response are inspected.
+//!
+//! # Example
+//!
+//! ```
+//! use actix_web::App;
+//! use actix_services::{modsecurity::ModSecurity, waf::WafProxy};
+//!
+//! let mut security = ModSecurity::new();
+//! security.add_rules(r#"
+//!     SecRuleEngine On
+//!     SecRule REQUEST_URI "@rx admin" "id:1,phase:1,deny"
+//! "#).expect("failed to add rules");
+//!
+//! let app = App::new().service(WafProxy::new("/", "http://127.0.0.1:8080", security));
+//! ```
+
+use std::fmt::Debug;
+
+use actix_web::{
+    dev::{AppService, HttpServiceFactory},
+    http::{StatusCode, Uri},
+};
+
+use crate::{
+    chain::{Chain, Link},
+    modsecurity::{Middleware, ModSecurity},
+    revproxy::RevProxy,
+};
+
+/// Default max request body inspected in memory
+const DEFAULT_MAX_REQUEST_SIZE: usize = 1024 * 1024;
+/// Default max response body inspected in memory
+const DEFAULT_MAX_RESPONSE_SIZE: usize = 8 * 1024 * 1024;
+
+/// [`RevProxy`] mount protected by [`ModSecurity`]
+///
+/// Request phase interventions are answered with `403 Forbidden` and
+/// response phase interventions, which block content produced by the
+/// upstream, with `502 Bad Gateway`. Redirect interventions are kept.
+pub struct WafProxy {
+    mount_path: String,
+    security: ModSecurity,
+    proxy: RevProxy,
+    max_request_size: usize,
+    max_response_size: usize,
+    request_status: StatusCode,
+    response_status: StatusCode,
+}
+
+impl WafProxy {
+    /// Creates a new `WafProxy` forwarding inspected requests to the upstream uri.
+    pub fn new<U>(mount_path: &str, uri: U, security: ModSecurity) -> Self
+    where
+        U: TryInto<Uri>,
+        U::Error: Debug,
+    {
+        Self {
+            mount_path: mount_path.to_owned(),
+            security,
+            proxy: RevProxy::new("", uri),
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            request_status: StatusCode::FORBIDDEN,
+            response_status: StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// Customize the underlying [`RevProxy`] instance.
+    pub fn proxy<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(RevProxy) -> RevProxy,
+    {
+        self.proxy = configure(self.proxy);
+        self
+    }
+
+    /// Set the max request body size loaded into memory for inspection.
+    ///
+    /// Default is 1MiB.
+    pub fn max_request_size(mut self, max_request_size: usize) -> Self {
+        self.max_request_size = max_request_size;
+        self
+    }
+
+    /// Set the max response body size loaded into memory for inspection.
+    ///
+    /// Default is 8MiB.
+    pub fn max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    /// Set the status code returned for request phase interventions.
+    ///
+    /// Default is `403 Forbidden`.
+    pub fn request_status(mut self, status: StatusCode) -> Self {
+        self.request_status = status;
+        self
+    }
+
+    /// Set the status code returned for response phase interventions.
+    ///
+    /// Default is `502 Bad Gateway`.
+    pub fn response_status(mut self, status: StatusCode) -> Self {
+        self.response_status = status;
+        self
+    }
+
+    /// Assemble the preset into a [`Chain`] instance.
+    pub fn build(mut self) -> Chain {
+        self.security
+            .set_max_request_size(Some(self.max_request_size))
+            .set_max_response_size(Some(self.max_response_size));
+        let middleware = Middleware::new(self.security)
+            .request_status(self.request_status)
+            .response_status(self.response_status);
+        Chain::new(&self.mount_path).link(Link::new(self.proxy).wrap(middleware))
+    }
+}
+
+impl HttpServiceFactory for WafProxy {
+    fn register(self, config: &mut AppService) {
+        self.build().register(config)
+    }
+}
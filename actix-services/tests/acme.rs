@@ -0,0 +1,55 @@
+use actix_services::{
+    acme::AcmeChallenge,
+    chain::{Chain, Link},
+};
+use actix_web::{
+    App, HttpResponse,
+    test::{self, TestRequest},
+    web,
+};
+
+#[actix_web::test]
+async fn test_acme_memory() {
+    let acme = AcmeChallenge::memory();
+    acme.insert("token-1", "token-1.thumbprint");
+    let chain = Chain::default()
+        .link(acme.clone().link())
+        .link(Link::new(web::to(|| async {
+            HttpResponse::Ok().body("backend")
+        })));
+    let srv = test::init_service(App::new().service(chain)).await;
+
+    let req = TestRequest::with_uri("/.well-known/acme-challenge/token-1").to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "token-1.thumbprint");
+
+    // unknown tokens fall through to the backends
+    acme.remove("token-1");
+    let req = TestRequest::with_uri("/.well-known/acme-challenge/token-1").to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "backend");
+}
+
+#[actix_web::test]
+async fn test_acme_directory() {
+    let root = std::env::temp_dir().join(format!("actix-acme-{}", std::process::id()));
+    let dir = root.join("challenges");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("token_2"), "token_2.thumbprint\n").unwrap();
+    // kept outside of the token directory so no token can name it
+    std::fs::write(root.join("secret"), "do not serve").unwrap();
+
+    let srv = test::init_service(App::new().service(AcmeChallenge::directory(&dir))).await;
+    let req = TestRequest::with_uri("/.well-known/acme-challenge/token_2").to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "token_2.thumbprint");
+
+    for token in ["secret", "..%2Fsecret", "..", "%2E%2E%2Fsecret"] {
+        let uri = format!("/.well-known/acme-challenge/{token}");
+        let req = TestRequest::with_uri(&uri).to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status().as_u16(), 404, "{uri}");
+        assert_ne!(test::read_body(res).await, "do not serve");
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}
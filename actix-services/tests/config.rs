@@ -0,0 +1,42 @@
+use actix_services::config::Config;
+use actix_web::{
+    App,
+    http::header::{self, HeaderValue},
+    test::{self, TestRequest},
+};
+
+const CONFIG: &str = r#"
+[[mount]]
+type = "proxy"
+upstream = "http://127.0.0.1:1"
+rewrite = { rules = "RewriteRule /old/(.*) /new/$1 [R=301]" }
+"#;
+
+#[actix_web::test]
+async fn test_config_rewrite() {
+    let config = Config::from_toml(CONFIG).expect("invalid config");
+    assert_eq!(config.mounts.len(), 1);
+
+    let services = config.services().expect("failed to build services");
+    let srv = test::init_service(App::new().service(services)).await;
+
+    let req = TestRequest::with_uri("/old/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().to_string(), "301 Moved Permanently");
+    assert_eq!(
+        res.headers().get(header::LOCATION),
+        Some(&HeaderValue::from_static("/new/page"))
+    );
+}
+
+#[test]
+fn test_config_empty_chain() {
+    let config = Config::from_toml(
+        r#"
+        [[mount]]
+        type = "chain"
+    "#,
+    )
+    .expect("invalid config");
+    assert!(config.services().is_err());
+}
@@ -0,0 +1,84 @@
+use std::fs;
+
+use actix_services::{php::PhpSite, testkit::FastCGIStub};
+use actix_web::{
+    App,
+    http::StatusCode,
+    test::{self, TestRequest},
+};
+use tempfile::TempDir;
+
+/// Create a document root with a script, a static file and sensitive files
+fn document_root() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("assets")).unwrap();
+    fs::write(root.join("index.php"), "<?php echo 'secret source';").unwrap();
+    fs::write(root.join("legacy.php5"), "<?php echo 'secret source';").unwrap();
+    fs::write(root.join("assets/style.css"), "body {}").unwrap();
+    fs::write(root.join(".env"), "DB_PASSWORD=secret").unwrap();
+    fs::write(root.join("dump.sql"), "DROP TABLE users;").unwrap();
+    dir
+}
+
+#[actix_web::test]
+async fn test_php_site() {
+    let stub = FastCGIStub::start(|req| {
+        let script = req.params.get("SCRIPT_NAME").cloned().unwrap_or_default();
+        let uri = req.params.get("REQUEST_URI").cloned().unwrap_or_default();
+        format!("Status: 200\r\nContent-Type: text/plain\r\n\r\n{script} {uri}")
+    })
+    .await
+    .expect("failed to start fastcgi stub");
+
+    let root = document_root();
+    let site = PhpSite::new(root.path(), &stub.address());
+    let srv = test::init_service(App::new().service(site)).await;
+
+    let req = TestRequest::with_uri("/assets/style.css").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("X-Content-Type-Options").unwrap(),
+        "nosniff"
+    );
+    assert_eq!(test::read_body(res).await, "body {}");
+
+    let req = TestRequest::with_uri("/index.php").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(test::read_body(res).await, "/index.php /index.php");
+
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(test::read_body(res).await, "/index.php /");
+
+    let req = TestRequest::with_uri("/blog/hello-world").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(test::read_body(res).await, "/index.php /blog/hello-world");
+
+    // encoded paths must not bypass the script check and leak sources
+    for path in [
+        "/index.ph%70",
+        "/index.PH%50",
+        "/legacy.php5",
+        "/legacy.ph%705",
+    ] {
+        let req = TestRequest::with_uri(path).to_request();
+        let res = test::call_service(&srv, req).await;
+        let body = test::read_body(res).await;
+        assert!(!body.starts_with(b"<?php"), "{path}");
+    }
+
+    for path in [
+        "/.env",
+        "/dump.sql",
+        "/.git/config",
+        "/%2eenv",
+        "/dump.sq%6c",
+        "/DUMP.SQ%4C",
+    ] {
+        let req = TestRequest::with_uri(path).to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{path}");
+    }
+}
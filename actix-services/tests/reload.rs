@@ -0,0 +1,93 @@
+use actix_services::reload::Reloader;
+use actix_web::{
+    App,
+    http::StatusCode,
+    test::{self, TestRequest},
+};
+
+const CONFIG: &str = r#"
+[[mount]]
+path = "/api"
+type = "proxy"
+upstream = "http://127.0.0.1:1"
+"#;
+
+const UPDATED: &str = r#"
+[[mount]]
+path = "/api"
+type = "proxy"
+upstream = "http://127.0.0.1:2"
+
+[[mount]]
+path = "/legacy"
+type = "proxy"
+upstream = "http://127.0.0.1:3"
+rewrite = { rules = "RewriteRule /legacy/(.*) /api/$1 [R=301]" }
+"#;
+
+const RULES: &str = "RewriteRule /legacy/(.*) /api/$1 [R=301]";
+
+#[actix_web::test]
+async fn test_reload() {
+    let path = std::env::temp_dir().join(format!("actix-reload-{}.toml", std::process::id()));
+    std::fs::write(&path, CONFIG).expect("failed to write config");
+
+    let reloader = Reloader::new(&path).expect("invalid config");
+    let srv = test::init_service(App::new().service(reloader.service())).await;
+
+    let req = TestRequest::with_uri("/legacy/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    std::fs::write(&path, UPDATED).expect("failed to write config");
+    let diff = reloader.reload().expect("failed to reload");
+    assert_eq!(diff.added, vec!["/legacy".to_owned()]);
+    assert_eq!(diff.changed, vec!["/api".to_owned()]);
+    assert!(diff.removed.is_empty());
+    assert_eq!(reloader.generation(), 1);
+
+    let req = TestRequest::with_uri("/legacy/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    std::fs::write(&path, "[[mount]]\ntype = \"chain\"\n").expect("failed to write config");
+    assert!(reloader.reload().is_err());
+    assert_eq!(reloader.generation(), 1);
+    assert_eq!(reloader.config().mounts.len(), 2);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[actix_web::test]
+async fn test_rebuild_error() {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("actix-rebuild-{}.toml", std::process::id()));
+    let rules = dir.join(format!("actix-rebuild-{}.rules", std::process::id()));
+    std::fs::write(&path, CONFIG).expect("failed to write config");
+
+    let reloader = Reloader::new(&path).expect("invalid config");
+    let srv = test::init_service(App::new().service(reloader.service())).await;
+
+    let config = format!(
+        "{CONFIG}\n[[mount]]\npath = \"/legacy\"\ntype = \"proxy\"\n\
+         upstream = \"http://127.0.0.1:3\"\nrewrite = {{ rules_files = [{rules:?}] }}\n"
+    );
+    std::fs::write(&rules, RULES).expect("failed to write rules");
+    std::fs::write(&path, config).expect("failed to write config");
+    reloader.reload().expect("failed to reload");
+
+    // rules removed after validation fail the rebuild within the worker
+    std::fs::remove_file(&rules).expect("failed to remove rules");
+    let req = TestRequest::with_uri("/legacy/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    // the rebuild is retried on the next request
+    std::fs::write(&rules, RULES).expect("failed to write rules");
+    let req = TestRequest::with_uri("/legacy/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+
+    let _ = std::fs::remove_file(&rules);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,36 @@
+use actix_services::{
+    chain::{Chain, Link},
+    status::Status,
+};
+use actix_web::{
+    App, HttpResponse, guard,
+    test::{self, TestRequest},
+    web,
+};
+use serde_json::{Value, json};
+
+#[actix_web::test]
+async fn test_status_report() {
+    let chain = Chain::new("/site").link(Link::new(web::get().to(HttpResponse::Ok)));
+    let status = Status::new("/_status")
+        .guard(guard::Header("X-Admin", "1"))
+        .chain("site", chain.stats())
+        .section("custom", "build", || json!({ "version": "1.2.3" }));
+    let srv = test::init_service(App::new().service(status).service(chain)).await;
+
+    let req = TestRequest::with_uri("/site/").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert!(res.status().is_success());
+
+    let req = TestRequest::with_uri("/_status").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().as_u16(), 404);
+
+    let req = TestRequest::with_uri("/_status")
+        .insert_header(("X-Admin", "1"))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&srv, req).await;
+    assert_eq!(report["custom"]["build"]["version"], "1.2.3");
+    assert_eq!(report["chain"]["site"]["links"][0]["requests"], 1);
+    assert_eq!(report["chain"]["site"]["links"][0]["circuit_open"], false);
+}
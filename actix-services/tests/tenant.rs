@@ -0,0 +1,58 @@
+use actix_services::tenant::Tenants;
+use actix_web::{
+    App, HttpResponse,
+    http::StatusCode,
+    test::{self, TestRequest},
+    web,
+};
+
+#[actix_web::test]
+async fn test_tenant_host() {
+    let tenants = Tenants::new("/")
+        .tenant("Blog.example.com", web::to(|| async { "blog" }))
+        .tenant("shop.example.com", web::to(|| async { "shop" }));
+    let srv = test::init_service(App::new().service(tenants)).await;
+
+    let req = TestRequest::with_uri("/index.php")
+        .insert_header(("Host", "blog.example.com:8080"))
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "blog");
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("Host", "SHOP.example.com"))
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "shop");
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("Host", "other.example.com"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_tenant_header() {
+    let tenants = Tenants::new("/api")
+        .by_header("X-Tenant")
+        .tenant("acme", web::to(|| async { "acme" }))
+        .default_tenant(web::to(|| async { HttpResponse::Forbidden().finish() }));
+    let srv = test::init_service(App::new().service(tenants)).await;
+
+    let req = TestRequest::with_uri("/api/users")
+        .insert_header(("X-Tenant", "acme"))
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "acme");
+
+    let req = TestRequest::with_uri("/api/users")
+        .insert_header(("X-Tenant", "ACME"))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let req = TestRequest::with_uri("/api/users").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
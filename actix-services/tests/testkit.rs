@@ -0,0 +1,704 @@
+use actix_services::{
+    chain::{Chain, Link},
+    common::ClientCert,
+    fastcgi::{Confinement, FastCGI, Recorder},
+    revproxy::{
+        Audit, AuditRecord, ClientCertHeaders, Redactor, ResponseCache, RevProxy, RouteTable,
+    },
+    testkit::{FastCGIStub, HttpStub},
+};
+use actix_web::{
+    App, HttpMessage, HttpRequest, HttpResponse,
+    http::StatusCode,
+    test::{self, TestRequest},
+    web,
+};
+
+#[actix_web::test]
+async fn test_fastcgi_params() {
+    let stub = FastCGIStub::start(|req| {
+        let method = req.params.get("REQUEST_METHOD").cloned().unwrap_or_default();
+        let script = req.params.get("SCRIPT_NAME").cloned().unwrap_or_default();
+        let protocol = req
+            .params
+            .get("SERVER_PROTOCOL")
+            .cloned()
+            .unwrap_or_default();
+        let gateway = req
+            .params
+            .get("GATEWAY_INTERFACE")
+            .cloned()
+            .unwrap_or_default();
+        let software = req
+            .params
+            .get("SERVER_SOFTWARE")
+            .cloned()
+            .unwrap_or_default();
+        let body = String::from_utf8_lossy(&req.body);
+        format!(
+            "Status: 200\r\nContent-Type: text/plain\r\n\r\n\
+            {method} {script} {protocol} {gateway} {software} {body}"
+        )
+    })
+    .await
+    .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address()).server_software("test");
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::post()
+        .uri("/index.php")
+        .set_payload("hello")
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "POST /index.php HTTP/1.1 CGI/1.1 test hello");
+}
+
+#[actix_web::test]
+async fn test_chain_fallthrough() {
+    let fastcgi = FastCGIStub::start(|_| "Status: 404\r\n\r\n".to_owned())
+        .await
+        .expect("failed to start fastcgi stub");
+    let upstream = HttpStub::start(|cfg: &mut web::ServiceConfig| {
+        cfg.default_service(web::to(|| async { HttpResponse::Ok().body("upstream") }));
+    })
+    .expect("failed to start http stub");
+
+    let chain = Chain::default()
+        .link(Link::new(FastCGI::new("", ".", &fastcgi.address())))
+        .link(Link::new(RevProxy::new("", upstream.url(""))));
+    let srv = test::init_service(App::new().service(chain)).await;
+
+    let req = TestRequest::with_uri("/missing.php").to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "upstream");
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_control_handle() {
+    let first = FastCGIStub::start(|_| "Status: 200\r\n\r\nfirst".to_owned())
+        .await
+        .expect("failed to start fastcgi stub");
+    let second = FastCGIStub::start(|_| "Status: 200\r\n\r\nsecond".to_owned())
+        .await
+        .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &first.address());
+    let control = fastcgi.control_handle();
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::with_uri("/index.php").to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "first");
+
+    control.set_address(second.addr().into());
+    let req = TestRequest::with_uri("/index.php").to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "second");
+}
+
+#[actix_web::test]
+async fn test_fastcgi_invalid_headers() {
+    let stub = FastCGIStub::start(|req| match req.params.get("SCRIPT_NAME") {
+        Some(script) if script == "/large.php" => format!("X-Large: {}\r\n\r\n", "x".repeat(256)),
+        _ => "Bad Header\r\n\r\nbody".to_owned(),
+    })
+    .await
+    .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address()).max_header_size(128);
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::with_uri("/large.php").to_request();
+    let err = test::try_call_service(&srv, req).await.unwrap_err();
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::BAD_GATEWAY
+    );
+
+    let req = TestRequest::with_uri("/malformed.php").to_request();
+    let err = test::try_call_service(&srv, req).await.unwrap_err();
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::BAD_GATEWAY
+    );
+}
+
+#[actix_web::test]
+async fn test_fastcgi_body_limit() {
+    let stub = FastCGIStub::start(|_| panic!("request should be rejected"))
+        .await
+        .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address()).max_body_size(16);
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::post()
+        .uri("/upload.php")
+        .insert_header(("Expect", "100-continue"))
+        .set_payload("x".repeat(1024))
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[actix_web::test]
+async fn test_fastcgi_forward_body() {
+    let stub = FastCGIStub::start(|req| {
+        let length = req
+            .params
+            .get("CONTENT_LENGTH")
+            .cloned()
+            .unwrap_or_default();
+        let body = String::from_utf8_lossy(&req.body);
+        format!("Status: 200\r\n\r\n{length}:{body}")
+    })
+    .await
+    .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address());
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+    let req = TestRequest::post()
+        .uri("/index.php")
+        .set_payload("hello")
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "5:hello");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address()).forward_body(false);
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+    let req = TestRequest::post()
+        .uri("/index.php")
+        .set_payload("hello")
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, ":");
+}
+
+#[actix_web::test]
+async fn test_fastcgi_recorder() {
+    let stub = FastCGIStub::start(|_| "Status: 200\r\n\r\nrecorded".to_owned())
+        .await
+        .expect("failed to start fastcgi stub");
+
+    let dir = std::env::temp_dir().join(format!("fastcgi-recorder-{}", stub.addr().port()));
+    let recorder = Recorder::new(&dir).header("X-Record");
+    let fastcgi = FastCGI::new("", ".", &stub.address()).recorder(recorder);
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::post()
+        .uri("/index.php")
+        .set_payload("ignored")
+        .to_request();
+    test::call_and_read_body(&srv, req).await;
+    assert!(!dir.exists());
+
+    let req = TestRequest::post()
+        .uri("/index.php")
+        .insert_header(("X-Record", "1"))
+        .set_payload("hello")
+        .to_request();
+    test::call_and_read_body(&srv, req).await;
+
+    let entry = std::fs::read_dir(&dir)
+        .expect("missing recording directory")
+        .next()
+        .expect("missing recording")
+        .expect("invalid recording");
+    let recording = std::fs::read_to_string(entry.path()).expect("unreadable recording");
+    std::fs::remove_dir_all(&dir).expect("failed to cleanup recordings");
+
+    assert!(recording.contains("SCRIPT_NAME=/index.php"));
+    assert!(recording.contains("== stdin (5 bytes, 5 recorded)\nhello"));
+    assert!(recording.contains("Status: 200\\r\\n\\r\\nrecorded"));
+}
+
+#[cfg(unix)]
+#[actix_web::test]
+async fn test_fastcgi_confinement() {
+    use std::os::unix::fs::symlink;
+
+    let stub = FastCGIStub::start(|_| "Status: 200\r\n\r\n".to_owned())
+        .await
+        .expect("failed to start fastcgi stub");
+
+    let base = std::env::temp_dir().join(format!("fastcgi-confine-{}", stub.addr().port()));
+    let root = base.join("root");
+    std::fs::create_dir_all(&root).expect("failed to create root");
+    std::fs::write(root.join("index.php"), "").expect("failed to write script");
+    std::fs::write(base.join("outside.php"), "").expect("failed to write script");
+    symlink(root.join("index.php"), root.join("inside.php")).expect("failed to symlink");
+    symlink(base.join("outside.php"), root.join("escape.php")).expect("failed to symlink");
+
+    let cases = [
+        (Confinement::Strict, "/index.php", StatusCode::OK),
+        (Confinement::Strict, "/inside.php", StatusCode::FORBIDDEN),
+        (Confinement::Strict, "/escape.php", StatusCode::FORBIDDEN),
+        (Confinement::FollowSymlinks, "/inside.php", StatusCode::OK),
+        (
+            Confinement::FollowSymlinks,
+            "/escape.php",
+            StatusCode::FORBIDDEN,
+        ),
+    ];
+    for (confinement, path, status) in cases {
+        let fastcgi = FastCGI::new("", &root, &stub.address()).confinement(confinement);
+        let srv = test::init_service(App::new().service(fastcgi)).await;
+        let req = TestRequest::with_uri(path).to_request();
+        let res = test::call_service(&srv, req).await;
+        assert_eq!(res.status(), status, "{confinement:?} {path}");
+    }
+    std::fs::remove_dir_all(&base).expect("failed to cleanup root");
+}
+
+#[actix_web::test]
+async fn test_fastcgi_status_line() {
+    let stub = FastCGIStub::start(|req| match req.params.get("SCRIPT_NAME") {
+        Some(script) if script == "/custom.php" => "Status: 299 Custom Reason\r\n\r\n".to_owned(),
+        _ => "status: 404\r\n\r\n".to_owned(),
+    })
+    .await
+    .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address());
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::with_uri("/custom.php").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status().as_u16(), 299);
+    assert_eq!(res.response().head().reason(), "Custom Reason");
+
+    let req = TestRequest::with_uri("/missing.php").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.response().head().reason(), "Not Found");
+}
+
+#[actix_web::test]
+async fn test_fastcgi_dev_mode() {
+    let stub = FastCGIStub::start(|_| "Bad Header\r\n\r\n".to_owned())
+        .await
+        .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address()).dev_mode(true);
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::with_uri("/broken.php").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = test::read_body(res).await;
+    assert!(body.starts_with(b"FastCGI Error\n\nFailed to parse response headers"));
+}
+
+#[actix_web::test]
+async fn test_fastcgi_duplicate_headers() {
+    let stub = FastCGIStub::start(|req| {
+        let cookie = req.params.get("HTTP_COOKIE").cloned().unwrap_or_default();
+        let accept = req.params.get("HTTP_ACCEPT").cloned().unwrap_or_default();
+        format!("Status: 200\r\n\r\n{cookie}|{accept}")
+    })
+    .await
+    .expect("failed to start fastcgi stub");
+
+    let fastcgi = FastCGI::new("", ".", &stub.address());
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+
+    let req = TestRequest::with_uri("/index.php")
+        .append_header(("Cookie", "a=1"))
+        .append_header(("Cookie", "b=2"))
+        .append_header(("Accept", "text/html"))
+        .append_header(("Accept", "*/*"))
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "a=1; b=2|text/html, */*");
+}
+
+#[actix_web::test]
+async fn test_revproxy_client_factory() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|req: actix_web::HttpRequest| async move {
+            let agent = req.headers().get("User-Agent").cloned();
+            HttpResponse::Ok().body(agent.map(|v| v.as_bytes().to_vec()).unwrap_or_default())
+        }));
+    })
+    .expect("failed to start http stub");
+
+    let proxy = RevProxy::new("", upstream.url("/")).client_factory(|| {
+        actix_services::revproxy::awc::Client::builder()
+            .add_default_header(("User-Agent", "custom-agent"))
+            .finish()
+    });
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "custom-agent");
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_revproxy_upstream_failover() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|| async { HttpResponse::Ok().body("alive") }));
+    })
+    .expect("failed to start http stub");
+    let dead = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let dead_url = format!("http://{}/", dead.local_addr().unwrap());
+    drop(dead);
+
+    let proxy = RevProxy::new("", dead_url.as_str())
+        .upstream(upstream.url("/"))
+        .fail_timeout(std::time::Duration::from_secs(60));
+    let control = proxy.control_handle();
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    // the dead upstream is avoided after its first failure
+    let mut statuses = Vec::new();
+    for _ in 0..4 {
+        let req = TestRequest::with_uri("/").to_request();
+        let res = test::try_call_service(&srv, req).await;
+        statuses.push(res.map(|res| res.status()).ok());
+    }
+    let ok = statuses.iter().filter(|s| **s == Some(StatusCode::OK));
+    assert!(ok.count() >= 3, "unexpected statuses: {statuses:?}");
+
+    // drained upstreams are removed once idle
+    control.remove_upstream(dead_url.as_str()).unwrap();
+    assert_eq!(
+        control.upstreams(),
+        vec![upstream.url("/").parse::<actix_web::http::Uri>().unwrap()]
+    );
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_revproxy_hardened_headers() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|| async {
+            HttpResponse::Ok()
+                .insert_header(("Server", "Apache/2.4.1"))
+                .insert_header(("X-Powered-By", "PHP/8.1"))
+                .insert_header(("X-Backend", "10.0.3.12:8080"))
+                .insert_header(("X-Public", "93.184.216.34"))
+                .body("ok")
+        }));
+    })
+    .expect("failed to start http stub");
+
+    let proxy = RevProxy::new("", upstream.url("/")).harden();
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::with_uri("/").to_request();
+    let res = test::call_service(&srv, req).await;
+    let headers = res.headers();
+    assert!(!headers.contains_key("Server"));
+    assert!(!headers.contains_key("X-Powered-By"));
+    assert!(!headers.contains_key("X-Backend"));
+    assert_eq!(headers.get("X-Public").unwrap(), "93.184.216.34");
+    assert_eq!(headers.get("X-Content-Type-Options").unwrap(), "nosniff");
+    assert!(headers.contains_key("Strict-Transport-Security"));
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_revproxy_head_requests() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|req: actix_web::HttpRequest| async move {
+            HttpResponse::Ok()
+                .insert_header(("X-Method", req.method().as_str()))
+                .body("hello world")
+        }));
+    })
+    .expect("failed to start http stub");
+
+    let proxy = RevProxy::new("", upstream.url("/"));
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::default()
+        .method(actix_web::http::Method::HEAD)
+        .to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.headers().get("X-Method").unwrap(), "HEAD");
+    assert_eq!(res.headers().get("Content-Length").unwrap(), "11");
+
+    let proxy = RevProxy::new("", upstream.url("/")).head_for_get(true);
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let req = TestRequest::get().to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.headers().get("X-Method").unwrap(), "HEAD");
+    assert!(test::read_body(res).await.is_empty());
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_revproxy_limit_rate() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|| async {
+            HttpResponse::Ok().body("x".repeat(6000))
+        }));
+    })
+    .expect("failed to start http stub");
+
+    let proxy = RevProxy::new("", upstream.url("/"))
+        .limit_rate(10_000)
+        .limit_rate_after(1000);
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let start = std::time::Instant::now();
+    let req = TestRequest::get().to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body.len(), 6000);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(400));
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_revproxy_audit() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|body: String| async move {
+            HttpResponse::Ok().body(body)
+        }));
+    })
+    .expect("failed to start http stub");
+
+    let records = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let sink = records.clone();
+    let audit = Audit::new(move |record: AuditRecord| sink.borrow_mut().push(record)).max_body(5);
+    let proxy = RevProxy::new("", upstream.url("/")).audit(audit);
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::post()
+        .uri("/submit?id=1")
+        .set_payload("hello world")
+        .to_request();
+    let body = test::call_and_read_body(&srv, req).await;
+    assert_eq!(body, "hello world");
+
+    upstream.stop().await;
+
+    let records = records.borrow();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].uri, "/submit?id=1");
+    assert_eq!(records[0].body, "hello");
+    assert_eq!(records[0].body_size, 11);
+}
+
+#[actix_web::test]
+async fn test_revproxy_audit_masking() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|| async { HttpResponse::Ok().finish() }));
+    })
+    .expect("failed to start http stub");
+
+    let records = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let sink = records.clone();
+    let audit = Audit::new(move |record: AuditRecord| sink.borrow_mut().push(record))
+        .masker(Redactor::new().arg("password").arg("token"));
+    let proxy = RevProxy::new("", upstream.url("/")).audit(audit);
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::post()
+        .uri("/login?token=secret&page=1")
+        .insert_header(("Authorization", "Basic dXNlcjpwYXNz"))
+        .insert_header(("Content-Type", "application/x-www-form-urlencoded"))
+        .set_payload("user=bob&password=hunter2&card=4111-1111-1111-1111")
+        .to_request();
+    test::call_service(&srv, req).await;
+    upstream.stop().await;
+
+    let records = records.borrow();
+    assert_eq!(records[0].uri, "/login?token=***&page=1");
+    assert_eq!(records[0].headers.get("authorization").unwrap(), "***");
+    assert_eq!(
+        records[0].body,
+        "user=bob&password=***&card=****-****-****-1111"
+    );
+}
+
+#[actix_web::test]
+async fn test_revproxy_cache() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let upstream = HttpStub::start(move |cfg| {
+        let counter = counter.clone();
+        cfg.default_service(web::to(move || {
+            let count = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                HttpResponse::Ok()
+                    .insert_header(("Cache-Control", "max-age=1, stale-while-revalidate=30"))
+                    .body(count.to_string())
+            }
+        }));
+    })
+    .expect("failed to start http stub");
+
+    let proxy = RevProxy::new("", upstream.url("/")).cache(ResponseCache::new());
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let get = || TestRequest::with_uri("/cached").to_request();
+
+    assert_eq!(test::call_and_read_body(&srv, get()).await, "0");
+    let res = test::call_service(&srv, get()).await;
+    assert!(res.headers().contains_key("Age"));
+    assert_eq!(test::read_body(res).await, "0");
+
+    // stale responses are served while revalidating in the background
+    actix_web::rt::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(test::call_and_read_body(&srv, get()).await, "0");
+    actix_web::rt::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert_eq!(test::call_and_read_body(&srv, get()).await, "1");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_revproxy_cache_vary() {
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|req: actix_web::HttpRequest| async move {
+            let lang = req.headers().get("Accept-Language").cloned();
+            HttpResponse::Ok()
+                .insert_header(("Cache-Control", "max-age=60"))
+                .insert_header(("Vary", "Accept-Language"))
+                .body(lang.map(|v| v.as_bytes().to_vec()).unwrap_or_default())
+        }));
+    })
+    .expect("failed to start http stub");
+
+    let cache = ResponseCache::new().normalize_query(true);
+    let proxy = RevProxy::new("", upstream.url("/")).cache(cache.clone());
+    let srv = test::init_service(App::new().service(proxy)).await;
+    let get = |uri: &str, lang: &str| {
+        TestRequest::with_uri(uri)
+            .insert_header(("Accept-Language", lang))
+            .to_request()
+    };
+
+    assert_eq!(
+        test::call_and_read_body(&srv, get("/p?a=1&b=2", "en")).await,
+        "en"
+    );
+    assert_eq!(
+        test::call_and_read_body(&srv, get("/p?b=2&a=1", "fr")).await,
+        "fr"
+    );
+    assert_eq!(cache.len(), 2);
+    let res = test::call_service(&srv, get("/p?b=2&a=1", "en")).await;
+    assert!(res.headers().contains_key("Age"));
+    assert_eq!(test::read_body(res).await, "en");
+
+    assert_eq!(cache.purge_prefix("/p"), 2);
+    assert!(cache.is_empty());
+    upstream.stop().await;
+}
+
+#[actix_web::test]
+async fn test_revproxy_routes() {
+    let stub = |name: &'static str| {
+        HttpStub::start(move |cfg| {
+            cfg.default_service(web::to(
+                move || async move { HttpResponse::Ok().body(name) },
+            ));
+        })
+        .expect("failed to start http stub")
+    };
+    let (default, api, admin, auth) = (stub("default"), stub("api"), stub("admin"), stub("auth"));
+
+    let routes = RouteTable::new()
+        .prefix("/api", api.url("/"))
+        .prefix("/api/admin", admin.url("/"))
+        .host("auth.example.com", auth.url("/"));
+    let proxy = RevProxy::new("", default.url("/")).routes(routes);
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    for (host, path, expected) in [
+        ("example.com", "/api/users", "api"),
+        ("example.com", "/api/admin/users", "admin"),
+        ("example.com", "/apis", "default"),
+        ("auth.example.com", "/login", "auth"),
+    ] {
+        let req = TestRequest::with_uri(path)
+            .insert_header(("Host", host))
+            .to_request();
+        assert_eq!(
+            test::call_and_read_body(&srv, req).await,
+            expected,
+            "{host}{path}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn test_revproxy_fallback() {
+    let origin = HttpStub::start(|cfg| {
+        cfg.route(
+            "/logo.png",
+            web::get().to(|| async { HttpResponse::Ok().body("origin") }),
+        );
+    })
+    .expect("failed to start http stub");
+    let app = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|| async { HttpResponse::Ok().body("app") }));
+    })
+    .expect("failed to start http stub");
+
+    let proxy = RevProxy::new("", origin.url("/")).fallback_on([404], app.url("/"));
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::with_uri("/logo.png").to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "origin");
+    let req = TestRequest::with_uri("/dashboard").to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, "app");
+}
+
+#[actix_web::test]
+async fn test_client_cert_forwarding() {
+    let cert = ClientCert::new(b"der".to_vec())
+        .subject("CN=client")
+        .serial("01")
+        .verified(true);
+
+    let stub = FastCGIStub::start(|req| {
+        let param = |name: &str| req.params.get(name).cloned().unwrap_or_default();
+        let (dn, verify) = (param("SSL_CLIENT_S_DN"), param("SSL_CLIENT_VERIFY"));
+        format!("Status: 200\r\n\r\n{dn}:{verify}")
+    })
+    .await
+    .expect("failed to start fastcgi stub");
+    let fastcgi = FastCGI::new("", ".", &stub.address()).client_cert_params(true);
+    let srv = test::init_service(App::new().service(fastcgi)).await;
+    let req = TestRequest::with_uri("/index.php").to_request();
+    req.extensions_mut().insert(cert.clone());
+    assert_eq!(
+        test::call_and_read_body(&srv, req).await,
+        "CN=client:SUCCESS"
+    );
+
+    let upstream = HttpStub::start(|cfg| {
+        cfg.default_service(web::to(|req: HttpRequest| async move {
+            let header = |name: &str| {
+                req.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_owned()
+            };
+            let (dn, verify) = (header("x-ssl-client-s-dn"), header("x-ssl-client-verify"));
+            HttpResponse::Ok().body(format!("{dn}:{verify}"))
+        }));
+    })
+    .expect("failed to start http stub");
+    let proxy = RevProxy::new("", upstream.url("/")).forward_client_cert(ClientCertHeaders::new());
+    let srv = test::init_service(App::new().service(proxy)).await;
+
+    let req = TestRequest::with_uri("/").to_request();
+    req.extensions_mut().insert(cert);
+    assert_eq!(
+        test::call_and_read_body(&srv, req).await,
+        "CN=client:SUCCESS"
+    );
+
+    let req = TestRequest::with_uri("/")
+        .insert_header(("X-SSL-Client-S-DN", "CN=spoofed"))
+        .to_request();
+    assert_eq!(test::call_and_read_body(&srv, req).await, ":NONE");
+}
@@ -0,0 +1,47 @@
+use actix_services::{modsecurity::ModSecurity, testkit::HttpStub, waf::WafProxy};
+use actix_web::{
+    App, HttpResponse,
+    http::StatusCode,
+    test::{self, TestRequest},
+    web,
+};
+
+const RULES: &str = r#"
+    SecRuleEngine On
+    SecResponseBodyAccess On
+    SecResponseBodyMimeType text/plain
+    SecRule REQUEST_URI "@rx admin" "id:1,phase:1,deny"
+    SecRule RESPONSE_BODY "@contains secret" "id:2,phase:4,deny"
+"#;
+
+#[actix_web::test]
+async fn test_waf_proxy() {
+    let upstream = HttpStub::start(|cfg: &mut web::ServiceConfig| {
+        cfg.route("/leak", web::to(|| async { "secret" }));
+        cfg.default_service(web::to(|| async { HttpResponse::Ok().body("upstream") }));
+    })
+    .expect("failed to start upstream");
+
+    let mut security = ModSecurity::new();
+    security.add_rules(RULES).expect("failed to add rules");
+    let waf = WafProxy::new("/", upstream.url(""), security);
+    let srv = test::init_service(App::new().service(waf)).await;
+
+    let req = TestRequest::with_uri("/page").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "upstream");
+
+    // request phase interventions never reach the upstream
+    let req = TestRequest::with_uri("/admin").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // response phase interventions block the upstream content
+    let req = TestRequest::with_uri("/leak").to_request();
+    let res = test::call_service(&srv, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+    assert_ne!(test::read_body(res).await, "secret");
+
+    upstream.stop().await;
+}
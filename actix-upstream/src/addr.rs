@@ -0,0 +1,146 @@
+//! Upstream Address Abstraction with Support for Unix/TCP
+
+use std::{
+    fmt, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Compiled Unix/TCP Socket Address
+///
+/// Hostnames are resolved when connecting rather than when parsed.
+/// Addresses using the `dns+` scheme prefix (such as `dns+tcp://`) are
+/// periodically re-resolved according to the [`Resolver`](crate::Resolver) TTL.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum StreamAddr {
+    Unix(PathBuf),
+    Tcp(Vec<SocketAddr>),
+    Dns {
+        host: String,
+        port: u16,
+        refresh: bool,
+    },
+    #[cfg(feature = "rustls")]
+    Tls {
+        host: String,
+        port: u16,
+        refresh: bool,
+    },
+}
+
+impl From<&Path> for StreamAddr {
+    #[inline]
+    fn from(value: &Path) -> Self {
+        Self::Unix(value.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for StreamAddr {
+    #[inline]
+    fn from(value: PathBuf) -> Self {
+        Self::Unix(value)
+    }
+}
+
+impl From<SocketAddr> for StreamAddr {
+    #[inline]
+    fn from(value: SocketAddr) -> Self {
+        Self::Tcp(vec![value])
+    }
+}
+
+impl fmt::Display for StreamAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let host = |host: &str| match host.contains(':') {
+            true => format!("[{host}]"),
+            false => host.to_owned(),
+        };
+        let dns = |refresh: bool| if refresh { "dns+" } else { "" };
+        match self {
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+            Self::Tcp(addrs) => {
+                write!(f, "tcp://")?;
+                for (n, addr) in addrs.iter().enumerate() {
+                    let sep = if n > 0 { "," } else { "" };
+                    write!(f, "{sep}{addr}")?;
+                }
+                Ok(())
+            }
+            Self::Dns {
+                host: name,
+                port,
+                refresh,
+            } => write!(f, "{}tcp://{}:{port}", dns(*refresh), host(name)),
+            #[cfg(feature = "rustls")]
+            Self::Tls {
+                host: name,
+                port,
+                refresh,
+            } => write!(f, "{}tls://{}:{port}", dns(*refresh), host(name)),
+        }
+    }
+}
+
+/// Split `host:port` into its components
+fn split_host(addr: &str) -> io::Result<(String, u16)> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid address {addr:?}"),
+        )
+    };
+    let (host, port) = addr.rsplit_once(':').ok_or_else(invalid)?;
+    let port = port.parse().map_err(|_| invalid())?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok((host.to_owned(), port))
+}
+
+impl TryFrom<&str> for StreamAddr {
+    type Error = io::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (scheme, addr) = value.split_once("://").unwrap_or(("tcp", value));
+        let scheme = scheme.to_lowercase();
+        let (refresh, scheme) = match scheme.strip_prefix("dns+") {
+            Some(scheme) => (true, scheme),
+            None => (false, scheme.as_str()),
+        };
+        match scheme {
+            "unix" => Ok(Self::Unix(PathBuf::from(addr))),
+            #[cfg(feature = "rustls")]
+            "tls" => {
+                let (host, port) = split_host(addr)?;
+                Ok(Self::Tls {
+                    host,
+                    port,
+                    refresh,
+                })
+            }
+            _ => match addr.parse::<SocketAddr>() {
+                Ok(addr) if !refresh => Ok(Self::Tcp(vec![addr])),
+                _ => {
+                    let (host, port) = split_host(addr)?;
+                    Ok(Self::Dns {
+                        host,
+                        port,
+                        refresh,
+                    })
+                }
+            },
+        }
+    }
+}
+
+impl FromStr for StreamAddr {
+    type Err = io::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.try_into()
+    }
+}
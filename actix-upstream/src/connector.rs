@@ -0,0 +1,305 @@
+//! Upstream Dialer with Connect Timeout and Happy-Eyeballs Support
+
+use std::{io, net::SocketAddr, pin::pin, time::Duration};
+
+use futures_util::{
+    StreamExt,
+    future::{Either, select},
+    stream::FuturesUnordered,
+};
+use tokio::net::{TcpStream, UnixStream};
+
+#[cfg(feature = "rustls")]
+use std::sync::{Arc, OnceLock};
+#[cfg(feature = "rustls")]
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{ClientConfig, RootCertStore, pki_types::ServerName},
+};
+
+use crate::{EgressProxy, Resolver, SockStream, StreamAddr};
+
+/// Default delay between staggered connection attempts (RFC 8305)
+const DEFAULT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Address family preference for dual-stack TCP upstreams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Start with the family of the first resolved address.
+    ///
+    /// System resolvers order addresses by RFC 6724 which
+    /// generally prefers IPv6 when available.
+    #[default]
+    Resolved,
+    /// Start with IPv6 addresses and fall back to IPv4.
+    Ipv6,
+    /// Start with IPv4 addresses and fall back to IPv6.
+    Ipv4,
+    /// Only attempt IPv6 addresses.
+    Ipv6Only,
+    /// Only attempt IPv4 addresses.
+    Ipv4Only,
+}
+
+/// Configurable dialer for [`StreamAddr`] upstreams.
+///
+/// TCP addresses are attempted using "happy eyeballs" (RFC 8305) where
+/// address families are interleaved and connection attempts are staggered
+/// rather than tried strictly one after another, so a broken IPv6 path
+/// only delays a connection by the attempt delay.
+///
+/// TCP upstreams may be tunneled through an [`EgressProxy`] where direct
+/// egress is not permitted.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix_upstream::{Connector, IpPreference};
+///
+/// let connector = Connector::new()
+///     .ip_preference(IpPreference::Ipv4)
+///     .attempt_delay(Duration::from_millis(100));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Connector {
+    timeout: Option<Duration>,
+    attempt_delay: Duration,
+    preference: IpPreference,
+    resolver: Resolver,
+    proxy: Option<EgressProxy>,
+    #[cfg(feature = "rustls")]
+    tls: Option<Arc<ClientConfig>>,
+}
+
+impl Default for Connector {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            attempt_delay: DEFAULT_ATTEMPT_DELAY,
+            preference: IpPreference::default(),
+            resolver: Resolver::shared(),
+            proxy: None,
+            #[cfg(feature = "rustls")]
+            tls: None,
+        }
+    }
+}
+
+impl Connector {
+    /// Construct a new connector with default settings.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum time allowed to establish a connection.
+    ///
+    /// Default is no timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the delay before starting the next staggered TCP connection attempt.
+    ///
+    /// Default is 250ms.
+    pub fn attempt_delay(mut self, delay: Duration) -> Self {
+        self.attempt_delay = delay;
+        self
+    }
+
+    /// Set the address family attempted first for dual-stack upstreams.
+    ///
+    /// Default is [`IpPreference::Resolved`].
+    pub fn ip_preference(mut self, preference: IpPreference) -> Self {
+        self.preference = preference;
+        self
+    }
+
+    /// Set the [`Resolver`] used to resolve upstream hostnames.
+    ///
+    /// Default is the process-wide [`Resolver::shared()`].
+    pub fn resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Tunnel TCP upstream connections through the specified [`EgressProxy`].
+    ///
+    /// Default is to connect directly.
+    pub fn proxy(mut self, proxy: EgressProxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Set the rustls client configuration used for `tls://` upstreams.
+    ///
+    /// Default uses the webpki root certificates.
+    #[cfg(feature = "rustls")]
+    pub fn tls_config(mut self, config: Arc<ClientConfig>) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// Connect to the specified upstream address.
+    pub async fn connect(&self, addr: &StreamAddr) -> io::Result<SockStream> {
+        let connect = async {
+            match addr {
+                StreamAddr::Unix(path) => Ok(SockStream::Unix(UnixStream::connect(path).await?)),
+                StreamAddr::Tcp(addrs) => match &self.proxy {
+                    Some(proxy) => Ok(SockStream::Tcp(self.tunnel_addrs(proxy, addrs).await?)),
+                    None => Ok(SockStream::Tcp(self.connect_tcp(addrs).await?)),
+                },
+                StreamAddr::Dns {
+                    host,
+                    port,
+                    refresh,
+                } => Ok(SockStream::Tcp(
+                    self.connect_host(host, *port, *refresh).await?,
+                )),
+                #[cfg(feature = "rustls")]
+                StreamAddr::Tls {
+                    host,
+                    port,
+                    refresh,
+                } => {
+                    let stream = self.connect_host(host, *port, *refresh).await?;
+                    self.connect_tls(stream, host).await
+                }
+            }
+        };
+        match self.timeout {
+            None => connect.await,
+            Some(timeout) => tokio::time::timeout(timeout, connect)
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "upstream connect timed out"))?,
+        }
+    }
+
+    /// Connect to the first responsive TCP address using happy-eyeballs.
+    pub async fn connect_tcp(&self, addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+        let mut pending = interleave(addrs, self.preference).into_iter();
+        let mut attempts = FuturesUnordered::new();
+        let mut last_err = None;
+        while let Some(addr) = pending.next() {
+            tracing::trace!("connecting to upstream {addr}");
+            attempts.push(TcpStream::connect(addr));
+            if pending.len() == 0 {
+                break;
+            }
+            // wait for the attempt delay or a failure before starting the next attempt
+            let delay = pin!(tokio::time::sleep(self.attempt_delay));
+            match select(attempts.next(), delay).await {
+                Either::Left((Some(Ok(stream)), _)) => return Ok(stream),
+                Either::Left((Some(Err(err)), _)) => last_err = Some(err),
+                Either::Left((None, _)) | Either::Right(_) => {}
+            }
+        }
+        while let Some(result) = attempts.next().await {
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no upstream addresses to connect")
+        }))
+    }
+
+    /// Connect to a hostname directly or through the configured proxy
+    async fn connect_host(&self, host: &str, port: u16, refresh: bool) -> io::Result<TcpStream> {
+        match &self.proxy {
+            Some(proxy) => self.tunnel(proxy, host, port).await,
+            None => {
+                let addrs = self.resolver.resolve(host, port, refresh).await?;
+                self.connect_tcp(&addrs).await
+            }
+        }
+    }
+
+    /// Open a tunnel to the target through the proxy
+    async fn tunnel(&self, proxy: &EgressProxy, host: &str, port: u16) -> io::Result<TcpStream> {
+        let addrs = self
+            .resolver
+            .resolve(proxy.host(), proxy.port(), false)
+            .await?;
+        let mut stream = self.connect_tcp(&addrs).await?;
+        proxy.tunnel(&mut stream, host, port).await?;
+        Ok(stream)
+    }
+
+    /// Attempt tunnels to each address in order of preference
+    async fn tunnel_addrs(
+        &self,
+        proxy: &EgressProxy,
+        addrs: &[SocketAddr],
+    ) -> io::Result<TcpStream> {
+        let mut last_err = None;
+        for addr in interleave(addrs, self.preference) {
+            match self
+                .tunnel(proxy, &addr.ip().to_string(), addr.port())
+                .await
+            {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no upstream addresses to connect",
+            )
+        }))
+    }
+
+    #[cfg(feature = "rustls")]
+    async fn connect_tls(&self, stream: TcpStream, host: &str) -> io::Result<SockStream> {
+        let config = self.tls.clone().unwrap_or_else(default_tls_config);
+        let name = ServerName::try_from(host.to_owned())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let stream = TlsConnector::from(config).connect(name, stream).await?;
+        Ok(SockStream::Tls(Box::new(stream)))
+    }
+}
+
+#[cfg(feature = "rustls")]
+fn default_tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            let config = ClientConfig::builder_with_provider(Arc::new(
+                tokio_rustls::rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .expect("ring provider supports default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+            Arc::new(config)
+        })
+        .clone()
+}
+
+/// Interleave address families starting with the preferred family.
+fn interleave(addrs: &[SocketAddr], preference: IpPreference) -> Vec<SocketAddr> {
+    let ipv6 = match preference {
+        IpPreference::Resolved => addrs.first().is_some_and(SocketAddr::is_ipv6),
+        IpPreference::Ipv6 | IpPreference::Ipv6Only => true,
+        IpPreference::Ipv4 | IpPreference::Ipv4Only => false,
+    };
+    let (primary, secondary): (Vec<_>, Vec<_>) =
+        addrs.iter().copied().partition(|addr| addr.is_ipv6() == ipv6);
+    if matches!(preference, IpPreference::Ipv6Only | IpPreference::Ipv4Only) {
+        return primary;
+    }
+    let (mut primary, mut secondary) = (primary.into_iter(), secondary.into_iter());
+    let mut interleaved = Vec::with_capacity(addrs.len());
+    loop {
+        match (primary.next(), secondary.next()) {
+            (None, None) => break,
+            (a, b) => interleaved.extend(a.into_iter().chain(b)),
+        }
+    }
+    interleaved
+}
@@ -0,0 +1,40 @@
+//! Shared upstream socket dialing for Actix-Web services.
+//!
+//! Provides a single abstraction over Unix/TCP(/TLS) upstream sockets
+//! including connect timeouts and dual-stack "happy eyeballs" connection
+//! attempts so each service does not need to re-implement socket dialing.
+//! The address family attempted first is configurable using
+//! [`IpPreference`].
+//!
+//! Hostnames are resolved asynchronously when connecting using a shared
+//! caching [`Resolver`]. Use the `dns+tcp://` scheme to periodically
+//! re-resolve upstreams behind dynamic DNS.
+//!
+//! TCP upstreams can be dialed through a SOCKS5 or HTTP CONNECT
+//! [`EgressProxy`] in networks where direct egress is forbidden.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use actix_upstream::{Connector, SockStream, StreamAddr};
+//!
+//! async fn connect() -> std::io::Result<SockStream> {
+//!     let addr: StreamAddr = "tcp://localhost:9000".parse()?;
+//!     Connector::new()
+//!         .timeout(Duration::from_secs(5))
+//!         .connect(&addr)
+//!         .await
+//! }
+//! ```
+mod addr;
+mod connector;
+mod proxy;
+mod resolver;
+mod stream;
+
+pub use addr::StreamAddr;
+pub use connector::{Connector, IpPreference};
+pub use proxy::{EgressProxy, ProxyProtocol};
+pub use resolver::Resolver;
+pub use stream::SockStream;
@@ -0,0 +1,109 @@
+//! Caching Asynchronous DNS Resolver for Upstream Hostnames
+
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// Default time-to-live for periodically re-resolved entries
+const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+struct Entry {
+    addrs: Vec<SocketAddr>,
+    resolved: Instant,
+}
+
+/// Shared DNS cache for upstream hostnames.
+///
+/// Hostnames are resolved asynchronously at connect time. Entries for
+/// `dns+` addresses are re-resolved once their TTL expires while all
+/// other entries are resolved once and kept. When re-resolution fails the
+/// previous addresses continue to be used.
+///
+/// Clones share the same cache.
+#[derive(Clone, Debug)]
+pub struct Resolver {
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<(String, u16), Entry>>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_TTL,
+            cache: Arc::default(),
+        }
+    }
+}
+
+impl Resolver {
+    /// Construct a new resolver with an empty cache.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process-wide resolver used by default by every [`Connector`](crate::Connector).
+    pub fn shared() -> Self {
+        static SHARED: OnceLock<Resolver> = OnceLock::new();
+        SHARED.get_or_init(Resolver::default).clone()
+    }
+
+    /// Set the time-to-live for periodically re-resolved entries.
+    ///
+    /// Default is 30 seconds.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Resolve the host and port into socket addresses.
+    ///
+    /// `refresh` re-resolves the host once the cached entry is older than the TTL.
+    pub async fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+        refresh: bool,
+    ) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+
+        let key = (host.to_owned(), port);
+        let cached = self.cache.lock().expect("poisoned lock").get(&key).cloned();
+        if let Some(entry) = cached.as_ref()
+            && (!refresh || entry.resolved.elapsed() < self.ttl)
+        {
+            return Ok(entry.addrs.clone());
+        }
+
+        tracing::debug!("resolving upstream {host}:{port}");
+        let result = tokio::net::lookup_host((host, port))
+            .await
+            .map(|addrs| addrs.collect::<Vec<_>>());
+        match (result, cached) {
+            (Ok(addrs), _) if !addrs.is_empty() => {
+                let entry = Entry {
+                    addrs: addrs.clone(),
+                    resolved: Instant::now(),
+                };
+                self.cache.lock().expect("poisoned lock").insert(key, entry);
+                Ok(addrs)
+            }
+            (result, Some(entry)) => {
+                tracing::warn!("failed to re-resolve {host}:{port}: {result:?}. using cached");
+                Ok(entry.addrs)
+            }
+            (Ok(_), None) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no addresses found for {host}:{port}"),
+            )),
+            (Err(err), None) => Err(err),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Socket Connection Abstraction with Support for Unix/TCP
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_rt::net::{ActixStream, Ready};
+use pin_project::pin_project;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpStream, UnixStream},
+};
+
+use crate::{Connector, StreamAddr};
+
+/// Socket abstraction on [`TcpStream`](tokio::net::TcpStream) or
+/// [`UnixStream`](tokio::net::UnixStream)
+#[pin_project(project = AbsStreamProj)]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SockStream {
+    Unix(#[pin] UnixStream),
+    Tcp(#[pin] TcpStream),
+    #[cfg(feature = "rustls")]
+    Tls(#[pin] Box<tokio_rustls::client::TlsStream<TcpStream>>),
+}
+
+impl SockStream {
+    /// Connect to the relevant unix/tcp socket using a connection uri
+    ///
+    /// Uses the default [`Connector`] settings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use actix_upstream::SockStream;
+    ///
+    /// async fn connect() -> std::io::Result<()> {
+    ///   let unix  = SockStream::connect(&"unix:///var/run/program.sock".parse()?).await?;
+    ///   let unix2 = SockStream::connect(&PathBuf::from("/var/run/program.sock").into()).await?;
+    ///   let tcp   = SockStream::connect(&"tcp://localhost:9000".parse()?).await?;
+    ///   let tcp2  = SockStream::connect(&"192.168.0.2:9000".parse()?).await?;
+    ///   Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub async fn connect(addr: &StreamAddr) -> io::Result<Self> {
+        Connector::default().connect(addr).await
+    }
+
+    fn tcp(&self) -> Option<&TcpStream> {
+        match self {
+            Self::Unix(_) => None,
+            Self::Tcp(t) => Some(t),
+            #[cfg(feature = "rustls")]
+            Self::Tls(t) => Some(t.get_ref().0),
+        }
+    }
+}
+
+impl AsyncRead for SockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.project() {
+            AbsStreamProj::Unix(u) => u.poll_read(cx, buf),
+            AbsStreamProj::Tcp(t) => t.poll_read(cx, buf),
+            #[cfg(feature = "rustls")]
+            AbsStreamProj::Tls(t) => t.poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for SockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        match self.project() {
+            AbsStreamProj::Unix(u) => u.poll_write(cx, buf),
+            AbsStreamProj::Tcp(t) => t.poll_write(cx, buf),
+            #[cfg(feature = "rustls")]
+            AbsStreamProj::Tls(t) => t.poll_write(cx, buf),
+        }
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        match self.project() {
+            AbsStreamProj::Unix(u) => u.poll_flush(cx),
+            AbsStreamProj::Tcp(t) => t.poll_flush(cx),
+            #[cfg(feature = "rustls")]
+            AbsStreamProj::Tls(t) => t.poll_flush(cx),
+        }
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        match self.project() {
+            AbsStreamProj::Unix(u) => u.poll_shutdown(cx),
+            AbsStreamProj::Tcp(t) => t.poll_shutdown(cx),
+            #[cfg(feature = "rustls")]
+            AbsStreamProj::Tls(t) => t.poll_shutdown(cx),
+        }
+    }
+}
+
+impl ActixStream for SockStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        match (self, self.tcp()) {
+            (Self::Unix(u), _) => UnixStream::poll_read_ready(u, cx).map_ok(|_| Ready::READABLE),
+            (_, Some(t)) => TcpStream::poll_read_ready(t, cx).map_ok(|_| Ready::READABLE),
+            _ => Poll::Ready(Ok(Ready::READABLE)),
+        }
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        match (self, self.tcp()) {
+            (Self::Unix(u), _) => UnixStream::poll_write_ready(u, cx).map_ok(|_| Ready::WRITABLE),
+            (_, Some(t)) => TcpStream::poll_write_ready(t, cx).map_ok(|_| Ready::WRITABLE),
+            _ => Poll::Ready(Ok(Ready::WRITABLE)),
+        }
+    }
+}